@@ -0,0 +1,96 @@
+/*
+ * C API of sv-parser.
+ *
+ * A tree is parsed from a buffer by sv_parse() and freed by sv_tree_free().
+ * Its nodes are numbered in pre-order from the root, which is 0, and are
+ * walked by the sv_node_*() functions. Offsets are byte offsets within the
+ * preprocessed text given by sv_tree_text(), which are mapped back to the
+ * source files by sv_tree_origin().
+ *
+ * Link with libsv_parser_capi, built by `cargo build -p sv-parser-capi`.
+ */
+
+#ifndef SV_PARSER_H
+#define SV_PARSER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The node returned for no node, e.g. for the parent of the root. */
+#define SV_NO_NODE SIZE_MAX
+
+typedef struct SvSyntaxTree SvSyntaxTree;
+
+/*
+ * Parse `len` bytes of UTF-8 at `text` as the contents of the file `path`,
+ * looking for include files in the `include_count` paths at `include_paths`.
+ * Incomplete source code is accepted if `allow_incomplete` is not 0.
+ *
+ * Returns the tree, or NULL on failure, when a message is stored at `error`
+ * unless it is NULL. The message is freed by sv_string_free().
+ */
+SvSyntaxTree *sv_parse(const char *text, size_t len, const char *path,
+                       const char *const *include_paths, size_t include_count,
+                       int allow_incomplete, char **error);
+
+/* Free a tree returned by sv_parse(). Nothing is done for NULL. */
+void sv_tree_free(SvSyntaxTree *tree);
+
+/* Free a string returned by this API. Nothing is done for NULL. */
+void sv_string_free(char *s);
+
+/*
+ * The preprocessed text of the tree, which isn't NUL-terminated. Its length
+ * is stored at `len` unless it is NULL.
+ */
+const char *sv_tree_text(const SvSyntaxTree *tree, size_t *len);
+
+/* The number of nodes of the tree. */
+size_t sv_tree_node_count(const SvSyntaxTree *tree);
+
+/*
+ * The name of the kind of `node`, e.g. "ModuleDeclarationAnsi", or "Locate"
+ * for a token. It lives as long as the tree. NULL is returned for an invalid
+ * node.
+ */
+const char *sv_node_kind(const SvSyntaxTree *tree, size_t node);
+
+/* The parent of `node`, or SV_NO_NODE for the root or an invalid node. */
+size_t sv_node_parent(const SvSyntaxTree *tree, size_t node);
+
+/* The number of children of `node`, which is 0 for an invalid node. */
+size_t sv_node_child_count(const SvSyntaxTree *tree, size_t node);
+
+/*
+ * The child of `node` at `index`, in the order of the text, or SV_NO_NODE if
+ * there is none.
+ */
+size_t sv_node_child(const SvSyntaxTree *tree, size_t node, size_t index);
+
+/*
+ * Store the range of the text of `node` within the preprocessed text at
+ * `begin` and `end`, and return 1. Nothing is stored and 0 is returned for a
+ * node without tokens, or an invalid node.
+ */
+int sv_node_span(const SvSyntaxTree *tree, size_t node, size_t *begin,
+                 size_t *end);
+
+/*
+ * Store the path of the file which `offset` of the preprocessed text
+ * originates from at `path`, and the byte offset within the file at
+ * `origin_offset`, and return 1. The path lives as long as the tree. Nothing
+ * is stored and 0 is returned for text without an origin, e.g. the expansion
+ * of `__FILE__.
+ */
+int sv_tree_origin(const SvSyntaxTree *tree, size_t offset, const char **path,
+                   size_t *origin_offset);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SV_PARSER_H */
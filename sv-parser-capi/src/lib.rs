@@ -0,0 +1,436 @@
+//! The C API of sv-parser, declared by `include/sv_parser.h`.
+//!
+//! A tree is parsed from a buffer by `sv_parse()` and freed by
+//! `sv_tree_free()`. Its nodes are numbered in pre-order from the root, which
+//! is 0, and are walked by the `sv_node_*()` functions. Offsets are byte
+//! offsets within the preprocessed text given by `sv_tree_text()`, which are
+//! mapped back to the source files by `sv_tree_origin()`.
+
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{ptr, slice};
+use sv_parser::{
+    parse_sv_str, Diagnostic, Error, Location, NodeEvent, NodeKind, RefNode, SyntaxTree,
+};
+
+/// The node returned for no node, e.g. for the parent of the root.
+pub const SV_NO_NODE: usize = usize::MAX;
+
+/// A parsed tree, with its nodes numbered for the `sv_node_*()` functions.
+pub struct SvSyntaxTree {
+    tree: SyntaxTree,
+    nodes: Vec<Node>,
+    // The names of the kinds of the nodes, indexed by `Node::kind`.
+    kinds: Vec<CString>,
+    // The paths returned by `sv_tree_origin()`, which live as long as the
+    // tree.
+    paths: Mutex<HashMap<PathBuf, CString>>,
+}
+
+struct Node {
+    kind: usize,
+    parent: usize,
+    children: Vec<usize>,
+    // The range of the text of the tokens within the node.
+    span: Option<(usize, usize)>,
+}
+
+impl SvSyntaxTree {
+    fn new(tree: SyntaxTree) -> Self {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut kinds = Vec::new();
+        let mut kind_ids: HashMap<NodeKind, usize> = HashMap::new();
+        let mut stack: Vec<usize> = Vec::new();
+        for event in (&tree).into_iter().event() {
+            match event {
+                NodeEvent::Enter(x) => {
+                    let kind = *kind_ids.entry(x.kind()).or_insert_with(|| {
+                        kinds.push(CString::new(x.kind().as_str()).unwrap());
+                        kinds.len() - 1
+                    });
+                    let span = match x {
+                        RefNode::Locate(x) => Some((x.offset, x.offset + x.len)),
+                        _ => None,
+                    };
+                    let id = nodes.len();
+                    let parent = stack.last().copied().unwrap_or(SV_NO_NODE);
+                    if parent != SV_NO_NODE {
+                        nodes[parent].children.push(id);
+                    }
+                    nodes.push(Node {
+                        kind,
+                        parent,
+                        children: Vec::new(),
+                        span,
+                    });
+                    stack.push(id);
+                }
+                NodeEvent::Leave(_) => {
+                    // The span of a node covers those of its children.
+                    let id = stack.pop().unwrap();
+                    if let (Some(&parent), Some((begin, end))) = (stack.last(), nodes[id].span) {
+                        let span = &mut nodes[parent].span;
+                        *span =
+                            Some(span.map_or((begin, end), |(x, y)| (x.min(begin), y.max(end))));
+                    }
+                }
+            }
+        }
+
+        SvSyntaxTree {
+            tree,
+            nodes,
+            kinds,
+            paths: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Parse `len` bytes of UTF-8 at `text` as the contents of the file `path`,
+/// looking for include files in the `include_count` paths at
+/// `include_paths`. Incomplete source code is accepted if
+/// `allow_incomplete` is not 0.
+///
+/// Returns the tree, or null on failure, when a message is stored at `error`
+/// unless it is null. The message is freed by `sv_string_free()`.
+///
+/// # Safety
+///
+/// `text` must point to `len` readable bytes, and `path` and each of the
+/// include paths must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sv_parse(
+    text: *const c_char,
+    len: usize,
+    path: *const c_char,
+    include_paths: *const *const c_char,
+    include_count: usize,
+    allow_incomplete: c_int,
+    error: *mut *mut c_char,
+) -> *mut SvSyntaxTree {
+    let set_error = |message: String| {
+        if !error.is_null() {
+            *error = CString::new(message.replace('\0', " ")).unwrap().into_raw();
+        }
+    };
+
+    let bytes = if text.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(text as *const u8, len)
+    };
+    let s = match std::str::from_utf8(bytes) {
+        Ok(x) => x,
+        Err(_) => {
+            set_error(String::from("text is not UTF-8"));
+            return ptr::null_mut();
+        }
+    };
+    let path = PathBuf::from(string(path));
+    let include_paths: Vec<PathBuf> = if include_paths.is_null() {
+        Vec::new()
+    } else {
+        slice::from_raw_parts(include_paths, include_count)
+            .iter()
+            .map(|x| PathBuf::from(string(*x)))
+            .collect()
+    };
+
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        parse_sv_str(
+            s,
+            &path,
+            &HashMap::new(),
+            &include_paths,
+            false,
+            allow_incomplete != 0,
+        )
+    }));
+    match ret {
+        Ok(Ok((tree, _))) => Box::into_raw(Box::new(SvSyntaxTree::new(tree))),
+        Ok(Err(x)) => {
+            set_error(error_message(&x, &path, s));
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_error(String::from("internal error"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a tree returned by `sv_parse()`. Nothing is done for null.
+///
+/// # Safety
+///
+/// `tree` must be null or a tree which isn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn sv_tree_free(tree: *mut SvSyntaxTree) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Free a string returned by this API. Nothing is done for null.
+///
+/// # Safety
+///
+/// `s` must be null or a string which isn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn sv_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// The preprocessed text of the tree, which isn't NUL-terminated. Its
+/// length is stored at `len` unless it is null.
+///
+/// # Safety
+///
+/// `tree` must be a tree returned by `sv_parse()`.
+#[no_mangle]
+pub unsafe extern "C" fn sv_tree_text(tree: *const SvSyntaxTree, len: *mut usize) -> *const c_char {
+    let text = (*tree).tree.text().text();
+    if !len.is_null() {
+        *len = text.len();
+    }
+    text.as_ptr() as *const c_char
+}
+
+/// The number of nodes of the tree.
+///
+/// # Safety
+///
+/// `tree` must be a tree returned by `sv_parse()`.
+#[no_mangle]
+pub unsafe extern "C" fn sv_tree_node_count(tree: *const SvSyntaxTree) -> usize {
+    (*tree).nodes.len()
+}
+
+/// The name of the kind of `node`, e.g. `"ModuleDeclarationAnsi"`, or
+/// `"Locate"` for a token. It lives as long as the tree. Null is returned
+/// for an invalid node.
+///
+/// # Safety
+///
+/// `tree` must be a tree returned by `sv_parse()`.
+#[no_mangle]
+pub unsafe extern "C" fn sv_node_kind(tree: *const SvSyntaxTree, node: usize) -> *const c_char {
+    let tree = &*tree;
+    match tree.nodes.get(node) {
+        Some(x) => tree.kinds[x.kind].as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// The parent of `node`, or `SV_NO_NODE` for the root or an invalid node.
+///
+/// # Safety
+///
+/// `tree` must be a tree returned by `sv_parse()`.
+#[no_mangle]
+pub unsafe extern "C" fn sv_node_parent(tree: *const SvSyntaxTree, node: usize) -> usize {
+    let tree = &*tree;
+    tree.nodes.get(node).map_or(SV_NO_NODE, |x| x.parent)
+}
+
+/// The number of children of `node`, which is 0 for an invalid node.
+///
+/// # Safety
+///
+/// `tree` must be a tree returned by `sv_parse()`.
+#[no_mangle]
+pub unsafe extern "C" fn sv_node_child_count(tree: *const SvSyntaxTree, node: usize) -> usize {
+    let tree = &*tree;
+    tree.nodes.get(node).map_or(0, |x| x.children.len())
+}
+
+/// The child of `node` at `index`, in the order of the text, or
+/// `SV_NO_NODE` if there is none.
+///
+/// # Safety
+///
+/// `tree` must be a tree returned by `sv_parse()`.
+#[no_mangle]
+pub unsafe extern "C" fn sv_node_child(
+    tree: *const SvSyntaxTree,
+    node: usize,
+    index: usize,
+) -> usize {
+    let tree = &*tree;
+    let child = tree.nodes.get(node).and_then(|x| x.children.get(index));
+    child.copied().unwrap_or(SV_NO_NODE)
+}
+
+/// Store the range of the text of `node` within the preprocessed text at
+/// `begin` and `end`, and return 1. Nothing is stored and 0 is returned for
+/// a node without tokens, or an invalid node.
+///
+/// # Safety
+///
+/// `tree` must be a tree returned by `sv_parse()`, and `begin` and `end`
+/// must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn sv_node_span(
+    tree: *const SvSyntaxTree,
+    node: usize,
+    begin: *mut usize,
+    end: *mut usize,
+) -> c_int {
+    let tree = &*tree;
+    match tree.nodes.get(node).and_then(|x| x.span) {
+        Some((x, y)) => {
+            *begin = x;
+            *end = y;
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Store the path of the file which `offset` of the preprocessed text
+/// originates from at `path`, and the byte offset within the file at
+/// `origin_offset`, and return 1. The path lives as long as the tree.
+/// Nothing is stored and 0 is returned for text without an origin, e.g. the
+/// expansion of `` `__FILE__ ``.
+///
+/// # Safety
+///
+/// `tree` must be a tree returned by `sv_parse()`, and `path` and
+/// `origin_offset` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn sv_tree_origin(
+    tree: *const SvSyntaxTree,
+    offset: usize,
+    path: *mut *const c_char,
+    origin_offset: *mut usize,
+) -> c_int {
+    let tree = &*tree;
+    match tree.tree.text().origin(offset) {
+        Some((x, y)) => {
+            let mut paths = tree.paths.lock().unwrap();
+            let name = paths.entry(x.clone()).or_insert_with(|| {
+                let name = x.to_string_lossy().replace('\0', " ");
+                CString::new(name).unwrap()
+            });
+            // The string is kept by the tree, and isn't moved by moving the
+            // `CString`.
+            *path = name.as_ptr();
+            *origin_offset = y;
+            1
+        }
+        None => 0,
+    }
+}
+
+// The string at `s`, which is empty for null.
+unsafe fn string(s: *const c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(s).to_string_lossy().into_owned()
+    }
+}
+
+// The diagnostic of `error`, prefixed by its location as `path:line:column`.
+// The location within the parsed buffer is found from `text`, as there may
+// be no file at `path`.
+fn error_message(error: &Error, path: &Path, text: &str) -> String {
+    let location = match error.origin() {
+        Some((x, offset)) if x == path => Some(Location::new(x, offset, text)),
+        _ => error.location(),
+    };
+    let diagnostic = Diagnostic::from(error);
+    match location {
+        Some(x) => format!(
+            "{}:{}:{}: {}",
+            x.path.display(),
+            x.line,
+            x.column,
+            diagnostic
+        ),
+        None => diagnostic.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let src = "module A; endmodule";
+        let path = CString::new("a.sv").unwrap();
+        unsafe {
+            let tree = sv_parse(
+                src.as_ptr() as *const c_char,
+                src.len(),
+                path.as_ptr(),
+                ptr::null(),
+                0,
+                0,
+                ptr::null_mut(),
+            );
+            assert!(!tree.is_null());
+            assert_eq!(
+                CStr::from_ptr(sv_node_kind(tree, 0)).to_str(),
+                Ok("SourceText")
+            );
+            assert_eq!(sv_node_parent(tree, 0), SV_NO_NODE);
+            assert_eq!(sv_node_kind(tree, sv_tree_node_count(tree)), ptr::null());
+
+            let (mut begin, mut end) = (0, 0);
+            assert_eq!(sv_node_span(tree, 0, &mut begin, &mut end), 1);
+            assert_eq!((begin, end), (0, src.len()));
+
+            let child = sv_node_child(tree, 0, 0);
+            assert!(sv_node_child_count(tree, 0) > 0);
+            assert_eq!(sv_node_parent(tree, child), 0);
+            assert_eq!(sv_node_child(tree, 0, usize::MAX), SV_NO_NODE);
+
+            let kind = |x| CStr::from_ptr(sv_node_kind(tree, x)).to_str().unwrap();
+            let node = (0..sv_tree_node_count(tree))
+                .find(|x| kind(*x) == "ModuleIdentifier")
+                .unwrap();
+            assert_eq!(sv_node_span(tree, node, &mut begin, &mut end), 1);
+            let mut len = 0;
+            let text = sv_tree_text(tree, &mut len) as *const u8;
+            let text = std::str::from_utf8(slice::from_raw_parts(text, len)).unwrap();
+            assert_eq!(text[begin..end].trim(), "A");
+
+            let mut origin = ptr::null();
+            let mut offset = 0;
+            assert_eq!(sv_tree_origin(tree, begin, &mut origin, &mut offset), 1);
+            assert_eq!(CStr::from_ptr(origin).to_str(), Ok("a.sv"));
+            assert_eq!(offset, 7);
+            sv_tree_free(tree);
+        }
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let src = "module A;\n  wire a\nendmodule\n";
+        let path = CString::new("a.sv").unwrap();
+        let mut error = ptr::null_mut();
+        unsafe {
+            let tree = sv_parse(
+                src.as_ptr() as *const c_char,
+                src.len(),
+                path.as_ptr(),
+                ptr::null(),
+                0,
+                0,
+                &mut error,
+            );
+            assert!(tree.is_null());
+            let message = CStr::from_ptr(error).to_str().unwrap();
+            assert!(message.starts_with("a.sv:3:1: error[parse]: Parse error"));
+            sv_string_free(error);
+        }
+    }
+}
@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+use structopt::{clap::AppSettings, StructOpt};
+use sv_parser::{
+    parse_sv, preprocess, preprocess_with_config, to_verible_json, Define, DefineText, Defines,
+    Diagnostic, Error, PreprocessObserver, PreprocessedText, PreprocessorConfig, Project, Tags,
+};
+
+/// Parse SystemVerilog files and print their syntax trees.
+#[derive(StructOpt)]
+#[structopt(name = "sv-parser", setting = AppSettings::SubcommandsNegateReqs)]
+struct Opt {
+    /// Files to parse in order, with the macros defined by each file
+    /// visible in the files after it
+    #[structopt(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Include path
+    #[structopt(short = "I", long = "include", multiple = true, number_of_values = 1)]
+    pub includes: Vec<PathBuf>,
+
+    /// Define a macro, as NAME or NAME=TEXT
+    #[structopt(short = "D", long = "define", multiple = true, number_of_values = 1)]
+    pub defines: Vec<String>,
+
+    /// Print the syntax tree as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    /// Print the syntax trees of all files as JSON in the form of
+    /// verible-verilog-syntax --export_json
+    #[structopt(long = "verible-json", conflicts_with = "json")]
+    pub verible_json: bool,
+
+    /// Allow incomplete source code
+    #[structopt(long = "incomplete")]
+    pub incomplete: bool,
+
+    /// Print nothing but errors
+    #[structopt(short = "q", long = "quiet")]
+    pub quiet: bool,
+
+    /// Print the preprocessed text instead of parsing it
+    #[structopt(short = "E", long = "preprocess", conflicts_with = "verible-json")]
+    pub preprocess: bool,
+
+    /// Mark the origin of the preprocessed text with `line directives
+    #[structopt(long = "line-markers", requires = "preprocess")]
+    pub line_markers: bool,
+
+    /// Write the output to a file instead of stdout
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// Preprocess files and print the files they include
+    Deps {
+        /// Files to preprocess in order, with the macros defined by each
+        /// file visible in the files after it
+        #[structopt(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Include path
+        #[structopt(short = "I", long = "include", multiple = true, number_of_values = 1)]
+        includes: Vec<PathBuf>,
+
+        /// Define a macro, as NAME or NAME=TEXT
+        #[structopt(short = "D", long = "define", multiple = true, number_of_values = 1)]
+        defines: Vec<String>,
+
+        /// Print a Graphviz DOT graph with an edge from each file to each
+        /// file it includes, or a Makefile rule for each file
+        #[structopt(long = "format", default_value = "make", possible_values = &["dot", "make"])]
+        format: String,
+
+        /// The target of the Makefile rules instead of each file itself
+        #[structopt(long = "target")]
+        target: Option<String>,
+
+        /// Write the output to a file instead of stdout
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Parse files and print a tags file of their definitions for editors
+    Tags {
+        /// Files to parse in order, with the macros defined by each file
+        /// visible in the files after it
+        #[structopt(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Include path
+        #[structopt(short = "I", long = "include", multiple = true, number_of_values = 1)]
+        includes: Vec<PathBuf>,
+
+        /// Define a macro, as NAME or NAME=TEXT
+        #[structopt(short = "D", long = "define", multiple = true, number_of_values = 1)]
+        defines: Vec<String>,
+
+        /// Print a tags file of ctags, or a TAGS file of etags
+        #[structopt(long = "format", default_value = "ctags", possible_values = &["ctags", "etags"])]
+        format: String,
+
+        /// Write the output to a file instead of stdout
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Preprocess files and print the macros which are never used, used only
+    /// in skipped branches, or tested but never defined
+    Defines {
+        /// Files to preprocess in order, with the macros defined by each
+        /// file visible in the files after it
+        #[structopt(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Include path
+        #[structopt(short = "I", long = "include", multiple = true, number_of_values = 1)]
+        includes: Vec<PathBuf>,
+
+        /// Define a macro, as NAME or NAME=TEXT
+        #[structopt(short = "D", long = "define", multiple = true, number_of_values = 1)]
+        defines: Vec<String>,
+
+        /// Write the output to a file instead of stdout
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    // Deeply nested source code needs a larger stack than the main thread's.
+    let builder = std::thread::Builder::new().stack_size(20 * 1024 * 1024);
+    let child = builder
+        .spawn(move || match &opt.command {
+            Some(Command::Deps {
+                files,
+                includes,
+                defines,
+                format,
+                target,
+                output,
+            }) => run_deps(files, includes, defines, format, target, output),
+            Some(Command::Tags {
+                files,
+                includes,
+                defines,
+                format,
+                output,
+            }) => run_tags(files, includes, defines, format, output),
+            Some(Command::Defines {
+                files,
+                includes,
+                defines,
+                output,
+            }) => run_defines(files, includes, defines, output),
+            None => run(&opt),
+        })
+        .expect("thread spawn failure");
+    let exit = child.join().unwrap_or(2);
+    process::exit(exit);
+}
+
+// Parse each file, and get the exit code, which is 1 if any file failed.
+fn run(opt: &Opt) -> i32 {
+    let mut defines = parse_defines(&opt.defines);
+    let mut out = match create_output(&opt.output) {
+        Some(x) => x,
+        None => return 1,
+    };
+
+    let mut trees = Vec::new();
+    let mut exit = 0;
+    for path in &opt.files {
+        let ret = if opt.preprocess {
+            preprocess(path, &defines, &opt.includes, false, false).and_then(|(text, defines)| {
+                if !opt.quiet {
+                    if opt.line_markers {
+                        write!(out, "{}", line_markers(&text))?;
+                    } else {
+                        write!(out, "{}", text.text())?;
+                    }
+                }
+                Ok(defines)
+            })
+        } else {
+            parse_sv(path, &defines, &opt.includes, false, opt.incomplete).and_then(
+                |(syntax_tree, defines)| {
+                    if opt.verible_json {
+                        trees.push((path, syntax_tree));
+                    } else if !opt.quiet {
+                        if opt.json {
+                            writeln!(out, "{}", syntax_tree.to_json())?;
+                        } else {
+                            write!(out, "{}", syntax_tree.dump())?;
+                        }
+                    }
+                    Ok(defines)
+                },
+            )
+        };
+        match ret {
+            Ok(new_defines) => defines = new_defines,
+            Err(x) => {
+                report(path, &x);
+                exit = 1;
+            }
+        }
+    }
+    if opt.verible_json && !opt.quiet {
+        let trees = trees.iter().map(|(path, tree)| (path, tree));
+        if let Err(x) = writeln!(out, "{}", to_verible_json(trees)) {
+            eprintln!("{}", x);
+            exit = 1;
+        }
+    }
+    if let Err(x) = out.flush() {
+        eprintln!("{}", x);
+        exit = 1;
+    }
+    exit
+}
+
+// Preprocess each file, and print its includes as `format`.
+fn run_deps(
+    files: &[PathBuf],
+    includes: &[PathBuf],
+    defines: &[String],
+    format: &str,
+    target: &Option<String>,
+    output: &Option<PathBuf>,
+) -> i32 {
+    let mut defines = parse_defines(defines);
+    let mut out = match create_output(output) {
+        Some(x) => x,
+        None => return 1,
+    };
+
+    let observer = Arc::new(IncludeObserver::default());
+    let config = PreprocessorConfig {
+        observer: Some(observer.clone()),
+        ..Default::default()
+    };
+    let mut ret = String::new();
+    let mut exit = 0;
+    for path in files {
+        match preprocess_with_config(path, &defines, includes, &config) {
+            Ok((text, new_defines)) => {
+                if format == "make" {
+                    let target = target.clone().unwrap_or_else(|| make_path(path));
+                    ret.push_str(&target);
+                    ret.push(':');
+                    for x in std::iter::once(path).chain(text.includes()) {
+                        ret.push_str(&format!(" \\\n  {}", make_path(x)));
+                    }
+                    ret.push('\n');
+                    // An empty rule for each include, so that make doesn't
+                    // fail when it is removed.
+                    for x in text.includes() {
+                        ret.push_str(&format!("\n{}:\n", make_path(x)));
+                    }
+                }
+                defines = new_defines;
+            }
+            Err(x) => {
+                report(path, &x);
+                exit = 1;
+            }
+        }
+    }
+
+    if format == "dot" {
+        ret.push_str("digraph Includes {\n");
+        for path in files {
+            ret.push_str(&format!("  {:?};\n", path.display().to_string()));
+        }
+        for (from, to) in observer.edges.lock().unwrap().iter() {
+            ret.push_str(&format!(
+                "  {:?} -> {:?};\n",
+                from.display().to_string(),
+                to.display().to_string()
+            ));
+        }
+        ret.push_str("}\n");
+    }
+
+    if let Err(x) = out.write_all(ret.as_bytes()).and_then(|_| out.flush()) {
+        eprintln!("{}", x);
+        exit = 1;
+    }
+    exit
+}
+
+// Parse each file, and print the tags of all files which parsed as `format`.
+fn run_tags(
+    files: &[PathBuf],
+    includes: &[PathBuf],
+    defines: &[String],
+    format: &str,
+    output: &Option<PathBuf>,
+) -> i32 {
+    let mut defines = parse_defines(defines);
+    let mut out = match create_output(output) {
+        Some(x) => x,
+        None => return 1,
+    };
+
+    let mut trees = Vec::new();
+    let mut exit = 0;
+    for path in files {
+        match parse_sv(path, &defines, includes, false, false) {
+            Ok((syntax_tree, new_defines)) => {
+                trees.push(syntax_tree);
+                defines = new_defines;
+            }
+            Err(x) => {
+                report(path, &x);
+                exit = 1;
+            }
+        }
+    }
+
+    let tags = Tags::new(&trees);
+    let ret = if format == "etags" {
+        tags.to_etags()
+    } else {
+        tags.to_ctags()
+    };
+    if let Err(x) = out.write_all(ret.as_bytes()).and_then(|_| out.flush()) {
+        eprintln!("{}", x);
+        exit = 1;
+    }
+    exit
+}
+
+// Preprocess the files as one compilation unit, and print the macros which
+// are likely dead, after the errors of the files which fail to preprocess.
+fn run_defines(
+    files: &[PathBuf],
+    includes: &[PathBuf],
+    defines: &[String],
+    output: &Option<PathBuf>,
+) -> i32 {
+    let mut out = match create_output(output) {
+        Some(x) => x,
+        None => return 1,
+    };
+
+    let project = Project {
+        files: files.to_vec(),
+        include_paths: includes.to_vec(),
+        defines: parse_defines(defines),
+        ..Default::default()
+    };
+    let report = project.define_report();
+    let mut exit = if report.errors.is_empty() { 0 } else { 1 };
+    let mut ret = String::new();
+    for diagnostic in report.diagnostics().iter() {
+        ret.push_str(&format!("{}\n", diagnostic));
+        if let Some(location) = diagnostic.location() {
+            ret.push_str(&format!("{}\n", location));
+        }
+    }
+    if let Err(x) = out.write_all(ret.as_bytes()).and_then(|_| out.flush()) {
+        eprintln!("{}", x);
+        exit = 1;
+    }
+    exit
+}
+
+// Collects the edges from each file to each file it includes, without
+// duplicates.
+#[derive(Debug, Default)]
+struct IncludeObserver {
+    edges: Mutex<Vec<(PathBuf, PathBuf)>>,
+}
+
+impl PreprocessObserver for IncludeObserver {
+    fn include_enter(&self, path: &Path, from: &Path, _offset: usize) {
+        let edge = (from.to_path_buf(), path.to_path_buf());
+        let mut edges = self.edges.lock().unwrap();
+        if !edges.contains(&edge) {
+            edges.push(edge);
+        }
+    }
+}
+
+// A path with its spaces escaped for a Makefile.
+fn make_path(path: &Path) -> String {
+    path.display().to_string().replace(' ', "\\ ")
+}
+
+// The macros given as NAME or NAME=TEXT.
+fn parse_defines(defines: &[String]) -> Defines {
+    let mut ret = Defines::new();
+    for define in defines {
+        let mut define = define.splitn(2, '=');
+        let name = String::from(define.next().unwrap());
+        let text = define
+            .next()
+            .map(|x| DefineText::new(String::from(x), None));
+        ret.insert(name.clone(), Some(Define::new(name, vec![], text)));
+    }
+    ret
+}
+
+// The file of `path` or stdout if `None`, or `None` after printing the error
+// if the file can't be created.
+fn create_output(path: &Option<PathBuf>) -> Option<Box<dyn Write>> {
+    match path {
+        Some(path) => match File::create(path) {
+            Ok(x) => Some(Box::new(BufWriter::new(x))),
+            Err(x) => {
+                eprintln!("{}: {}", path.display(), x);
+                None
+            }
+        },
+        None => Some(Box::new(io::stdout())),
+    }
+}
+
+fn report(path: &Path, error: &Error) {
+    let diagnostic = Diagnostic::from(error);
+    eprintln!("{}: {}", path.display(), diagnostic);
+    if let Some(location) = error.location() {
+        eprintln!("{}", location);
+    }
+    for note in &diagnostic.notes {
+        eprintln!("  = note: {}", note);
+    }
+}
+
+// The preprocessed text with a `line directive before each line which doesn't
+// follow on from the line before it in the same file, e.g. at the beginning
+// and the end of an included file or a macro expansion.
+fn line_markers(text: &PreprocessedText) -> String {
+    let lines: Vec<_> = text.text().split_inclusive('\n').collect();
+    let mut positions = Vec::with_capacity(lines.len());
+    let mut pos = 0;
+    for line in &lines {
+        positions.push(pos);
+        pos += line.len();
+    }
+
+    // The offsets of the beginning of each line of each origin file.
+    let mut line_starts: HashMap<&PathBuf, Vec<usize>> = HashMap::new();
+    let mut next = None;
+    let mut ret = String::new();
+    for (line, origin) in lines.iter().zip(text.origins(&positions)) {
+        if let Some((path, offset)) = origin {
+            let starts = line_starts.entry(path).or_insert_with(|| {
+                let text = std::fs::read(path).unwrap_or_default();
+                let newlines = text.iter().enumerate().filter(|(_, x)| **x == b'\n');
+                std::iter::once(0)
+                    .chain(newlines.map(|(i, _)| i + 1))
+                    .collect()
+            });
+            let number = starts.partition_point(|x| *x <= offset);
+            if next != Some((path, number)) {
+                ret.push_str(&format!("`line {} \"{}\" 0\n", number, path.display()));
+            }
+            next = Some((path, number + 1));
+        }
+        ret.push_str(line);
+    }
+    ret
+}
@@ -0,0 +1,76 @@
+use crate::{Diagnostic, Error, Severity};
+use codespan_reporting::diagnostic::{self, Label};
+use codespan_reporting::files::SimpleFiles;
+use codespan_reporting::term::{self, termcolor::NoColor, termcolor::WriteColor};
+use std::io;
+
+impl Diagnostic {
+    /// Write the diagnostic as an annotated snippet of its file, with a
+    /// caret under its text and its notes, reading the file of its origin.
+    /// Only the message is written if the file can't be read.
+    pub fn emit(&self, writer: &mut dyn WriteColor) -> io::Result<()> {
+        let severity = match self.severity {
+            Severity::Note => diagnostic::Severity::Note,
+            Severity::Warning => diagnostic::Severity::Warning,
+            Severity::Error => diagnostic::Severity::Error,
+        };
+        let mut files = SimpleFiles::new();
+        let mut ret = diagnostic::Diagnostic::new(severity)
+            .with_code(self.code.as_str())
+            .with_message(self.message.as_str())
+            .with_notes(self.notes.clone());
+
+        if let Some((path, offset)) = &self.origin {
+            if let Ok(text) = std::fs::read(path) {
+                let text = String::from_utf8_lossy(&text).into_owned();
+                let (begin, end) = span(&text, *offset, self.len);
+                let file = files.add(path.display().to_string(), text);
+                ret = ret.with_labels(vec![Label::primary(file, begin..end)]);
+            }
+        }
+
+        term::emit(writer, &term::Config::default(), &files, &ret)
+            .map_err(|x| io::Error::new(io::ErrorKind::Other, x.to_string()))
+    }
+
+    /// The text written by `emit()` without colors.
+    pub fn render(&self) -> String {
+        let mut writer = NoColor::new(Vec::new());
+        // Writing to a `Vec` doesn't fail.
+        let _ = self.emit(&mut writer);
+        String::from_utf8_lossy(&writer.into_inner()).into_owned()
+    }
+}
+
+impl Error {
+    /// Write the error as an annotated snippet like `Diagnostic::emit()`.
+    pub fn emit(&self, writer: &mut dyn WriteColor) -> io::Result<()> {
+        Diagnostic::from(self).emit(writer)
+    }
+
+    /// The text written by `emit()` without colors.
+    pub fn render(&self) -> String {
+        Diagnostic::from(self).render()
+    }
+}
+
+// The byte range of the text of a diagnostic within `text`. Without a length
+// it is the token at `offset`, or the character there if it is white space.
+fn span(text: &str, offset: usize, len: usize) -> (usize, usize) {
+    let mut begin = offset.min(text.len());
+    while !text.is_char_boundary(begin) {
+        begin -= 1;
+    }
+    let mut end = if len > 0 {
+        (begin + len).min(text.len())
+    } else {
+        let rest = &text[begin..];
+        let token = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let first = rest.chars().next().map_or(0, char::len_utf8);
+        begin + token.max(first)
+    };
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+    (begin, end)
+}
@@ -1,6 +1,10 @@
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+#[cfg(feature = "codespan")]
+mod codespan;
+
 // -----------------------------------------------------------------------------
 
 #[derive(Error, Debug)]
@@ -18,14 +22,72 @@ pub enum Error {
     #[error("File could not be read as UTF8: {0:?}")]
     ReadUtf8(PathBuf),
 
-    #[error("Include error")]
+    /// A file starts with bytes which can't begin a source text, e.g. the
+    /// byte order mark of UTF-16, described by `description`.
+    #[error("File starts with {description}: {path:?}")]
+    LeadingBytes {
+        path: PathBuf,
+        description: &'static str,
+    },
+
+    /// An error in a file included by the `include at `offset` within
+    /// `path`, which is on `line`.
+    #[error("Include error: included from {}:{line}", .path.display())]
     Include {
-        #[from]
+        #[source]
         source: Box<Error>,
+        path: PathBuf,
+        offset: usize,
+        line: usize,
     },
 
-    #[error("Parse error: {0:?}")]
-    Parse(Option<(PathBuf, usize)>),
+    #[error("Include file not found: {name:?}")]
+    IncludeNotFound {
+        name: PathBuf,
+        path: PathBuf,
+        offset: usize,
+        include_paths: Vec<PathBuf>,
+    },
+
+    // Boxed to keep `Error` small, as the preprocessor returns it through
+    // deeply recursive macro expansion.
+    #[error("Parse error: {origin:?}{}", expected_list(.expected))]
+    Parse {
+        origin: Option<(PathBuf, usize)>,
+        /// The line of the preprocessed text where parsing stopped.
+        line: Option<Box<str>>,
+        /// The rules being parsed there, innermost first.
+        rules: Box<[String]>,
+        /// The symbols and keywords which could have come next.
+        expected: Box<[String]>,
+    },
+
+    /// Parentheses, concatenations or statements are nested beyond
+    /// `nest_limit()`.
+    #[error("Nesting too deep: {origin:?}")]
+    NestTooDeep { origin: Option<(PathBuf, usize)> },
+
+    /// Parsing tried more tokens than `work_limit()` allows. The origin is
+    /// where it was parsing when the limit was reached, which is usually
+    /// within the text that made it backtrack.
+    #[error("Parse too complex: {origin:?}")]
+    TooComplex { origin: Option<(PathBuf, usize)> },
+
+    /// A string literal isn't closed before the end of its line. The origin
+    /// is that of its opening quote.
+    #[error("Unterminated string literal: {origin:?}")]
+    UnterminatedString { origin: Option<(PathBuf, usize)> },
+
+    /// A block comment isn't closed before the end of the text. The origin is
+    /// that of its `/*`.
+    #[error("Unterminated block comment: {origin:?}")]
+    UnterminatedComment { origin: Option<(PathBuf, usize)> },
+
+    #[error("SystemVerilog `{construct}` in Verilog: {origin:?}")]
+    SystemVerilog {
+        origin: Option<(PathBuf, usize)>,
+        construct: String,
+    },
 
     #[error("Preprocess error: {0:?}")]
     Preprocess(Option<(PathBuf, usize)>),
@@ -33,8 +95,12 @@ pub enum Error {
     #[error("Define argument not found: {0}")]
     DefineArgNotFound(String),
 
-    #[error("Define not found: {0}")]
-    DefineNotFound(String),
+    #[error("Define not found: {name}")]
+    DefineNotFound {
+        name: String,
+        path: PathBuf,
+        offset: usize,
+    },
 
     #[error("Define must have argument")]
     DefineNoArgs(String), // String is the macro identifier.
@@ -44,4 +110,339 @@ pub enum Error {
 
     #[error("Include line can't have other items")]
     IncludeLine,
+
+    /// A tree serialized by `SyntaxTree::to_bytes()` can't be read back, as
+    /// it is broken or of another version.
+    #[error("Cache error: {0}")]
+    Cache(String),
+}
+
+impl Error {
+    /// The file and the byte offset within it where the error is, if known.
+    /// For an error within an included file, it is that of the included file.
+    pub fn origin(&self) -> Option<(&Path, usize)> {
+        match self {
+            Error::Include { source, .. } => source.origin(),
+            Error::IncludeNotFound { path, offset, .. }
+            | Error::DefineNotFound { path, offset, .. } => Some((path.as_path(), *offset)),
+            Error::Parse { origin, .. }
+            | Error::NestTooDeep { origin }
+            | Error::TooComplex { origin }
+            | Error::UnterminatedString { origin }
+            | Error::UnterminatedComment { origin }
+            | Error::SystemVerilog { origin, .. }
+            | Error::Preprocess(origin) => origin.as_ref().map(|(x, y)| (x.as_path(), *y)),
+            _ => None,
+        }
+    }
+
+    /// The file the error is in, which is also known for an error reading a
+    /// file, e.g. `Error::File`.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::File { path, .. } | Error::ReadUtf8(path) | Error::LeadingBytes { path, .. } => {
+                Some(path.as_path())
+            }
+            _ => self.origin().map(|x| x.0),
+        }
+    }
+
+    /// The `include directives through which the file of the error was
+    /// reached, innermost first, each as the including file and the line of
+    /// the directive.
+    pub fn includes(&self) -> Vec<(&Path, usize)> {
+        let mut ret = Vec::new();
+        let mut error = self;
+        while let Error::Include {
+            source, path, line, ..
+        } = error
+        {
+            ret.push((path.as_path(), *line));
+            error = &**source;
+        }
+        ret.reverse();
+        ret
+    }
+
+    /// A short name of the kind of the error, e.g. `"parse"`, used as the
+    /// code of its `Diagnostic`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Io(_) => "io",
+            Error::File { .. } => "file",
+            Error::ReadUtf8(_) => "read-utf8",
+            Error::LeadingBytes { .. } => "leading-bytes",
+            Error::Include { .. } => "include",
+            Error::IncludeNotFound { .. } => "include-not-found",
+            Error::Parse { .. } => "parse",
+            Error::NestTooDeep { .. } => "nest-too-deep",
+            Error::TooComplex { .. } => "too-complex",
+            Error::UnterminatedString { .. } => "unterminated-string",
+            Error::UnterminatedComment { .. } => "unterminated-comment",
+            Error::SystemVerilog { .. } => "systemverilog",
+            Error::Preprocess(_) => "preprocess",
+            Error::DefineArgNotFound(_) => "define-arg-not-found",
+            Error::DefineNotFound { .. } => "define-not-found",
+            Error::DefineNoArgs(_) => "define-no-args",
+            Error::ExceedRecursiveLimit => "exceed-recursive-limit",
+            Error::IncludeLine => "include-line",
+            Error::Cache(_) => "cache",
+        }
+    }
+
+    /// The location of `origin()`, with the line and column found by reading
+    /// the file again. `None` is returned if the origin isn't known or the
+    /// file can't be read, e.g. for a text not read from a file, whose
+    /// location can be given by `Location::new()`.
+    pub fn location(&self) -> Option<Location> {
+        let (path, offset) = self.origin()?;
+        let text = std::fs::read(path).ok()?;
+        let text = String::from_utf8_lossy(&text);
+        Some(Location::new(path, offset, &text))
+    }
+}
+
+/// A position within a file, with the line containing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    /// Byte offset within the file.
+    pub offset: usize,
+    /// The line number, from 1.
+    pub line: usize,
+    /// The column in characters, from 1.
+    pub column: usize,
+    /// The text of the line, without its line ending.
+    pub source_line: String,
+}
+
+impl Location {
+    /// The location of `offset` within `text`, which is the contents of the
+    /// file at `path`. An offset beyond the text is taken as its end.
+    pub fn new<T: AsRef<Path>>(path: T, offset: usize, text: &str) -> Self {
+        let mut offset = offset.min(text.len());
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let begin = text[..offset].rfind('\n').map_or(0, |x| x + 1);
+        let end = text[offset..].find('\n').map_or(text.len(), |x| offset + x);
+        Location {
+            path: PathBuf::from(path.as_ref()),
+            offset,
+            line: text[..begin].matches('\n').count() + 1,
+            column: text[begin..offset].chars().count() + 1,
+            source_line: String::from(text[begin..end].trim_end_matches('\r')),
+        }
+    }
+}
+
+/// Formats as `path:line:column`, followed by the source line and a caret
+/// under the column.
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let number = self.line.to_string();
+        let margin = " ".repeat(number.len());
+        // Tabs are kept so that the caret lines up with the source line.
+        let indent: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|x| if x == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "{}:{}:{}", self.path.display(), self.line, self.column)?;
+        writeln!(f, "{} |", margin)?;
+        writeln!(f, "{} | {}", number, self.source_line)?;
+        write!(f, "{} | {}^", margin, indent)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// The severity of a `Diagnostic`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in preprocessing, parsing or linting, which doesn't
+/// necessarily stop it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A short name of the kind of the problem, e.g. `"parse"` or the name
+    /// of a lint rule.
+    pub code: String,
+    pub message: String,
+    /// The file and the byte offset within it, if known.
+    pub origin: Option<(PathBuf, usize)>,
+    /// The length in bytes of the text the diagnostic is about, which is 0
+    /// if it is only known where it starts.
+    pub len: usize,
+    /// Notes on the context of the problem, e.g. that it is in an included
+    /// file.
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new<T: Into<String>, U: Into<String>>(severity: Severity, code: T, message: U) -> Self {
+        Diagnostic {
+            severity,
+            code: code.into(),
+            message: message.into(),
+            origin: None,
+            len: 0,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Set the file, the byte offset within it and the length of the text.
+    pub fn with_origin<T: AsRef<Path>>(mut self, path: T, offset: usize, len: usize) -> Self {
+        self.origin = Some((PathBuf::from(path.as_ref()), offset));
+        self.len = len;
+        self
+    }
+
+    /// The location of the origin like `Error::location()`.
+    pub fn location(&self) -> Option<Location> {
+        let (path, offset) = self.origin.as_ref()?;
+        let text = std::fs::read(path).ok()?;
+        let text = String::from_utf8_lossy(&text);
+        Some(Location::new(path, *offset, &text))
+    }
+}
+
+/// Formats as `error[parse]: message`.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// An error as a diagnostic. An `Error::Include` is given as the error in
+/// the included file, with a note of each file it is included from. The
+/// message leaves out the origin, which is that of the diagnostic.
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Self {
+        let includes = error.includes();
+        let mut error = error;
+        while let Error::Include { source, .. } = error {
+            error = &**source;
+        }
+        let message = match error {
+            Error::Parse { expected, .. } => format!("Parse error{}", expected_list(expected)),
+            Error::NestTooDeep { .. } => String::from("Nesting too deep"),
+            Error::TooComplex { .. } => String::from("Parse too complex"),
+            Error::UnterminatedString { .. } => String::from("Unterminated string literal"),
+            Error::UnterminatedComment { .. } => String::from("Unterminated block comment"),
+            Error::SystemVerilog { construct, .. } => {
+                format!("SystemVerilog `{}` in Verilog", construct)
+            }
+            Error::Preprocess(_) => String::from("Preprocess error"),
+            _ => error.to_string(),
+        };
+        let mut ret = Diagnostic::new(Severity::Error, error.code(), message);
+        ret.origin = error.origin().map(|(x, y)| (PathBuf::from(x), y));
+        for (path, line) in includes {
+            ret.notes
+                .push(format!("included from {}:{}", path.display(), line));
+        }
+        ret
+    }
+}
+
+/// The diagnostics of the steps from preprocessing to linting, in the order
+/// they were found.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.entries.push(diagnostic);
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The number of diagnostics of `severity`.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.entries
+            .iter()
+            .filter(|x| x.severity == severity)
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.entries
+    }
+}
+
+impl Extend<Diagnostic> for Diagnostics {
+    fn extend<T: IntoIterator<Item = Diagnostic>>(&mut self, iter: T) {
+        self.entries.extend(iter);
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+fn expected_list(expected: &[String]) -> String {
+    const MAX: usize = 8;
+    if expected.is_empty() {
+        return String::new();
+    }
+    let mut ret: Vec<String> = expected
+        .iter()
+        .take(MAX)
+        .map(|x| format!("`{}`", x))
+        .collect();
+    if expected.len() > MAX {
+        ret.push(format!("{} others", expected.len() - MAX));
+    }
+    format!(", expected {}", ret.join(" or "))
 }
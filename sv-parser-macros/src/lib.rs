@@ -15,12 +15,19 @@ pub fn node_derive(input: TokenStream) -> TokenStream {
 
 fn impl_node(ast: &DeriveInput) -> TokenStream {
     let name = &ast.ident;
+    let name_str = name.to_string();
 
-    let next = match ast.data {
+    let (next, locates_mut, serialize, deserialize) = match ast.data {
         Enum(ref data) => {
             let mut items = quote! {};
-            for v in &data.variants {
+            let mut locates_mut_items = quote! {};
+            let mut serialize_items = quote! {};
+            let mut variants = quote! {};
+            let mut deserialize_items = quote! {};
+            for (i, v) in data.variants.iter().enumerate() {
                 let ident = &v.ident;
+                let ty = &v.fields.iter().next().unwrap().ty;
+                let i = i as u32;
                 let item = quote! {
                     #name::#ident(x) => { x.into() },
                 };
@@ -28,24 +35,110 @@ fn impl_node(ast: &DeriveInput) -> TokenStream {
                     #items
                     #item
                 };
+                locates_mut_items = quote! {
+                    #locates_mut_items
+                    #name::#ident(x) => x.locates_mut(f),
+                };
+                serialize_items = quote! {
+                    #serialize_items
+                    #name::#ident(x) => serializer.serialize_newtype_variant(
+                        stringify!(#name),
+                        #i,
+                        stringify!(#ident),
+                        x,
+                    ),
+                };
+                variants = quote! {
+                    #variants
+                    #ident(#ty),
+                };
+                deserialize_items = quote! {
+                    #deserialize_items
+                    Helper::#ident(x) => #name::#ident(x),
+                };
             }
 
-            quote! {
-                match self {
-                    #items
-                }
-            }
+            (
+                quote! {
+                    match self {
+                        #items
+                    }
+                },
+                quote! {
+                    match self {
+                        #locates_mut_items
+                    }
+                },
+                quote! {
+                    match self {
+                        #serialize_items
+                    }
+                },
+                quote! {
+                    #[derive(serde::Deserialize)]
+                    #[serde(rename = #name_str)]
+                    enum Helper {
+                        #variants
+                    }
+                    let x = <Helper as serde::Deserialize>::deserialize(deserializer)?;
+                    Ok(match x {
+                        #deserialize_items
+                    })
+                },
+            )
         }
-        Struct(_) => {
-            quote! {
-                (&(self.nodes)).into()
-            }
+        Struct(ref data) => {
+            let ty = &data.fields.iter().next().unwrap().ty;
+            (
+                quote! {
+                    (&(self.nodes)).into()
+                },
+                quote! {
+                    self.nodes.locates_mut(f)
+                },
+                // The type is left out of a binary format, which is only read
+                // back into the same type.
+                quote! {
+                    if serializer.is_human_readable() {
+                        use serde::ser::SerializeStruct;
+                        let mut state = serializer.serialize_struct(stringify!(#name), 2)?;
+                        state.serialize_field("type", stringify!(#name))?;
+                        state.serialize_field("nodes", &self.nodes)?;
+                        state.end()
+                    } else {
+                        serde::Serialize::serialize(&self.nodes, serializer)
+                    }
+                },
+                quote! {
+                    if deserializer.is_human_readable() {
+                        #[derive(serde::Deserialize)]
+                        #[serde(rename = #name_str)]
+                        struct Helper {
+                            #[serde(rename = "type")]
+                            _type: String,
+                            nodes: #ty,
+                        }
+                        let x = <Helper as serde::Deserialize>::deserialize(deserializer)?;
+                        Ok(#name { nodes: x.nodes })
+                    } else {
+                        let nodes = <#ty as serde::Deserialize>::deserialize(deserializer)?;
+                        Ok(#name { nodes })
+                    }
+                },
+            )
         }
-        _ => {
+        _ => (
             quote! {
                 vec![].into()
-            }
-        }
+            },
+            quote! {},
+            quote! {
+                serializer.serialize_unit()
+            },
+            quote! {
+                Err(serde::de::Error::custom("unsupported node"))
+            },
+        ),
     };
 
     let gen = quote! {
@@ -53,6 +146,30 @@ fn impl_node(ast: &DeriveInput) -> TokenStream {
             fn next(&'a self) -> RefNodes<'a> {
                 #next
             }
+
+            fn kind(&self) -> NodeKind {
+                NodeKind::#name
+            }
+        }
+
+        impl LocatesMut for #name {
+            fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+                #locates_mut
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for #name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                #serialize
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for #name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                #deserialize
+            }
         }
 
         impl<'a> From<&'a #name> for RefNodes<'a> {
@@ -88,7 +205,7 @@ fn impl_node(ast: &DeriveInput) -> TokenStream {
                     match x {
                         RefNode::Locate(x) => if let Some(loc) = locate {
                             assert_eq!(x.offset, loc.offset + loc.len);
-                            locate = Some(Locate { offset: loc.offset, line: loc.line, len: loc.len + x.len });
+                            locate = Some(Locate { offset: loc.offset, line: loc.line, column: loc.column, len: loc.len + x.len });
                         } else {
                             locate = Some(*x);
                         },
@@ -127,8 +244,15 @@ fn impl_any_node(ast: &DeriveInput) -> TokenStream {
 
     let mut try_froms = quote! {};
     let mut from_items = quote! {};
-    for v in &data.variants {
+    let mut locates_mut_items = quote! {};
+    let mut serialize_items = quote! {};
+    let mut serialize_variant_items = quote! {};
+    let mut variants = quote! {};
+    let mut deserialize_items = quote! {};
+    for (i, v) in data.variants.iter().enumerate() {
         let ident = &v.ident;
+        let ty = &v.fields.iter().next().unwrap().ty;
+        let i = i as u32;
 
         try_froms = quote! {
             #try_froms
@@ -147,11 +271,81 @@ fn impl_any_node(ast: &DeriveInput) -> TokenStream {
             #from_items
             AnyNode::#ident(x) => RefNode::#ident(&x),
         };
+
+        locates_mut_items = quote! {
+            #locates_mut_items
+            AnyNode::#ident(x) => x.locates_mut(f),
+        };
+
+        serialize_items = quote! {
+            #serialize_items
+            AnyNode::#ident(x) => serde::Serialize::serialize(x, serializer),
+        };
+
+        serialize_variant_items = quote! {
+            #serialize_variant_items
+            AnyNode::#ident(x) => serializer.serialize_newtype_variant(
+                "AnyNode",
+                #i,
+                stringify!(#ident),
+                x,
+            ),
+        };
+
+        variants = quote! {
+            #variants
+            #ident(#ty),
+        };
+
+        deserialize_items = quote! {
+            #deserialize_items
+            Helper::#ident(x) => AnyNode::#ident(x),
+        };
     }
 
     let gen = quote! {
         #try_froms
 
+        impl LocatesMut for AnyNode {
+            fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+                match self {
+                    #locates_mut_items
+                }
+            }
+        }
+
+        // A human readable format has only the node, as the type of a struct
+        // is in its fields, and can't be read back into an `AnyNode`.
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for AnyNode {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if serializer.is_human_readable() {
+                    match self {
+                        #serialize_items
+                    }
+                } else {
+                    match self {
+                        #serialize_variant_items
+                    }
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for AnyNode {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                #[derive(serde::Deserialize)]
+                #[serde(rename = "AnyNode")]
+                enum Helper {
+                    #variants
+                }
+                let x = <Helper as serde::Deserialize>::deserialize(deserializer)?;
+                Ok(match x {
+                    #deserialize_items
+                })
+            }
+        }
+
         impl<'a> From<&'a AnyNode> for RefNode<'a>  {
             fn from(x: &'a AnyNode) -> Self {
                 match x {
@@ -177,12 +371,30 @@ fn impl_ref_node(ast: &DeriveInput) -> TokenStream {
 
     let mut next_items = quote! {};
     let mut into_iter_items = quote! {};
-    for v in &data.variants {
+    let mut id_items = quote! {};
+    let mut try_froms = quote! {};
+    for (i, v) in data.variants.iter().enumerate() {
         let ident = &v.ident;
+        try_froms = quote! {
+            #try_froms
+            impl<'a> TryFrom<RefNode<'a>> for &'a #ident {
+                type Error = ();
+                fn try_from(x: RefNode<'a>) -> Result<Self, Self::Error> {
+                    match x {
+                        RefNode::#ident(x) => Ok(x),
+                        _ => Err(()),
+                    }
+                }
+            }
+        };
         next_items = quote! {
             #next_items
             RefNode::#ident(x) => x.next(),
         };
+        id_items = quote! {
+            #id_items
+            RefNode::#ident(x) => (#i, *x as *const _ as *const () as usize),
+        };
         into_iter_items = quote! {
             #into_iter_items
             RefNode::#ident(x) => x.into_iter(),
@@ -191,12 +403,22 @@ fn impl_ref_node(ast: &DeriveInput) -> TokenStream {
 
     let name = &ast.ident;
     let gen = quote! {
+        #try_froms
+
         impl<'a> #name<'a> {
             fn next(&self) -> RefNodes<'a> {
                 match self {
                     #next_items
                 }
             }
+
+            /// Return the variant index and address of the referenced node.
+            /// Unlike `==`, this distinguishes nodes which have equal contents.
+            pub fn id(&self) -> (usize, usize) {
+                match self {
+                    #id_items
+                }
+            }
         }
 
         impl<'a> IntoIterator for #name<'a> {
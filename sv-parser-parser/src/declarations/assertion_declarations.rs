@@ -302,26 +302,27 @@ pub(crate) fn property_spec(s: Span) -> IResult<Span, PropertySpec> {
     Ok((s, PropertySpec { nodes: (a, b, c) }))
 }
 
+// Property operators follow the precedence of IEEE1800-2017 Table 16-3. The
+// operators without a left operand and the lowest precedence take the longest
+// property_expr to their right, so they are tried first. A sequence followed
+// by an implication takes the whole sequence_expr as its antecedent.
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn property_expr(s: Span) -> IResult<Span, PropertyExpr> {
     alt((
-        alt((
-            property_expr_binary_property,
-            property_expr_binary_sequence,
-            map(terminated(sequence_expr, peek(not(symbol("(")))), |x| {
-                PropertyExpr::SequenceExpr(Box::new(x))
-            }),
-            property_expr_strong,
-            property_expr_weak,
-            property_expr_not,
-            property_expr_paren,
-        )),
+        property_expr_clocking_event,
+        property_expr_binary_sequence,
+        property_expr_binary_property,
+    ))(s)
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn property_expr_operand(s: Span) -> IResult<Span, PropertyExpr> {
+    alt((
         alt((
             property_expr_if,
             property_expr_case,
-            property_expr_nexttime,
-            property_expr_s_nexttime,
             property_expr_always,
             property_expr_s_always,
             property_expr_eventually,
@@ -330,10 +331,25 @@ pub(crate) fn property_expr(s: Span) -> IResult<Span, PropertyExpr> {
             property_expr_reject_on,
             property_expr_sync_accept_on,
             property_expr_sync_reject_on,
+            property_expr_clocking_event,
+        )),
+        alt((
+            property_expr_not,
+            property_expr_nexttime,
+            property_expr_s_nexttime,
+            property_expr_strong,
+            property_expr_weak,
+            map(
+                terminated(
+                    sequence_expr_binary(PRECEDENCE_INTERSECT),
+                    peek(not(symbol("("))),
+                ),
+                |x| PropertyExpr::SequenceExpr(Box::new(x)),
+            ),
+            property_expr_paren,
             map(property_instance, |x| {
                 PropertyExpr::PropertyInstance(Box::new(x))
             }),
-            property_expr_clocking_event,
         )),
     ))(s)
 }
@@ -356,7 +372,7 @@ pub(crate) fn property_expr_weak(s: Span) -> IResult<Span, PropertyExpr> {
     let (s, b) = paren(sequence_expr)(s)?;
     Ok((
         s,
-        PropertyExpr::Strong(Box::new(PropertyExprStrong { nodes: (a, b) })),
+        PropertyExpr::Weak(Box::new(PropertyExprWeak { nodes: (a, b) })),
     ))
 }
 
@@ -374,33 +390,52 @@ pub(crate) fn property_expr_paren(s: Span) -> IResult<Span, PropertyExpr> {
 #[packrat_parser]
 pub(crate) fn property_expr_not(s: Span) -> IResult<Span, PropertyExpr> {
     let (s, a) = keyword("not")(s)?;
-    let (s, b) = property_expr(s)?;
+    let (s, b) = property_expr_operand(s)?;
     Ok((
         s,
         PropertyExpr::Not(Box::new(PropertyExprNot { nodes: (a, b) })),
     ))
 }
 
+// The precedence of the property `or`. The operators below it associate right
+// to left.
+pub(crate) const PRECEDENCE_PROPERTY_OR: u8 = 3;
+
+#[tracable_parser]
+pub(crate) fn property_binary_operator(s: Span) -> IResult<Span, (Keyword, u8)> {
+    alt((
+        map(keyword("and"), |x| (x, 4)),
+        map(keyword("or"), |x| (x, PRECEDENCE_PROPERTY_OR)),
+        map(keyword("iff"), |x| (x, 2)),
+        map(
+            alt((
+                keyword("until_with"),
+                keyword("until"),
+                keyword("s_until_with"),
+                keyword("s_until"),
+                keyword("implies"),
+            )),
+            |x| (x, 1),
+        ),
+    ))(s)
+}
+
 #[recursive_parser]
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn property_expr_binary_property(s: Span) -> IResult<Span, PropertyExpr> {
-    let (s, a) = property_expr(s)?;
-    let (s, b) = alt((
-        keyword("or"),
-        keyword("and"),
-        keyword("until_with"),
-        keyword("until"),
-        keyword("s_until_with"),
-        keyword("s_until"),
-        keyword("implies"),
-        keyword("iff"),
-    ))(s)?;
-    let (s, c) = property_expr(s)?;
-    Ok((
-        s,
-        PropertyExpr::BinaryProperty(Box::new(PropertyExprBinaryProperty { nodes: (a, b, c) })),
-    ))
+    let (s, a) = property_expr_operand(s)?;
+    let (s, b) = many0(pair(property_binary_operator, property_expr_operand))(s)?;
+    let b = b.into_iter().map(|((x, p), y)| (p, x, y)).collect();
+    let a = fold_binary(
+        a,
+        b,
+        |p| p < PRECEDENCE_PROPERTY_OR,
+        |a, b, c| {
+            PropertyExpr::BinaryProperty(Box::new(PropertyExprBinaryProperty { nodes: (a, b, c) }))
+        },
+    );
+    Ok((s, a))
 }
 
 #[recursive_parser]
@@ -450,7 +485,7 @@ pub(crate) fn property_expr_case(s: Span) -> IResult<Span, PropertyExpr> {
 pub(crate) fn property_expr_nexttime(s: Span) -> IResult<Span, PropertyExpr> {
     let (s, a) = keyword("nexttime")(s)?;
     let (s, b) = opt(bracket(constant_expression))(s)?;
-    let (s, c) = property_expr(s)?;
+    let (s, c) = property_expr_operand(s)?;
     Ok((
         s,
         PropertyExpr::Nexttime(Box::new(PropertyExprNexttime { nodes: (a, b, c) })),
@@ -462,7 +497,7 @@ pub(crate) fn property_expr_nexttime(s: Span) -> IResult<Span, PropertyExpr> {
 pub(crate) fn property_expr_s_nexttime(s: Span) -> IResult<Span, PropertyExpr> {
     let (s, a) = keyword("s_nexttime")(s)?;
     let (s, b) = opt(bracket(constant_expression))(s)?;
-    let (s, c) = property_expr(s)?;
+    let (s, c) = property_expr_operand(s)?;
     Ok((
         s,
         PropertyExpr::SNexttime(Box::new(PropertyExprSNexttime { nodes: (a, b, c) })),
@@ -705,14 +740,64 @@ pub(crate) fn data_type_or_implicit_sequence_formal_type(
     ))(s)
 }
 
+// Sequence operators follow the precedence of IEEE1800-2017 Table 16-3:
+// cycle delays bind tightest, then throughout, within, intersect, and, or.
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn sequence_expr(s: Span) -> IResult<Span, SequenceExpr> {
+    sequence_expr_binary(PRECEDENCE_SEQUENCE_OR)(s)
+}
+
+// The precedences of the binary sequence operators.
+pub(crate) const PRECEDENCE_SEQUENCE_OR: u8 = 1;
+pub(crate) const PRECEDENCE_INTERSECT: u8 = 3;
+
+#[tracable_parser]
+pub(crate) fn sequence_binary_operator(s: Span) -> IResult<Span, (Keyword, u8)> {
+    alt((
+        map(keyword("within"), |x| (x, 4)),
+        map(keyword("intersect"), |x| (x, PRECEDENCE_INTERSECT)),
+        map(keyword("and"), |x| (x, 2)),
+        map(keyword("or"), |x| (x, PRECEDENCE_SEQUENCE_OR)),
+    ))(s)
+}
+
+// Parse a sequence_expr whose binary operators have at least the precedence
+// `min`. All of them associate left to right.
+pub(crate) fn sequence_expr_binary<'a>(
+    min: u8,
+) -> impl Fn(Span<'a>) -> IResult<Span<'a>, SequenceExpr> {
+    move |s: Span<'a>| {
+        let (s, a) = sequence_expr_operand(s)?;
+        let (s, b) = many0(pair(
+            verify(sequence_binary_operator, |x: &(Keyword, u8)| x.1 >= min),
+            sequence_expr_operand,
+        ))(s)?;
+        let b = b.into_iter().map(|((x, p), y)| (p, x, y)).collect();
+        let a = fold_binary(
+            a,
+            b,
+            |_| false,
+            |a, b, c| SequenceExpr::Binary(Box::new(SequenceExprBinary { nodes: (a, b, c) })),
+        );
+        Ok((s, a))
+    }
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn sequence_expr_operand(s: Span) -> IResult<Span, SequenceExpr> {
     alt((
-        sequence_expr_binary,
-        sequence_expr_expr_cycle_delay_expr,
-        sequence_expr_cycle_delay_expr,
         sequence_expr_throughout,
+        sequence_expr_cycle_delay_expr,
+        sequence_expr_expr_cycle_delay_expr,
+    ))(s)
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn sequence_expr_primary(s: Span) -> IResult<Span, SequenceExpr> {
+    alt((
         terminated(sequence_expr_expression, peek(not(symbol("(")))),
         sequence_expr_instance,
         sequence_expr_paren,
@@ -725,28 +810,33 @@ pub(crate) fn sequence_expr(s: Span) -> IResult<Span, SequenceExpr> {
 #[packrat_parser]
 pub(crate) fn sequence_expr_cycle_delay_expr(s: Span) -> IResult<Span, SequenceExpr> {
     let (s, a) = cycle_delay_range(s)?;
-    let (s, b) = sequence_expr(s)?;
-    let (s, c) = many0(pair(cycle_delay_range, sequence_expr))(s)?;
+    let (s, b) = sequence_expr_primary(s)?;
+    let (s, c) = many0(pair(cycle_delay_range, sequence_expr_primary))(s)?;
     Ok((
         s,
         SequenceExpr::CycleDelayExpr(Box::new(SequenceExprCycleDelayExpr { nodes: (a, b, c) })),
     ))
 }
 
+// A sequence_expr_primary, which is followed by cycle delays if it is the
+// first operand of them.
 #[recursive_parser]
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn sequence_expr_expr_cycle_delay_expr(s: Span) -> IResult<Span, SequenceExpr> {
-    let (s, a) = sequence_expr(s)?;
-    let (s, b) = cycle_delay_range(s)?;
-    let (s, c) = sequence_expr(s)?;
-    let (s, d) = many0(pair(cycle_delay_range, sequence_expr))(s)?;
-    Ok((
-        s,
-        SequenceExpr::ExprCycleDelayExpr(Box::new(SequenceExprExprCycleDelayExpr {
-            nodes: (a, b, c, d),
-        })),
-    ))
+    let (s, a) = sequence_expr_primary(s)?;
+    let (s, b) = many0(pair(cycle_delay_range, sequence_expr_primary))(s)?;
+    let mut b = b.into_iter();
+    if let Some((c, d)) = b.next() {
+        Ok((
+            s,
+            SequenceExpr::ExprCycleDelayExpr(Box::new(SequenceExprExprCycleDelayExpr {
+                nodes: (a, c, d, b.collect()),
+            })),
+        ))
+    } else {
+        Ok((s, a))
+    }
 }
 
 #[recursive_parser]
@@ -786,24 +876,6 @@ pub(crate) fn sequence_expr_paren(s: Span) -> IResult<Span, SequenceExpr> {
     ))
 }
 
-#[recursive_parser]
-#[tracable_parser]
-#[packrat_parser]
-pub(crate) fn sequence_expr_binary(s: Span) -> IResult<Span, SequenceExpr> {
-    let (s, a) = sequence_expr(s)?;
-    let (s, b) = alt((
-        keyword("and"),
-        keyword("intersect"),
-        keyword("or"),
-        keyword("within"),
-    ))(s)?;
-    let (s, c) = sequence_expr(s)?;
-    Ok((
-        s,
-        SequenceExpr::Binary(Box::new(SequenceExprBinary { nodes: (a, b, c) })),
-    ))
-}
-
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn sequence_expr_first_match(s: Span) -> IResult<Span, SequenceExpr> {
@@ -824,7 +896,7 @@ pub(crate) fn sequence_expr_first_match(s: Span) -> IResult<Span, SequenceExpr>
 pub(crate) fn sequence_expr_throughout(s: Span) -> IResult<Span, SequenceExpr> {
     let (s, a) = expression_or_dist(s)?;
     let (s, b) = keyword("throughout")(s)?;
-    let (s, c) = sequence_expr(s)?;
+    let (s, c) = sequence_expr_operand(s)?;
     Ok((
         s,
         SequenceExpr::Throughout(Box::new(SequenceExprThroughout { nodes: (a, b, c) })),
@@ -635,13 +635,36 @@ pub(crate) fn bins_selection(s: Span) -> IResult<Span, BinsSelection> {
     ))
 }
 
+// `with` binds to its operand, and `&&` binds tighter than `||`. Both
+// associate left to right.
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn select_expression(s: Span) -> IResult<Span, SelectExpression> {
-    alt((
-        select_expression_and,
-        select_expression_or,
+    let (s, a) = select_expression_with(s)?;
+    let (s, b) = many0(pair(
+        alt((map(symbol("&&"), |x| (x, 2)), map(symbol("||"), |x| (x, 1)))),
         select_expression_with,
+    ))(s)?;
+    let b = b.into_iter().map(|((x, p), y)| (p, (x, p), y)).collect();
+    let a = fold_binary(
+        a,
+        b,
+        |_| false,
+        |a, (b, p), c| {
+            if p == 2 {
+                SelectExpression::And(Box::new(SelectExpressionAnd { nodes: (a, b, c) }))
+            } else {
+                SelectExpression::Or(Box::new(SelectExpressionOr { nodes: (a, b, c) }))
+            }
+        },
+    );
+    Ok((s, a))
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn select_expression_operand(s: Span) -> IResult<Span, SelectExpression> {
+    alt((
         map(select_condition, |x| {
             SelectExpression::SelectCondition(Box::new(x))
         }),
@@ -665,32 +688,6 @@ pub(crate) fn select_expression_not(s: Span) -> IResult<Span, SelectExpression>
     ))
 }
 
-#[recursive_parser]
-#[tracable_parser]
-#[packrat_parser]
-pub(crate) fn select_expression_and(s: Span) -> IResult<Span, SelectExpression> {
-    let (s, a) = select_expression(s)?;
-    let (s, b) = symbol("&&")(s)?;
-    let (s, c) = select_expression(s)?;
-    Ok((
-        s,
-        SelectExpression::And(Box::new(SelectExpressionAnd { nodes: (a, b, c) })),
-    ))
-}
-
-#[recursive_parser]
-#[tracable_parser]
-#[packrat_parser]
-pub(crate) fn select_expression_or(s: Span) -> IResult<Span, SelectExpression> {
-    let (s, a) = select_expression(s)?;
-    let (s, b) = symbol("||")(s)?;
-    let (s, c) = select_expression(s)?;
-    Ok((
-        s,
-        SelectExpression::Or(Box::new(SelectExpressionOr { nodes: (a, b, c) })),
-    ))
-}
-
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn select_expression_paren(s: Span) -> IResult<Span, SelectExpression> {
@@ -701,20 +698,23 @@ pub(crate) fn select_expression_paren(s: Span) -> IResult<Span, SelectExpression
     ))
 }
 
+// A select_expression_operand followed by any number of `with` clauses.
 #[recursive_parser]
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn select_expression_with(s: Span) -> IResult<Span, SelectExpression> {
-    let (s, a) = select_expression(s)?;
-    let (s, b) = keyword("with")(s)?;
-    let (s, c) = paren(with_covergroup_expression)(s)?;
-    let (s, d) = opt(pair(keyword("matches"), integer_covergroup_expression))(s)?;
-    Ok((
-        s,
+    let (s, a) = select_expression_operand(s)?;
+    let (s, b) = many0(tuple((
+        keyword("with"),
+        paren(with_covergroup_expression),
+        opt(pair(keyword("matches"), integer_covergroup_expression)),
+    )))(s)?;
+    let a = b.into_iter().fold(a, |a, (b, c, d)| {
         SelectExpression::With(Box::new(SelectExpressionWith {
             nodes: (a, b, c, d),
-        })),
-    ))
+        }))
+    });
+    Ok((s, a))
 }
 
 #[recursive_parser]
@@ -33,10 +33,10 @@ pub(crate) fn param_assignment(s: Span) -> IResult<Span, ParamAssignment> {
 #[packrat_parser]
 pub(crate) fn specparam_assignment(s: Span) -> IResult<Span, SpecparamAssignment> {
     alt((
-        specparam_assignment_mintypmax,
         map(pulse_control_specparam, |x| {
             SpecparamAssignment::PulseControlSpecparam(Box::new(x))
         }),
+        specparam_assignment_mintypmax,
     ))(s)
 }
 
@@ -94,7 +94,7 @@ pub(crate) fn pulse_control_specparam_with_descriptor(
     s: Span,
 ) -> IResult<Span, PulseControlSpecparam> {
     let (s, a) = symbol("PATHPULSE$")(s)?;
-    let (s, b) = specify_input_terminal_descriptor(s)?;
+    let (s, b) = pulse_control_input_terminal_descriptor(s)?;
     let (s, c) = symbol("$")(s)?;
     let (s, d) = specify_output_terminal_descriptor(s)?;
     let (s, e) = symbol("=")(s)?;
@@ -110,6 +110,26 @@ pub(crate) fn pulse_control_specparam_with_descriptor(
     ))
 }
 
+// The input terminal of a pulse control specparam ends at `$`, which would
+// otherwise continue a simple identifier.
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn pulse_control_input_terminal_descriptor(
+    s: Span,
+) -> IResult<Span, SpecifyInputTerminalDescriptor> {
+    let (s, a) = alt((
+        map(escaped_identifier, |x| {
+            Identifier::EscapedIdentifier(Box::new(x))
+        }),
+        map(ws(c_identifier_impl), |x| {
+            Identifier::SimpleIdentifier(Box::new(SimpleIdentifier { nodes: x }))
+        }),
+    ))(s)?;
+    let (s, b) = opt(bracket(constant_range_expression))(s)?;
+    let a = InputIdentifier::InputPortIdentifier(Box::new(InputPortIdentifier { nodes: (a,) }));
+    Ok((s, SpecifyInputTerminalDescriptor { nodes: (a, b) }))
+}
+
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn error_limit_value(s: Span) -> IResult<Span, ErrorLimitValue> {
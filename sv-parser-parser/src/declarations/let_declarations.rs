@@ -93,7 +93,10 @@ pub(crate) fn let_list_of_arguments(s: Span) -> IResult<Span, LetListOfArguments
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn let_list_of_arguments_ordered(s: Span) -> IResult<Span, LetListOfArguments> {
-    let (s, a) = list(symbol(","), opt(let_actual_arg))(s)?;
+    let (s, a) = list(
+        terminated(symbol(","), peek(not(symbol(".")))),
+        opt(let_actual_arg),
+    )(s)?;
     let (s, b) = many0(tuple((
         symbol(","),
         symbol("."),
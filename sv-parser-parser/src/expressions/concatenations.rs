@@ -5,14 +5,14 @@ use crate::*;
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn concatenation(s: Span) -> IResult<Span, Concatenation> {
-    let (s, a) = brace(list(symbol(","), expression))(s)?;
+    let (s, a) = brace(nest(list(symbol(","), expression)))(s)?;
     Ok((s, Concatenation { nodes: (a,) }))
 }
 
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn constant_concatenation(s: Span) -> IResult<Span, ConstantConcatenation> {
-    let (s, a) = brace(list(symbol(","), constant_expression))(s)?;
+    let (s, a) = brace(nest(list(symbol(","), constant_expression)))(s)?;
     Ok((s, ConstantConcatenation { nodes: (a,) }))
 }
 
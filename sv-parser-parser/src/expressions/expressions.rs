@@ -78,8 +78,8 @@ pub(crate) fn expression_or_cond_pattern_ternary(
 #[packrat_parser]
 pub(crate) fn constant_expression(s: Span) -> IResult<Span, ConstantExpression> {
     alt((
-        constant_expression_ternary,
         constant_expression_binary,
+        constant_expression_ternary,
         constant_expression_unary,
         map(constant_primary, |x| {
             ConstantExpression::ConstantPrimary(Box::new(x))
@@ -99,20 +99,51 @@ pub(crate) fn constant_expression_unary(s: Span) -> IResult<Span, ConstantExpres
     ))
 }
 
+// Parse operands separated by binary operators as expression_binary does.
 #[recursive_parser]
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn constant_expression_binary(s: Span) -> IResult<Span, ConstantExpression> {
-    let (s, a) = constant_expression(s)?;
-    let (s, b) = binary_operator(s)?;
-    let (s, c) = many0(attribute_instance)(s)?;
-    let (s, d) = constant_expression(s)?;
-    Ok((
-        s,
+    let (s, a) = constant_expression_operand(s)?;
+    let (s, b) = many1(tuple((
+        binary_operator_precedence,
+        many0(attribute_instance),
+        constant_expression_operand,
+    )))(s)?;
+    let b = b.into_iter().map(|((x, p), y, z)| (p, (x, y), z)).collect();
+    let a = fold_binary(a, b, is_implication, |a, (b, c), d| {
         ConstantExpression::Binary(Box::new(ConstantExpressionBinary {
             nodes: (a, b, c, d),
-        })),
-    ))
+        }))
+    });
+    let (s, b) = opt(tuple((
+        symbol("?"),
+        many0(attribute_instance),
+        constant_expression,
+        symbol(":"),
+        constant_expression,
+    )))(s)?;
+    if let Some((b, c, d, e, f)) = b {
+        Ok((
+            s,
+            ConstantExpression::Ternary(Box::new(ConstantExpressionTernary {
+                nodes: (a, b, c, d, e, f),
+            })),
+        ))
+    } else {
+        Ok((s, a))
+    }
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn constant_expression_operand(s: Span) -> IResult<Span, ConstantExpression> {
+    alt((
+        constant_expression_unary,
+        map(constant_primary, |x| {
+            ConstantExpression::ConstantPrimary(Box::new(x))
+        }),
+    ))(s)
 }
 
 #[recursive_parser]
@@ -285,20 +316,163 @@ pub(crate) fn expression_operator_assignment(s: Span) -> IResult<Span, Expressio
     ))
 }
 
+// Parse operands separated by binary operators or followed by `inside`, and
+// fold them into a tree by operator precedence. A conditional operator
+// following them takes the whole tree as its predicate.
 #[recursive_parser]
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn expression_binary(s: Span) -> IResult<Span, Expression> {
-    let (s, a) = expression(s)?;
-    let (s, b) = binary_operator(s)?;
-    let (s, c) = many0(attribute_instance)(s)?;
-    let (s, d) = expression(s)?;
-    Ok((
-        s,
-        Expression::Binary(Box::new(ExpressionBinary {
-            nodes: (a, b, c, d),
+    let (s, a) = expression_operand(s)?;
+    let (s, b) = many1(expression_operator)(s)?;
+    let a = fold_operators(a, b, is_implication, fold_expression);
+    let (s, b) = opt(tuple((
+        symbol("?"),
+        many0(attribute_instance),
+        expression,
+        symbol(":"),
+        expression,
+    )))(s)?;
+    if let Some((b, c, d, e, f)) = b {
+        let a = CondPredicate {
+            nodes: (List {
+                nodes: (ExpressionOrCondPattern::Expression(Box::new(a)), vec![]),
+            },),
+        };
+        Ok((
+            s,
+            Expression::ConditionalExpression(Box::new(ConditionalExpression {
+                nodes: (a, b, c, d, e, f),
+            })),
+        ))
+    } else {
+        Ok((s, a))
+    }
+}
+
+// Parse an expression whose binary operators all bind tighter than `->`, so
+// that a following `->` is left to the caller, as in a constraint implication.
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn expression_without_implication(s: Span) -> IResult<Span, Expression> {
+    let (s, a) = expression_operand(s)?;
+    let (s, b) = many0(verify(expression_operator, |x| !is_implication(x.0)))(s)?;
+    let a = fold_operators(a, b, is_implication, fold_expression);
+    Ok((s, a))
+}
+
+// An operator in a chain of expression_binary, with what follows it.
+pub(crate) enum ChainOperator {
+    Binary(BinaryOperator, Vec<AttributeInstance>),
+    Inside(Keyword, Brace<OpenRangeList>),
+}
+
+// Parse an operator after an operand, with its precedence: a binary operator
+// with its right operand, or `inside` with its range list and no operand.
+// `inside` has the precedence of the relational operators.
+#[tracable_parser]
+pub(crate) fn expression_operator(
+    s: Span,
+) -> IResult<Span, (u8, ChainOperator, Option<Expression>)> {
+    alt((
+        map(
+            tuple((
+                binary_operator_precedence,
+                many0(attribute_instance),
+                expression_operand,
+            )),
+            |((x, p), y, z)| (p, ChainOperator::Binary(x, y), Some(z)),
+        ),
+        map(pair(keyword("inside"), brace(open_range_list)), |(x, y)| {
+            (PRECEDENCE_RELATIONAL, ChainOperator::Inside(x, y), None)
+        }),
+    ))(s)
+}
+
+fn fold_expression(a: Expression, b: ChainOperator, c: Option<Expression>) -> Expression {
+    match b {
+        ChainOperator::Binary(b, d) => Expression::Binary(Box::new(ExpressionBinary {
+            nodes: (a, b, d, c.unwrap()),
         })),
-    ))
+        ChainOperator::Inside(b, d) => {
+            Expression::InsideExpression(Box::new(InsideExpression { nodes: (a, b, d) }))
+        }
+    }
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn expression_operand(s: Span) -> IResult<Span, Expression> {
+    alt((
+        expression_unary,
+        map(inc_or_dec_expression, |x| {
+            Expression::IncOrDecExpression(Box::new(x))
+        }),
+        expression_operator_assignment,
+        map(tagged_union_expression, |x| {
+            Expression::TaggedUnionExpression(Box::new(x))
+        }),
+        map(primary, |x| Expression::Primary(Box::new(x))),
+    ))(s)
+}
+
+// Fold `a` and the following operators and operands into a tree, where each
+// operator is given with its precedence. Operators of the same precedence
+// associate left to right, unless `right` holds for that precedence.
+pub(crate) fn fold_binary<T, O, F>(a: T, b: Vec<(u8, O, T)>, right: fn(u8) -> bool, mut f: F) -> T
+where
+    F: FnMut(T, O, T) -> T,
+{
+    let b = b.into_iter().map(|(p, x, y)| (p, x, Some(y))).collect();
+    fold_operators(a, b, right, |a, x, d| f(a, x, d.unwrap()))
+}
+
+// Fold as fold_binary does, where an operator without an operand is a postfix
+// operator, applied to the tree before it of the operators binding tighter.
+pub(crate) fn fold_operators<T, O, F>(
+    a: T,
+    b: Vec<(u8, O, Option<T>)>,
+    right: fn(u8) -> bool,
+    mut f: F,
+) -> T
+where
+    F: FnMut(T, O, Option<T>) -> T,
+{
+    fn reduce<T, O, F>(operands: &mut Vec<T>, operators: &mut Vec<(u8, O)>, f: &mut F)
+    where
+        F: FnMut(T, O, Option<T>) -> T,
+    {
+        let (_, x) = operators.pop().unwrap();
+        let d = operands.pop().unwrap();
+        let a = operands.pop().unwrap();
+        operands.push(f(a, x, Some(d)));
+    }
+
+    let mut operands = vec![a];
+    let mut operators: Vec<(u8, O)> = Vec::new();
+    for (p, x, y) in b {
+        while let Some(&(q, _)) = operators.last() {
+            if q > p || (q == p && !right(p)) {
+                reduce(&mut operands, &mut operators, &mut f);
+            } else {
+                break;
+            }
+        }
+        match y {
+            Some(y) => {
+                operators.push((p, x));
+                operands.push(y);
+            }
+            None => {
+                let a = operands.pop().unwrap();
+                operands.push(f(a, x, None));
+            }
+        }
+    }
+    while !operators.is_empty() {
+        reduce(&mut operands, &mut operators, &mut f);
+    }
+    operands.pop().unwrap()
 }
 
 #[tracable_parser]
@@ -62,6 +62,42 @@ pub(crate) fn binary_operator(s: Span) -> IResult<Span, BinaryOperator> {
     Ok((s, BinaryOperator { nodes: (a,) }))
 }
 
+// The precedence of implication and equivalence, the lowest of the binary
+// operators.
+pub(crate) const PRECEDENCE_IMPLICATION: u8 = 0;
+
+// The precedence of the relational operators, which `inside` shares.
+pub(crate) const PRECEDENCE_RELATIONAL: u8 = 7;
+
+// Implication and equivalence associate right to left.
+pub(crate) fn is_implication(p: u8) -> bool {
+    p == PRECEDENCE_IMPLICATION
+}
+
+// Parse a binary operator along with its precedence, which is higher for the
+// operators binding tighter. `&&&` separates the parts of a cond_predicate, so
+// it isn't taken as `&&`.
+#[tracable_parser]
+pub(crate) fn binary_operator_precedence(s: Span) -> IResult<Span, (BinaryOperator, u8)> {
+    let (s, _) = not(tag("&&&"))(s)?;
+    let (t, a) = binary_operator(s)?;
+    let p = match &s.fragment()[..a.nodes.0.nodes.0.len] {
+        "**" => 11,
+        "*" | "/" | "%" => 10,
+        "+" | "-" => 9,
+        "<<" | ">>" | "<<<" | ">>>" => 8,
+        "<" | "<=" | ">" | ">=" => PRECEDENCE_RELATIONAL,
+        "==" | "!=" | "===" | "!==" | "==?" | "!=?" => 6,
+        "&" => 5,
+        "^" | "^~" | "~^" => 4,
+        "|" => 3,
+        "&&" => 2,
+        "||" => 1,
+        _ => PRECEDENCE_IMPLICATION,
+    };
+    Ok((t, (a, p)))
+}
+
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn inc_or_dec_operator(s: Span) -> IResult<Span, IncOrDecOperator> {
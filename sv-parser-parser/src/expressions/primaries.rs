@@ -148,7 +148,7 @@ pub(crate) fn constant_primary_multiple_concatenation(s: Span) -> IResult<Span,
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn constant_primary_mintypmax_expression(s: Span) -> IResult<Span, ConstantPrimary> {
-    let (s, a) = paren(constant_mintypmax_expression)(s)?;
+    let (s, a) = paren(nest(constant_mintypmax_expression))(s)?;
     Ok((
         s,
         ConstantPrimary::MintypmaxExpression(Box::new(ConstantPrimaryMintypmaxExpression {
@@ -263,7 +263,7 @@ pub(crate) fn primary_multiple_concatenation(s: Span) -> IResult<Span, Primary>
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn primary_mintypmax_expression(s: Span) -> IResult<Span, Primary> {
-    let (s, a) = paren(mintypmax_expression)(s)?;
+    let (s, a) = paren(nest(mintypmax_expression))(s)?;
     Ok((
         s,
         Primary::MintypmaxExpression(Box::new(PrimaryMintypmaxExpression { nodes: (a,) })),
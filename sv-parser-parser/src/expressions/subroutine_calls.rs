@@ -24,6 +24,7 @@ pub(crate) fn system_tf_call(s: Span) -> IResult<Span, SystemTfCall> {
     alt((
         system_tf_call_arg_expression,
         system_tf_call_arg_data_type,
+        system_tf_call_arg_tokens,
         system_tf_call_arg_optional,
     ))(s)
 }
@@ -67,6 +68,53 @@ pub(crate) fn system_tf_call_arg_expression(s: Span) -> IResult<Span, SystemTfCa
     ))
 }
 
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn system_tf_call_arg_tokens(s: Span) -> IResult<Span, SystemTfCall> {
+    if !tolerant_system_tf() {
+        return Err(Err::Error(make_error(s, ErrorKind::Fix)));
+    }
+    let (s, a) = system_tf_identifier(s)?;
+    let (s, _) = peek(not(paren(list_of_arguments)))(s)?;
+    let (s, b) = paren(many0(system_tf_arg_token))(s)?;
+    Ok((
+        s,
+        SystemTfCall::ArgTokens(Box::new(SystemTfCallArgTokens { nodes: (a, b) })),
+    ))
+}
+
+#[tracable_parser]
+pub(crate) fn system_tf_arg_token(s: Span) -> IResult<Span, SystemTfArgToken> {
+    alt((
+        map(string_literal, |x| {
+            SystemTfArgToken::StringLiteral(Box::new(x))
+        }),
+        system_tf_arg_token_paren,
+        system_tf_arg_token_text,
+    ))(s)
+}
+
+#[tracable_parser]
+pub(crate) fn system_tf_arg_token_paren(s: Span) -> IResult<Span, SystemTfArgToken> {
+    let (s, a) = paren(nest(many0(system_tf_arg_token)))(s)?;
+    Ok((
+        s,
+        SystemTfArgToken::Paren(Box::new(SystemTfArgTokenParen { nodes: (a,) })),
+    ))
+}
+
+#[tracable_parser]
+pub(crate) fn system_tf_arg_token_text(s: Span) -> IResult<Span, SystemTfArgToken> {
+    let (s, a) = ws(map(
+        alt((is_not(" \t\r\n\"(),/"), tag(","), tag("/"))),
+        into_locate,
+    ))(s)?;
+    Ok((
+        s,
+        SystemTfArgToken::Text(Box::new(SystemTfArgTokenText { nodes: a })),
+    ))
+}
+
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn subroutine_call(s: Span) -> IResult<Span, SubroutineCall> {
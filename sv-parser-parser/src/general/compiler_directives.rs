@@ -6,60 +6,7 @@ use crate::*;
 #[packrat_parser]
 pub(crate) fn compiler_directive(s: Span) -> IResult<Span, CompilerDirective> {
     begin_directive();
-    let ret = alt((
-        map(resetall_compiler_directive, |x| {
-            CompilerDirective::ResetallCompilerDirective(Box::new(x))
-        }),
-        map(include_compiler_directive, |x| {
-            CompilerDirective::IncludeCompilerDirective(Box::new(x))
-        }),
-        map(text_macro_definition, |x| {
-            CompilerDirective::TextMacroDefinition(Box::new(x))
-        }),
-        map(undefine_compiler_directive, |x| {
-            CompilerDirective::UndefineCompilerDirective(Box::new(x))
-        }),
-        map(undefineall_compiler_directive, |x| {
-            CompilerDirective::UndefineallCompilerDirective(Box::new(x))
-        }),
-        map(conditional_compiler_directive, |x| {
-            CompilerDirective::ConditionalCompilerDirective(Box::new(x))
-        }),
-        map(timescale_compiler_directive, |x| {
-            CompilerDirective::TimescaleCompilerDirective(Box::new(x))
-        }),
-        map(default_nettype_compiler_directive, |x| {
-            CompilerDirective::DefaultNettypeCompilerDirective(Box::new(x))
-        }),
-        map(unconnected_drive_compiler_directive, |x| {
-            CompilerDirective::UnconnectedDriveCompilerDirective(Box::new(x))
-        }),
-        map(nounconnected_drive_compiler_directive, |x| {
-            CompilerDirective::NounconnectedDriveCompilerDirective(Box::new(x))
-        }),
-        map(celldefine_compiler_directive, |x| {
-            CompilerDirective::CelldefineDriveCompilerDirective(Box::new(x))
-        }),
-        map(endcelldefine_compiler_directive, |x| {
-            CompilerDirective::EndcelldefineDriveCompilerDirective(Box::new(x))
-        }),
-        map(pragma, |x| CompilerDirective::Pragma(Box::new(x))),
-        map(line_compiler_directive, |x| {
-            CompilerDirective::LineCompilerDirective(Box::new(x))
-        }),
-        map(position_compiler_directive, |x| {
-            CompilerDirective::PositionCompilerDirective(Box::new(x))
-        }),
-        map(keywords_directive, |x| {
-            CompilerDirective::KeywordsDirective(Box::new(x))
-        }),
-        map(endkeywords_directive, |x| {
-            CompilerDirective::EndkeywordsDirective(Box::new(x))
-        }),
-        map(text_macro_usage, |x| {
-            CompilerDirective::TextMacroUsage(Box::new(x))
-        }),
-    ))(s);
+    let ret = compiler_directive_impl(s, true);
     end_directive();
     ret
 }
@@ -68,59 +15,73 @@ pub(crate) fn compiler_directive(s: Span) -> IResult<Span, CompilerDirective> {
 #[packrat_parser]
 pub(crate) fn compiler_directive_without_resetall(s: Span) -> IResult<Span, CompilerDirective> {
     begin_directive();
-    let ret = alt((
-        map(include_compiler_directive, |x| {
+    let ret = compiler_directive_impl(s, false);
+    end_directive();
+    ret
+}
+
+// Parse the directive named by the word after the backtick, or a text macro
+// usage if it names none, rather than trying the parser of each directive in
+// turn.
+fn compiler_directive_impl(s: Span, resetall: bool) -> IResult<Span, CompilerDirective> {
+    let (_, name) = peek(preceded(
+        symbol("`"),
+        take_while(|x: char| AZ09_.contains(x)),
+    ))(s)?;
+    match *name.fragment() {
+        "resetall" if resetall => map(resetall_compiler_directive, |x| {
+            CompilerDirective::ResetallCompilerDirective(Box::new(x))
+        })(s),
+        "include" => map(include_compiler_directive, |x| {
             CompilerDirective::IncludeCompilerDirective(Box::new(x))
-        }),
-        map(text_macro_definition, |x| {
+        })(s),
+        "define" => map(text_macro_definition, |x| {
             CompilerDirective::TextMacroDefinition(Box::new(x))
-        }),
-        map(undefine_compiler_directive, |x| {
+        })(s),
+        "undef" => map(undefine_compiler_directive, |x| {
             CompilerDirective::UndefineCompilerDirective(Box::new(x))
-        }),
-        map(undefineall_compiler_directive, |x| {
+        })(s),
+        "undefineall" => map(undefineall_compiler_directive, |x| {
             CompilerDirective::UndefineallCompilerDirective(Box::new(x))
-        }),
-        map(conditional_compiler_directive, |x| {
+        })(s),
+        "ifdef" | "ifndef" => map(conditional_compiler_directive, |x| {
             CompilerDirective::ConditionalCompilerDirective(Box::new(x))
-        }),
-        map(timescale_compiler_directive, |x| {
+        })(s),
+        "timescale" => map(timescale_compiler_directive, |x| {
             CompilerDirective::TimescaleCompilerDirective(Box::new(x))
-        }),
-        map(default_nettype_compiler_directive, |x| {
+        })(s),
+        "default_nettype" => map(default_nettype_compiler_directive, |x| {
             CompilerDirective::DefaultNettypeCompilerDirective(Box::new(x))
-        }),
-        map(unconnected_drive_compiler_directive, |x| {
+        })(s),
+        "unconnected_drive" => map(unconnected_drive_compiler_directive, |x| {
             CompilerDirective::UnconnectedDriveCompilerDirective(Box::new(x))
-        }),
-        map(nounconnected_drive_compiler_directive, |x| {
+        })(s),
+        "nounconnected_drive" => map(nounconnected_drive_compiler_directive, |x| {
             CompilerDirective::NounconnectedDriveCompilerDirective(Box::new(x))
-        }),
-        map(celldefine_compiler_directive, |x| {
+        })(s),
+        "celldefine" => map(celldefine_compiler_directive, |x| {
             CompilerDirective::CelldefineDriveCompilerDirective(Box::new(x))
-        }),
-        map(endcelldefine_compiler_directive, |x| {
+        })(s),
+        "endcelldefine" => map(endcelldefine_compiler_directive, |x| {
             CompilerDirective::EndcelldefineDriveCompilerDirective(Box::new(x))
-        }),
-        map(pragma, |x| CompilerDirective::Pragma(Box::new(x))),
-        map(line_compiler_directive, |x| {
+        })(s),
+        "pragma" => map(pragma, |x| CompilerDirective::Pragma(Box::new(x)))(s),
+        "line" => map(line_compiler_directive, |x| {
             CompilerDirective::LineCompilerDirective(Box::new(x))
-        }),
-        map(position_compiler_directive, |x| {
+        })(s),
+        "__FILE__" | "__LINE__" => map(position_compiler_directive, |x| {
             CompilerDirective::PositionCompilerDirective(Box::new(x))
-        }),
-        map(keywords_directive, |x| {
+        })(s),
+        "begin_keywords" => map(keywords_directive, |x| {
             CompilerDirective::KeywordsDirective(Box::new(x))
-        }),
-        map(endkeywords_directive, |x| {
+        })(s),
+        "end_keywords" => map(endkeywords_directive, |x| {
             CompilerDirective::EndkeywordsDirective(Box::new(x))
-        }),
-        map(text_macro_usage, |x| {
+        })(s),
+        _ => map(text_macro_usage, |x| {
             CompilerDirective::TextMacroUsage(Box::new(x))
-        }),
-    ))(s);
-    end_directive();
-    ret
+        })(s),
+    }
 }
 
 #[tracable_parser]
@@ -460,12 +421,12 @@ pub(crate) fn conditional_compiler_directive(
 pub(crate) fn ifdef_directive(s: Span) -> IResult<Span, IfdefDirective> {
     let (s, a) = symbol("`")(s)?;
     let (s, b) = keyword("ifdef")(s)?;
-    let (s, c) = text_macro_identifier(s)?;
+    let (s, c) = ifdef_condition(s)?;
     let (s, d) = ifdef_group_of_lines(s)?;
     let (s, e) = many0(tuple((
         symbol("`"),
         keyword("elsif"),
-        text_macro_identifier,
+        ifdef_condition,
         elsif_group_of_lines,
     )))(s)?;
     let (s, f) = opt(tuple((symbol("`"), keyword("else"), else_group_of_lines)))(s)?;
@@ -484,12 +445,12 @@ pub(crate) fn ifdef_directive(s: Span) -> IResult<Span, IfdefDirective> {
 pub(crate) fn ifndef_directive(s: Span) -> IResult<Span, IfndefDirective> {
     let (s, a) = symbol("`")(s)?;
     let (s, b) = keyword("ifndef")(s)?;
-    let (s, c) = text_macro_identifier(s)?;
+    let (s, c) = ifdef_condition(s)?;
     let (s, d) = ifndef_group_of_lines(s)?;
     let (s, e) = many0(tuple((
         symbol("`"),
         keyword("elsif"),
-        text_macro_identifier,
+        ifdef_condition,
         elsif_group_of_lines,
     )))(s)?;
     let (s, f) = opt(tuple((symbol("`"), keyword("else"), else_group_of_lines)))(s)?;
@@ -503,6 +464,73 @@ pub(crate) fn ifndef_directive(s: Span) -> IResult<Span, IfndefDirective> {
     ))
 }
 
+// IEEE1800-2023 Clause 22.6 allows an expression of macro identifiers in
+// parentheses as the condition.
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn ifdef_condition(s: Span) -> IResult<Span, IfdefCondition> {
+    alt((
+        map(text_macro_identifier, |x| {
+            IfdefCondition::TextMacroIdentifier(Box::new(x))
+        }),
+        ifdef_condition_paren,
+    ))(s)
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn ifdef_condition_paren(s: Span) -> IResult<Span, IfdefCondition> {
+    let (s, a) = paren(ifdef_macro_expression)(s)?;
+    Ok((
+        s,
+        IfdefCondition::Paren(Box::new(IfdefConditionParen { nodes: (a,) })),
+    ))
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn ifdef_macro_expression(s: Span) -> IResult<Span, IfdefMacroExpression> {
+    let (s, a) = ifdef_macro_operand(s)?;
+    let (s, b) = many0(pair(
+        alt((symbol("&&"), symbol("||"), symbol("->"), symbol("<->"))),
+        ifdef_macro_operand,
+    ))(s)?;
+    Ok((s, IfdefMacroExpression { nodes: (a, b) }))
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn ifdef_macro_operand(s: Span) -> IResult<Span, IfdefMacroOperand> {
+    alt((
+        map(text_macro_identifier, |x| {
+            IfdefMacroOperand::TextMacroIdentifier(Box::new(x))
+        }),
+        ifdef_macro_operand_not,
+        ifdef_macro_operand_paren,
+    ))(s)
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn ifdef_macro_operand_not(s: Span) -> IResult<Span, IfdefMacroOperand> {
+    let (s, a) = symbol("!")(s)?;
+    let (s, b) = ifdef_macro_operand(s)?;
+    Ok((
+        s,
+        IfdefMacroOperand::Not(Box::new(IfdefMacroOperandNot { nodes: (a, b) })),
+    ))
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn ifdef_macro_operand_paren(s: Span) -> IResult<Span, IfdefMacroOperand> {
+    let (s, a) = paren(ifdef_macro_expression)(s)?;
+    Ok((
+        s,
+        IfdefMacroOperand::Paren(Box::new(IfdefMacroOperandParen { nodes: (a,) })),
+    ))
+}
+
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn ifdef_group_of_lines(s: Span) -> IResult<Span, IfdefGroupOfLines> {
@@ -552,6 +580,12 @@ pub(crate) fn source_description(s: Span) -> IResult<Span, SourceDescription> {
             SourceDescription::EscapedIdentifier(Box::new(x))
         }),
         source_description_not_directive,
+        map(protected_envelope, |x| {
+            SourceDescription::ProtectedEnvelope(Box::new(x))
+        }),
+        map(legacy_protected_envelope, |x| {
+            SourceDescription::LegacyProtectedEnvelope(Box::new(x))
+        }),
         map(compiler_directive, |x| {
             SourceDescription::CompilerDirective(Box::new(x))
         }),
@@ -778,6 +812,106 @@ pub(crate) fn simple_identifier_pragma_impl(s: Span) -> IResult<Span, Locate> {
     Ok((s, into_locate(a)))
 }
 
+// IEEE1800-2017 Clause 34.5
+// The encoded key/data blocks of a decryption envelope are not SystemVerilog
+// text, so everything between `pragma protect begin_protected and
+// `pragma protect end_protected is taken verbatim.
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn protected_envelope(s: Span) -> IResult<Span, ProtectedEnvelope> {
+    begin_directive();
+    let ret = protected_envelope_impl(s);
+    end_directive();
+    ret
+}
+
+pub(crate) fn protected_envelope_impl(s: Span) -> IResult<Span, ProtectedEnvelope> {
+    let (s, _) = peek(tuple((
+        symbol("`"),
+        keyword("pragma"),
+        keyword("protect"),
+        keyword("begin_protected"),
+    )))(s)?;
+    let (s, a) = pragma(s)?;
+    let (s, b) = take_until_pragma_protect_end(s)?;
+    let (s, c) = pragma(s)?;
+    Ok((
+        s,
+        ProtectedEnvelope {
+            nodes: (a, into_locate(b), c),
+        },
+    ))
+}
+
+fn take_until_pragma_protect_end(s: Span) -> IResult<Span, Span> {
+    let fragment = s.fragment();
+    let mut pos = 0;
+    while let Some(i) = fragment[pos..].find("`pragma") {
+        let begin = pos + i;
+        let rest = fragment[begin + "`pragma".len()..].trim_start_matches([' ', '\t']);
+        if let Some(rest) = rest.strip_prefix("protect") {
+            let rest = rest.trim_start_matches([' ', '\t']);
+            if let Some(rest) = rest.strip_prefix("end_protected") {
+                if !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$') {
+                    return take(begin)(s);
+                }
+            }
+        }
+        pos = begin + 1;
+    }
+    Err(Err::Error(make_error(s, ErrorKind::TakeUntil)))
+}
+
+// Verilog-XL encrypts the text between `protect and `endprotect, and leaves
+// it between `protected and `endprotected. Neither is SystemVerilog text, so
+// it is taken verbatim.
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn legacy_protected_envelope(s: Span) -> IResult<Span, LegacyProtectedEnvelope> {
+    begin_directive();
+    let ret = legacy_protected_envelope_impl(s);
+    end_directive();
+    ret
+}
+
+pub(crate) fn legacy_protected_envelope_impl(s: Span) -> IResult<Span, LegacyProtectedEnvelope> {
+    let (s, a) = symbol("`")(s)?;
+    let (s, b) = alt((keyword_exact("protected"), keyword_exact("protect")))(s)?;
+    let end = if b.nodes.0.len == "protected".len() {
+        "endprotected"
+    } else {
+        "endprotect"
+    };
+    let (s, c) = take_until_directive(end)(s)?;
+    let (s, d) = symbol("`")(s)?;
+    let (s, e) = keyword(end)(s)?;
+    Ok((
+        s,
+        LegacyProtectedEnvelope {
+            nodes: (a, b, into_locate(c), d, e),
+        },
+    ))
+}
+
+// Take the text before the directive `t, which mustn't be the prefix of a
+// longer directive.
+fn take_until_directive<'a>(t: &'a str) -> impl Fn(Span<'a>) -> IResult<Span<'a>, Span<'a>> {
+    move |s: Span<'a>| {
+        let fragment = s.fragment();
+        let mut pos = 0;
+        while let Some(i) = fragment[pos..].find('`') {
+            let begin = pos + i;
+            if let Some(rest) = fragment[begin + 1..].strip_prefix(t) {
+                if !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$') {
+                    return take(begin)(s);
+                }
+            }
+            pos = begin + 1;
+        }
+        Err(Err::Error(make_error(s, ErrorKind::TakeUntil)))
+    }
+}
+
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn line_compiler_directive(s: Span) -> IResult<Span, LineCompilerDirective> {
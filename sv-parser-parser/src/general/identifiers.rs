@@ -29,7 +29,7 @@ pub(crate) fn bin_identifier(s: Span) -> IResult<Span, BinIdentifier> {
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn c_identifier(s: Span) -> IResult<Span, CIdentifier> {
-    let (s, a) = ws(c_identifier_impl)(s)?;
+    let (s, a) = ws(alt((c_identifier_impl, c_identifier_escaped_impl)))(s)?;
     Ok((s, CIdentifier { nodes: a }))
 }
 
@@ -49,6 +49,15 @@ pub(crate) fn c_identifier_impl(s: Span) -> IResult<Span, Locate> {
     }
 }
 
+// A C identifier may be escaped, e.g. to use a SystemVerilog keyword as the
+// linkage name of a DPI import.
+#[tracable_parser]
+pub(crate) fn c_identifier_escaped_impl(s: Span) -> IResult<Span, Locate> {
+    let (s, a) = recognize(tuple((tag("\\"), is_a(AZ_), opt(is_a(AZ09_)))))(s)?;
+    let (s, _) = peek(alt((is_a(" \t\r\n"), eof)))(s)?;
+    Ok((s, into_locate(a)))
+}
+
 #[tracable_parser]
 pub(crate) fn cell_identifier(s: Span) -> IResult<Span, CellIdentifier> {
     let (s, a) = identifier(s)?;
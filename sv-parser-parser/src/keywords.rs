@@ -1,4 +1,6 @@
-pub(crate) const KEYWORDS_1364_1995: &[&str] = &[
+use phf::phf_set;
+
+pub(crate) static KEYWORDS_1364_1995: phf::Set<&str> = phf_set! {
     "always",
     "and",
     "assign",
@@ -101,9 +103,9 @@ pub(crate) const KEYWORDS_1364_1995: &[&str] = &[
     "wor",
     "xnor",
     "xor",
-];
+};
 
-pub(crate) const KEYWORDS_1364_2001: &[&str] = &[
+pub(crate) static KEYWORDS_1364_2001: phf::Set<&str> = phf_set! {
     "always",
     "and",
     "assign",
@@ -227,9 +229,9 @@ pub(crate) const KEYWORDS_1364_2001: &[&str] = &[
     "signed",
     "unsigned",
     "use",
-];
+};
 
-pub(crate) const KEYWORDS_1364_2001_NOCONFIG: &[&str] = &[
+pub(crate) static KEYWORDS_1364_2001_NOCONFIG: phf::Set<&str> = phf_set! {
     "always",
     "and",
     "assign",
@@ -343,9 +345,9 @@ pub(crate) const KEYWORDS_1364_2001_NOCONFIG: &[&str] = &[
     "showcancelled",
     "signed",
     "unsigned",
-];
+};
 
-pub(crate) const KEYWORDS_1364_2005: &[&str] = &[
+pub(crate) static KEYWORDS_1364_2005: phf::Set<&str> = phf_set! {
     "always",
     "and",
     "assign",
@@ -470,9 +472,9 @@ pub(crate) const KEYWORDS_1364_2005: &[&str] = &[
     "unsigned",
     "use",
     "uwire",
-];
+};
 
-pub(crate) const KEYWORDS_1800_2005: &[&str] = &[
+pub(crate) static KEYWORDS_1800_2005: phf::Set<&str> = phf_set! {
     "always",
     "and",
     "assign",
@@ -694,9 +696,9 @@ pub(crate) const KEYWORDS_1800_2005: &[&str] = &[
     "wildcard",
     "with",
     "within",
-];
+};
 
-pub(crate) const KEYWORDS_1800_2009: &[&str] = &[
+pub(crate) static KEYWORDS_1800_2009: phf::Set<&str> = phf_set! {
     "always",
     "and",
     "assign",
@@ -941,9 +943,9 @@ pub(crate) const KEYWORDS_1800_2009: &[&str] = &[
     "until_with",
     "untyped",
     "weak",
-];
+};
 
-pub(crate) const KEYWORDS_1800_2012: &[&str] = &[
+pub(crate) static KEYWORDS_1800_2012: phf::Set<&str> = phf_set! {
     "always",
     "and",
     "assign",
@@ -1192,9 +1194,9 @@ pub(crate) const KEYWORDS_1800_2012: &[&str] = &[
     "interconnect",
     "nettype",
     "soft",
-];
+};
 
-pub(crate) const KEYWORDS_1800_2017: &[&str] = &[
+pub(crate) static KEYWORDS_1800_2017: phf::Set<&str> = phf_set! {
     "accept_on",
     "alias",
     "always",
@@ -1443,9 +1445,9 @@ pub(crate) const KEYWORDS_1800_2017: &[&str] = &[
     "wor",
     "xnor",
     "xor",
-];
+};
 
-pub(crate) const KEYWORDS_DIRECTIVE: &[&str] = &[
+pub(crate) static KEYWORDS_DIRECTIVE: phf::Set<&str> = phf_set! {
     "begin_keywords",
     "celldefine",
     "default_nettype",
@@ -1466,4 +1468,10 @@ pub(crate) const KEYWORDS_DIRECTIVE: &[&str] = &[
     "unconnected_drive",
     "undef",
     "undefineall",
+};
+
+pub(crate) const SYMBOLS_1800: &[&str] = &[
+    "!=?", "##", "#-#", "#=#", "%=", "&=", "'", "'0", "'1", "'X", "'Z", "'x", "'z", "'{", "*::*",
+    "*=", "++", "+=", "--", "-=", "->>", ".*", "/=", ":/", "::", ":=", "<->", "<<<=", "<<=", "==?",
+    ">>=", ">>>=", "@@", "^=", "|->", "|=", "|=>",
 ];
@@ -6,6 +6,9 @@ pub mod utils;
 pub(crate) use keywords::*;
 pub(crate) use utils::*;
 
+pub mod tokens;
+pub use tokens::{find_unterminated, tokenize, Token, TokenKind};
+
 mod tests;
 
 pub mod behavioral_statements;
@@ -18,6 +21,8 @@ pub mod primitive_instances;
 pub mod source_text;
 pub mod specify_section;
 pub mod udp_declaration_and_instantiation;
+#[cfg(feature = "ams")]
+pub mod verilog_ams;
 pub(crate) use behavioral_statements::*;
 pub(crate) use declarations::*;
 pub(crate) use expressions::*;
@@ -28,13 +33,15 @@ pub(crate) use primitive_instances::*;
 pub(crate) use source_text::*;
 pub(crate) use specify_section::*;
 pub(crate) use udp_declaration_and_instantiation::*;
+#[cfg(feature = "ams")]
+pub(crate) use verilog_ams::*;
 
 pub(crate) use nom::branch::*;
 pub(crate) use nom::bytes::complete::*;
 pub(crate) use nom::character::complete::*;
 pub(crate) use nom::combinator::*;
 pub(crate) use nom::error::{context, make_error, ErrorKind};
-pub(crate) use nom::multi::*;
+pub(crate) use nom::multi::{fold_many0, many1, many_till};
 pub(crate) use nom::sequence::*;
 pub(crate) use nom::Err;
 pub(crate) use nom_greedyerror::GreedyError;
@@ -52,6 +59,9 @@ pub struct SpanInfo {
     #[cfg(feature = "trace")]
     pub tracable_info: TracableInfo,
     pub recursive_info: RecursiveInfo,
+    /// The nesting depth of expressions and statements, limited by
+    /// `utils::nest_limit()`.
+    pub depth: usize,
 }
 
 pub type Span<'a> = nom_locate::LocatedSpan<&'a str, SpanInfo>;
@@ -80,15 +90,26 @@ impl HasTracableInfo for SpanInfo {
     }
 }
 
-impl HasExtraState<bool> for SpanInfo {
-    fn get_extra_state(&self) -> bool {
-        in_directive()
+// The depth is part of the state, as a result found at one depth may not be
+// found deeper because of the nesting limit.
+impl HasExtraState<(bool, usize)> for SpanInfo {
+    fn get_extra_state(&self) -> (bool, usize) {
+        (in_directive(), self.depth)
     }
 }
 
 // -----------------------------------------------------------------------------
 
-nom_packrat::storage!(AnyNode, bool, 1024);
+// The packrat storage memoizes the results of parser calls by rule, position,
+// directive state and nesting depth, and keeps the latest PACKRAT_SIZE of them. Deeply
+// nested expressions backtrack over more calls than the default keeps, so the
+// `large_packrat` feature trades memory for linear parse time on such inputs.
+#[cfg(not(feature = "large_packrat"))]
+const PACKRAT_SIZE: usize = 1024;
+#[cfg(feature = "large_packrat")]
+const PACKRAT_SIZE: usize = 65536;
+
+nom_packrat::storage!(AnyNode, (bool, usize), PACKRAT_SIZE);
 
 pub fn sv_parser(s: Span) -> IResult<Span, SourceText> {
     init();
@@ -100,6 +121,18 @@ pub fn sv_parser_incomplete(s: Span) -> IResult<Span, SourceText> {
     source_text_incomplete(s)
 }
 
+pub fn verilog_parser(s: Span) -> IResult<Span, SourceText> {
+    init();
+    begin_keywords("1364-2005");
+    source_text(s)
+}
+
+pub fn verilog_parser_incomplete(s: Span) -> IResult<Span, SourceText> {
+    init();
+    begin_keywords("1364-2005");
+    source_text_incomplete(s)
+}
+
 pub fn lib_parser(s: Span) -> IResult<Span, LibraryText> {
     init();
     library_text(s)
@@ -115,8 +148,63 @@ pub fn pp_parser(s: Span) -> IResult<Span, PreprocessorText> {
     preprocessor_text(s)
 }
 
+pub fn expression_parser(s: Span) -> IResult<Span, Expression> {
+    init();
+    snippet(expression)(s)
+}
+
+pub fn statement_parser(s: Span) -> IResult<Span, Statement> {
+    init();
+    snippet(statement)(s)
+}
+
+pub fn module_item_parser(s: Span) -> IResult<Span, ModuleItem> {
+    init();
+    snippet(module_item)(s)
+}
+
+pub fn package_item_parser(s: Span) -> IResult<Span, PackageItem> {
+    init();
+    snippet(package_item)(s)
+}
+
+/// Parse the whole of `s` as descriptions, for a part of a source text which
+/// begins at a description.
+pub fn descriptions_parser(s: Span) -> IResult<Span, Vec<Description>> {
+    init();
+    let (s, (a, _)) = many_till(description, eof)(s)?;
+    Ok((s, a))
+}
+
+/// Parse the white space and time units declaration at the beginning of a
+/// source text, which `description_parser()` then parses one description at
+/// a time.
+pub fn source_text_header_parser(
+    s: Span,
+) -> IResult<Span, (Vec<WhiteSpace>, Option<TimeunitsDeclaration>)> {
+    init();
+    pair(many0(white_space), opt(timeunits_declaration))(s)
+}
+
+/// Parse the description at the beginning of `s`, which follows the header
+/// or a description parsed before. The `begin_keywords version in effect is
+/// kept from those, but the memoized results are dropped.
+pub fn description_parser(s: Span) -> IResult<Span, Description> {
+    nom_packrat::init!();
+    description(s)
+}
+
+// Parse the whole of `s` with `f`, after any leading white space.
+fn snippet<'a, O>(
+    f: impl FnMut(Span<'a>) -> IResult<Span<'a>, O>,
+) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, O> {
+    preceded(many0(white_space), terminated(f, eof))
+}
+
 fn init() {
     nom_packrat::init!();
     clear_directive();
     clear_version();
+    clear_expected();
+    clear_work();
 }
@@ -5,20 +5,24 @@ use crate::*;
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn class_item(s: Span) -> IResult<Span, ClassItem> {
-    alt((
-        class_item_property,
-        class_item_method,
-        class_item_constraint,
-        class_item_declaration,
-        class_item_covergroup,
-        map(pair(local_parameter_declaration, symbol(";")), |x| {
-            ClassItem::LocalParameterDeclaration(Box::new(x))
-        }),
-        map(pair(parameter_declaration, symbol(";")), |x| {
-            ClassItem::ParameterDeclaration(Box::new(x))
-        }),
-        map(symbol(";"), |x| ClassItem::Empty(Box::new(x))),
-    ))(s)
+    context(
+        "class_item",
+        alt((
+            class_item_property,
+            class_item_method,
+            class_item_constraint,
+            class_item_declaration,
+            class_item_interface_class_declaration,
+            class_item_covergroup,
+            map(pair(local_parameter_declaration, symbol(";")), |x| {
+                ClassItem::LocalParameterDeclaration(Box::new(x))
+            }),
+            map(pair(parameter_declaration, symbol(";")), |x| {
+                ClassItem::ParameterDeclaration(Box::new(x))
+            }),
+            map(symbol(";"), |x| ClassItem::Empty(Box::new(x))),
+        )),
+    )(s)
 }
 
 #[tracable_parser]
@@ -65,6 +69,19 @@ pub(crate) fn class_item_declaration(s: Span) -> IResult<Span, ClassItem> {
     ))
 }
 
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn class_item_interface_class_declaration(s: Span) -> IResult<Span, ClassItem> {
+    let (s, a) = many0(attribute_instance)(s)?;
+    let (s, b) = interface_class_declaration(s)?;
+    Ok((
+        s,
+        ClassItem::InterfaceClassDeclaration(Box::new(ClassItemInterfaceClassDeclaration {
+            nodes: (a, b),
+        })),
+    ))
+}
+
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn class_item_covergroup(s: Span) -> IResult<Span, ClassItem> {
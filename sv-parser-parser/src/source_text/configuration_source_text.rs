@@ -146,7 +146,12 @@ pub(crate) fn liblist_clause(s: Span) -> IResult<Span, LiblistClause> {
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn use_clause(s: Span) -> IResult<Span, UseClause> {
-    alt((use_clause_cell, use_clause_named, use_clause_cell_named))(s)
+    alt((
+        use_clause_parameter,
+        use_clause_cell_named,
+        use_clause_named,
+        use_clause_cell,
+    ))(s)
 }
 
 #[tracable_parser]
@@ -180,7 +185,10 @@ pub(crate) fn use_clause_named(s: Span) -> IResult<Span, UseClause> {
 #[packrat_parser]
 pub(crate) fn use_clause_cell_named(s: Span) -> IResult<Span, UseClause> {
     let (s, a) = keyword("use")(s)?;
-    let (s, b) = opt(pair(library_identifier, symbol(".")))(s)?;
+    let (s, b) = opt(terminated(
+        pair(library_identifier, symbol(".")),
+        peek(pair(cell_identifier, named_parameter_assignment)),
+    ))(s)?;
     let (s, c) = cell_identifier(s)?;
     let (s, d) = list(symbol(","), named_parameter_assignment)(s)?;
     let (s, e) = opt(pair(symbol(":"), config))(s)?;
@@ -192,6 +200,24 @@ pub(crate) fn use_clause_cell_named(s: Span) -> IResult<Span, UseClause> {
     ))
 }
 
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn use_clause_parameter(s: Span) -> IResult<Span, UseClause> {
+    let (s, a) = keyword("use")(s)?;
+    let (s, b) = opt(pair(
+        opt(pair(library_identifier, symbol("."))),
+        cell_identifier,
+    ))(s)?;
+    let (s, c) = parameter_value_assignment(s)?;
+    let (s, d) = opt(pair(symbol(":"), config))(s)?;
+    Ok((
+        s,
+        UseClause::Parameter(Box::new(UseClauseParameter {
+            nodes: (a, b, c, d),
+        })),
+    ))
+}
+
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn config(s: Span) -> IResult<Span, Config> {
@@ -78,11 +78,11 @@ pub(crate) fn constraint_primary(s: Span) -> IResult<Span, ConstraintPrimary> {
 #[packrat_parser]
 pub(crate) fn constraint_expression(s: Span) -> IResult<Span, ConstraintExpression> {
     alt((
+        constraint_expression_arrow,
         constraint_expression_expression,
         map(pair(uniqueness_constraint, symbol(";")), |x| {
             ConstraintExpression::UniquenessConstraint(Box::new(x))
         }),
-        constraint_expression_arrow,
         constraint_expression_if,
         constraint_expression_foreach,
         constraint_expression_disable,
@@ -111,11 +111,13 @@ pub(crate) fn soft(s: Span) -> IResult<Span, Soft> {
     Ok((s, Soft { nodes: (a,) }))
 }
 
+// `->` in a constraint is an implication of a constraint_set, rather than the
+// logical implication operator of an expression.
 #[recursive_parser]
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn constraint_expression_arrow(s: Span) -> IResult<Span, ConstraintExpression> {
-    let (s, a) = expression(s)?;
+    let (s, a) = expression_without_implication(s)?;
     let (s, b) = symbol("->")(s)?;
     let (s, c) = constraint_set(s)?;
     Ok((
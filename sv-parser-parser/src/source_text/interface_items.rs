@@ -70,14 +70,17 @@ pub(crate) fn extern_tf_declaration_task(s: Span) -> IResult<Span, ExternTfDecla
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn interface_item(s: Span) -> IResult<Span, InterfaceItem> {
-    alt((
-        map(pair(port_declaration, symbol(";")), |x| {
-            InterfaceItem::PortDeclaration(Box::new(x))
-        }),
-        map(non_port_interface_item, |x| {
-            InterfaceItem::NonPortInterfaceItem(Box::new(x))
-        }),
-    ))(s)
+    context(
+        "interface_item",
+        alt((
+            map(pair(port_declaration, symbol(";")), |x| {
+                InterfaceItem::PortDeclaration(Box::new(x))
+            }),
+            map(non_port_interface_item, |x| {
+                InterfaceItem::NonPortInterfaceItem(Box::new(x))
+            }),
+        )),
+    )(s)
 }
 
 #[tracable_parser]
@@ -117,20 +117,31 @@ pub(crate) fn module_common_item(s: Span) -> IResult<Span, ModuleCommonItem> {
         map(elaboration_system_task, |x| {
             ModuleCommonItem::ElaborationSystemTask(Box::new(x))
         }),
+        #[cfg(feature = "ams")]
+        map(analog_construct, |x| {
+            ModuleCommonItem::AnalogConstruct(Box::new(x))
+        }),
+        #[cfg(feature = "ams")]
+        map(branch_declaration, |x| {
+            ModuleCommonItem::BranchDeclaration(Box::new(x))
+        }),
     ))(s)
 }
 
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn module_item(s: Span) -> IResult<Span, ModuleItem> {
-    alt((
-        map(pair(port_declaration, symbol(";")), |x| {
-            ModuleItem::PortDeclaration(Box::new(x))
-        }),
-        map(non_port_module_item, |x| {
-            ModuleItem::NonPortModuleItem(Box::new(x))
-        }),
-    ))(s)
+    context(
+        "module_item",
+        alt((
+            map(pair(port_declaration, symbol(";")), |x| {
+                ModuleItem::PortDeclaration(Box::new(x))
+            }),
+            map(non_port_module_item, |x| {
+                ModuleItem::NonPortModuleItem(Box::new(x))
+            }),
+        )),
+    )(s)
 }
 
 #[tracable_parser]
@@ -376,12 +387,12 @@ pub(crate) fn bind_target_instance_list(s: Span) -> IResult<Span, BindTargetInst
 #[packrat_parser]
 pub(crate) fn bind_instantiation(s: Span) -> IResult<Span, BindInstantiation> {
     alt((
-        map(program_instantiation, |x| {
-            BindInstantiation::ProgramInstantiation(Box::new(x))
-        }),
         map(module_instantiation, |x| {
             BindInstantiation::ModuleInstantiation(Box::new(x))
         }),
+        map(program_instantiation, |x| {
+            BindInstantiation::ProgramInstantiation(Box::new(x))
+        }),
         map(interface_instantiation, |x| {
             BindInstantiation::InterfaceInstantiation(Box::new(x))
         }),
@@ -5,14 +5,17 @@ use crate::*;
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn program_item(s: Span) -> IResult<Span, ProgramItem> {
-    alt((
-        map(pair(port_declaration, symbol(";")), |x| {
-            ProgramItem::PortDeclaration(Box::new(x))
-        }),
-        map(non_port_program_item, |x| {
-            ProgramItem::NonPortProgramItem(Box::new(x))
-        }),
-    ))(s)
+    context(
+        "program_item",
+        alt((
+            map(pair(port_declaration, symbol(";")), |x| {
+                ProgramItem::PortDeclaration(Box::new(x))
+            }),
+            map(non_port_program_item, |x| {
+                ProgramItem::NonPortProgramItem(Box::new(x))
+            }),
+        )),
+    )(s)
 }
 
 #[tracable_parser]
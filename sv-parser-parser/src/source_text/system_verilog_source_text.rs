@@ -45,6 +45,14 @@ pub(crate) fn description(s: Span) -> IResult<Span, Description> {
         map(package_declaration, |x| {
             Description::PackageDeclaration(Box::new(x))
         }),
+        #[cfg(feature = "ams")]
+        map(nature_declaration, |x| {
+            Description::NatureDeclaration(Box::new(x))
+        }),
+        #[cfg(feature = "ams")]
+        map(discipline_declaration, |x| {
+            Description::DisciplineDeclaration(Box::new(x))
+        }),
         description_package_item,
         description_bind_directive,
         map(config_declaration, |x| {
@@ -115,13 +123,16 @@ pub(crate) fn module_ansi_header(s: Span) -> IResult<Span, ModuleAnsiHeader> {
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn module_declaration(s: Span) -> IResult<Span, ModuleDeclaration> {
-    alt((
-        module_declaration_ansi,
-        module_declaration_nonansi,
-        module_declaration_wildcard,
-        module_declaration_extern_ansi,
-        module_declaration_extern_nonansi,
-    ))(s)
+    context(
+        "module_declaration",
+        alt((
+            module_declaration_ansi,
+            module_declaration_nonansi,
+            module_declaration_wildcard,
+            module_declaration_extern_ansi,
+            module_declaration_extern_nonansi,
+        )),
+    )(s)
 }
 
 #[tracable_parser]
@@ -211,13 +222,16 @@ pub(crate) fn module_keyword(s: Span) -> IResult<Span, ModuleKeyword> {
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn interface_declaration(s: Span) -> IResult<Span, InterfaceDeclaration> {
-    alt((
-        interface_declaration_ansi,
-        interface_declaration_nonansi,
-        interface_declaration_wildcard,
-        interface_declaration_extern_ansi,
-        interface_declaration_extern_nonansi,
-    ))(s)
+    context(
+        "interface_declaration",
+        alt((
+            interface_declaration_ansi,
+            interface_declaration_nonansi,
+            interface_declaration_wildcard,
+            interface_declaration_extern_ansi,
+            interface_declaration_extern_nonansi,
+        )),
+    )(s)
 }
 
 #[tracable_parser]
@@ -334,13 +348,16 @@ pub(crate) fn interface_ansi_header(s: Span) -> IResult<Span, InterfaceAnsiHeade
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn program_declaration(s: Span) -> IResult<Span, ProgramDeclaration> {
-    alt((
-        program_declaration_ansi,
-        program_declaration_nonansi,
-        program_declaration_wildcard,
-        program_declaration_extern_ansi,
-        program_declaration_extern_nonansi,
-    ))(s)
+    context(
+        "program_declaration",
+        alt((
+            program_declaration_ansi,
+            program_declaration_nonansi,
+            program_declaration_wildcard,
+            program_declaration_extern_ansi,
+            program_declaration_extern_nonansi,
+        )),
+    )(s)
 }
 
 #[tracable_parser]
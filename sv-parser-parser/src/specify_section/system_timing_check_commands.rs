@@ -265,9 +265,11 @@ pub(crate) fn width_timing_check(s: Span) -> IResult<Span, WidthTimingCheck> {
         controlled_referecne_event,
         symbol(","),
         timing_check_limit,
-        symbol(","),
-        threshold,
-        opt(pair(symbol(","), opt(notifier))),
+        opt(triple(
+            symbol(","),
+            threshold,
+            opt(pair(symbol(","), opt(notifier))),
+        )),
     )))(s)?;
     let (s, c) = symbol(";")(s)?;
     Ok((s, WidthTimingCheck { nodes: (a, b, c) }))
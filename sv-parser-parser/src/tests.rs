@@ -120,6 +120,21 @@ mod unit {
             "library rtlLib \"*.v\" -incdir \"aaa\";\ninclude \"bbb\";;",
             Ok((_, _))
         );
+        test!(
+            library_text,
+            "library rtlLib rtl/*.v\n\t-incdir inc;\n",
+            Ok((_, _))
+        );
+        test!(
+            file_path_spec,
+            "rtl/*.v\n",
+            Ok((
+                _,
+                FilePathSpec::NonLiteral(FilePathSpecNonLiteral {
+                    nodes: (Locate { len: 7, .. }, _)
+                })
+            ))
+        );
     }
 
     #[test]
@@ -309,9 +324,593 @@ mod unit {
         test!(comment, "/*! comment\n * aaa\n */", Ok((_, _)));
     }
 
+    #[test]
+    fn test_many0() {
+        nom_packrat::init!();
+        let s = Span::new_extra("a", SpanInfo::default());
+        let (_, x) = many0(symbol(","))(s).unwrap();
+        assert_eq!(x.capacity(), 0);
+        let s = Span::new_extra(", a", SpanInfo::default());
+        let (_, x) = many0(symbol(","))(s).unwrap();
+        assert_eq!((x.len(), x.capacity()), (1, 1));
+        let s = Span::new_extra(",,,,, a", SpanInfo::default());
+        let (_, x) = many0(symbol(","))(s).unwrap();
+        assert_eq!((x.len(), x.capacity()), (5, 5));
+    }
+
     #[test]
     fn test_expression() {
         test!(expression, "(!a ? 0 : !b : 1 : c ? 0 : 1)", Ok((_, _)));
+        test!(
+            expression,
+            "a + b * c ** d - e",
+            Ok((_, Expression::Binary(_)))
+        );
+        test!(expression, "a -> b <-> c", Ok((_, Expression::Binary(_))));
+        test!(
+            expression,
+            "a + b ? c : d + e",
+            Ok((_, Expression::ConditionalExpression(_)))
+        );
+        test!(
+            expression,
+            "a + b inside {c, d}",
+            Ok((_, Expression::InsideExpression(_)))
+        );
+        test!(
+            constant_expression,
+            "A * 2 == B ? C : D",
+            Ok((_, ConstantExpression::Ternary(_)))
+        );
+    }
+
+    #[test]
+    fn test_expression_precedence() {
+        nom_packrat::init!();
+        let s = Span::new_extra("a - b * c - d", SpanInfo::default());
+        let (_, x) = all_consuming(expression)(s).unwrap();
+        // (a - (b * c)) - d
+        if let Expression::Binary(x) = x {
+            let (a, _, _, d) = &x.nodes;
+            assert!(matches!(d, Expression::Primary(_)));
+            if let Expression::Binary(a) = a {
+                assert!(matches!(a.nodes.0, Expression::Primary(_)));
+                assert!(matches!(a.nodes.3, Expression::Binary(_)));
+            } else {
+                panic!("{:?}", a);
+            }
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra("a + b inside {c} == d inside {e}", SpanInfo::default());
+        let (_, x) = all_consuming(expression)(s).unwrap();
+        // ((a + b) inside {c}) == (d inside {e})
+        if let Expression::Binary(x) = x {
+            assert!(matches!(x.nodes.3, Expression::InsideExpression(_)));
+            if let Expression::InsideExpression(a) = &x.nodes.0 {
+                assert!(matches!(a.nodes.0, Expression::Binary(_)));
+            } else {
+                panic!("{:?}", x.nodes.0);
+            }
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra("a -> b -> c", SpanInfo::default());
+        let (_, x) = all_consuming(expression)(s).unwrap();
+        // a -> (b -> c)
+        if let Expression::Binary(x) = x {
+            assert!(matches!(x.nodes.0, Expression::Primary(_)));
+            assert!(matches!(x.nodes.3, Expression::Binary(_)));
+        } else {
+            panic!("{:?}", x);
+        }
+    }
+
+    #[test]
+    fn test_property_precedence() {
+        nom_packrat::init!();
+        let s = Span::new_extra("a or b and c ##1 d within e", SpanInfo::default());
+        let (_, x) = all_consuming(sequence_expr)(s).unwrap();
+        // a or (b and ((c ##1 d) within e))
+        if let SequenceExpr::Binary(x) = x {
+            assert!(matches!(x.nodes.0, SequenceExpr::Expression(_)));
+            if let SequenceExpr::Binary(c) = &x.nodes.2 {
+                assert!(matches!(c.nodes.0, SequenceExpr::Expression(_)));
+                if let SequenceExpr::Binary(c) = &c.nodes.2 {
+                    assert!(matches!(c.nodes.0, SequenceExpr::ExprCycleDelayExpr(_)));
+                } else {
+                    panic!("{:?}", c);
+                }
+            } else {
+                panic!("{:?}", x);
+            }
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra("not a until b iff c", SpanInfo::default());
+        let (_, x) = all_consuming(property_expr)(s).unwrap();
+        // (not a) until (b iff c)
+        if let PropertyExpr::BinaryProperty(x) = x {
+            assert!(matches!(x.nodes.0, PropertyExpr::Not(_)));
+            assert!(matches!(x.nodes.2, PropertyExpr::BinaryProperty(_)));
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra("a and b |-> c |=> weak(d)", SpanInfo::default());
+        let (_, x) = all_consuming(property_expr)(s).unwrap();
+        // (a and b) |-> (c |=> weak(d))
+        if let PropertyExpr::BinarySequence(x) = x {
+            assert!(matches!(x.nodes.0, SequenceExpr::Binary(_)));
+            if let PropertyExpr::BinarySequence(c) = &x.nodes.2 {
+                assert!(matches!(c.nodes.2, PropertyExpr::Weak(_)));
+            } else {
+                panic!("{:?}", x);
+            }
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra("@(posedge c) a |=> @(posedge d) b", SpanInfo::default());
+        let (_, x) = all_consuming(property_expr)(s).unwrap();
+        if let PropertyExpr::ClockingEvent(x) = x {
+            if let PropertyExpr::BinarySequence(b) = &x.nodes.1 {
+                assert!(matches!(b.nodes.2, PropertyExpr::ClockingEvent(_)));
+            } else {
+                panic!("{:?}", x);
+            }
+        } else {
+            panic!("{:?}", x);
+        }
+    }
+
+    #[test]
+    fn test_select_expression_precedence() {
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            "binsof(a) || binsof(b) with (b > 1) && !binsof(c)",
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(select_expression)(s).unwrap();
+        // binsof(a) || ((binsof(b) with (b > 1)) && !binsof(c))
+        if let SelectExpression::Or(x) = x {
+            assert!(matches!(x.nodes.0, SelectExpression::SelectCondition(_)));
+            if let SelectExpression::And(c) = &x.nodes.2 {
+                assert!(matches!(c.nodes.0, SelectExpression::With(_)));
+                assert!(matches!(c.nodes.2, SelectExpression::Not(_)));
+            } else {
+                panic!("{:?}", x);
+            }
+        } else {
+            panic!("{:?}", x);
+        }
+    }
+
+    #[test]
+    fn test_constraint_implication() {
+        nom_packrat::init!();
+        let s = Span::new_extra("a == 1 -> b -> c dist {0 := 1};", SpanInfo::default());
+        let (_, x) = all_consuming(constraint_expression)(s).unwrap();
+        // (a == 1) -> (b -> (c dist {0 := 1}))
+        if let ConstraintExpression::Arrow(x) = x {
+            assert!(matches!(x.nodes.0, Expression::Binary(_)));
+            if let ConstraintSet::ConstraintExpression(c) = &x.nodes.2 {
+                assert!(matches!(**c, ConstraintExpression::Arrow(_)));
+            } else {
+                panic!("{:?}", x);
+            }
+        } else {
+            panic!("{:?}", x);
+        }
+    }
+
+    #[test]
+    fn test_bind_directive() {
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            "bind cpu: cpu1 fpu_props #(.W(8)) r1(.*), r2(.a(x));",
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(bind_directive)(s).unwrap();
+        if let BindDirective::Scope(x) = x {
+            assert!(x.nodes.2.is_some());
+            assert!(matches!(
+                x.nodes.3,
+                BindInstantiation::ModuleInstantiation(_)
+            ));
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra("bind top.dut[1] chk c1(a ##1 b, clk);", SpanInfo::default());
+        let (_, x) = all_consuming(bind_directive)(s).unwrap();
+        if let BindDirective::Instance(x) = x {
+            assert!(matches!(
+                x.nodes.2,
+                BindInstantiation::CheckerInstantiation(_)
+            ));
+        } else {
+            panic!("{:?}", x);
+        }
+    }
+
+    #[test]
+    fn test_nested_interface_class() {
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            r##"class Seq extends uvm_pkg::uvm_sequence #(Item #(8), Rsp #(int)::T)
+                    implements Put #(Item #(8));
+                  interface class Get #(type T = int) extends Put #(T);
+                    pure virtual function T get();
+                  endclass
+                endclass"##,
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(class_declaration)(s).unwrap();
+        assert!(x.nodes.5.is_some());
+        assert!(x.nodes.6.is_some());
+        assert!(matches!(
+            x.nodes.8[..],
+            [ClassItem::InterfaceClassDeclaration(_)]
+        ));
+    }
+
+    #[test]
+    fn test_let() {
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            "let f(untyped a, logic [3:0] b = 4'h1, c = 2) = a + b + c;",
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(let_declaration)(s).unwrap();
+        let ports = x.nodes.2.unwrap().nodes.1.unwrap().nodes.0;
+        assert!(matches!(ports.nodes.0.nodes.1, LetFormalType::Untyped(_)));
+        assert_eq!(ports.nodes.1.len(), 2);
+        assert!(ports.nodes.1.iter().all(|x| x.1.nodes.4.is_some()));
+
+        nom_packrat::init!();
+        let s = Span::new_extra("p::f(x, , .c(3))", SpanInfo::default());
+        let (_, x) = all_consuming(let_expression)(s).unwrap();
+        assert!(x.nodes.0.is_some());
+        if let Some(LetListOfArguments::Ordered(x)) = x.nodes.2.unwrap().nodes.1 {
+            assert_eq!(x.nodes.0.nodes.1.len(), 1);
+            assert_eq!(x.nodes.1.len(), 1);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn test_udp_table() {
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            r##"initial q = 1'b1;
+                table
+                // clk d : q : q+
+                   r    0  : ? : 0;
+                   (01) 1  : ? : 1;
+                   (?0) ?  : ? : -;
+                   ?    *  : ? : -;
+                endtable"##,
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(udp_body)(s).unwrap();
+        if let UdpBody::SequentialBody(x) = x {
+            assert!(x.nodes.0.is_some());
+            assert_eq!(x.nodes.3.len(), 3);
+            assert!(matches!(x.nodes.2.nodes.0, SeqInputList::EdgeInputList(_)));
+            assert!(matches!(x.nodes.3[2].nodes.4, NextState::Minus(_)));
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            "table 0 0 : 0; 1 ? : 1; ? 1 : 1; x b : x; endtable",
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(udp_body)(s).unwrap();
+        if let UdpBody::CombinationalBody(x) = x {
+            assert_eq!(x.nodes.2.len(), 3);
+        } else {
+            panic!("{:?}", x);
+        }
+    }
+
+    #[test]
+    fn test_gate_strength_and_delay() {
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            "nand (strong0, pull1) #(1:2:3, 2:3:4) g1 (y, a, b), g2 (z, c, d);",
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(gate_instantiation)(s).unwrap();
+        if let GateInstantiation::NInput(x) = x {
+            assert!(matches!(x.nodes.1, Some(DriveStrength::Strength01(_))));
+            assert!(matches!(x.nodes.2, Some(Delay2::Mintypmax(_))));
+            assert_eq!(x.nodes.3.nodes.1.len(), 1);
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            "bufif1 (weak1, highz0) #(1, 2, 3) b (o, i, en);",
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(gate_instantiation)(s).unwrap();
+        if let GateInstantiation::Enable(x) = x {
+            assert!(matches!(x.nodes.1, Some(DriveStrength::Strength1z(_))));
+            assert!(matches!(x.nodes.2, Some(Delay3::Mintypmax(_))));
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra("pulldown (pull0) (w);", SpanInfo::default());
+        let (_, x) = all_consuming(gate_instantiation)(s).unwrap();
+        if let GateInstantiation::Pulldown(x) = x {
+            assert!(matches!(x.nodes.1, Some(PulldownStrength::Strength0(_))));
+            assert!(x.nodes.2.nodes.0.nodes.0.is_none());
+        } else {
+            panic!("{:?}", x);
+        }
+
+        nom_packrat::init!();
+        let s = Span::new_extra("pullup (pull0, strong1) u (w);", SpanInfo::default());
+        let (_, x) = all_consuming(gate_instantiation)(s).unwrap();
+        if let GateInstantiation::Pullup(x) = x {
+            assert!(matches!(x.nodes.1, Some(PullupStrength::Strength01(_))));
+        } else {
+            panic!("{:?}", x);
+        }
+    }
+
+    #[test]
+    fn test_net_alias() {
+        nom_packrat::init!();
+        let s = Span::new_extra("alias a = b[3:0] = {c, d};", SpanInfo::default());
+        let (_, x) = all_consuming(module_common_item)(s).unwrap();
+        if let ModuleCommonItem::NetAlias(x) = x {
+            assert_eq!(x.nodes.3.nodes.1.len(), 1);
+            assert!(matches!(x.nodes.3.nodes.1[0].1, NetLvalue::Lvalue(_)));
+        } else {
+            panic!("{:?}", x);
+        }
+    }
+
+    #[test]
+    fn test_checker_declaration() {
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            r##"checker handshake(input logic clk, req, sequence ack_seq = ack ##1 !ack,
+                                  output bit err = 1'b0);
+                  default clocking @(posedge clk); endclocking
+                  default disable iff rst;
+                  rand bit [1:0] sel;
+                  always_ff @(posedge clk) err <= !req;
+                  a1: assert property (req |-> ack_seq);
+                  for (genvar i = 0; i < 2; i++) begin : g
+                    c1: cover property (sel == i);
+                  end
+                  checker inner(a);
+                    a2: assert property (@(posedge clk) a);
+                  endchecker
+                endchecker : handshake"##,
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(checker_declaration)(s).unwrap();
+        let ports = x.nodes.2.unwrap().nodes.1.unwrap().nodes.0;
+        assert_eq!(ports.nodes.1.len(), 3);
+        assert!(matches!(
+            ports.nodes.1[2].1.nodes.1,
+            Some(CheckerPortDirection::Output(_))
+        ));
+        assert_eq!(x.nodes.4.len(), 7);
+        assert!(x.nodes.6.is_some());
+    }
+
+    #[test]
+    fn test_extern_and_wildcard_declarations() {
+        let module = |x: &'static str| {
+            nom_packrat::init!();
+            let s = Span::new_extra(x, SpanInfo::default());
+            all_consuming(module_declaration)(s).unwrap().1
+        };
+        let x = module("extern module a #(parameter size = 8) (input [size:0] a, output b);");
+        assert!(matches!(x, ModuleDeclaration::ExternAnsi(_)));
+        let x = module("extern module m ({a, b}, c);");
+        assert!(matches!(x, ModuleDeclaration::ExternNonansi(_)));
+        let x = module("module m (.*); input a, b; output c; endmodule");
+        assert!(matches!(x, ModuleDeclaration::Wildcard(_)));
+
+        let udp = |x: &'static str| {
+            nom_packrat::init!();
+            let s = Span::new_extra(x, SpanInfo::default());
+            all_consuming(udp_declaration)(s).unwrap().1
+        };
+        let x = udp("extern primitive p (output q, input a, b);");
+        assert!(matches!(x, UdpDeclaration::ExternAnsi(_)));
+        let x = udp("extern primitive p (q, a, b);");
+        assert!(matches!(x, UdpDeclaration::ExternNonansi(_)));
+    }
+
+    #[test]
+    fn test_package_export() {
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            r##"package p2;
+                  import p1::*;
+                  export p1::x, p1::*;
+                  export *::*;
+                endpackage"##,
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(package_declaration)(s).unwrap();
+        let items: Vec<_> = x.nodes.6.iter().map(|x| &x.1).collect();
+        if let PackageItem::PackageExportDeclaration(x) = items[1] {
+            if let PackageExportDeclaration::Item(x) = x.as_ref() {
+                assert!(matches!(
+                    x.nodes.1.nodes.1[0].1,
+                    PackageImportItem::Asterisk(_)
+                ));
+            } else {
+                panic!("{:?}", x);
+            }
+        } else {
+            panic!("{:?}", items[1]);
+        }
+        assert!(matches!(
+            items[2],
+            PackageItem::PackageExportDeclaration(x)
+                if matches!(x.as_ref(), PackageExportDeclaration::Asterisk(_))
+        ));
+    }
+
+    #[cfg(feature = "ams")]
+    #[test]
+    fn test_verilog_ams() {
+        nom_packrat::init!();
+        let s = Span::new_extra(
+            r##"nature Voltage
+                  units = "V";
+                  access = V;
+                  abstol = 1e-6;
+                endnature
+                discipline electrical;
+                  potential Voltage;
+                  flow Current;
+                  flow.abstol = 1e-12;
+                  domain continuous;
+                enddiscipline
+                module res(p, n);
+                  inout p, n;
+                  electrical p, n;
+                  parameter real r = 1000.0;
+                  branch (p, n) br;
+                  analog begin
+                    I(br) <+ V(br) / r;
+                    if (r < 1.0) V(p, n) <+ 0.0;
+                  end
+                endmodule"##,
+            SpanInfo::default(),
+        );
+        let (_, x) = all_consuming(source_text)(s).unwrap();
+        assert!(matches!(x.nodes.2[0], Description::NatureDeclaration(_)));
+        if let Description::DisciplineDeclaration(x) = &x.nodes.2[1] {
+            assert_eq!(x.nodes.3.len(), 4);
+            assert!(matches!(
+                x.nodes.3[2],
+                DisciplineItem::NatureAttributeOverride(_)
+            ));
+        } else {
+            panic!("{:?}", x.nodes.2[1]);
+        }
+
+        let mut branches = 0;
+        let mut contributions = 0;
+        for node in &x {
+            match node {
+                RefNode::BranchDeclaration(_) => branches += 1,
+                RefNode::ContributionStatement(_) => contributions += 1,
+                _ => (),
+            }
+        }
+        assert_eq!(branches, 1);
+        assert_eq!(contributions, 2);
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let src = "module a; // c\n  assign x = 4'b10_x1 + 'z + 1.5ns * $bits(\\y ) <<< \"s\\\"\";\nendmodule";
+        let tokens: Vec<_> = tokenize(src)
+            .iter()
+            .map(|x| (x.kind, x.locate.str(src)))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Keyword, "module"),
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Operator, ";"),
+                (TokenKind::Comment, "// c"),
+                (TokenKind::Keyword, "assign"),
+                (TokenKind::Identifier, "x"),
+                (TokenKind::Operator, "="),
+                (TokenKind::Number, "4'b10_x1"),
+                (TokenKind::Operator, "+"),
+                (TokenKind::Number, "'z"),
+                (TokenKind::Operator, "+"),
+                (TokenKind::Number, "1.5ns"),
+                (TokenKind::Operator, "*"),
+                (TokenKind::SystemIdentifier, "$bits"),
+                (TokenKind::Operator, "("),
+                (TokenKind::Identifier, "\\y"),
+                (TokenKind::Operator, ")"),
+                (TokenKind::Operator, "<<<"),
+                (TokenKind::String, "\"s\\\"\""),
+                (TokenKind::Operator, ";"),
+                (TokenKind::Keyword, "endmodule"),
+            ]
+        );
+        let x = tokenize(src)[4].locate;
+        assert_eq!((x.line, x.column), (2, 3));
+
+        let src = "`begin_keywords \"1364-1995\" logic `end_keywords logic";
+        let kinds: Vec<_> = tokenize(src).iter().map(|x| x.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Directive,
+                TokenKind::String,
+                TokenKind::Identifier,
+                TokenKind::Directive,
+                TokenKind::Keyword,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_unterminated() {
+        let find = |s| find_unterminated(s).map(|x| (x.kind, x.locate.offset));
+        assert_eq!(find("a = \"b\\\"\"; /* c */ // \"d"), None);
+        assert_eq!(find("a = \"b);\nc = \"d\";"), Some((TokenKind::String, 4)));
+        assert_eq!(find("a = \"b\\\nc\"; /* d"), Some((TokenKind::Comment, 12)));
+        assert_eq!(find("/*/"), Some((TokenKind::Comment, 0)));
+    }
+
+    #[test]
+    fn test_compiler_directive_name() {
+        test!(
+            compiler_directive,
+            "`resetall",
+            Ok((_, CompilerDirective::ResetallCompilerDirective(_)))
+        );
+        test!(
+            compiler_directive,
+            "`ifndef A `endif",
+            Ok((_, CompilerDirective::ConditionalCompilerDirective(_)))
+        );
+        test!(
+            compiler_directive,
+            "`__LINE__",
+            Ok((_, CompilerDirective::PositionCompilerDirective(_)))
+        );
+        test!(
+            compiler_directive,
+            "`includes",
+            Ok((_, CompilerDirective::TextMacroUsage(_)))
+        );
+        test!(compiler_directive_without_resetall, "`resetall", Err(_));
     }
 
     #[test]
@@ -463,6 +1062,33 @@ mod unit {
             Ok((_, _))
         );
     }
+
+    #[test]
+    fn test_packrat() {
+        // Each level of nesting backtracks over the inner expression, so
+        // without the memoized results the work grows exponentially.
+        let mut paren = String::from("a");
+        let mut concat = String::from("a");
+        let mut cond = String::from("a");
+        for i in 0..8 {
+            paren = format!("({})", paren);
+            concat = format!("{{{}}}", concat);
+            cond = format!("(b{} ? {} : c)", i, cond);
+        }
+        let storage = |size| {
+            PACKRAT_STORAGE.with(|x| *x.borrow_mut() = nom_packrat::PackratStorage::new(Some(size)))
+        };
+        for expr in &[paren, concat, cond] {
+            let text = format!("module m; assign x = {}; endmodule", expr);
+            let span = Span::new_extra(text.as_str(), SpanInfo::default());
+            set_work_limit(Some(10_000));
+            assert!(sv_parser(span).is_ok());
+            storage(1);
+            assert!(matches!(sv_parser(span), Err(Err::Failure(_))));
+            storage(PACKRAT_SIZE);
+            set_work_limit(None);
+        }
+    }
 }
 
 mod spec {
@@ -8170,6 +8796,28 @@ mod spec {
                 endproperty"##,
             Ok((_, _))
         );
+        test!(
+            many1(module_item),
+            r##"property p1;
+                  first_match(a ##[1:3] b) intersect c[*2] within d ##1 e |-> f;
+                endproperty
+
+                property p2;
+                  !stop throughout a ##1 b and c or d |=> e s_until_with f implies g;
+                endproperty
+
+                property p3;
+                  not a and nexttime b or s_nexttime [2] c iff weak(d);
+                endproperty
+
+                property p4;
+                  @(posedge clk0) a |=> @(posedge clk1) b ##1 c |-> case (d)
+                    1'b0: e;
+                    default: accept_on(f) always g;
+                  endcase;
+                endproperty"##,
+            Ok((_, _))
+        );
         test!(
             many1(module_item),
             r##"assert property (@(clk) go ##1 get[*2] |-> reject_on(stop) put[->2]);"##,
@@ -9936,6 +10584,23 @@ mod spec {
                 endclass"##,
             Ok((_, _))
         );
+        test!(
+            many1(module_item),
+            r##"class C;
+                  rand bit [7:0] a, b, c[4];
+                  rand bit s;
+                  constraint c1 {
+                    soft a dist { 0 := 1, [1:10] :/ 4, 255 := 2 };
+                    unique { a, b, c };
+                    solve s before a, b;
+                    s -> { a < b; b inside {[1:5], 7}; }
+                    if (a > 2) b == 1; else if (a == 0) { b == 0; } else soft b != 1;
+                    foreach (c[i]) { i > 0 -> c[i] > c[i - 1]; }
+                    disable soft a;
+                  }
+                endclass"##,
+            Ok((_, _))
+        );
         test!(
             many1(module_item),
             r##"class c;
@@ -10548,6 +11213,25 @@ mod spec {
                 end"##,
             Ok((_, _))
         );
+        test!(
+            many1(module_item),
+            r##"initial begin
+                  randsequence( TOP )
+                    TOP : rand join (0.5) S1 S2 S3(1)
+                        | S1 := (w + 1) { int n = 2; n++; }
+                        | S2 := pkg::W
+                        ;
+                    S1  : case ( mode )
+                            0, 1 : S2 ;
+                            2    : S3(2) ;
+                            default S2 ;
+                          endcase ;
+                    S2  : repeat (n) S3(0) ;
+                    void S3(int i) : if (i > 0) S2 else S1 { $display(i); } ;
+                  endsequence
+                end"##,
+            Ok((_, _))
+        );
         // TODO
         // function can't return queue
         //test!(
@@ -10969,6 +11653,33 @@ mod spec {
                 endmodule"##,
             Ok((_, _))
         );
+        test!(
+            many1(module_item),
+            r##"covergroup cg (ref bit [7:0] v, input int lim) @(posedge clk);
+                  option.per_instance = 1;
+                  type_option.weight = 2;
+                  bit [7:0] cp_v: coverpoint v iff (!rst) {
+                    option.auto_bin_max = 16;
+                    bins zero = {0};
+                    bins low[4] = {[1:lim], 100} iff (en);
+                    bins high[] = {[200:$]} with (item % 2 == 0);
+                    wildcard bins odd = {8'b???????1};
+                    bins seq = (1 => 2 => 3), ([4:5], 6 => 7 [* 2:3]), (8 [-> 2] => 9 [= 1]);
+                    wildcard bins tr[] = (8'b0000000? => 8'b1111111?);
+                    ignore_bins ign = {[10:20]};
+                    illegal_bins ill = default sequence;
+                    bins others = default;
+                  }
+                  cp_w: coverpoint w;
+                  vxw: cross cp_v, cp_w iff (en) {
+                    option.weight = 0;
+                    bins b1 = binsof(cp_v.zero) || binsof(cp_w) intersect {[0:3]} && !binsof(cp_v.low);
+                    ignore_bins b2 = (binsof(cp_v) intersect {1, [2:5]}) with (cp_v > cp_w) matches 2;
+                    illegal_bins b3 = vxw with (cp_v == cp_w);
+                  }
+                endgroup"##,
+            Ok((_, _))
+        );
         test!(
             many1(module_item),
             r##"covergroup yy;
@@ -15267,22 +15978,20 @@ mod spec {
                 endspecify"##,
             Ok((_, _))
         );
-        // TODO
-        // specify_input_terminal_descriptor can have $
-        //test!(
-        //    many1(module_item),
-        //    r##"specify
-        //        (clk => q) = 12;
-        //        (data => q) = 10;
-        //        (clr, pre *> q) = 4;
-
-        //        specparam
-        //          PATHPULSE$clk$q = (2,9),
-        //          PATHPULSE$clr$q = (0,4),
-        //          PATHPULSE$ = 3;
-        //        endspecify"##,
-        //    Ok((_, _))
-        //);
+        test!(
+            many1(module_item),
+            r##"specify
+                  (clk => q) = 12;
+                  (data => q) = 10;
+                  (clr, pre *> q) = 4;
+
+                  specparam
+                    PATHPULSE$clk$q = (2,9),
+                    PATHPULSE$clr$q = (0,4),
+                    PATHPULSE$ = 3;
+                endspecify"##,
+            Ok((_, _))
+        );
         test!(
             many1(module_item),
             r##"specify
@@ -15380,21 +16089,26 @@ mod spec {
                 endspecify"##,
             Ok((_, _))
         );
-        // TODO
-        // $width must have threshold
-        //test!(
-        //    many1(module_item),
-        //    r##"specify
-        //          // Legal Calls
-        //          $width ( negedge clr, lim );
-        //          $width ( negedge clr, lim, thresh, notif );
-        //          $width ( negedge clr, lim, 0, notif );
-        //          // Illegal Calls
-        //          //$width ( negedge clr, lim, , notif );
-        //          //$width ( negedge clr, lim, notif );
-        //        endspecify"##,
-        //    Ok((_, _))
-        //);
+        test!(
+            many1(module_item),
+            r##"specify
+                  // Legal Calls
+                  $width ( negedge clr, lim );
+                  $width ( negedge clr, lim, thresh, notif );
+                  $width ( negedge clr, lim, 0, notif );
+                  // Illegal Calls
+                  //$width ( negedge clr, lim, , notif );
+                  //$width ( negedge clr, lim, notif );
+                endspecify"##,
+            Ok((_, _))
+        );
+        test!(
+            many1(module_item),
+            r##"specify
+                  $width ( negedge clr, lim, , notif );
+                endspecify"##,
+            Err(_)
+        );
         test!(
             many1(module_item),
             r##"specify
@@ -15646,87 +16360,87 @@ mod spec {
                 endconfig"##,
             Ok((_, _))
         );
-        // TODO
-        // use don't have #()
-        //test!(
-        //    source_text,
-        //    r##"config cfgl;
-        //          design rtlLib.top;
-        //          instance top use #(.WIDTH(32));
-        //          instance top.a1 use #(.W(top.WIDTH));
-        //        endconfig"##,
-        //    Ok((_, _))
-        //);
-        // TODO
-        // use don't have #()
-        //test!(
-        //    source_text,
-        //    r##"module top4 ();
-        //          parameter S = 16;
-        //          adder a1 #(.ID("a1"))();
-        //          adder a2 #(.ID("a2"))();
-        //          adder a3 #(.ID("a3"))();
-        //          adder a4 #(.ID("a4"))();
-        //        endmodule
-
-        //        config cfg2;
-        //          localparam S = 24;
-        //          design rtlLib.top4;
-        //          instance top4.a1 use #(.W(top4.S));
-        //          instance top4.a2 use #(.W(S));
-        //        endconfig"##,
-        //    Ok((_, _))
-        //);
-        // TODO
-        // use don't have #()
-        //test!(
-        //    source_text,
-        //    r##"config cfg3;
-        //          design rtlLib.top5;
-        //          instance top5.a1 use #(.W()); // set only parameter W back to its default
-        //        endconfig"##,
-        //    Ok((_, _))
-        //);
-        // TODO
-        // use don't have #()
-        //test!(
-        //    source_text,
-        //    r##"config cfg4;
-        //          design rtlLib.top;
-        //          instance top.a1 use #(); // set all parameters in instance a1
-        //                                   // back to their defaults
-        //        endconfig"##,
-        //    Ok((_, _))
-        //);
-        // TODO
-        // use don't have #()
-        //test!(
-        //    source_text,
-        //    r##"module test;
-        //          top8 t();
-        //          defparam t.WIDTH = 64;
-        //          defparam t.a1.W = 16;
-        //        endmodule
-
-        //        module top8 ();
-        //          parameter WIDTH = 32;
-        //          adder a1 #(.ID("a1")) ();
-        //          adder a2 #(.ID("a2"),.W(WIDTH))();
-        //        endmodule
-
-        //        module adder #(parameter ID = "id",
-        //                                 W = 8,
-        //                                 D = 512)
-        //                     ();
-        //          $display("ID = %s, W = %d, D = %d", ID, W, D);
-        //        endmodule
-
-        //        config cfg6;
-        //          design rtlLib.test;
-        //          instance test.t use #(.WIDTH(48));
-        //        endconfig"##,
-        //    Ok((_, _))
-        //);
+        test!(
+            source_text,
+            r##"config cfgl;
+                  design rtlLib.top;
+                  instance top use #(.WIDTH(32));
+                  instance top.a1 use #(.W(top.WIDTH));
+                endconfig"##,
+            Ok((_, _))
+        );
+        test!(
+            source_text,
+            r##"module top4 ();
+                  parameter S = 16;
+                  adder #(.ID("a1")) a1 ();
+                  adder #(.ID("a2")) a2 ();
+                  adder #(.ID("a3")) a3 ();
+                  adder #(.ID("a4")) a4 ();
+                endmodule
+
+                config cfg2;
+                  localparam S = 24;
+                  design rtlLib.top4;
+                  instance top4.a1 use #(.W(top4.S));
+                  instance top4.a2 use #(.W(S));
+                endconfig"##,
+            Ok((_, _))
+        );
+        test!(
+            source_text,
+            r##"config cfg3;
+                  design rtlLib.top5;
+                  instance top5.a1 use #(.W()); // set only parameter W back to its default
+                endconfig"##,
+            Ok((_, _))
+        );
+        test!(
+            source_text,
+            r##"config cfg4;
+                  design rtlLib.top;
+                  instance top.a1 use #(); // set all parameters in instance a1
+                                           // back to their defaults
+                endconfig"##,
+            Ok((_, _))
+        );
+        test!(
+            source_text,
+            r##"config cfg5;
+                  design rtlLib.top;
+                  instance top.a1 use gateLib.adder #(.W(16));
+                  instance top.a2 use adder .W(16), .D(64) : config;
+                  cell adder use rtlLib.adder #() : config;
+                endconfig"##,
+            Ok((_, _))
+        );
+        test!(
+            source_text,
+            r##"module test;
+                  top8 t();
+                  defparam t.WIDTH = 64;
+                  defparam t.a1.W = 16;
+                endmodule
+
+                module top8 ();
+                  parameter WIDTH = 32;
+                  adder #(.ID("a1")) a1 ();
+                  adder #(.ID("a2"),.W(WIDTH)) a2 ();
+                endmodule
+
+                module adder #(parameter ID = "id",
+                                         W = 8,
+                                         D = 512)
+                             ();
+                  initial $display("ID = %s, W = %d, D = %d", ID, W, D);
+                endmodule
+
+                config cfg6;
+                  design rtlLib.test;
+                  instance test.t use #(.WIDTH(48));
+                endconfig"##,
+            Ok((_, _))
+        );
         test!(
             source_text,
             r##"config cfg1;
@@ -15845,16 +16559,23 @@ mod spec {
 
     #[test]
     fn clause35() {
-        // TODO
-        // c_identifier can't accept \begin.
-        //test!(
-        //    source_text,
-        //    r##"export "DPI-C" f_plus = function \f+ ; // "f+" exported as "f_plus"
-        //        export "DPI-C" function f; // "f" exported under its own name
-        //        import "DPI-C" init_1 = function void \init[1] (); // "init_1" is a linkage name
-        //        import "DPI-C" \begin = function void \init[2] (); // "begin" is a linkage name"##,
-        //    Ok((_, _))
-        //);
+        test!(
+            source_text,
+            r##"export "DPI-C" f_plus = function \f+ ; // "f+" exported as "f_plus"
+                export "DPI-C" function f; // "f" exported under its own name
+                import "DPI-C" init_1 = function void \init[1] (); // "init_1" is a linkage name
+                import "DPI-C" \begin = function void \init[2] (); // "begin" is a linkage name"##,
+            Ok((_, _))
+        );
+        test!(
+            source_text,
+            r##"package dpi_pkg;
+                  import "DPI" context task c_wait(input int n);
+                  import "DPI-C" pure c_sqrt = function real sqrt(input real x);
+                  export "DPI-C" c_notify = task sv_notify;
+                endpackage"##,
+            Ok((_, _))
+        );
         test!(
             source_text,
             r##"import "DPI-C" function void myInit();
@@ -0,0 +1,233 @@
+use crate::*;
+
+// -----------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    /// A simple or escaped identifier.
+    Identifier,
+    /// A system task or function name, e.g. `$display`.
+    SystemIdentifier,
+    /// A number, including its size, base and time unit.
+    Number,
+    String,
+    Operator,
+    Comment,
+    /// A compiler directive or macro usage, e.g. `` `define ``.
+    Directive,
+    /// A character which begins no token.
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub locate: Locate,
+}
+
+// Operators and punctuation, longest first so that the first match is the
+// longest.
+const OPERATORS: &[&str] = &[
+    "<<<=", ">>>=", "*::*", "===", "!==", "==?", "!=?", "<<<", ">>>", "<<=", ">>=", "<->", "|->",
+    "|=>", "->>", "#-#", "#=#", "==", "!=", "&&", "||", "**", "<=", ">=", "<<", ">>", "->", "++",
+    "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "~&", "~|", "~^", "^~", "::", ":=", ":/",
+    ".*", "##", "@@", "'{", "+:", "-:", "+", "-", "*", "/", "%", "=", "<", ">", "!", "~", "&", "|",
+    "^", "?", ":", ";", ",", ".", "(", ")", "[", "]", "{", "}", "#", "@", "'", "$",
+];
+
+const TIME_UNITS: &[&str] = &["ms", "us", "ns", "ps", "fs", "s"];
+
+/// Split `s` into tokens, without parsing it. White space is skipped, and
+/// every other character belongs to a token. Keywords are those of
+/// IEEE1800-2017, or of the version given by `begin_keywords.
+pub fn tokenize(s: &str) -> Vec<Token> {
+    let mut ret = Vec::new();
+    let mut versions: Vec<Option<Version>> = Vec::new();
+    let mut in_begin_keywords = false;
+    let mut pos = 0;
+    let mut line = 1;
+    let mut column = 1;
+    while let Some(c) = s[pos..].chars().next() {
+        let rest = &s[pos..];
+        let (kind, len) = if c.is_whitespace() {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += c.len_utf8() as u32;
+            }
+            pos += c.len_utf8();
+            continue;
+        } else if rest.starts_with("//") {
+            (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            let len = body.find("*/").map_or(rest.len(), |x| x + 4);
+            (TokenKind::Comment, len)
+        } else if c == '"' {
+            (TokenKind::String, string_len(rest).0)
+        } else if c == '\\' {
+            let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (TokenKind::Identifier, len)
+        } else if c == '`' && rest[1..].starts_with(is_word) {
+            (TokenKind::Directive, 1 + word_len(&rest[1..]))
+        } else if c == '$' && rest[1..].starts_with(is_word) {
+            (TokenKind::SystemIdentifier, 1 + word_len(&rest[1..]))
+        } else if c.is_ascii_digit() {
+            (TokenKind::Number, number_len(rest))
+        } else if c == '\'' && based_len(rest) > 0 {
+            (TokenKind::Number, based_len(rest))
+        } else if c == '\''
+            && rest[1..].starts_with(|c| "01xXzZ".contains(c))
+            && !rest[2..].starts_with(is_word)
+        {
+            (TokenKind::Number, 2)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = word_len(rest);
+            let keywords = keywords_of(versions.last().copied().flatten());
+            if keywords.contains(&rest[..len]) {
+                (TokenKind::Keyword, len)
+            } else {
+                (TokenKind::Identifier, len)
+            }
+        } else if let Some(x) = OPERATORS.iter().find(|x| rest.starts_with(*x)) {
+            (TokenKind::Operator, x.len())
+        } else {
+            (TokenKind::Unknown, c.len_utf8())
+        };
+
+        let text = &rest[..len];
+        match kind {
+            TokenKind::Directive if text == "`begin_keywords" => in_begin_keywords = true,
+            TokenKind::Directive if text == "`end_keywords" => {
+                versions.pop();
+            }
+            TokenKind::String if in_begin_keywords => {
+                versions.push(version_of(text.trim_matches('"')));
+                in_begin_keywords = false;
+            }
+            TokenKind::Comment => (),
+            _ => in_begin_keywords = false,
+        }
+
+        ret.push(Token {
+            kind,
+            locate: Locate {
+                offset: pos,
+                line,
+                column,
+                len,
+            },
+        });
+        match text.rfind('\n') {
+            Some(x) => {
+                line += text.matches('\n').count() as u32;
+                column = (len - x) as u32;
+            }
+            None => column += len as u32,
+        }
+        pos += len;
+    }
+    ret
+}
+
+/// The first string literal or block comment of `s` which isn't closed: a
+/// string literal before an unescaped newline, or a block comment before the
+/// end of `s`.
+pub fn find_unterminated(s: &str) -> Option<Token> {
+    tokenize(s).into_iter().find(|x| {
+        let text = x.locate.str(s);
+        match x.kind {
+            TokenKind::String => !string_len(text).1,
+            TokenKind::Comment => {
+                text.starts_with("/*") && (text.len() < 4 || !text.ends_with("*/"))
+            }
+            _ => false,
+        }
+    })
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
+fn word_len(s: &str) -> usize {
+    s.find(|c| !is_word(c)).unwrap_or(s.len())
+}
+
+// The length of the string literal at the beginning of `s`, which ends at the
+// closing quote or at an unescaped newline, and whether it is closed.
+fn string_len(s: &str) -> (usize, bool) {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            '"' if !escaped => return (i + 1, true),
+            '\n' if !escaped => return (i, false),
+            _ => escaped = !escaped && c == '\\',
+        }
+    }
+    (s.len(), false)
+}
+
+// The length of the number at the beginning of `s`, which begins with a
+// digit: an unsigned or real number with an optional time unit, or a size
+// followed by a based number.
+fn number_len(s: &str) -> usize {
+    let digits = |s: &str| {
+        s.find(|c: char| !c.is_ascii_digit() && c != '_')
+            .unwrap_or(s.len())
+    };
+    let mut len = digits(s);
+    let size = len;
+    let rest = &s[len..];
+    if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        len += 1 + digits(&rest[1..]);
+    }
+    let rest = &s[len..];
+    if rest.starts_with(['e', 'E']) {
+        let sign = usize::from(rest[1..].starts_with(['+', '-']));
+        if rest[1 + sign..].starts_with(|c: char| c.is_ascii_digit()) {
+            len += 1 + sign + digits(&rest[1 + sign..]);
+        }
+    }
+    let rest = &s[len..];
+    if let Some(x) = TIME_UNITS.iter().find(|x| rest.starts_with(*x)) {
+        if !rest[x.len()..].starts_with(is_word) {
+            return len + x.len();
+        }
+    }
+    if len == size {
+        len + based_len(&s[len..])
+    } else {
+        len
+    }
+}
+
+// The length of the based number at the beginning of `s`, from the
+// apostrophe, or 0 if there is none.
+fn based_len(s: &str) -> usize {
+    let mut chars = s.char_indices().peekable();
+    if chars.next().map(|x| x.1) != Some('\'') {
+        return 0;
+    }
+    if chars.peek().is_some_and(|x| x.1 == 's' || x.1 == 'S') {
+        chars.next();
+    }
+    match chars.next() {
+        Some((_, c)) if "bBoOdDhH".contains(c) => (),
+        _ => return 0,
+    }
+    while chars.peek().is_some_and(|x| x.1 == ' ' || x.1 == '\t') {
+        chars.next();
+    }
+    let begin = chars.peek().map_or(s.len(), |x| x.0);
+    let value = &s[begin..];
+    let len = value
+        .find(|c: char| !c.is_ascii_hexdigit() && !"xXzZ?_".contains(c))
+        .unwrap_or(value.len());
+    if len == 0 {
+        0
+    } else {
+        begin + len
+    }
+}
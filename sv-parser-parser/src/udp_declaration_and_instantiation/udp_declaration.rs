@@ -35,13 +35,16 @@ pub(crate) fn udp_ansi_declaration(s: Span) -> IResult<Span, UdpAnsiDeclaration>
 #[tracable_parser]
 #[packrat_parser]
 pub(crate) fn udp_declaration(s: Span) -> IResult<Span, UdpDeclaration> {
-    alt((
-        udp_declaration_nonansi,
-        udp_declaration_ansi,
-        udp_declaration_extern_nonansi,
-        udp_declaration_extern_ansi,
-        udp_declaration_wildcard,
-    ))(s)
+    context(
+        "udp_declaration",
+        alt((
+            udp_declaration_nonansi,
+            udp_declaration_ansi,
+            udp_declaration_extern_nonansi,
+            udp_declaration_extern_ansi,
+            udp_declaration_wildcard,
+        )),
+    )(s)
 }
 
 #[tracable_parser]
@@ -9,6 +9,7 @@ where
     F: FnMut(Span<'a>) -> IResult<Span<'a>, O>,
 {
     move |s: Span<'a>| {
+        let (s, _) = work(s)?;
         let (s, x) = f(s)?;
         let (s, y) = many0(white_space)(s)?;
         Ok((s, (x, y)))
@@ -30,7 +31,8 @@ where
 #[cfg(not(feature = "trace"))]
 pub(crate) fn symbol<'a>(t: &'a str) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, Symbol> {
     move |s: Span<'a>| {
-        let (s, x) = map(ws(map(tag(t), into_locate)), |x| Symbol { nodes: x })(s)?;
+        let (s, x) = map(ws(map(tag(t), into_locate)), |x| Symbol { nodes: x })(s)
+            .map_err(|e| expect(e, &s, t))?;
         Ok((s, x))
     }
 }
@@ -40,7 +42,8 @@ pub(crate) fn symbol<'a>(t: &'a str) -> impl FnMut(Span<'a>) -> IResult<Span<'a>
     move |s: Span<'a>| {
         let (depth, s) = nom_tracable::forward_trace(s, &format!("symbol(\"{}\")", t));
         let body = || {
-            let (s, x) = map(ws(map(tag(t), into_locate)), |x| Symbol { nodes: x })(s)?;
+            let (s, x) = map(ws(map(tag(t), into_locate)), |x| Symbol { nodes: x })(s)
+                .map_err(|e| expect(e, &s, t))?;
             Ok((s, x))
         };
         let ret = body();
@@ -51,7 +54,8 @@ pub(crate) fn symbol<'a>(t: &'a str) -> impl FnMut(Span<'a>) -> IResult<Span<'a>
 #[cfg(not(feature = "trace"))]
 pub(crate) fn symbol_exact<'a>(t: &'a str) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, Symbol> {
     move |s: Span<'a>| {
-        let (s, x) = map(no_ws(map(tag(t), into_locate)), |x| Symbol { nodes: x })(s)?;
+        let (s, x) = map(no_ws(map(tag(t), into_locate)), |x| Symbol { nodes: x })(s)
+            .map_err(|e| expect(e, &s, t))?;
         Ok((s, x))
     }
 }
@@ -61,7 +65,8 @@ pub(crate) fn symbol_exact<'a>(t: &'a str) -> impl FnMut(Span<'a>) -> IResult<Sp
     move |s: Span<'a>| {
         let (depth, s) = nom_tracable::forward_trace(s, &format!("symbol(\"{}\")", t));
         let body = || {
-            let (s, x) = map(no_ws(map(tag(t), into_locate)), |x| Symbol { nodes: x })(s)?;
+            let (s, x) = map(no_ws(map(tag(t), into_locate)), |x| Symbol { nodes: x })(s)
+                .map_err(|e| expect(e, &s, t))?;
             Ok((s, x))
         };
         let ret = body();
@@ -78,7 +83,8 @@ pub(crate) fn keyword<'a>(t: &'a str) -> impl FnMut(Span<'a>) -> IResult<Span<'a
                 terminated(map(tag(t), into_locate), peek(none_of(AZ09_))),
             ))),
             |x| Keyword { nodes: x },
-        )(s)?;
+        )(s)
+        .map_err(|e| expect(e, &s, t))?;
         Ok((s, x))
     }
 }
@@ -94,7 +100,43 @@ pub(crate) fn keyword<'a>(t: &'a str) -> impl FnMut(Span<'a>) -> IResult<Span<'a
                     terminated(map(tag(t), into_locate), peek(none_of(AZ09_))),
                 ))),
                 |x| Keyword { nodes: x },
-            )(s)?;
+            )(s)
+            .map_err(|e| expect(e, &s, t))?;
+            Ok((s, x))
+        };
+        let ret = body();
+        nom_tracable::backward_trace(ret, &format!("keyword(\"{}\")", t), depth)
+    }
+}
+
+#[cfg(not(feature = "trace"))]
+pub(crate) fn keyword_exact<'a>(t: &'a str) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, Keyword> {
+    move |s: Span<'a>| {
+        let (s, x) = map(
+            no_ws(alt((
+                all_consuming(map(tag(t), into_locate)),
+                terminated(map(tag(t), into_locate), peek(none_of(AZ09_))),
+            ))),
+            |x| Keyword { nodes: x },
+        )(s)
+        .map_err(|e| expect(e, &s, t))?;
+        Ok((s, x))
+    }
+}
+
+#[cfg(feature = "trace")]
+pub(crate) fn keyword_exact<'a>(t: &'a str) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, Keyword> {
+    move |s: Span<'a>| {
+        let (depth, s) = nom_tracable::forward_trace(s, &format!("keyword(\"{}\")", t));
+        let body = || {
+            let (s, x) = map(
+                no_ws(alt((
+                    all_consuming(map(tag(t), into_locate)),
+                    terminated(map(tag(t), into_locate), peek(none_of(AZ09_))),
+                ))),
+                |x| Keyword { nodes: x },
+            )(s)
+            .map_err(|e| expect(e, &s, t))?;
             Ok((s, x))
         };
         let ret = body();
@@ -261,6 +303,42 @@ where
     }
 }
 
+// Parse `f` as many times as it matches, as nom's many0 does. The vector is
+// only allocated for the first item, and sized to the items at the end, as
+// it is kept in the tree and most of these vectors have none or one item.
+pub(crate) fn many0<'a, O, F>(mut f: F) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, Vec<O>>
+where
+    F: FnMut(Span<'a>) -> IResult<Span<'a>, O>,
+{
+    move |s: Span<'a>| {
+        let mut s = s;
+        let mut ret = Vec::new();
+        loop {
+            match f(s) {
+                Ok((t, x)) => {
+                    if t.fragment().len() == s.fragment().len() {
+                        return Err(Err::Error(make_error(s, ErrorKind::Many0)));
+                    }
+                    s = t;
+                    push_compact(&mut ret, x);
+                }
+                Err(Err::Error(_)) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        ret.shrink_to_fit();
+        Ok((s, ret))
+    }
+}
+
+// Push `x` to `v`, making room for `x` alone if it is the first item.
+fn push_compact<T>(v: &mut Vec<T>, x: T) {
+    if v.is_empty() {
+        v.reserve_exact(1);
+    }
+    v.push(x);
+}
+
 pub(crate) fn list<'a, O1, O2, F, G>(
     mut f: F,
     mut g: G,
@@ -276,11 +354,12 @@ where
         while let Ok((t, b)) = f(s) {
             if let Ok((u, c)) = g(t) {
                 s = u;
-                ret.push((b, c));
+                push_compact(&mut ret, (b, c));
             } else {
                 break;
             }
         }
+        ret.shrink_to_fit();
         Ok((s, List { nodes: (a, ret) }))
     }
 }
@@ -355,6 +434,153 @@ pub(crate) fn clear_directive() {
 
 // -----------------------------------------------------------------------------
 
+thread_local!(
+    static EXPECTED: core::cell::RefCell<Option<(usize, Vec<String>)>> = const {
+        core::cell::RefCell::new(None)
+    }
+);
+
+// Record that the symbol or keyword `t` was expected at `s`, keeping only
+// those expected at the furthest position.
+fn expect<'a>(
+    e: Err<GreedyError<Span<'a>, ErrorKind>>,
+    s: &Span<'a>,
+    t: &str,
+) -> Err<GreedyError<Span<'a>, ErrorKind>> {
+    let pos = s.location_offset();
+    EXPECTED.with(|x| {
+        let mut x = x.borrow_mut();
+        match &mut *x {
+            Some((furthest, expected)) if *furthest == pos => {
+                if !expected.iter().any(|x| x == t) {
+                    expected.push(String::from(t));
+                }
+            }
+            Some((furthest, _)) if *furthest > pos => (),
+            _ => *x = Some((pos, vec![String::from(t)])),
+        }
+    });
+    e
+}
+
+pub(crate) fn clear_expected() {
+    EXPECTED.with(|x| *x.borrow_mut() = None);
+}
+
+/// The furthest position at which the last parse on this thread failed to
+/// find a symbol or keyword, and the symbols and keywords expected there.
+pub fn expected() -> Option<(usize, Vec<String>)> {
+    EXPECTED.with(|x| x.borrow().clone())
+}
+
+// -----------------------------------------------------------------------------
+
+/// The default of `nest_limit()`.
+pub const DEFAULT_NEST_LIMIT: usize = 256;
+
+thread_local!(
+    static NEST_LIMIT: core::cell::Cell<usize> = const {
+        core::cell::Cell::new(DEFAULT_NEST_LIMIT)
+    }
+);
+
+/// The depth of nested parentheses, concatenations and statements beyond which
+/// parsing on this thread fails, instead of overflowing the stack. The stack
+/// used by each level depends on the build, so this is to be raised only along
+/// with the stack size of the thread.
+pub fn nest_limit() -> usize {
+    NEST_LIMIT.with(|x| x.get())
+}
+
+/// Set `nest_limit()` for the parsing on this thread.
+pub fn set_nest_limit(limit: usize) {
+    NEST_LIMIT.with(|x| x.set(limit));
+}
+
+// Parse with `f` one level deeper, failing without backtracking beyond
+// `nest_limit()`.
+pub(crate) fn nest<'a, O, F>(mut f: F) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, O>
+where
+    F: FnMut(Span<'a>) -> IResult<Span<'a>, O>,
+{
+    move |mut s: Span<'a>| {
+        let depth = s.extra.depth;
+        if depth >= nest_limit() {
+            return Err(Err::Failure(make_error(s, ErrorKind::TooLarge)));
+        }
+        s.extra.depth = depth + 1;
+        let (mut s, x) = f(s)?;
+        s.extra.depth = depth;
+        Ok((s, x))
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+thread_local!(
+    static TOLERANT_SYSTEM_TF: core::cell::Cell<bool> = const {
+        core::cell::Cell::new(false)
+    }
+);
+
+/// Whether the parsing on this thread accepts system task and function calls
+/// whose arguments match no rule of the LRM, as found in some vendor netlists.
+/// Such arguments are kept as a balanced list of tokens.
+pub fn tolerant_system_tf() -> bool {
+    TOLERANT_SYSTEM_TF.with(|x| x.get())
+}
+
+/// Set `tolerant_system_tf()` for the parsing on this thread.
+pub fn set_tolerant_system_tf(tolerant: bool) {
+    TOLERANT_SYSTEM_TF.with(|x| x.set(tolerant));
+}
+
+// -----------------------------------------------------------------------------
+
+thread_local!(
+    static WORK_LIMIT: core::cell::Cell<Option<usize>> = const {
+        core::cell::Cell::new(None)
+    }
+);
+
+thread_local!(
+    static WORK: core::cell::Cell<usize> = const {
+        core::cell::Cell::new(0)
+    }
+);
+
+/// The number of tokens which the parsing of a source text on this thread
+/// may try to parse, counting again those tried after backtracking, beyond
+/// which it fails. This bounds the time taken by pathological input. There
+/// is no limit by default.
+pub fn work_limit() -> Option<usize> {
+    WORK_LIMIT.with(|x| x.get())
+}
+
+/// Set `work_limit()` for the parsing on this thread.
+pub fn set_work_limit(limit: Option<usize>) {
+    WORK_LIMIT.with(|x| x.set(limit));
+}
+
+pub(crate) fn clear_work() {
+    WORK.with(|x| x.set(0));
+}
+
+// Count an attempt to parse a token at `s`, failing without backtracking
+// with `ErrorKind::Count` beyond `work_limit()`.
+pub(crate) fn work(s: Span) -> IResult<Span, ()> {
+    let work = WORK.with(|x| {
+        x.set(x.get() + 1);
+        x.get()
+    });
+    match work_limit() {
+        Some(limit) if work > limit => Err(Err::Failure(make_error(s, ErrorKind::Count))),
+        _ => Ok((s, ())),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
 #[derive(Clone, Copy, Debug)]
 pub(crate) enum Version {
     Ieee1364_1995,
@@ -374,21 +600,26 @@ thread_local!(
     }
 );
 
+// The version named by the argument of `begin_keywords.
+pub(crate) fn version_of(version: &str) -> Option<Version> {
+    match version {
+        "1364-1995" => Some(Version::Ieee1364_1995),
+        "1364-2001" => Some(Version::Ieee1364_2001),
+        "1364-2001-noconfig" => Some(Version::Ieee1364_2001Noconfig),
+        "1364-2005" => Some(Version::Ieee1364_2005),
+        "1800-2005" => Some(Version::Ieee1800_2005),
+        "1800-2009" => Some(Version::Ieee1800_2009),
+        "1800-2012" => Some(Version::Ieee1800_2012),
+        "1800-2017" => Some(Version::Ieee1800_2017),
+        "directive" => Some(Version::Directive),
+        _ => None,
+    }
+}
+
 pub(crate) fn begin_keywords(version: &str) {
-    CURRENT_VERSION.with(|current_version| match version {
-        "1364-1995" => current_version.borrow_mut().push(Version::Ieee1364_1995),
-        "1364-2001" => current_version.borrow_mut().push(Version::Ieee1364_2001),
-        "1364-2001-noconfig" => current_version
-            .borrow_mut()
-            .push(Version::Ieee1364_2001Noconfig),
-        "1364-2005" => current_version.borrow_mut().push(Version::Ieee1364_2005),
-        "1800-2005" => current_version.borrow_mut().push(Version::Ieee1800_2005),
-        "1800-2009" => current_version.borrow_mut().push(Version::Ieee1800_2009),
-        "1800-2012" => current_version.borrow_mut().push(Version::Ieee1800_2012),
-        "1800-2017" => current_version.borrow_mut().push(Version::Ieee1800_2017),
-        "directive" => current_version.borrow_mut().push(Version::Directive),
-        _ => (),
-    });
+    if let Some(version) = version_of(version) {
+        CURRENT_VERSION.with(|current_version| current_version.borrow_mut().push(version));
+    }
 }
 
 pub(crate) fn end_keywords() {
@@ -425,30 +656,37 @@ pub(crate) fn concat<'a>(a: Span<'a>, b: Span<'a>) -> Option<Span<'a>> {
 }
 
 pub(crate) fn is_keyword(s: &Span) -> bool {
-    let keywords = match current_version() {
-        Some(Version::Ieee1364_1995) => KEYWORDS_1364_1995,
-        Some(Version::Ieee1364_2001) => KEYWORDS_1364_2001,
-        Some(Version::Ieee1364_2001Noconfig) => KEYWORDS_1364_2001_NOCONFIG,
-        Some(Version::Ieee1364_2005) => KEYWORDS_1364_2005,
-        Some(Version::Ieee1800_2005) => KEYWORDS_1800_2005,
-        Some(Version::Ieee1800_2009) => KEYWORDS_1800_2009,
-        Some(Version::Ieee1800_2012) => KEYWORDS_1800_2012,
-        Some(Version::Ieee1800_2017) => KEYWORDS_1800_2017,
-        Some(Version::Directive) => KEYWORDS_DIRECTIVE,
-        None => KEYWORDS_1800_2017,
-    };
-    for k in keywords {
-        if s.fragment() == k {
-            return true;
-        }
+    keywords_of(current_version()).contains(*s.fragment())
+}
+
+// The keywords of `version`, or of IEEE1800-2017 by default. They are perfect
+// hash sets, as every identifier is looked up in them.
+pub(crate) fn keywords_of(version: Option<Version>) -> &'static phf::Set<&'static str> {
+    match version {
+        Some(Version::Ieee1364_1995) => &KEYWORDS_1364_1995,
+        Some(Version::Ieee1364_2001) => &KEYWORDS_1364_2001,
+        Some(Version::Ieee1364_2001Noconfig) => &KEYWORDS_1364_2001_NOCONFIG,
+        Some(Version::Ieee1364_2005) => &KEYWORDS_1364_2005,
+        Some(Version::Ieee1800_2005) => &KEYWORDS_1800_2005,
+        Some(Version::Ieee1800_2009) => &KEYWORDS_1800_2009,
+        Some(Version::Ieee1800_2012) => &KEYWORDS_1800_2012,
+        Some(Version::Ieee1800_2017) => &KEYWORDS_1800_2017,
+        Some(Version::Directive) => &KEYWORDS_DIRECTIVE,
+        None => &KEYWORDS_1800_2017,
     }
-    false
+}
+
+/// Whether `t` is a keyword or operator of IEEE1800-2017 which IEEE1364-2005
+/// lacks.
+pub fn is_systemverilog_only(t: &str) -> bool {
+    (KEYWORDS_1800_2017.contains(t) && !KEYWORDS_1364_2005.contains(t)) || SYMBOLS_1800.contains(&t)
 }
 
 pub(crate) fn into_locate(s: Span) -> Locate {
     Locate {
         offset: s.location_offset(),
         line: s.location_line(),
+        column: s.get_column() as u32,
         len: s.fragment().len(),
     }
 }
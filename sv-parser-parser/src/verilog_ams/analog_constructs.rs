@@ -0,0 +1,64 @@
+use crate::*;
+
+// -----------------------------------------------------------------------------
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn analog_construct(s: Span) -> IResult<Span, AnalogConstruct> {
+    let (s, a) = keyword("analog")(s)?;
+    let (s, b) = opt(keyword("initial"))(s)?;
+    let (s, c) = statement(s)?;
+    Ok((s, AnalogConstruct { nodes: (a, b, c) }))
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn branch_declaration(s: Span) -> IResult<Span, BranchDeclaration> {
+    let (s, a) = keyword("branch")(s)?;
+    let (s, b) = paren(list(symbol(","), branch_terminal))(s)?;
+    let (s, c) = list(symbol(","), branch_identifier)(s)?;
+    let (s, d) = symbol(";")(s)?;
+    Ok((
+        s,
+        BranchDeclaration {
+            nodes: (a, b, c, d),
+        },
+    ))
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn branch_terminal(s: Span) -> IResult<Span, BranchTerminal> {
+    let (s, a) = net_identifier(s)?;
+    let (s, b) = opt(bracket(constant_range_expression))(s)?;
+    Ok((s, BranchTerminal { nodes: (a, b) }))
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn contribution_statement(s: Span) -> IResult<Span, ContributionStatement> {
+    let (s, a) = branch_lvalue(s)?;
+    let (s, b) = symbol("<+")(s)?;
+    let (s, c) = expression(s)?;
+    let (s, d) = symbol(";")(s)?;
+    Ok((
+        s,
+        ContributionStatement {
+            nodes: (a, b, c, d),
+        },
+    ))
+}
+
+#[tracable_parser]
+#[packrat_parser]
+pub(crate) fn branch_lvalue(s: Span) -> IResult<Span, BranchLvalue> {
+    let (s, a) = identifier(s)?;
+    let (s, b) = paren(list(symbol(","), branch_terminal))(s)?;
+    Ok((s, BranchLvalue { nodes: (a, b) }))
+}
+
+#[tracable_parser]
+pub(crate) fn branch_identifier(s: Span) -> IResult<Span, BranchIdentifier> {
+    let (s, a) = identifier(s)?;
+    Ok((s, BranchIdentifier { nodes: (a,) }))
+}
@@ -0,0 +1,221 @@
+use crate::preprocess::{expand_env, Define, DefineText, Defines, RECURSIVE_LIMIT};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use sv_parser_error::Error;
+
+/// Parse a simulator-style argument file, returning the source files,
+/// include paths, and pre-defines it specifies, ready to be given to
+/// `preprocess()`.
+///
+/// The following arguments are recognized:
+/// - `-f FILE` reads further arguments from `FILE`.
+/// - `-F FILE` is the same as `-f`, except that relative paths inside `FILE`
+///   are interpreted relative to the directory containing it.
+/// - `+incdir+DIR[+DIR...]` appends include paths.
+/// - `+define+NAME[=VALUE][+NAME[=VALUE]...]` adds pre-defines.
+///
+/// Any other argument beginning with `-` or `+` is ignored, and everything
+/// else is taken as a source file.
+/// Environment variables in paths are expanded, and `//` and `/* */`
+/// comments are ignored.
+pub fn parse_file<T: AsRef<Path>>(
+    path: T,
+) -> Result<(Vec<PathBuf>, Vec<PathBuf>, Defines), Error> {
+    let mut files = Vec::new();
+    let mut include_paths = Vec::new();
+    let mut defines = HashMap::new();
+    parse_file_inner(
+        path.as_ref(),
+        None,
+        &mut files,
+        &mut include_paths,
+        &mut defines,
+        0,
+    )?;
+    Ok((files, include_paths, defines))
+}
+
+fn parse_file_inner(
+    path: &Path,
+    base: Option<&Path>,
+    files: &mut Vec<PathBuf>,
+    include_paths: &mut Vec<PathBuf>,
+    defines: &mut Defines,
+    depth: usize,
+) -> Result<(), Error> {
+    if depth > RECURSIVE_LIMIT {
+        return Err(Error::ExceedRecursiveLimit);
+    }
+
+    let s = fs::read_to_string(path).map_err(|x| Error::File {
+        source: x,
+        path: PathBuf::from(path),
+    })?;
+
+    let resolve = |x: &str| {
+        let x = expand_env(Path::new(x.trim_matches('"')));
+        match base {
+            Some(base) if x.is_relative() => base.join(x),
+            _ => x,
+        }
+    };
+
+    let mut tokens = split_args(&s).into_iter();
+    while let Some(token) = tokens.next() {
+        if token == "-f" || token == "-F" {
+            if let Some(x) = tokens.next() {
+                let x = resolve(&x);
+                let new_base = if token == "-F" {
+                    Some(x.parent().unwrap_or_else(|| Path::new("")).to_path_buf())
+                } else {
+                    None
+                };
+                parse_file_inner(
+                    &x,
+                    new_base.as_deref(),
+                    files,
+                    include_paths,
+                    defines,
+                    depth + 1,
+                )?;
+            }
+        } else if let Some(x) = token.strip_prefix("+incdir+") {
+            for dir in x.split('+').filter(|x| !x.is_empty()) {
+                include_paths.push(resolve(dir));
+            }
+        } else if let Some(x) = token.strip_prefix("+define+") {
+            for define in x.split('+').filter(|x| !x.is_empty()) {
+                let mut define = define.splitn(2, '=');
+                let ident = String::from(define.next().unwrap());
+                let text = define
+                    .next()
+                    .map(|x| DefineText::new(String::from(x), None));
+                let define = Define::new(ident.clone(), vec![], text);
+                defines.insert(ident, Some(define));
+            }
+        } else if token.starts_with('-') || token.starts_with('+') {
+            // Options for other tools are ignored.
+        } else {
+            files.push(resolve(&token));
+        }
+    }
+
+    Ok(())
+}
+
+// Split the contents of an argument file at whitespace, dropping comments.
+// Whitespace between double quotes doesn't split, and the quotes are kept.
+fn split_args(s: &str) -> Vec<String> {
+    let mut ret = Vec::new();
+    let mut token = String::new();
+    let mut is_string = false;
+
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_string {
+            token.push(c);
+            if c == '"' {
+                is_string = false;
+            }
+        } else if c == '"' {
+            token.push(c);
+            is_string = true;
+        } else if c == '/' && chars.peek() == Some(&'/') {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+            if !token.is_empty() {
+                ret.push(std::mem::take(&mut token));
+            }
+        } else if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut prev = ' ';
+            for c in chars.by_ref() {
+                if prev == '*' && c == '/' {
+                    break;
+                }
+                prev = c;
+            }
+            if !token.is_empty() {
+                ret.push(std::mem::take(&mut token));
+            }
+        } else if c.is_whitespace() {
+            if !token.is_empty() {
+                ret.push(std::mem::take(&mut token));
+            }
+        } else {
+            token.push(c);
+        }
+    }
+    if !token.is_empty() {
+        ret.push(token);
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn testfile_path(s: &str) -> PathBuf {
+        PathBuf::from(format!(
+            "{}/testcases/{}",
+            env::var("CARGO_MANIFEST_DIR").unwrap(),
+            s
+        ))
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            split_args("a.sv // b.sv\n+define+MSG=\"x y\" /* -f\nc.f */d.sv"),
+            vec!["a.sv", "+define+MSG=\"x y\"", "d.sv"]
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let (files, include_paths, defines) = parse_file(testfile_path("args.f")).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                testfile_path("include_noindent.sv"),
+                testfile_path("macro_basic.sv"),
+            ]
+        );
+        assert_eq!(
+            include_paths,
+            vec![testfile_path(""), testfile_path("expected")]
+        );
+        assert_eq!(defines.len(), 2);
+        assert_eq!(
+            defines["WIDTH"],
+            Some(Define::new(
+                String::from("WIDTH"),
+                vec![],
+                Some(DefineText::new(String::from("8"), None))
+            ))
+        );
+        assert_eq!(
+            defines["DEBUG"],
+            Some(Define::new(String::from("DEBUG"), vec![], None))
+        );
+    }
+
+    #[test]
+    fn parse_not_found() {
+        match parse_file(testfile_path("args_missing.f")).unwrap_err() {
+            Error::File { path, .. } => {
+                assert_eq!(path, testfile_path("args_missing.f"));
+            }
+            _ => {
+                panic!("Error::File not raised.");
+            }
+        }
+    }
+}
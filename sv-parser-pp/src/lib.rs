@@ -1,5 +1,9 @@
 #![allow(clippy::type_complexity)]
 #![recursion_limit = "256"]
 
+#[cfg(feature = "fs")]
+pub mod args;
+#[cfg(feature = "mmap")]
+mod mmap;
 pub mod preprocess;
 pub mod range;
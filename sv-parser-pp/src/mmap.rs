@@ -0,0 +1,68 @@
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+
+/// A read-only memory map of a whole file.
+#[derive(Debug)]
+pub(crate) struct Mmap {
+    ptr: *const u8,
+    len: usize,
+}
+
+// The mapping is never written through, so it can be shared like `&[u8]`.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    #[cfg(unix)]
+    pub(crate) fn new(f: &File) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = f.metadata()?.len() as usize;
+        if len == 0 {
+            // mmap() fails for an empty length.
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty file"));
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                f.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Mmap {
+            ptr: ptr as *const u8,
+            len,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn new(_f: &File) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Other, "unsupported platform"))
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
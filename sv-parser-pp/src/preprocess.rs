@@ -1,181 +1,1735 @@
+#[cfg(feature = "mmap")]
+use crate::mmap::Mmap;
 use crate::range::Range;
 use nom::combinator::all_consuming;
 use nom_greedyerror::error_position;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
 use std::convert::TryInto;
-use std::fs::File;
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Debug;
+#[cfg(feature = "fs")]
+use std::fs;
 use std::hash::BuildHasher;
-use std::io::{BufReader, Read};
-use std::path::{Path, PathBuf};
-use sv_parser_error::Error;
-use sv_parser_parser::{pp_parser, Span, SpanInfo};
+use std::io::{self, Read, Write};
+use std::ops::Deref;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use sv_parser_error::{Diagnostic, Diagnostics, Error, Severity};
+use sv_parser_parser::{find_unterminated, pp_parser, Span, SpanInfo, TokenKind};
 use sv_parser_syntaxtree::{
-    IncludeCompilerDirective, Locate, NodeEvent, RefNode, SourceDescription, TextMacroUsage,
-    WhiteSpace,
+    CompilerDirective, ConditionalCompilerDirective, EventCursor, EventIter, IfdefCondition,
+    IfdefMacroExpression, IfdefMacroOperand, IfndefDirective, IncludeCompilerDirective, Iter,
+    LegacyProtectedEnvelope, Locate, NodeEvent, PreprocessorText, RefNode, RefNodes,
+    SourceDescription, TextMacroUsage, WhiteSpace,
 };
-use std::collections::hash_map::RandomState;
 
-const RECURSIVE_LIMIT: usize = 64;
+pub(crate) const RECURSIVE_LIMIT: usize = 64;
 
+// The amount of text held before it's written out by preprocess_to().
+const FLUSH_SIZE: usize = 64 * 1024;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug)]
 pub struct PreprocessedText {
-    text: String,
-    origins: BTreeMap<Range, Origin>,
+    text: Text,
+    // The length of text already written out by preprocess_to(), and
+    // removed from `text`.
+    flushed: usize,
+    // Sorted by range, which never overlap.
+    origins: Vec<Origin>,
+    skipped_regions: Vec<SkippedRegion>,
+    protected_regions: Vec<ProtectedRegion>,
+    timescales: Vec<Timescale>,
+    default_nettypes: Vec<(usize, String)>,
+    celldefines: Vec<Range>,
+    warnings: Vec<Warning>,
+    offset_maps: HashMap<PathBuf, Vec<(usize, usize)>>,
+    // The included files in the order first included, without duplicates.
+    includes: Vec<PathBuf>,
+    expansions: Vec<MacroExpansion>,
+}
+
+// The preprocessed text, which is shared with the source file it begins with
+// for as long as it is a prefix of the file, so that a file which preprocessing
+// leaves untouched isn't copied. The parser needs the text in one piece, so
+// the first change makes a copy.
+enum Text {
+    Shared(Arc<Source>, usize),
+    Owned(String),
+}
+
+impl Text {
+    fn push_str(&mut self, s: &str) {
+        if let Text::Shared(source, len) = self {
+            let rest = &source.text[*len..];
+            if rest.as_ptr() == s.as_ptr() && s.len() <= rest.len() {
+                *len += s.len();
+                return;
+            }
+        }
+        self.to_mut().push_str(s);
+    }
+
+    fn to_mut(&mut self) -> &mut String {
+        if let Text::Shared(source, len) = self {
+            let owned = String::from(&source.text[..*len]);
+            *self = Text::Owned(owned);
+        }
+        match self {
+            Text::Owned(x) => x,
+            Text::Shared(..) => unreachable!(),
+        }
+    }
+}
+
+impl Deref for Text {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Text::Shared(source, len) => &source.text[..*len],
+            Text::Owned(x) => x,
+        }
+    }
+}
+
+impl Debug for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Text {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Text {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <String as serde::Deserialize>::deserialize(deserializer).map(Text::Owned)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug)]
 pub struct Origin {
     range: Range,
     origin: Option<(PathBuf, Range)>,
 }
 
+impl Origin {
+    // Whether text at `range` with `origin` directly follows this segment,
+    // both in the preprocessed text and in the same origin file, so that
+    // the two can be one segment. Macro expansions, whose text differs in
+    // length from their origin, are never joined.
+    fn continues_to(&self, range: Range, origin: Option<(&Path, Range)>) -> bool {
+        if self.range.end != range.begin {
+            return false;
+        }
+        match (&self.origin, origin) {
+            (None, None) => true,
+            (Some((last_path, last_range)), Some((path, origin_range))) => {
+                last_range.end == origin_range.begin
+                    && last_range.end - last_range.begin == self.range.end - self.range.begin
+                    && origin_range.end - origin_range.begin == range.end - range.begin
+                    && last_path == path
+            }
+            _ => false,
+        }
+    }
+
+    fn extend(&mut self, range: Range, origin: Option<(&Path, Range)>) {
+        self.range.end = range.end;
+        if let (Some((_, last_range)), Some((_, origin_range))) = (&mut self.origin, origin) {
+            last_range.end = origin_range.end;
+        }
+    }
+}
+
+/// A region of an original file excluded by conditional compilation.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug)]
+pub struct SkippedRegion {
+    /// The file containing the region.
+    pub path: PathBuf,
+    /// Byte range of the region within `path`.
+    pub range: Range,
+    /// The condition tested by the deciding branch: that of the skipped
+    /// branch itself, or of the earlier branch which was taken. This is a
+    /// macro identifier, or the text of an expression such as `"(A && !B)"`.
+    pub guard: String,
+}
+
+/// The encoded contents of a `pragma protect envelope, or of a `protect or
+/// `protected block, which are dropped from the preprocessed text.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug)]
+pub struct ProtectedRegion {
+    /// The file containing the region.
+    pub path: PathBuf,
+    /// Byte range of the encoded contents within `path`.
+    pub range: Range,
+}
+
+/// A problem found in preprocessing, which doesn't stop it.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// The file containing the directive causing the warning.
+    pub path: PathBuf,
+    /// Byte offset of the directive within `path`.
+    pub offset: usize,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WarningKind {
+    /// A macro is redefined with different arguments or text.
+    MacroRedefined(String),
+    /// `undef of a macro which isn't defined.
+    UndefUndefined(String),
+    /// `undefineall clears these macros given as pre-defines.
+    PreDefinesCleared(Vec<String>),
+    /// An include file not found as written was found by
+    /// `PreprocessorConfig::include_fallback`.
+    IncludeFallback { name: PathBuf, resolved: PathBuf },
+    /// An undefined directive passed through by
+    /// `PreprocessorConfig::pass_unknown_directives`.
+    UnknownDirective(String),
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        let (code, message) = match &warning.kind {
+            WarningKind::MacroRedefined(x) => {
+                ("macro-redefined", format!("Macro redefined: {}", x))
+            }
+            WarningKind::UndefUndefined(x) => (
+                "undef-undefined",
+                format!("Undefined macro undefined: {}", x),
+            ),
+            WarningKind::PreDefinesCleared(x) => (
+                "pre-defines-cleared",
+                format!("Pre-defines cleared by `undefineall: {}", x.join(", ")),
+            ),
+            WarningKind::IncludeFallback { name, resolved } => (
+                "include-fallback",
+                format!("Include file {:?} found as {:?}", name, resolved),
+            ),
+            WarningKind::UnknownDirective(x) => (
+                "unknown-directive",
+                format!("Unknown directive passed through: {}", x),
+            ),
+        };
+        Diagnostic::new(Severity::Warning, code, message).with_origin(
+            &warning.path,
+            warning.offset,
+            0,
+        )
+    }
+}
+
+/// An expansion of a macro usage, given by `PreprocessedText::expansions()`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug)]
+pub struct MacroExpansion {
+    pub name: String,
+    /// The file containing the usage. For a usage within the text of
+    /// another macro, this is the file of the outermost usage.
+    pub path: PathBuf,
+    /// Byte offset of the usage within `path`, which is likewise that of the
+    /// outermost usage.
+    pub offset: usize,
+    /// The actual arguments as written, or `None` for each one left empty.
+    pub arguments: Vec<Option<String>>,
+    /// Byte range of the expanded text within the preprocessed text.
+    pub range: Range,
+    /// The index of the expansion whose text contains the usage, if any.
+    pub parent: Option<usize>,
+}
+
+/// A `timescale directive.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Timescale {
+    /// Time unit, e.g. `"1ns"`.
+    pub unit: String,
+    /// Time precision, e.g. `"10ps"`.
+    pub precision: String,
+    /// The file containing the directive.
+    pub path: PathBuf,
+    /// Byte offset of the directive within `path`.
+    pub offset: usize,
+}
+
 impl PreprocessedText {
     fn new() -> Self {
         PreprocessedText {
-            text: String::new(),
-            origins: BTreeMap::new(),
+            text: Text::Owned(String::new()),
+            flushed: 0,
+            origins: Vec::new(),
+            skipped_regions: Vec::new(),
+            protected_regions: Vec::new(),
+            timescales: Vec::new(),
+            default_nettypes: Vec::new(),
+            celldefines: Vec::new(),
+            warnings: Vec::new(),
+            offset_maps: HashMap::new(),
+            includes: Vec::new(),
+            expansions: Vec::new(),
+        }
+    }
+
+    // An empty text, which shares the text of `source` as long as only that
+    // is pushed in order.
+    fn with_source(source: &Arc<Source>) -> Self {
+        PreprocessedText {
+            text: Text::Shared(source.clone(), 0),
+            ..PreprocessedText::new()
         }
     }
 
+    // The length of the text, including any already written out.
+    fn len(&self) -> usize {
+        self.flushed + self.text.len()
+    }
+
     fn push<T: AsRef<Path>>(&mut self, s: &str, origin: Option<(T, Range)>) {
-        let base = self.text.len();
+        let base = self.len();
         self.text.push_str(s);
 
-        let origin = if let Some((origin_path, origin_range)) = origin {
-            let origin_path = PathBuf::from(origin_path.as_ref());
-            Some((origin_path, origin_range))
+        // Empty text has no positions to look up.
+        if s.is_empty() {
+            return;
+        }
+        let range = Range::new(base, base + s.len());
+
+        let origin_ref = origin.as_ref().map(|(x, y)| (x.as_ref(), *y));
+        if let Some(last) = self.origins.last_mut() {
+            if last.continues_to(range, origin_ref) {
+                last.extend(range, origin_ref);
+                return;
+            }
+        }
+
+        let origin = origin.map(|(x, y)| (PathBuf::from(x.as_ref()), y));
+        self.origins.push(Origin { range, origin });
+    }
+
+    fn merge(&mut self, other: PreprocessedText) {
+        // Text written out from `other` must follow all of this text.
+        debug_assert!(other.flushed == 0 || self.text.is_empty());
+        let base = self.len();
+        self.flushed += other.flushed;
+        if self.text.is_empty() {
+            self.text = other.text;
+        } else {
+            self.text.push_str(&other.text);
+        }
+        for (i, mut origin) in other.origins.into_iter().enumerate() {
+            origin.range.offset(base);
+            // Only the first can continue the last of this text.
+            if i == 0 {
+                if let Some(last) = self.origins.last_mut() {
+                    let origin_ref = origin.origin.as_ref().map(|(x, y)| (x.as_path(), *y));
+                    if last.continues_to(origin.range, origin_ref) {
+                        last.extend(origin.range, origin_ref);
+                        continue;
+                    }
+                }
+            }
+            self.origins.push(origin);
+        }
+        self.skipped_regions.extend(other.skipped_regions);
+        self.protected_regions.extend(other.protected_regions);
+        self.timescales.extend(other.timescales);
+        for (pos, value) in other.default_nettypes {
+            self.default_nettypes.push((pos + base, value));
+        }
+        for mut range in other.celldefines {
+            range.offset(base);
+            self.celldefines.push(range);
+        }
+        self.warnings.extend(other.warnings);
+        self.offset_maps.extend(other.offset_maps);
+        for path in other.includes {
+            if !self.includes.contains(&path) {
+                self.includes.push(path);
+            }
+        }
+        let index = self.expansions.len();
+        for mut expansion in other.expansions {
+            expansion.range.offset(base);
+            expansion.parent = expansion.parent.map(|x| x + index);
+            self.expansions.push(expansion);
+        }
+    }
+
+    // Record the expansion of a usage, followed by the `nested` expansions
+    // within its text, whose ranges are relative to that text.
+    fn push_expansion(&mut self, expansion: MacroExpansion, nested: Vec<MacroExpansion>) {
+        let index = self.expansions.len();
+        let base = expansion.range.begin;
+        let (path, offset) = (expansion.path.clone(), expansion.offset);
+        self.expansions.push(expansion);
+        for mut x in nested {
+            x.range.offset(base);
+            x.parent = Some(x.parent.map_or(index, |x| x + index + 1));
+            x.path = path.clone();
+            x.offset = offset;
+            self.expansions.push(x);
+        }
+    }
+
+    // Write out the text held so far.
+    fn flush(&mut self, writer: &mut dyn Write) -> Result<(), Error> {
+        writer.write_all(self.text.as_bytes())?;
+        self.flushed += self.text.len();
+        self.text = Text::Owned(String::new());
+        Ok(())
+    }
+
+    fn push_skipped<T: AsRef<Path>>(
+        &mut self,
+        node: RefNode,
+        path: T,
+        offsets: &[(usize, usize)],
+        guard: &str,
+    ) {
+        let mut beg = None;
+        let mut end = 0;
+        for x in node {
+            if let RefNode::Locate(x) = x {
+                if beg.is_none() {
+                    beg = Some(x.offset);
+                }
+                end = x.offset + x.len;
+            }
+        }
+        if let Some(beg) = beg {
+            self.skipped_regions.push(SkippedRegion {
+                path: PathBuf::from(path.as_ref()),
+                range: Range::new(original_offset(offsets, beg), original_offset(offsets, end)),
+                guard: String::from(guard),
+            });
+        }
+    }
+
+    /// The text `s` of the file at `path` as it is, without preprocessing.
+    pub fn from_source<T: AsRef<Path>>(s: &str, path: T) -> Self {
+        let mut ret = PreprocessedText::new();
+        ret.push(s, Some((path, Range::new(0, s.len()))));
+        ret
+    }
+
+    /// Append `s` without an origin, e.g. the text of tokens added to a tree
+    /// after parsing, and get the offset at which it starts.
+    pub fn append(&mut self, s: &str) -> usize {
+        let base = self.len();
+        self.push::<&Path>(s, None);
+        base
+    }
+
+    /// The preprocessed text. This is empty when it was written out by
+    /// `preprocess_to()`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The text from the first to the last token of the specified node or
+    /// `Locate`, with any white space between them.
+    pub fn get_str<'a, T: Into<RefNodes<'a>>>(&self, nodes: T) -> Option<&str> {
+        let mut beg = None;
+        let mut end = 0;
+        for n in Iter::new(nodes.into()) {
+            if let RefNode::Locate(x) = n {
+                if beg.is_none() {
+                    beg = Some(x.offset);
+                }
+                end = x.offset + x.len;
+            }
+        }
+        self.text.get(beg?..end)
+    }
+
+    /// Replace the text from `begin` to `end` with spaces, keeping line
+    /// breaks, so that offsets and origins are unchanged.
+    pub fn blank(&mut self, begin: usize, end: usize) {
+        let blank: String = self.text[begin..end]
+            .chars()
+            .map(|c| match c {
+                '\n' | '\r' => c.to_string(),
+                _ => " ".repeat(c.len_utf8()),
+            })
+            .collect();
+        self.text.to_mut().replace_range(begin..end, &blank);
+    }
+
+    /// Regions of the original files excluded by `ifdef/`ifndef, in the
+    /// order they were encountered.
+    pub fn skipped_regions(&self) -> &[SkippedRegion] {
+        &self.skipped_regions
+    }
+
+    /// Encoded contents of `pragma protect envelopes, in the order they were
+    /// encountered.
+    pub fn protected_regions(&self) -> &[ProtectedRegion] {
+        &self.protected_regions
+    }
+
+    /// `timescale directives, in the order they were encountered.
+    pub fn timescales(&self) -> &[Timescale] {
+        &self.timescales
+    }
+
+    /// The `default_nettype in effect at `pos` of the preprocessed text.
+    /// Before any directive (and after `resetall) this is `"wire"`.
+    pub fn default_nettype(&self, pos: usize) -> &str {
+        let i = self.default_nettypes.partition_point(|(x, _)| *x <= pos);
+        if i == 0 {
+            "wire"
+        } else {
+            &self.default_nettypes[i - 1].1
+        }
+    }
+
+    /// Start with `value` in effect as the `default_nettype, instead of
+    /// `"wire"`, e.g. as left by the file before in the same compilation
+    /// unit.
+    pub fn set_initial_default_nettype<T: Into<String>>(&mut self, value: T) {
+        self.default_nettypes.insert(0, (0, value.into()));
+    }
+
+    /// Regions of the preprocessed text bracketed by `celldefine and
+    /// `endcelldefine (or `resetall, or the end of the file).
+    pub fn celldefines(&self) -> &[Range] {
+        &self.celldefines
+    }
+
+    /// Whether `pos` of the preprocessed text lies within a `celldefine
+    /// region.
+    pub fn is_celldefine(&self, pos: usize) -> bool {
+        self.celldefines
+            .iter()
+            .any(|x| x.begin <= pos && pos < x.end)
+    }
+
+    /// Warnings found in preprocessing, in the order found.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// The warnings as diagnostics, e.g. to report them along with those of
+    /// parsing.
+    pub fn diagnostics(&self) -> Diagnostics {
+        let mut ret = Diagnostics::new();
+        ret.extend(self.warnings.iter().map(Diagnostic::from));
+        ret
+    }
+
+    /// The expansions of macro usages in the order of the text, each one
+    /// followed by the expansions within its text.
+    pub fn expansions(&self) -> &[MacroExpansion] {
+        &self.expansions
+    }
+
+    /// The files read by `include, directly or not, in the order they were
+    /// first included. These are the files the text depends on besides the
+    /// top file.
+    pub fn includes(&self) -> &[PathBuf] {
+        &self.includes
+    }
+
+    pub fn origin(&self, pos: usize) -> Option<(&PathBuf, usize)> {
+        self.find_origin(pos).and_then(|i| self.origin_at(i, pos))
+    }
+
+    /// `origin()` of each of `positions`.
+    /// This is faster than calling `origin()` for each position when
+    /// `positions` are in ascending order.
+    pub fn origins(&self, positions: &[usize]) -> Vec<Option<(&PathBuf, usize)>> {
+        let contains = |i: usize, pos: usize| match self.origins.get(i) {
+            Some(x) => x.range.begin <= pos && pos < x.range.end,
+            None => false,
+        };
+
+        let mut ret = Vec::with_capacity(positions.len());
+        let mut i = 0;
+        for &pos in positions {
+            // Ascending positions are usually in the same or next segment.
+            if !contains(i, pos) {
+                if contains(i + 1, pos) {
+                    i += 1;
+                } else if let Some(x) = self.find_origin(pos) {
+                    i = x;
+                } else {
+                    ret.push(None);
+                    continue;
+                }
+            }
+            ret.push(self.origin_at(i, pos));
+        }
+        ret
+    }
+
+    // Index within `origins` of the segment containing `pos`.
+    fn find_origin(&self, pos: usize) -> Option<usize> {
+        let i = self.origins.partition_point(|x| x.range.end <= pos);
+        match self.origins.get(i) {
+            Some(x) if x.range.begin <= pos => Some(i),
+            _ => None,
+        }
+    }
+
+    fn origin_at(&self, i: usize, pos: usize) -> Option<(&PathBuf, usize)> {
+        let origin = &self.origins[i];
+        if let Some((ref origin_path, ref origin_range)) = origin.origin {
+            let ret_pos = pos - origin.range.begin + origin_range.begin;
+            let ret_pos = match self.offset_maps.get(origin_path) {
+                Some(offsets) => original_offset(offsets, ret_pos),
+                None => ret_pos,
+            };
+            Some((origin_path, ret_pos))
         } else {
             None
+        }
+    }
+
+    /// Segments of the preprocessed text with their origins, in order, as
+    /// `(range in preprocessed text, origin path, range in origin file)`.
+    /// Text without an origin, such as the expansion of `__FILE__`, is
+    /// not included.
+    pub fn segments(&self) -> impl Iterator<Item = (Range, &PathBuf, Range)> + '_ {
+        self.origins.iter().filter_map(move |origin| {
+            if let Some((ref origin_path, ref origin_range)) = origin.origin {
+                let origin_range = match self.offset_maps.get(origin_path) {
+                    Some(offsets) => Range::new(
+                        original_offset(offsets, origin_range.begin),
+                        original_offset(offsets, origin_range.end),
+                    ),
+                    None => *origin_range,
+                };
+                Some((origin.range, origin_path, origin_range))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Positions in the preprocessed text which originate from `pos` of the
+    /// file `path`, in ascending order. This is the reverse of `origin()`;
+    /// there can be several when a file is included more than once.
+    pub fn origin_to_preprocessed<T: AsRef<Path>>(&self, path: T, pos: usize) -> Vec<usize> {
+        let path = path.as_ref();
+        let pos = match self.offset_maps.get(path) {
+            Some(offsets) => decoded_offset(offsets, pos),
+            None => pos,
+        };
+
+        let mut ret = vec![];
+        for origin in &self.origins {
+            if let Some((ref origin_path, ref origin_range)) = origin.origin {
+                if origin_path == path && origin_range.begin <= pos && pos < origin_range.end {
+                    let ret_pos = pos - origin_range.begin + origin.range.begin;
+                    if ret_pos < origin.range.end {
+                        ret.push(ret_pos);
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Define {
+    pub identifier: String,
+    pub arguments: Vec<(String, Option<String>)>,
+    pub text: Option<DefineText>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DefineText {
+    pub text: String,
+    pub origin: Option<(PathBuf, Range)>,
+}
+
+/// A piece of the text of a define, as given by `DefineText::fragments()`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DefineFragment {
+    /// The text to expand, with line continuations, `` `` `` and escaped
+    /// quotes resolved and one-line comments removed.
+    pub text: String,
+    /// Whether it is an identifier, which is replaced by the actual
+    /// argument of a formal argument of the same name.
+    pub identifier: bool,
+    /// The range of the fragment within `DefineText::text`.
+    pub range: Range,
+}
+
+impl Define {
+    pub fn new(
+        ident: String,
+        args: Vec<(String, Option<String>)>,
+        text: Option<DefineText>,
+    ) -> Self {
+        Define {
+            identifier: ident,
+            arguments: args,
+            text,
+        }
+    }
+
+    /// A define without arguments, equivalent to `` `define IDENT TEXT ``.
+    /// This is a convenient way to give pre-defines from the command line,
+    /// e.g. `Define::with_text("WIDTH", "8")`.
+    pub fn with_text<T: Into<String>, U: Into<String>>(ident: T, text: U) -> Self {
+        Define {
+            identifier: ident.into(),
+            arguments: vec![],
+            text: Some(DefineText::new(text.into(), None)),
+        }
+    }
+}
+
+impl DefineText {
+    pub fn new(text: String, origin: Option<(PathBuf, Range)>) -> Self {
+        DefineText { text, origin }
+    }
+
+    /// `text` split into the pieces it is expanded from, of which only the
+    /// identifiers are replaced by the actual arguments.
+    pub fn fragments(&self) -> Vec<DefineFragment> {
+        split_text(&self.text)
+    }
+}
+
+pub type Defines<V = RandomState> = HashMap<String, Option<Define>, V>;
+
+/// How the bytes of source files are decoded.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Encoding {
+    /// Files must be valid UTF-8, otherwise `Error::ReadUtf8` is returned.
+    #[default]
+    Utf8,
+    /// Invalid UTF-8 sequences are replaced with U+FFFD.
+    Utf8Lossy,
+    /// Files are read as Latin-1 (ISO-8859-1), so any byte is accepted.
+    Latin1,
+}
+
+/// Options for `preprocess_with_config()`.
+///
+/// Whatever the encoding, offsets reported by `PreprocessedText` and in
+/// errors are byte offsets within the original files.
+#[derive(Clone, Debug, Default)]
+pub struct PreprocessorConfig {
+    pub strip_comments: bool,
+    pub ignore_include: bool,
+    pub encoding: Encoding,
+    /// Convert CRLF and CR line endings in source files to LF.
+    pub normalize_line_endings: bool,
+    /// Reuse files parsed by earlier runs sharing the same cache.
+    pub cache: Option<PreprocessCache>,
+    /// Notified of each directive as it is preprocessed.
+    pub observer: Option<Arc<dyn PreprocessObserver>>,
+    /// How to look for include files which aren't found as written.
+    pub include_fallback: IncludeFallback,
+    /// Directories searched for a file included as `` `include <file> ``.
+    /// If any is given, such a relative path is looked for only in these,
+    /// not in the current directory nor the include paths. Otherwise both
+    /// forms of `` `include `` are looked for alike.
+    pub system_include_paths: Vec<PathBuf>,
+    /// Pass directives which aren't defined macros, such as vendor
+    /// directives like `` `protect128 ``, through to the preprocessed text
+    /// rather than failing with `Error::DefineNotFound`. Each is reported by
+    /// `WarningKind::UnknownDirective`.
+    pub pass_unknown_directives: bool,
+    /// Where source files are read and include files are looked for, which
+    /// is the file system if `None`. Without the `fs` feature, e.g. for
+    /// `wasm32-unknown-unknown`, no file can be read unless it is given.
+    pub file_provider: Option<Arc<dyn FileProvider>>,
+    /// Map source files into memory instead of reading them, as allowed by
+    /// `MapFiles::new()`.
+    #[cfg(feature = "mmap")]
+    pub mmap: Option<MapFiles>,
+}
+
+impl PreprocessorConfig {
+    // The file provider, or the default if none is given.
+    fn files(&self) -> &dyn FileProvider {
+        #[cfg(feature = "fs")]
+        let default = &FsFileProvider;
+        #[cfg(not(feature = "fs"))]
+        let default = &NullFileProvider;
+        self.file_provider.as_deref().unwrap_or(default)
+    }
+}
+
+/// Allows source files to be mapped into memory, given by
+/// `PreprocessorConfig::mmap`.
+#[cfg(feature = "mmap")]
+#[derive(Clone, Copy, Debug)]
+pub struct MapFiles(());
+
+#[cfg(feature = "mmap")]
+impl MapFiles {
+    /// Allow source files to be mapped into memory instead of read, which
+    /// saves copying large files such as generated netlists. Only files read
+    /// from the file system without `PreprocessorConfig::file_provider`, in
+    /// UTF-8 without a byte order mark and without `normalize_line_endings`,
+    /// are mapped, and only on Unix. Other files are read as usual.
+    ///
+    /// # Safety
+    ///
+    /// A mapped file is used as text in place, so it must not be modified or
+    /// truncated, by this process or any other, while it is mapped. A file is
+    /// mapped until the `PreprocessedText` and the syntax tree made from it
+    /// are dropped, and while it is held by `PreprocessorConfig::cache`.
+    /// Text changed in place may not be UTF-8 anymore, and reading a
+    /// truncated file raises `SIGBUS`.
+    pub unsafe fn new() -> Self {
+        MapFiles(())
+    }
+}
+
+/// Fallbacks tried, in the same places as usual, when an include file isn't
+/// found. Each use of a fallback is reported by `WarningKind::IncludeFallback`.
+#[derive(Clone, Debug, Default)]
+pub struct IncludeFallback {
+    /// Match file and directory names regardless of case.
+    pub case_insensitive: bool,
+    /// Pairs of extensions `(from, to)`, such that a name ending with `from`
+    /// is also looked for with `to` instead, e.g. `("vh", "svh")`.
+    pub extensions: Vec<(String, String)>,
+}
+
+/// Callbacks invoked while preprocessing, given by
+/// `PreprocessorConfig::observer`.
+///
+/// Each location is given as a path and a byte offset within that file.
+/// Every method does nothing by default.
+pub trait PreprocessObserver: Debug + Send + Sync {
+    /// A macro is defined by `define.
+    fn define(&self, _define: &Define, _path: &Path, _offset: usize) {}
+
+    /// A macro is undefined by `undef.
+    fn undef(&self, _identifier: &str, _path: &Path, _offset: usize) {}
+
+    /// Preprocessing of `path`, included from the location, begins.
+    fn include_enter(&self, _path: &Path, _from: &Path, _offset: usize) {}
+
+    /// Preprocessing of `path`, included by an earlier `include_enter()`,
+    /// ends.
+    fn include_leave(&self, _path: &Path) {}
+
+    /// A branch of `ifdef or `ifndef, guarded by `identifier` (or None for
+    /// `else), is either taken or skipped. For an expression condition,
+    /// `identifier` is the text of the expression.
+    fn conditional(&self, _identifier: Option<&str>, _taken: bool, _path: &Path, _offset: usize) {}
+
+    /// A macro is used. It is expanded, unless `skipped` as the usage is
+    /// within a branch of `ifdef or `ifndef which isn't taken. The usages
+    /// within the text of a macro are reported as it is expanded.
+    fn macro_usage(&self, _identifier: &str, _skipped: bool, _path: &Path, _offset: usize) {}
+}
+
+// Used when no observer is given.
+#[derive(Debug)]
+struct NullObserver;
+
+impl PreprocessObserver for NullObserver {}
+
+/// The files read while preprocessing, given by
+/// `PreprocessorConfig::file_provider`.
+pub trait FileProvider: Debug + Send + Sync {
+    /// The contents of the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Whether there is a file or a directory at `path`.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// The names of the entries of the directory at `path`, where an empty
+    /// path is the current directory. This is only used to find include
+    /// files regardless of case, and is unsupported by default.
+    fn read_dir(&self, _path: &Path) -> io::Result<Vec<OsString>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "reading a directory is unsupported",
+        ))
+    }
+
+    /// The modification time of the file at `path`, by which entries of
+    /// `PreprocessCache` are invalidated. A file without one isn't cached,
+    /// which is the default.
+    fn modified(&self, _path: &Path) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// Reads files from the file system, as is done when no
+/// `PreprocessorConfig::file_provider` is given.
+#[cfg(feature = "fs")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FsFileProvider;
+
+#[cfg(feature = "fs")]
+impl FileProvider for FsFileProvider {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        let path = if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
         };
+        let entries = fs::read_dir(path)?.filter_map(|x| x.ok());
+        Ok(entries.map(|x| x.file_name()).collect())
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|x| x.modified()).ok()
+    }
+}
+
+// Used when no file provider is given without the `fs` feature.
+#[cfg(not(feature = "fs"))]
+#[derive(Debug)]
+struct NullFileProvider;
+
+#[cfg(not(feature = "fs"))]
+impl FileProvider for NullFileProvider {
+    fn read(&self, _path: &Path) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "no file provider is given",
+        ))
+    }
+
+    fn exists(&self, _path: &Path) -> bool {
+        false
+    }
+}
+
+/// Serves files kept in memory, e.g. to preprocess in a browser where there
+/// is no file system. A file is found by the path it is inserted with, so
+/// an included file is inserted with the path it is included by, or joined
+/// to an include path.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryFileProvider {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFileProvider {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn insert<T: Into<PathBuf>, U: Into<Vec<u8>>>(&mut self, path: T, contents: U) {
+        self.files.insert(path.into(), contents.into());
+    }
+}
+
+impl FileProvider for MemoryFileProvider {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))
+    }
+
+    // A directory exists if a file is inserted within it.
+    fn exists(&self, path: &Path) -> bool {
+        self.files.keys().any(|x| x.starts_with(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        let mut ret: Vec<OsString> = Vec::new();
+        for x in self.files.keys() {
+            let name = match x.strip_prefix(path).map(|x| x.components().next()) {
+                Ok(Some(Component::Normal(x))) => x,
+                _ => continue,
+            };
+            if !ret.iter().any(|x| x == name) {
+                ret.push(name.to_os_string());
+            }
+        }
+        Ok(ret)
+    }
+}
+
+pub fn preprocess<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    strip_comments: bool,
+    ignore_include: bool,
+) -> Result<(PreprocessedText, Defines), Error> {
+    let config = PreprocessorConfig {
+        strip_comments,
+        ignore_include,
+        ..Default::default()
+    };
+    preprocess_with_config(path, pre_defines, include_paths, &config)
+}
+
+pub fn preprocess_with_config<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    config: &PreprocessorConfig,
+) -> Result<(PreprocessedText, Defines), Error> {
+    preprocess_inner(path, pre_defines, include_paths, config)
+}
+
+/// Preprocess the files `paths` in order as one compilation unit, as a
+/// simulator does the files given on one command line: each file starts
+/// with the macros defined by the files before it, and with the
+/// `` `default_nettype `` they leave. Returns the text of each file and the
+/// macros defined at the end of the last, or the first error.
+pub fn preprocess_all<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    paths: &[T],
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+) -> Result<(Vec<PreprocessedText>, Defines), Error> {
+    let config = PreprocessorConfig::default();
+    preprocess_all_with_config(paths, pre_defines, include_paths, &config)
+}
+
+fn preprocess_all_with_config<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    paths: &[T],
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    config: &PreprocessorConfig,
+) -> Result<(Vec<PreprocessedText>, Defines), Error> {
+    let mut texts = Vec::new();
+    let mut defines: Defines = pre_defines
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let mut default_nettype = String::from("wire");
+    for path in paths {
+        let (mut text, x) = preprocess_with_config(path, &defines, include_paths, config)?;
+        text.set_initial_default_nettype(default_nettype);
+        default_nettype = String::from(text.default_nettype(text.text().len()));
+        defines = x;
+        texts.push(text);
+    }
+    Ok((texts, defines))
+}
+
+/// Like `preprocess_with_config()`, but the preprocessed text is written to
+/// `writer` as it's produced, rather than built up in memory. The returned
+/// `PreprocessedText` has an empty `text()`, but its origins and other
+/// positions refer to the written text.
+pub fn preprocess_to<W: Write, T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    mut writer: W,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    config: &PreprocessorConfig,
+) -> Result<(PreprocessedText, Defines), Error> {
+    let source = read_source(path.as_ref(), config)?;
+    let ret = preprocess_source(
+        source,
+        path.as_ref(),
+        pre_defines,
+        include_paths,
+        config,
+        0, // resolve_depth
+        0, // include_depth
+        Some(&mut writer),
+    )?;
+    writer.flush()?;
+    Ok(ret)
+}
+
+/// A builder for preprocessing, gathering the pre-defines, include paths and
+/// `PreprocessorConfig` options given to `preprocess_with_config()`, e.g.
+/// `Preprocessor::new().include_path("include").strip_comments(true)`.
+#[derive(Clone, Debug, Default)]
+pub struct Preprocessor {
+    defines: Defines,
+    include_paths: Vec<PathBuf>,
+    config: PreprocessorConfig,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Pre-define a macro, as by `` `define ``. A define of `None` has no
+    /// text, like `+define+IDENT` on the command line.
+    pub fn define<T: Into<String>>(mut self, ident: T, define: Option<Define>) -> Self {
+        self.defines.insert(ident.into(), define);
+        self
+    }
+
+    pub fn defines<V: BuildHasher>(mut self, defines: &Defines<V>) -> Self {
+        for (k, v) in defines {
+            self.defines.insert(k.clone(), v.clone());
+        }
+        self
+    }
+
+    /// Add a directory searched for include files, after those added before.
+    pub fn include_path<T: AsRef<Path>>(mut self, path: T) -> Self {
+        self.include_paths.push(PathBuf::from(path.as_ref()));
+        self
+    }
+
+    pub fn include_paths<T: AsRef<Path>>(mut self, paths: &[T]) -> Self {
+        for path in paths {
+            self.include_paths.push(PathBuf::from(path.as_ref()));
+        }
+        self
+    }
+
+    /// Replace all the options with `config`.
+    pub fn config(mut self, config: PreprocessorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn strip_comments(mut self, strip_comments: bool) -> Self {
+        self.config.strip_comments = strip_comments;
+        self
+    }
+
+    pub fn ignore_include(mut self, ignore_include: bool) -> Self {
+        self.config.ignore_include = ignore_include;
+        self
+    }
+
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.config.encoding = encoding;
+        self
+    }
+
+    pub fn normalize_line_endings(mut self, normalize_line_endings: bool) -> Self {
+        self.config.normalize_line_endings = normalize_line_endings;
+        self
+    }
+
+    pub fn cache(mut self, cache: PreprocessCache) -> Self {
+        self.config.cache = Some(cache);
+        self
+    }
+
+    pub fn observer(mut self, observer: Arc<dyn PreprocessObserver>) -> Self {
+        self.config.observer = Some(observer);
+        self
+    }
+
+    pub fn include_fallback(mut self, include_fallback: IncludeFallback) -> Self {
+        self.config.include_fallback = include_fallback;
+        self
+    }
+
+    #[cfg(feature = "mmap")]
+    pub fn mmap(mut self, mmap: MapFiles) -> Self {
+        self.config.mmap = Some(mmap);
+        self
+    }
+
+    /// Add a directory searched for `` `include <file> ``, after those added
+    /// before. See `PreprocessorConfig::system_include_paths`.
+    pub fn system_include_path<T: AsRef<Path>>(mut self, path: T) -> Self {
+        self.config
+            .system_include_paths
+            .push(PathBuf::from(path.as_ref()));
+        self
+    }
+
+    pub fn pass_unknown_directives(mut self, pass_unknown_directives: bool) -> Self {
+        self.config.pass_unknown_directives = pass_unknown_directives;
+        self
+    }
+
+    pub fn file_provider(mut self, file_provider: Arc<dyn FileProvider>) -> Self {
+        self.config.file_provider = Some(file_provider);
+        self
+    }
+
+    /// Preprocess the file `path`, as by `preprocess_with_config()`.
+    pub fn preprocess<T: AsRef<Path>>(
+        &self,
+        path: T,
+    ) -> Result<(PreprocessedText, Defines), Error> {
+        preprocess_with_config(path, &self.defines, &self.include_paths, &self.config)
+    }
+
+    /// Preprocess the files `paths` in order as one compilation unit, as by
+    /// `preprocess_all()`.
+    pub fn preprocess_all<T: AsRef<Path>>(
+        &self,
+        paths: &[T],
+    ) -> Result<(Vec<PreprocessedText>, Defines), Error> {
+        preprocess_all_with_config(paths, &self.defines, &self.include_paths, &self.config)
+    }
+
+    /// Preprocess `s` as the contents of the file `path`.
+    pub fn preprocess_str<T: AsRef<Path>>(
+        &self,
+        s: &str,
+        path: T,
+    ) -> Result<(PreprocessedText, Defines), Error> {
+        let source = Source::parse(SourceText::Owned(String::from(s)), vec![], path.as_ref())?;
+        self.preprocess_source(source, path)
+    }
+
+    /// Preprocess `bytes` as the contents of the file `path`, decoded as the
+    /// contents of a file are, e.g. by `encoding()`.
+    pub fn preprocess_bytes<T: AsRef<Path>>(
+        &self,
+        bytes: &[u8],
+        path: T,
+    ) -> Result<(PreprocessedText, Defines), Error> {
+        let source = decode_source(bytes.to_vec(), path.as_ref(), &self.config)?;
+        self.preprocess_source(source, path)
+    }
+
+    /// Preprocess what `reader` reads to its end as the contents of the file
+    /// `path`, like `preprocess_bytes()`, e.g. from an archive or a pipe.
+    pub fn preprocess_reader<R: Read, T: AsRef<Path>>(
+        &self,
+        mut reader: R,
+        path: T,
+    ) -> Result<(PreprocessedText, Defines), Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(|x| Error::File {
+            source: x,
+            path: PathBuf::from(path.as_ref()),
+        })?;
+        let source = decode_source(bytes, path.as_ref(), &self.config)?;
+        self.preprocess_source(source, path)
+    }
+
+    /// Preprocess the file `path` to `writer`, as by `preprocess_to()`.
+    pub fn preprocess_to<W: Write, T: AsRef<Path>>(
+        &self,
+        writer: W,
+        path: T,
+    ) -> Result<(PreprocessedText, Defines), Error> {
+        preprocess_to(
+            writer,
+            path,
+            &self.defines,
+            &self.include_paths,
+            &self.config,
+        )
+    }
+
+    fn preprocess_source<T: AsRef<Path>>(
+        &self,
+        source: Source,
+        path: T,
+    ) -> Result<(PreprocessedText, Defines), Error> {
+        preprocess_source(
+            Arc::new(source),
+            path,
+            &self.defines,
+            &self.include_paths,
+            &self.config,
+            0,    // resolve_depth
+            0,    // include_depth
+            None, // writer
+        )
+    }
+}
+
+fn preprocess_inner<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    config: &PreprocessorConfig,
+) -> Result<(PreprocessedText, Defines), Error> {
+    let source = read_source(path.as_ref(), config)?;
+    preprocess_source(
+        source,
+        path.as_ref(),
+        pre_defines,
+        include_paths,
+        config,
+        0,    // resolve_depth
+        0,    // include_depth
+        None, // writer
+    )
+}
+
+// The contents of a source file, parsed and ready to be preprocessed.
+#[derive(Debug)]
+struct Source {
+    text: SourceText,
+    // Maps offsets within `text` to the original file, as from decode().
+    offsets: Vec<(usize, usize)>,
+    tree: PreprocessorText,
+    guard: Option<Guard>,
+}
+
+impl Source {
+    fn parse<T: AsRef<Path>>(
+        text: SourceText,
+        offsets: Vec<(usize, usize)>,
+        path: T,
+    ) -> Result<Self, Error> {
+        let origin = |pos| Some((PathBuf::from(path.as_ref()), original_offset(&offsets, pos)));
+        let error = |pos: Option<usize>| {
+            // An unclosed string literal or block comment makes the parse fail
+            // far from where it begins.
+            let unterminated =
+                find_unterminated(&text).filter(|x| pos.is_none_or(|pos| x.locate.offset <= pos));
+            match (unterminated, pos) {
+                (Some(x), _) if x.kind == TokenKind::String => Error::UnterminatedString {
+                    origin: origin(x.locate.offset),
+                },
+                (Some(x), _) => Error::UnterminatedComment {
+                    origin: origin(x.locate.offset),
+                },
+                (None, Some(pos)) => Error::Preprocess(origin(pos)),
+                (None, None) => Error::Preprocess(None),
+            }
+        };
+
+        let span = Span::new_extra(&text, SpanInfo::default());
+        let tree = match all_consuming(pp_parser)(span) {
+            Ok((_, x)) => x,
+            Err(nom::Err::Incomplete(_)) => return Err(error(None)),
+            Err(nom::Err::Error(e)) => return Err(error(error_position(&e))),
+            Err(nom::Err::Failure(e)) => return Err(error(error_position(&e))),
+        };
+
+        let guard = find_guard(&tree, &text);
+        Ok(Source {
+            text,
+            offsets,
+            tree,
+            guard,
+        })
+    }
+}
+
+// The text of a source file, which is mapped if `PreprocessorConfig::mmap`
+// allows it.
+#[derive(Debug)]
+enum SourceText {
+    Owned(String),
+    // Checked to be UTF-8 when mapped.
+    #[cfg(feature = "mmap")]
+    Mapped(Mmap),
+}
+
+impl Deref for SourceText {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            SourceText::Owned(x) => x,
+            #[cfg(feature = "mmap")]
+            SourceText::Mapped(x) => unsafe { std::str::from_utf8_unchecked(x) },
+        }
+    }
+}
+
+// How a file guards against being preprocessed more than once.
+#[derive(Clone, Debug, PartialEq)]
+enum Guard {
+    // `pragma once
+    Once,
+    // `ifndef NAME `define NAME ... `endif around the whole file.
+    Macro(String),
+}
+
+fn find_guard(tree: &PreprocessorText, s: &str) -> Option<Guard> {
+    let (ref descriptions,) = tree.nodes;
+
+    for x in descriptions {
+        if let SourceDescription::CompilerDirective(x) = x {
+            if let CompilerDirective::Pragma(x) = &**x {
+                let (_, _, ref name, _) = x.nodes;
+                if identifier(name.into(), s).as_deref() == Some("once") {
+                    return Some(Guard::Once);
+                }
+            }
+        }
+    }
+
+    // Only whitespace and comments may be outside of the guard, as they are
+    // all that would be lost by skipping the file.
+    let mut guard = None;
+    for x in descriptions {
+        match x {
+            SourceDescription::Comment(_) => (),
+            SourceDescription::NotDirective(x) if x.nodes.0.str(s).trim().is_empty() => (),
+            SourceDescription::CompilerDirective(x) if guard.is_none() => match &**x {
+                CompilerDirective::ConditionalCompilerDirective(x) => match &**x {
+                    ConditionalCompilerDirective::IfndefDirective(x) => {
+                        guard = Some(guard_macro(x, s)?);
+                    }
+                    _ => return None,
+                },
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+    guard.map(Guard::Macro)
+}
+
+// The macro of an `ifndef which defines it first, and has no `else.
+fn guard_macro(x: &IfndefDirective, s: &str) -> Option<String> {
+    let (_, _, ref id, ref body, ref elsif, ref elsebody, _, _) = x.nodes;
+    if !elsif.is_empty() || elsebody.is_some() {
+        return None;
+    }
+    let id = match id {
+        IfdefCondition::TextMacroIdentifier(x) => identifier((&**x).into(), s)?,
+        IfdefCondition::Paren(_) => return None,
+    };
+
+    let (ref descriptions,) = body.nodes;
+    for x in descriptions {
+        match x {
+            SourceDescription::Comment(_) => (),
+            SourceDescription::NotDirective(x) if x.nodes.0.str(s).trim().is_empty() => (),
+            SourceDescription::CompilerDirective(x) => match &**x {
+                CompilerDirective::TextMacroDefinition(x) => {
+                    let (_, _, ref proto, _) = x.nodes;
+                    let (ref name, _) = proto.nodes;
+                    if identifier(name.into(), s)? == id {
+                        return Some(id);
+                    } else {
+                        return None;
+                    }
+                }
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// A cache of parsed source files, shared between preprocessing runs by
+/// setting `PreprocessorConfig::cache`, so that headers included from many
+/// files are read and parsed only once.
+///
+/// Entries are keyed by path and invalidated when the modification time of
+/// the file changes. Clones of a cache share the same entries.
+#[derive(Clone, Debug, Default)]
+pub struct PreprocessCache {
+    entries: Arc<Mutex<HashMap<PathBuf, CacheEntry>>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    modified: SystemTime,
+    encoding: Encoding,
+    normalize_line_endings: bool,
+    source: Arc<Source>,
+}
+
+impl PreprocessCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The number of cached files.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn get(
+        &self,
+        path: &Path,
+        modified: SystemTime,
+        config: &PreprocessorConfig,
+    ) -> Option<Arc<Source>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(path) {
+            Some(x)
+                if x.modified == modified
+                    && x.encoding == config.encoding
+                    && x.normalize_line_endings == config.normalize_line_endings =>
+            {
+                Some(x.source.clone())
+            }
+            _ => None,
+        }
+    }
 
-        let range = Range::new(base, base + s.len());
-        let origin = Origin { range, origin };
-        self.origins.insert(range, origin);
+    fn insert(
+        &self,
+        path: &Path,
+        modified: SystemTime,
+        config: &PreprocessorConfig,
+        source: Arc<Source>,
+    ) {
+        let entry = CacheEntry {
+            modified,
+            encoding: config.encoding,
+            normalize_line_endings: config.normalize_line_endings,
+            source,
+        };
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(PathBuf::from(path), entry);
     }
+}
 
-    fn merge(&mut self, other: PreprocessedText) {
-        let base = self.text.len();
-        self.text.push_str(&other.text);
-        for (mut range, mut origin) in other.origins {
-            range.offset(base);
-            origin.range.offset(base);
-            self.origins.insert(range, origin);
+fn read_source(path: &Path, config: &PreprocessorConfig) -> Result<Arc<Source>, Error> {
+    let files = config.files();
+    let modified = files.modified(path);
+    let cache = match (&config.cache, modified) {
+        (Some(cache), Some(modified)) => Some((cache, modified)),
+        _ => None,
+    };
+    if let Some((cache, modified)) = cache {
+        if let Some(source) = cache.get(path, modified, config) {
+            return Ok(source);
         }
     }
 
-    pub fn text(&self) -> &str {
-        &self.text
+    #[cfg(feature = "mmap")]
+    let mapped = map_source(path, config)?;
+    #[cfg(not(feature = "mmap"))]
+    let mapped = None;
+
+    let source = match mapped {
+        Some(x) => x,
+        None => {
+            let bytes = files.read(path).map_err(|x| Error::File {
+                source: x,
+                path: PathBuf::from(path),
+            })?;
+            decode_source(bytes, path, config)?
+        }
+    };
+
+    let source = Arc::new(source);
+    if let Some((cache, modified)) = cache {
+        cache.insert(path, modified, config, source.clone());
     }
+    Ok(source)
+}
 
-    pub fn origin(&self, pos: usize) -> Option<(&PathBuf, usize)> {
-        let origin = self.origins.get(&Range::new(pos, pos + 1));
-        if let Some(origin) = origin {
-            if let Some((ref origin_path, ref origin_range)) = origin.origin {
-                let ret_pos = pos - origin.range.begin + origin_range.begin;
-                Some((&origin_path, ret_pos))
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+// Decode `bytes` as the contents of `path` as configured by `config`.
+fn decode_source(
+    bytes: Vec<u8>,
+    path: &Path,
+    config: &PreprocessorConfig,
+) -> Result<Source, Error> {
+    if let Some(description) = leading_bytes(&bytes) {
+        return Err(Error::LeadingBytes {
+            path: PathBuf::from(path),
+            description,
+        });
     }
+    let (s, offsets) = match decode(bytes, config.encoding) {
+        Some(x) => x,
+        None => return Err(Error::ReadUtf8(PathBuf::from(path))),
+    };
+    let (s, offsets) = if config.normalize_line_endings {
+        normalize_line_endings(&s, &offsets)
+    } else {
+        (s, offsets)
+    };
+    Source::parse(SourceText::Owned(s), offsets, path)
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Define {
-    pub identifier: String,
-    pub arguments: Vec<(String, Option<String>)>,
-    pub text: Option<DefineText>,
+// Map the file at `path` if `config.mmap` allows it and its contents are
+// used as they are, or return None to read it.
+#[cfg(feature = "mmap")]
+fn map_source(path: &Path, config: &PreprocessorConfig) -> Result<Option<Source>, Error> {
+    if config.mmap.is_none()
+        || config.file_provider.is_some()
+        || config.encoding != Encoding::Utf8
+        || config.normalize_line_endings
+    {
+        return Ok(None);
+    }
+
+    // If mapping fails, e.g. for an empty file, the file is read instead.
+    let map = match fs::File::open(path).and_then(|x| Mmap::new(&x)) {
+        Ok(x) => x,
+        Err(_) => return Ok(None),
+    };
+    // Anything decode_source() would convert or report is left to it.
+    if map.starts_with(BOM) || leading_bytes(&map).is_some() || std::str::from_utf8(&map).is_err() {
+        return Ok(None);
+    }
+    Source::parse(SourceText::Mapped(map), vec![], path).map(Some)
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct DefineText {
-    pub text: String,
-    pub origin: Option<(PathBuf, Range)>,
+// The UTF-8 byte order mark, which editors on Windows write at the start of
+// a file.
+const BOM: &[u8] = b"\xef\xbb\xbf";
+
+// A description of the start of a file which can't be a source text, e.g.
+// one in UTF-16, so that it is rejected with a clearer error than a parse
+// error at its start.
+fn leading_bytes(bytes: &[u8]) -> Option<&'static str> {
+    let control = |x: &u8| x.is_ascii_control() && !x.is_ascii_whitespace();
+    if bytes.starts_with(b"\xff\xfe") || bytes.starts_with(b"\xfe\xff") {
+        Some("a UTF-16 byte order mark")
+    } else if bytes.first().is_some_and(control) {
+        Some("a control character")
+    } else {
+        None
+    }
 }
 
-impl Define {
-    pub fn new(
-        ident: String,
-        args: Vec<(String, Option<String>)>,
-        text: Option<DefineText>,
-    ) -> Self {
-        Define {
-            identifier: ident,
-            arguments: args,
-            text,
+// Decode the contents of a file, also returning the (decoded, original) pairs
+// of offsets after each character whose length changed in decoding. A byte
+// order mark is dropped, with an offset pair after it.
+fn decode(mut bytes: Vec<u8>, encoding: Encoding) -> Option<(String, Vec<(usize, usize)>)> {
+    let bom = if bytes.starts_with(BOM) {
+        bytes.drain(..BOM.len());
+        BOM.len()
+    } else {
+        0
+    };
+    let (s, mut offsets) = decode_bytes(bytes, encoding)?;
+    if bom > 0 {
+        for x in &mut offsets {
+            x.1 += bom;
         }
+        offsets.insert(0, (0, bom));
     }
+    Some((s, offsets))
 }
 
-impl DefineText {
-    pub fn new(text: String, origin: Option<(PathBuf, Range)>) -> Self {
-        DefineText { text, origin }
+fn decode_bytes(bytes: Vec<u8>, encoding: Encoding) -> Option<(String, Vec<(usize, usize)>)> {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8(bytes).ok().map(|s| (s, vec![])),
+        Encoding::Utf8Lossy => {
+            let mut s = String::new();
+            let mut offsets = vec![];
+            let mut rest = &bytes[..];
+            loop {
+                match std::str::from_utf8(rest) {
+                    Ok(x) => {
+                        s.push_str(x);
+                        break;
+                    }
+                    Err(e) => {
+                        let (valid, after) = rest.split_at(e.valid_up_to());
+                        s.push_str(std::str::from_utf8(valid).unwrap());
+                        s.push(char::REPLACEMENT_CHARACTER);
+                        let invalid = e.error_len().unwrap_or(after.len());
+                        rest = &after[invalid..];
+                        offsets.push((s.len(), bytes.len() - rest.len()));
+                    }
+                }
+            }
+            Some((s, offsets))
+        }
+        Encoding::Latin1 => {
+            let mut s = String::new();
+            let mut offsets = vec![];
+            for (i, x) in bytes.iter().enumerate() {
+                s.push(*x as char);
+                if !x.is_ascii() {
+                    offsets.push((s.len(), i + 1));
+                }
+            }
+            Some((s, offsets))
+        }
     }
 }
 
-pub type Defines<V=RandomState> = HashMap<String, Option<Define>, V>;
+// Convert CRLF and CR to LF, updating the offsets given by decode().
+fn normalize_line_endings(s: &str, offsets: &[(usize, usize)]) -> (String, Vec<(usize, usize)>) {
+    let mut ret = String::new();
+    let mut ret_offsets = vec![];
+    let mut shifted = false;
+    let mut j = 0;
+
+    let mut chars = s.char_indices().peekable();
+    loop {
+        let next = chars.next();
+        let i = next.map_or(s.len(), |(i, _)| i);
+
+        // A new offset is needed whenever ret and s diverge further, either
+        // here or in decode().
+        while j < offsets.len() && offsets[j].0 < i {
+            j += 1;
+        }
+        if shifted || (j < offsets.len() && offsets[j].0 == i) {
+            ret_offsets.push((ret.len(), original_offset(offsets, i)));
+            shifted = false;
+        }
 
-pub fn preprocess<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
-    path: T,
-    pre_defines: &Defines<V>,
-    include_paths: &[U],
-    strip_comments: bool,
-    ignore_include: bool,
-) -> Result<(PreprocessedText, Defines), Error> {
-    preprocess_inner(
-        path,
-        pre_defines,
-        include_paths,
-        strip_comments,
-        ignore_include,
-        0, // include_depth
-    )
-}
+        match next {
+            Some((_, '\r')) if chars.peek().map(|(_, c)| *c) == Some('\n') => {
+                shifted = true;
+            }
+            Some((_, '\r')) => ret.push('\n'),
+            Some((_, c)) => ret.push(c),
+            None => break,
+        }
+    }
 
-fn preprocess_inner<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
-    path: T,
-    pre_defines: &Defines<V>,
-    include_paths: &[U],
-    strip_comments: bool,
-    ignore_include: bool,
-    include_depth: usize,
-) -> Result<(PreprocessedText, Defines), Error> {
+    (ret, ret_offsets)
+}
 
-    let f = File::open(path.as_ref()).map_err(|x| Error::File {
-        source: x,
-        path: PathBuf::from(path.as_ref()),
-    })?;
-    let mut reader = BufReader::new(f);
-    let mut s = String::new();
+// Map an offset within the original file to decoded text.
+fn decoded_offset(offsets: &[(usize, usize)], pos: usize) -> usize {
+    let i = offsets.partition_point(|(_, x)| *x <= pos);
+    if i == 0 {
+        pos
+    } else {
+        let (decoded, original) = offsets[i - 1];
+        decoded + pos - original
+    }
+}
 
-    if let Err(_) = reader.read_to_string(&mut s) {
-        Err(Error::ReadUtf8(PathBuf::from(path.as_ref())))
+// Map an offset within decoded text to the original file.
+fn original_offset(offsets: &[(usize, usize)], pos: usize) -> usize {
+    let i = offsets.partition_point(|(x, _)| *x <= pos);
+    if i == 0 {
+        pos
     } else {
-        preprocess_str(
-            &s,
-            path,
-            pre_defines,
-            include_paths,
-            ignore_include,
-            strip_comments,
-            0, // resolve_depth
-            include_depth,
-        )
+        let (decoded, original) = offsets[i - 1];
+        original + pos - decoded
     }
 }
 
-struct SkipNodes<'a> {
-    nodes: Vec<RefNode<'a>>,
+// Nodes are identified by RefNode::id(), which stays valid as long as the
+// Source containing them is alive.
+struct SkipNodes {
+    nodes: Vec<(usize, usize)>,
 }
 
-impl<'a> SkipNodes<'a> {
+impl SkipNodes {
     fn new() -> Self {
         Self { nodes: vec![] }
     }
 
-    fn push(&mut self, node: RefNode<'a>) {
+    fn push(&mut self, node: RefNode) {
         // if a node doesn't have locate, the node should be ignored
         // because the node can be identified in tree.
         let mut have_locate = false;
@@ -185,13 +1739,89 @@ impl<'a> SkipNodes<'a> {
             }
         }
         if have_locate {
-            self.nodes.push(node);
+            self.nodes.push(node.id());
+        }
+    }
+
+    fn contains(&self, node: &RefNode) -> bool {
+        self.nodes.contains(&node.id())
+    }
+}
+
+// The state of preprocessing a file, kept on a stack while the files it
+// includes are preprocessed.
+struct Frame {
+    source: Arc<Source>,
+    path: PathBuf,
+    // Where to resume the events of `source.tree` after an include, or
+    // None to start at the beginning.
+    cursor: Option<EventCursor>,
+    skip: bool,
+    skip_whitespace: bool,
+    skip_nodes: SkipNodes,
+    last_item_line: Option<u32>,
+    last_include_line: Option<u32>,
+    // The offset of the `include being preprocessed, while the frame is on
+    // the stack.
+    include_offset: usize,
+    celldefine: Option<usize>,
+    include_depth: usize,
+    ret: PreprocessedText,
+}
+
+impl Frame {
+    fn new<T: AsRef<Path>>(source: Arc<Source>, path: T, include_depth: usize) -> Self {
+        Frame {
+            ret: PreprocessedText::with_source(&source),
+            source,
+            path: PathBuf::from(path.as_ref()),
+            cursor: None,
+            skip: false,
+            skip_whitespace: false,
+            skip_nodes: SkipNodes::new(),
+            last_item_line: None,
+            last_include_line: None,
+            include_offset: 0,
+            celldefine: None,
+            include_depth,
+        }
+    }
+
+    // Close anything left open at the end of the file.
+    fn finish(mut self) -> PreprocessedText {
+        if let Some(begin) = self.celldefine {
+            self.ret.celldefines.push(Range::new(begin, self.ret.len()));
         }
+        if !self.source.offsets.is_empty() {
+            self.ret
+                .offset_maps
+                .insert(self.path, self.source.offsets.clone());
+        }
+        self.ret
+    }
+
+    // The file, offset and line of the `include being preprocessed.
+    fn include(&self) -> (&Path, usize, usize) {
+        let line = self.last_include_line.unwrap_or(0) as usize;
+        (&self.path, self.include_offset, line)
     }
+}
 
-    fn contains(&self, node: &RefNode<'a>) -> bool {
-        self.nodes.contains(node)
+// Wrap an error raised in a file reached through the `include directives of
+// `chain`, innermost first.
+fn include_error<'a, T: IntoIterator<Item = (&'a Path, usize, usize)>>(
+    mut error: Error,
+    chain: T,
+) -> Error {
+    for (path, offset, line) in chain {
+        error = Error::Include {
+            source: Box::new(error),
+            path: PathBuf::from(path),
+            offset,
+            line,
+        };
     }
+    error
 }
 
 pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
@@ -204,7 +1834,35 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
     resolve_depth: usize,
     include_depth: usize,
 ) -> Result<(PreprocessedText, Defines), Error> {
+    let config = PreprocessorConfig {
+        strip_comments,
+        ignore_include,
+        ..Default::default()
+    };
+    let source = Source::parse(SourceText::Owned(String::from(s)), vec![], path.as_ref())?;
+    preprocess_source(
+        Arc::new(source),
+        path,
+        pre_defines,
+        include_paths,
+        &config,
+        resolve_depth,
+        include_depth,
+        None, // writer
+    )
+}
 
+#[allow(clippy::too_many_arguments)]
+fn preprocess_source<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    source: Arc<Source>,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    config: &PreprocessorConfig,
+    resolve_depth: usize,
+    include_depth: usize,
+    writer: Option<&mut dyn Write>,
+) -> Result<(PreprocessedText, Defines), Error> {
     // IEEE1800-2017 Clause 22.4, page 675
     // A file included in the source using the `include compiler directive
     // may contain other `include compiler directives.
@@ -215,69 +1873,232 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
         return Err(Error::ExceedRecursiveLimit);
     }
 
-    let mut skip = false;
-    let mut skip_whitespace = false;
-    let mut skip_nodes = SkipNodes::new();
     let mut defines = HashMap::new();
+    for (k, v) in pre_defines {
+        defines.insert(k.clone(), (*v).clone());
+    }
+    add_sv_cov_pre_defines(&mut defines);
+
+    let mut pre_defined: Vec<String> = pre_defines.keys().cloned().collect();
+    pre_defined.sort();
 
-    let mut last_item_line = None;
-    let mut last_include_line = None;
+    let frame = Frame::new(source, path, include_depth);
+    preprocess_frames(
+        frame,
+        defines,
+        &pre_defined,
+        include_paths,
+        config,
+        resolve_depth,
+        writer,
+    )
+}
+
+// Preprocess `frame` and the files it includes, taking over `defines`.
+fn preprocess_frames<U: AsRef<Path>>(
+    mut frame: Frame,
+    mut defines: Defines,
+    pre_defined: &[String],
+    include_paths: &[U],
+    config: &PreprocessorConfig,
+    resolve_depth: usize,
+    mut writer: Option<&mut dyn Write>,
+) -> Result<(PreprocessedText, Defines), Error> {
+    // Rather than recursing, an included file is preprocessed by pushing the
+    // including file onto a stack, and resuming it at the end of the
+    // included file, so that long chains of includes can't overflow.
+    let mut stack: Vec<Frame> = Vec::new();
+
+    // Guards of the files included so far, so that a guarded file can be
+    // skipped without reading it again.
+    let mut guards: HashMap<PathBuf, Guard> = HashMap::new();
+
+    let observer = config.observer.as_deref().unwrap_or(&NullObserver);
+    loop {
+        let (include, new_defines) = preprocess_frame(
+            &mut frame,
+            defines,
+            pre_defined,
+            include_paths,
+            config,
+            resolve_depth,
+            writer.as_deref_mut(),
+        )
+        .map_err(|x| include_error(x, stack.iter().rev().map(Frame::include)))?;
+        defines = new_defines;
+
+        if let Some((include, offset)) = include {
+            enter_include(
+                &mut frame,
+                &mut stack,
+                &mut guards,
+                include,
+                offset,
+                &mut defines,
+                config,
+                writer.as_deref_mut(),
+            )?;
+        } else if !leave_include(&mut frame, &mut stack, observer) {
+            let mut ret = frame.finish();
+            if let Some(writer) = writer {
+                ret.flush(writer)?;
+            }
+            return Ok((ret, defines));
+        }
+    }
+}
+
+// Push `frame` onto `stack` and replace it with a frame of the file `include`
+// at `offset`, unless a guard of the file skips it. This and leave_include()
+// are apart from preprocess_frames(), which is on the stack for each level
+// of nested macro usages.
+#[allow(clippy::too_many_arguments)]
+fn enter_include(
+    frame: &mut Frame,
+    stack: &mut Vec<Frame>,
+    guards: &mut HashMap<PathBuf, Guard>,
+    include: PathBuf,
+    offset: usize,
+    defines: &mut Defines,
+    config: &PreprocessorConfig,
+    writer: Option<&mut (dyn Write + '_)>,
+) -> Result<(), Error> {
+    let guarded = match guards.get(&include) {
+        Some(Guard::Once) => true,
+        Some(Guard::Macro(x)) => defines.contains_key(x),
+        None => false,
+    };
+    if guarded {
+        return Ok(());
+    }
 
+    frame.include_offset = offset;
+    let chain = || {
+        std::iter::once(&*frame)
+            .chain(stack.iter().rev())
+            .map(Frame::include)
+    };
+    let include_depth = frame.include_depth + 1;
+    if include_depth > RECURSIVE_LIMIT {
+        return Err(include_error(Error::ExceedRecursiveLimit, chain()));
+    }
+    let source = read_source(&include, config).map_err(|x| include_error(x, chain()))?;
+    if let Some(guard) = &source.guard {
+        guards.insert(include.clone(), guard.clone());
+    }
+
+    // The predefined macros are restored for each file.
+    add_sv_cov_pre_defines(defines);
+
+    let observer = config.observer.as_deref().unwrap_or(&NullObserver);
+    observer.include_enter(&include, &frame.path, offset);
+
+    // The text of the including file so far precedes that of the included
+    // file.
+    if let Some(writer) = writer {
+        frame.ret.flush(writer)?;
+    }
+
+    let parent = std::mem::replace(frame, Frame::new(source, include, include_depth));
+    stack.push(parent);
+    frame.ret.includes.push(frame.path.clone());
+    Ok(())
+}
+
+// Finish `frame` and resume the including file from the top of `stack`
+// with its text, or return false if `frame` is of the file the stack began
+// with.
+fn leave_include(
+    frame: &mut Frame,
+    stack: &mut Vec<Frame>,
+    observer: &dyn PreprocessObserver,
+) -> bool {
+    let parent = match stack.pop() {
+        Some(x) => x,
+        None => return false,
+    };
+    let included = std::mem::replace(frame, parent);
+    observer.include_leave(&included.path);
+    let ret = included.finish();
+    frame.ret.merge(ret);
+    true
+}
+
+// Add the predefined coverage macros which aren't already defined.
+fn add_sv_cov_pre_defines(defines: &mut Defines) {
     // IEEE1800-2017 Clause 40.3.1, page 1121
     // The following predefined `define macros represent basic real-time
     // coverage capabilities accessible directly from SystemVerilog:
     let sv_cov_pre_defines = [
-        ("SV_COV_START",        "0"),
-        ("SV_COV_STOP",         "1"),
-        ("SV_COV_RESET",        "2"),
-        ("SV_COV_CHECK",        "3"),
-        ("SV_COV_MODULE",       "10"),
-        ("SV_COV_HIER",         "11"),
-        ("SV_COV_ASSERTION",    "20"),
-        ("SV_COV_FSM_STATE",    "21"),
-        ("SV_COV_STATEMENT",    "22"),
-        ("SV_COV_TOGGLE",       "23"),
-        ("SV_COV_OVERFLOW",     "-2"),
-        ("SV_COV_ERROR",        "-1"),
-        ("SV_COV_NOCOV",        "0"),
-        ("SV_COV_OK",           "1"),
-        ("SV_COV_PARTIAL",      "2"),
+        ("SV_COV_START", "0"),
+        ("SV_COV_STOP", "1"),
+        ("SV_COV_RESET", "2"),
+        ("SV_COV_CHECK", "3"),
+        ("SV_COV_MODULE", "10"),
+        ("SV_COV_HIER", "11"),
+        ("SV_COV_ASSERTION", "20"),
+        ("SV_COV_FSM_STATE", "21"),
+        ("SV_COV_STATEMENT", "22"),
+        ("SV_COV_TOGGLE", "23"),
+        ("SV_COV_OVERFLOW", "-2"),
+        ("SV_COV_ERROR", "-1"),
+        ("SV_COV_NOCOV", "0"),
+        ("SV_COV_OK", "1"),
+        ("SV_COV_PARTIAL", "2"),
     ];
     for (k, v) in sv_cov_pre_defines {
         let define = Define {
             identifier: k.to_string(),
             arguments: Vec::new(),
-            text: Some(DefineText {text: v.to_string(), origin: None}),
+            text: Some(DefineText::new(v.to_string(), None)),
         };
-        defines.insert(k.to_string(), Some(define));
-    }
-
-    for (k, v) in pre_defines {
-        defines.insert(k.clone(), (*v).clone());
+        defines.entry(k.to_string()).or_insert(Some(define));
     }
+}
 
-    let span = Span::new_extra(&s, SpanInfo::default());
-    let (_, pp_text) = all_consuming(pp_parser)(span).map_err(|x| match x {
-        nom::Err::Incomplete(_) => Error::Preprocess(None),
-        nom::Err::Error(e) => {
-            if let Some(pos) = error_position(&e) {
-                Error::Preprocess(Some((PathBuf::from(path.as_ref()), pos)))
-            } else {
-                Error::Preprocess(None)
-            }
-        }
-        nom::Err::Failure(e) => {
-            if let Some(pos) = error_position(&e) {
-                Error::Preprocess(Some((PathBuf::from(path.as_ref()), pos)))
-            } else {
-                Error::Preprocess(None)
+// Preprocess `frame` until either it includes a file, returning the path of
+// the included file and the offset of the `include, or it ends, returning
+// None.
+fn preprocess_frame<U: AsRef<Path>>(
+    frame: &mut Frame,
+    mut defines: Defines,
+    pre_defined: &[String],
+    include_paths: &[U],
+    config: &PreprocessorConfig,
+    resolve_depth: usize,
+    mut writer: Option<&mut (dyn Write + '_)>,
+) -> Result<(Option<(PathBuf, usize)>, Defines), Error> {
+    let source = frame.source.clone();
+    let s = &source.text[..];
+    let offsets = &source.offsets[..];
+    let path = &frame.path;
+    let strip_comments = config.strip_comments;
+    let ignore_include = config.ignore_include;
+    let observer = config.observer.as_deref().unwrap_or(&NullObserver);
+
+    let mut skip = frame.skip;
+    let mut skip_whitespace = frame.skip_whitespace;
+    let skip_nodes = &mut frame.skip_nodes;
+
+    let mut last_item_line = frame.last_item_line;
+    let mut last_include_line = frame.last_include_line;
+    let mut celldefine = frame.celldefine;
+
+    let ret = &mut frame.ret;
+    let mut include = None;
+
+    let nodes: RefNodes = (&source.tree).into();
+    let mut events = match &frame.cursor {
+        Some(x) => EventIter::resume(nodes, x),
+        None => Iter::new(nodes).event(),
+    };
+    while let Some(n) = events.next() {
+        if ret.text.len() >= FLUSH_SIZE {
+            if let Some(writer) = writer.as_deref_mut() {
+                ret.flush(writer)?;
             }
         }
-    })?;
-
-    let mut ret = PreprocessedText::new();
 
-    for n in pp_text.into_iter().event() {
         match n.clone() {
             NodeEvent::Enter(x) => {
                 if skip_nodes.contains(&x) {
@@ -329,42 +2150,70 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
             NodeEvent::Enter(RefNode::SourceDescriptionNotDirective(x)) => {
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
             }
             NodeEvent::Enter(RefNode::SourceDescription(SourceDescription::StringLiteral(x))) => {
                 let locate: Locate = (&**x).try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
             }
             NodeEvent::Enter(RefNode::SourceDescription(SourceDescription::EscapedIdentifier(
                 x,
             ))) => {
                 let locate: Locate = (&**x).try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
             }
             NodeEvent::Enter(RefNode::ResetallCompilerDirective(x)) => {
+                if let Some(begin) = celldefine.take() {
+                    ret.celldefines.push(Range::new(begin, ret.len()));
+                }
+                ret.default_nettypes.push((ret.len(), String::from("wire")));
+
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::ResetallCompilerDirective(_)) => {
                 skip_whitespace = false;
             }
             NodeEvent::Enter(RefNode::TimescaleCompilerDirective(x)) => {
+                let (_, _, ref unit_num, ref unit, _, ref precision_num, ref precision) = x.nodes;
+                let unit = format!(
+                    "{}{}",
+                    unit_num.nodes.0.str(s),
+                    get_str(unit.into(), s).trim()
+                );
+                let precision = format!(
+                    "{}{}",
+                    precision_num.nodes.0.str(s),
+                    get_str(precision.into(), s).trim()
+                );
+
                 let locate: Locate = x.try_into().unwrap();
+                ret.timescales.push(Timescale {
+                    unit,
+                    precision,
+                    path: path.clone(),
+                    offset: original_offset(offsets, locate.offset),
+                });
+
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::TimescaleCompilerDirective(_)) => {
                 skip_whitespace = false;
             }
             NodeEvent::Enter(RefNode::DefaultNettypeCompilerDirective(x)) => {
+                let (_, _, ref value) = x.nodes;
+                let value = String::from(value.nodes.0.nodes.0.str(s));
+                ret.default_nettypes.push((ret.len(), value));
+
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::DefaultNettypeCompilerDirective(_)) => {
@@ -373,7 +2222,7 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
             NodeEvent::Enter(RefNode::UnconnectedDriveCompilerDirective(x)) => {
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::UnconnectedDriveCompilerDirective(_)) => {
@@ -382,16 +2231,20 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
             NodeEvent::Enter(RefNode::NounconnectedDriveCompilerDirective(x)) => {
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::NounconnectedDriveCompilerDirective(_)) => {
                 skip_whitespace = false;
             }
             NodeEvent::Enter(RefNode::CelldefineDriveCompilerDirective(x)) => {
+                if celldefine.is_none() {
+                    celldefine = Some(ret.len());
+                }
+
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::CelldefineDriveCompilerDirective(_)) => {
@@ -400,7 +2253,11 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
             NodeEvent::Enter(RefNode::EndcelldefineDriveCompilerDirective(x)) => {
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
+
+                if let Some(begin) = celldefine.take() {
+                    ret.celldefines.push(Range::new(begin, ret.len()));
+                }
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::EndcelldefineDriveCompilerDirective(_)) => {
@@ -409,16 +2266,35 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
             NodeEvent::Enter(RefNode::Pragma(x)) => {
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::Pragma(_)) => {
                 skip_whitespace = false;
             }
+            NodeEvent::Enter(RefNode::ProtectedEnvelope(x)) => {
+                // Only the enclosing pragmas are kept; the encoded data
+                // between them is recorded but not emitted.
+                let (_, ref data, _) = x.nodes;
+                ret.protected_regions.push(ProtectedRegion {
+                    path: path.clone(),
+                    range: Range::new(
+                        original_offset(offsets, data.offset),
+                        original_offset(offsets, data.offset + data.len),
+                    ),
+                });
+            }
+            NodeEvent::Enter(RefNode::LegacyProtectedEnvelope(x)) => {
+                push_legacy_protected_envelope(ret, x, s, path, offsets);
+                skip_whitespace = true;
+            }
+            NodeEvent::Leave(RefNode::LegacyProtectedEnvelope(_)) => {
+                skip_whitespace = false;
+            }
             NodeEvent::Enter(RefNode::LineCompilerDirective(x)) => {
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::LineCompilerDirective(_)) => {
@@ -427,7 +2303,7 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
             NodeEvent::Enter(RefNode::KeywordsDirective(x)) => {
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::KeywordsDirective(_)) => {
@@ -436,7 +2312,7 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
             NodeEvent::Enter(RefNode::EndkeywordsDirective(x)) => {
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::EndkeywordsDirective(_)) => {
@@ -445,60 +2321,102 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
             NodeEvent::Enter(RefNode::UndefineCompilerDirective(x)) => {
                 let (_, _, ref name) = x.nodes;
                 let id = identifier((&name.nodes.0).into(), &s).unwrap();
-                defines.remove(&id);
+                let removed = defines.remove(&id);
 
                 let locate: Locate = x.try_into().unwrap();
+                if removed.is_none() {
+                    ret.warnings.push(Warning {
+                        kind: WarningKind::UndefUndefined(id.clone()),
+                        path: path.clone(),
+                        offset: original_offset(offsets, locate.offset),
+                    });
+                }
+                observer.undef(&id, path, original_offset(offsets, locate.offset));
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::UndefineCompilerDirective(_)) => {
                 skip_whitespace = false;
             }
             NodeEvent::Enter(RefNode::UndefineallCompilerDirective(x)) => {
+                let cleared: Vec<String> = pre_defined
+                    .iter()
+                    .filter(|x| defines.contains_key(*x))
+                    .cloned()
+                    .collect();
                 defines.clear();
 
                 let locate: Locate = x.try_into().unwrap();
+                if !cleared.is_empty() {
+                    ret.warnings.push(Warning {
+                        kind: WarningKind::PreDefinesCleared(cleared),
+                        path: path.clone(),
+                        offset: original_offset(offsets, locate.offset),
+                    });
+                }
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
                 skip_whitespace = true;
             }
             NodeEvent::Leave(RefNode::UndefineallCompilerDirective(_)) => {
                 skip_whitespace = false;
             }
             NodeEvent::Enter(RefNode::IfdefDirective(x)) => {
-                let (_, ref keyword, ref ifid, ref ifbody, ref elsif, ref elsebody, _, _) = x.nodes;
+                let (ref symbol, ref keyword, ref ifid, ref ifbody, ref elsif, ref elsebody, _, _) =
+                    x.nodes;
                 skip_nodes.push(keyword.into());
                 skip_nodes.push(ifid.into());
 
-                let ifid = identifier(ifid.into(), &s).unwrap();
-                let mut hit = false;
-                if defines.contains_key(&ifid) || is_predefined_text_macro(&ifid) {
-                    hit = true;
+                let defined = is_defined(ifid, s, &defines);
+                let ifid = condition_str(ifid, s);
+                let offset = original_offset(offsets, symbol.nodes.0.offset);
+                let mut hit = None;
+                if defined {
+                    hit = Some(ifid.clone());
+                    observer.conditional(Some(&ifid), true, path, offset);
                 } else {
                     skip_nodes.push(ifbody.into());
+                    ret.push_skipped(ifbody.into(), path, offsets, &ifid);
+                    observe_skipped(observer, ifbody.into(), s, path, offsets);
+                    observer.conditional(Some(&ifid), false, path, offset);
                 }
 
                 for x in elsif {
-                    let (_, ref keyword, ref elsifid, ref elsifbody) = x;
+                    let (ref symbol, ref keyword, ref elsifid, ref elsifbody) = x;
                     skip_nodes.push(keyword.into());
                     skip_nodes.push(elsifid.into());
 
-                    let elsifid = identifier(elsifid.into(), &s).unwrap();
-                    if hit {
+                    let defined = is_defined(elsifid, s, &defines);
+                    let elsifid = condition_str(elsifid, s);
+                    let offset = original_offset(offsets, symbol.nodes.0.offset);
+                    if let Some(ref hit) = hit {
                         skip_nodes.push(elsifbody.into());
-                    } else if defines.contains_key(&elsifid) || is_predefined_text_macro(&ifid) {
-                        hit = true;
+                        ret.push_skipped(elsifbody.into(), path, offsets, hit);
+                        observe_skipped(observer, elsifbody.into(), s, path, offsets);
+                        observer.conditional(Some(&elsifid), false, path, offset);
+                    } else if defined {
+                        observer.conditional(Some(&elsifid), true, path, offset);
+                        hit = Some(elsifid);
                     } else {
                         skip_nodes.push(elsifbody.into());
+                        ret.push_skipped(elsifbody.into(), path, offsets, &elsifid);
+                        observe_skipped(observer, elsifbody.into(), s, path, offsets);
+                        observer.conditional(Some(&elsifid), false, path, offset);
                     }
                 }
 
                 if let Some(elsebody) = elsebody {
-                    let (_, ref keyword, ref elsebody) = elsebody;
+                    let (ref symbol, ref keyword, ref elsebody) = elsebody;
                     skip_nodes.push(keyword.into());
-                    if hit {
+                    let offset = original_offset(offsets, symbol.nodes.0.offset);
+                    if let Some(ref hit) = hit {
                         skip_nodes.push(elsebody.into());
+                        ret.push_skipped(elsebody.into(), path, offsets, hit);
+                        observe_skipped(observer, elsebody.into(), s, path, offsets);
+                        observer.conditional(None, false, path, offset);
+                    } else {
+                        observer.conditional(None, true, path, offset);
                     }
                 }
             }
@@ -506,47 +2424,69 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
                 if let WhiteSpace::Space(_) = x {
                     let locate: Locate = x.try_into().unwrap();
                     let range = Range::new(locate.offset + locate.len, locate.offset + locate.len);
-                    ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                    ret.push(locate.str(&s), Some((path, range)));
                 }
             }
             NodeEvent::Enter(RefNode::Comment(x)) if !strip_comments => {
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
             }
             NodeEvent::Enter(RefNode::IfndefDirective(x)) => {
-                let (_, ref keyword, ref ifid, ref ifbody, ref elsif, ref elsebody, _, _) = x.nodes;
+                let (ref symbol, ref keyword, ref ifid, ref ifbody, ref elsif, ref elsebody, _, _) =
+                    x.nodes;
                 skip_nodes.push(keyword.into());
                 skip_nodes.push(ifid.into());
 
-                let ifid = identifier(ifid.into(), &s).unwrap();
-                let mut hit = false;
-                if !defines.contains_key(&ifid) && !is_predefined_text_macro(&ifid) {
-                    hit = true;
+                let defined = is_defined(ifid, s, &defines);
+                let ifid = condition_str(ifid, s);
+                let offset = original_offset(offsets, symbol.nodes.0.offset);
+                let mut hit = None;
+                if !defined {
+                    hit = Some(ifid.clone());
+                    observer.conditional(Some(&ifid), true, path, offset);
                 } else {
                     skip_nodes.push(ifbody.into());
+                    ret.push_skipped(ifbody.into(), path, offsets, &ifid);
+                    observe_skipped(observer, ifbody.into(), s, path, offsets);
+                    observer.conditional(Some(&ifid), false, path, offset);
                 }
 
                 for x in elsif {
-                    let (_, ref keyword, ref elsifid, ref elsifbody) = x;
+                    let (ref symbol, ref keyword, ref elsifid, ref elsifbody) = x;
                     skip_nodes.push(keyword.into());
                     skip_nodes.push(elsifid.into());
 
-                    let elsifid = identifier(elsifid.into(), &s).unwrap();
-                    if hit {
+                    let defined = is_defined(elsifid, s, &defines);
+                    let elsifid = condition_str(elsifid, s);
+                    let offset = original_offset(offsets, symbol.nodes.0.offset);
+                    if let Some(ref hit) = hit {
                         skip_nodes.push(elsifbody.into());
-                    } else if defines.contains_key(&elsifid) || is_predefined_text_macro(&ifid) {
-                        hit = true;
+                        ret.push_skipped(elsifbody.into(), path, offsets, hit);
+                        observe_skipped(observer, elsifbody.into(), s, path, offsets);
+                        observer.conditional(Some(&elsifid), false, path, offset);
+                    } else if defined {
+                        observer.conditional(Some(&elsifid), true, path, offset);
+                        hit = Some(elsifid);
                     } else {
                         skip_nodes.push(elsifbody.into());
+                        ret.push_skipped(elsifbody.into(), path, offsets, &elsifid);
+                        observe_skipped(observer, elsifbody.into(), s, path, offsets);
+                        observer.conditional(Some(&elsifid), false, path, offset);
                     }
                 }
 
                 if let Some(elsebody) = elsebody {
-                    let (_, ref keyword, ref elsebody) = elsebody;
+                    let (ref symbol, ref keyword, ref elsebody) = elsebody;
                     skip_nodes.push(keyword.into());
-                    if hit {
+                    let offset = original_offset(offsets, symbol.nodes.0.offset);
+                    if let Some(ref hit) = hit {
                         skip_nodes.push(elsebody.into());
+                        ret.push_skipped(elsebody.into(), path, offsets, hit);
+                        observe_skipped(observer, elsebody.into(), s, path, offsets);
+                        observer.conditional(None, false, path, offset);
+                    } else {
+                        observer.conditional(None, true, path, offset);
                     }
                 }
             }
@@ -584,10 +2524,7 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
                         let text: Locate = text.try_into().unwrap();
                         let range = Range::new(text.offset, text.offset + text.len);
                         let text = String::from(text.str(&s));
-                        Some(DefineText {
-                            text,
-                            origin: Some((PathBuf::from(path.as_ref()), range)),
-                        })
+                        Some(DefineText::new(text, Some((path.clone(), range))))
                     } else {
                         None
                     };
@@ -598,13 +2535,28 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
                         text: define_text,
                     };
 
+                    let (ref symbol, _, _, _) = x.nodes;
+                    let offset = original_offset(offsets, symbol.nodes.0.offset);
+                    observer.define(&define, path, offset);
+
+                    if let Some(Some(old)) = defines.get(&id) {
+                        let text = |x: &Define| x.text.as_ref().map(|x| x.text.clone());
+                        if old.arguments != define.arguments || text(old) != text(&define) {
+                            ret.warnings.push(Warning {
+                                kind: WarningKind::MacroRedefined(id.clone()),
+                                path: path.clone(),
+                                offset,
+                            });
+                        }
+                    }
+
                     defines.insert(id, Some(define));
                 }
 
                 // Keep TextMacroDefinition after preprocess_inner().
                 let locate: Locate = x.try_into().unwrap();
                 let range = Range::new(locate.offset, locate.offset + locate.len);
-                ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                ret.push(locate.str(&s), Some((path, range)));
             }
             NodeEvent::Enter(RefNode::IncludeCompilerDirective(x)) if !ignore_include => {
                 skip_nodes.push(x.into());
@@ -612,6 +2564,7 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
 
                 let locate: Locate = x.try_into().unwrap();
                 last_include_line = Some(locate.line);
+                let origin = (path.clone(), original_offset(offsets, locate.offset));
 
                 // IEEE1800-2017 Clause 22.4, page 675
                 // Only white space or a comment may appear on the same line as
@@ -622,7 +2575,8 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
                     }
                 }
 
-                let mut path = match x {
+                let mut angle_bracket = false;
+                let path = match x {
                     IncludeCompilerDirective::DoubleQuote(x) => {
                         let (_, ref keyword, ref literal) = x.nodes;
                         skip_nodes.push(keyword.into());
@@ -637,6 +2591,7 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
 
                         let (locate, _) = literal.nodes;
                         let p = locate.str(&s).trim_start_matches('<').trim_end_matches('>');
+                        angle_bracket = true;
                         PathBuf::from(p)
                     }
                     IncludeCompilerDirective::TextMacroUsage(x) => {
@@ -644,16 +2599,28 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
                         skip_nodes.push(keyword.into());
                         skip_nodes.push(x.into());
 
-                        if let Some((p, _, _)) = resolve_text_macro_usage(
+                        let locate: Locate = x.try_into().unwrap();
+                        let offset = original_offset(offsets, locate.offset);
+                        if let Some(expansion) = resolve_text_macro_usage(
                             x,
                             s,
-                            path.as_ref(),
-                            &defines,
+                            path,
+                            &mut defines,
                             include_paths,
-                            strip_comments,
+                            config,
                             resolve_depth + 1,
-                        )? {
-                            let p = p.trim().trim_matches('"');
+                        )
+                        .map_err(|e| define_not_found_at(e, path, offset))?
+                        {
+                            // The macro may expand to either form of filename.
+                            let p = expansion.text.trim();
+                            let p = match p.strip_prefix('<').and_then(|x| x.strip_suffix('>')) {
+                                Some(x) => {
+                                    angle_bracket = true;
+                                    x
+                                }
+                                None => p.trim_matches('"'),
+                            };
                             PathBuf::from(p)
                         } else {
                             PathBuf::from("")
@@ -661,59 +2628,47 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
                     }
                 };
 
-                // IEEE1800-2017 Clause 22.4, page 675
-                // The filename can be enclosed in either quotes or angle brackets,
-                // which affects how a tool searches for the file, as follows:
-                // - When the filename is enclosed in double quotes ("filename"), for
-                //   a relative path the compiler’s current working directory, and
-                //   optionally user-specified locations are searched.
-                // - When the filename is enclosed in angle brackets (<filename>), then
-                //   only an implementationdependent location containing files defined
-                //   by the language standard is searched. Relative path names are
-                //   interpreted relative to that location
-                //
-                // In this implementation, filenames enclosed in angle brackets are
-                // treated equivalently to those enclosed in double quotes.
-                if path.is_relative() && !path.exists() {
-                    for include_path in include_paths {
-                        let new_path = include_path.as_ref().join(&path);
-                        if new_path.exists() {
-                            path = new_path;
-                            break;
-                        }
-                    }
-                }
+                let path = find_include(path, angle_bracket, &origin, include_paths, config, ret)?;
 
-                let (include, new_defines) =
-                    preprocess_inner(
-                        path,
-                        &defines,
-                        include_paths,
-                        strip_comments,
-                        false, // ignore_include
-                        include_depth + 1).map_err(
-                        |x| Error::Include {
-                            source: Box::new(x),
-                        },
-                    )?;
-                defines = new_defines;
-                ret.merge(include);
+                frame.cursor = Some(events.cursor());
+                include = Some((path, origin.1));
+                break;
             }
             NodeEvent::Enter(RefNode::TextMacroUsage(x)) => {
                 skip_nodes.push(x.into());
                 skip = true;
 
-                if let Some((text, origin, new_defines)) = resolve_text_macro_usage(
+                let locate: Locate = x.try_into().unwrap();
+                let offset = original_offset(offsets, locate.offset);
+                let id = identifier((&x.nodes.1).into(), s).unwrap();
+                observer.macro_usage(&id, false, path, offset);
+                if config.pass_unknown_directives && !defines.contains_key(&id) {
+                    push_unknown_directive(ret, x, s, path, offset);
+                } else if let Some(expansion) = resolve_text_macro_usage(
                     x,
                     s,
-                    path.as_ref(),
-                    &defines,
+                    path,
+                    &mut defines,
                     include_paths,
-                    strip_comments,
+                    config,
                     resolve_depth + 1,
-                )? {
-                    ret.push(&text, origin);
-                    defines = new_defines;
+                )
+                .map_err(|e| define_not_found_at(e, path, offset))?
+                {
+                    let begin = ret.len();
+                    expansion.push_to(ret);
+                    let range = Range::new(begin, ret.len());
+                    ret.push_expansion(
+                        MacroExpansion {
+                            name: id,
+                            path: path.clone(),
+                            offset,
+                            arguments: expansion.arguments,
+                            range,
+                            parent: None,
+                        },
+                        expansion.nested,
+                    );
                 }
 
                 // Push the trailing whitespace attached to either
@@ -727,11 +2682,11 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
                             match x {
                                 RefNode::WhiteSpace(x) => {
                                     let locate: Locate = x.try_into().unwrap();
-                                    let range = Range::new(locate.offset, locate.offset + locate.len);
-                                    ret.push(locate.str(&s), Some((path.as_ref(), range)));
-                                }
-                                _ => {
+                                    let range =
+                                        Range::new(locate.offset, locate.offset + locate.len);
+                                    ret.push(locate.str(&s), Some((path, range)));
                                 }
+                                _ => {}
                             }
                         }
                     }
@@ -741,8 +2696,9 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
                             match x {
                                 RefNode::WhiteSpace(x) => {
                                     let locate: Locate = x.try_into().unwrap();
-                                    let range = Range::new(locate.offset, locate.offset + locate.len);
-                                    ret.push(locate.str(&s), Some((path.as_ref(), range)));
+                                    let range =
+                                        Range::new(locate.offset, locate.offset + locate.len);
+                                    ret.push(locate.str(&s), Some((path, range)));
                                 }
                                 _ => {}
                             }
@@ -759,10 +2715,7 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
                 let x = locate.str(s);
                 if x.starts_with("__FILE__") {
                     ret.push::<PathBuf>(
-                        &x.replace(
-                            "__FILE__",
-                            &format!("\"{}\"", path.as_ref().to_string_lossy()),
-                        ),
+                        &x.replace("__FILE__", &format!("\"{}\"", path.to_string_lossy())),
                         None,
                     );
                 } else if x.starts_with("__LINE__") {
@@ -773,7 +2726,158 @@ pub fn preprocess_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
         }
     }
 
-    Ok((ret, defines))
+    frame.skip = skip;
+    frame.skip_whitespace = skip_whitespace;
+    frame.last_item_line = last_item_line;
+    frame.last_include_line = last_include_line;
+    frame.celldefine = celldefine;
+
+    Ok((include, defines))
+}
+
+// Find the file `path` of an `include at `origin`, in the search paths for
+// a filename in quotes or, if `angle_bracket`, in angle brackets.
+fn find_include<U: AsRef<Path>>(
+    path: PathBuf,
+    angle_bracket: bool,
+    origin: &(PathBuf, usize),
+    include_paths: &[U],
+    config: &PreprocessorConfig,
+    ret: &mut PreprocessedText,
+) -> Result<PathBuf, Error> {
+    let mut path = expand_env(&path);
+
+    // IEEE1800-2017 Clause 22.4, page 675
+    // The filename can be enclosed in either quotes or angle brackets,
+    // which affects how a tool searches for the file, as follows:
+    // - When the filename is enclosed in double quotes ("filename"), for
+    //   a relative path the compiler’s current working directory, and
+    //   optionally user-specified locations are searched.
+    // - When the filename is enclosed in angle brackets (<filename>), then
+    //   only an implementationdependent location containing files defined
+    //   by the language standard is searched. Relative path names are
+    //   interpreted relative to that location
+    //
+    // In this implementation, the location for angle brackets is given
+    // by config.system_include_paths. Without it, filenames enclosed in
+    // angle brackets are treated equivalently to those enclosed in
+    // double quotes.
+    let files = config.files();
+    let system = angle_bracket && !config.system_include_paths.is_empty();
+    let search_paths: Vec<PathBuf> = if system {
+        config
+            .system_include_paths
+            .iter()
+            .map(|x| expand_env(x.as_path()))
+            .collect()
+    } else {
+        include_paths
+            .iter()
+            .map(|x| expand_env(x.as_ref()))
+            .collect()
+    };
+    let mut found = files.exists(&path) && !(system && path.is_relative());
+    if path.is_relative() && !found {
+        for include_path in &search_paths {
+            let new_path = include_path.join(&path);
+            if files.exists(&new_path) {
+                path = new_path;
+                found = true;
+                break;
+            }
+        }
+    }
+    if !found {
+        if let Some(resolved) = find_include_fallback(
+            &path,
+            &search_paths,
+            !system,
+            &config.include_fallback,
+            files,
+        ) {
+            ret.warnings.push(Warning {
+                kind: WarningKind::IncludeFallback {
+                    name: path,
+                    resolved: resolved.clone(),
+                },
+                path: origin.0.clone(),
+                offset: origin.1,
+            });
+            path = resolved;
+            found = true;
+        }
+    }
+    if !found {
+        return Err(Error::IncludeNotFound {
+            name: path,
+            path: origin.0.clone(),
+            offset: origin.1,
+            include_paths: search_paths,
+        });
+    }
+    Ok(path)
+}
+
+// Look for an include file with the fallbacks, in the current directory
+// unless `current_dir` is false and then the include paths for a relative
+// path.
+fn find_include_fallback(
+    path: &Path,
+    include_paths: &[PathBuf],
+    current_dir: bool,
+    fallback: &IncludeFallback,
+    files: &dyn FileProvider,
+) -> Option<PathBuf> {
+    let mut names = vec![PathBuf::from(path)];
+    for (from, to) in &fallback.extensions {
+        if path.extension().is_some_and(|x| x == from.as_str()) {
+            names.push(path.with_extension(to));
+        }
+    }
+
+    let mut dirs = Vec::new();
+    if current_dir || path.is_absolute() {
+        dirs.push(PathBuf::new());
+    }
+    if path.is_relative() {
+        dirs.extend(include_paths.iter().cloned());
+    }
+
+    for dir in &dirs {
+        for name in &names {
+            let found = if fallback.case_insensitive {
+                find_case_insensitive(dir, name, files)
+            } else {
+                Some(dir.join(name)).filter(|x| files.exists(x))
+            };
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+    None
+}
+
+// Find `path` within `dir`, matching each name regardless of case.
+fn find_case_insensitive(dir: &Path, path: &Path, files: &dyn FileProvider) -> Option<PathBuf> {
+    let mut ret = PathBuf::from(dir);
+    for x in path.components() {
+        match x {
+            Component::Normal(name) if !files.exists(&ret.join(name)) => {
+                let name = name.to_string_lossy().to_lowercase();
+                // The first in order, in case several names match.
+                let found = files
+                    .read_dir(&ret)
+                    .ok()?
+                    .into_iter()
+                    .filter(|x| x.to_string_lossy().to_lowercase() == name)
+                    .min()?;
+                ret.push(found);
+            }
+            x => ret.push(x),
+        }
+    }
+    Some(ret).filter(|x| files.exists(x))
 }
 
 fn identifier(node: RefNode, s: &str) -> Option<String> {
@@ -792,7 +2896,82 @@ fn identifier(node: RefNode, s: &str) -> Option<String> {
             _ => (),
         }
     }
-    None
+    None
+}
+
+// Whether the condition of `ifdef, `ifndef or `elsif holds.
+fn is_defined(x: &IfdefCondition, s: &str, defines: &Defines) -> bool {
+    match x {
+        IfdefCondition::TextMacroIdentifier(x) => {
+            let id = identifier((&**x).into(), s).unwrap();
+            defines.contains_key(&id) || is_predefined_text_macro(&id)
+        }
+        IfdefCondition::Paren(x) => {
+            let (ref x,) = x.nodes;
+            let (_, ref x, _) = x.nodes;
+            is_defined_expression(x, s, defines)
+        }
+    }
+}
+
+fn is_defined_expression(x: &IfdefMacroExpression, s: &str, defines: &Defines) -> bool {
+    let (ref first, ref rest) = x.nodes;
+    let mut values = vec![is_defined_operand(first, s, defines)];
+    let mut operators = vec![];
+    for (operator, operand) in rest {
+        operators.push(operator.nodes.0.str(s));
+        values.push(is_defined_operand(operand, s, defines));
+    }
+
+    // IEEE1800-2023 Clause 22.6
+    // The operators have the same precedence and associativity as in
+    // expressions: && binds tightest, then ||, and -> and <-> are right
+    // associative.
+    let apply = |operator: &str, a: bool, b: bool| match operator {
+        "&&" => a && b,
+        "||" => a || b,
+        "->" => !a || b,
+        _ => a == b,
+    };
+    for level in [&["&&"][..], &["||"][..]] {
+        while let Some(i) = operators.iter().position(|x| level.contains(x)) {
+            values[i] = apply(operators[i], values[i], values[i + 1]);
+            values.remove(i + 1);
+            operators.remove(i);
+        }
+    }
+    while let Some(i) = operators.len().checked_sub(1) {
+        values[i] = apply(operators[i], values[i], values[i + 1]);
+        values.remove(i + 1);
+        operators.remove(i);
+    }
+    values[0]
+}
+
+fn is_defined_operand(x: &IfdefMacroOperand, s: &str, defines: &Defines) -> bool {
+    match x {
+        IfdefMacroOperand::TextMacroIdentifier(x) => {
+            let id = identifier((&**x).into(), s).unwrap();
+            defines.contains_key(&id) || is_predefined_text_macro(&id)
+        }
+        IfdefMacroOperand::Not(x) => {
+            let (_, ref x) = x.nodes;
+            !is_defined_operand(x, s, defines)
+        }
+        IfdefMacroOperand::Paren(x) => {
+            let (ref x,) = x.nodes;
+            let (_, ref x, _) = x.nodes;
+            is_defined_expression(x, s, defines)
+        }
+    }
+}
+
+// The macro identifier of a condition, or the text of an expression.
+fn condition_str(x: &IfdefCondition, s: &str) -> String {
+    match x {
+        IfdefCondition::TextMacroIdentifier(x) => identifier((&**x).into(), s).unwrap(),
+        IfdefCondition::Paren(_) => String::from(get_str(x.into(), s).trim()),
+    }
 }
 
 fn get_str(node: RefNode, s: &str) -> String {
@@ -808,22 +2987,78 @@ fn get_str(node: RefNode, s: &str) -> String {
     ret
 }
 
+// Tell `observer` of the macro usages within `node`, a skipped branch.
+fn observe_skipped(
+    observer: &dyn PreprocessObserver,
+    node: RefNode,
+    s: &str,
+    path: &Path,
+    offsets: &[(usize, usize)],
+) {
+    for x in node {
+        if let RefNode::TextMacroUsage(x) = x {
+            let locate: Locate = x.try_into().unwrap();
+            if let Some(id) = identifier((&x.nodes.1).into(), s) {
+                observer.macro_usage(&id, true, path, original_offset(offsets, locate.offset));
+            }
+        }
+    }
+}
+
 fn is_predefined_text_macro(s: &str) -> bool {
     match s {
-        "__LINE__" | "__FILE__" => {
-            true
-        }
-        _ => {
-            false
+        "__LINE__" | "__FILE__" => true,
+        _ => false,
+    }
+}
+
+// Expand references to environment variables, written as either $NAME or
+// ${NAME}, as simulators do for include paths and filenames.
+// References to undefined variables are left unchanged.
+pub(crate) fn expand_env(path: &Path) -> PathBuf {
+    let s = match path.to_str() {
+        Some(s) if s.contains('$') => s,
+        _ => return PathBuf::from(path),
+    };
+
+    let mut ret = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find('$') {
+        ret.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, len) = if let Some(x) = after.strip_prefix('{') {
+            match x.find('}') {
+                Some(j) => (&x[..j], j + 2),
+                None => ("", 0),
+            }
+        } else {
+            let j = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..j], j)
+        };
+        match env::var(name) {
+            Ok(value) if !name.is_empty() => {
+                ret.push_str(&value);
+                rest = &after[len..];
+            }
+            _ => {
+                ret.push('$');
+                rest = after;
+            }
         }
     }
+    ret.push_str(rest);
+
+    PathBuf::from(ret)
 }
 
-fn split_text(s: &str) -> Vec<String> {
+fn split_text(s: &str) -> Vec<DefineFragment> {
     let mut is_string = false;
     let mut is_ident = false;
     let mut is_ident_prev;
     let mut x = String::from("");
+    let mut begin = 0;
     let mut ret = vec![];
 
     // IEEE1800-2017 Clause 22.5.1, page 676
@@ -842,18 +3077,19 @@ fn split_text(s: &str) -> Vec<String> {
     let mut is_leading_whitespace = true;
     let mut is_backslash_prev = false;
 
-    let mut iter = s.chars().peekable();
-    while let Some(c) = iter.next() {
-
+    let mut iter = s.char_indices().peekable();
+    while let Some((i, c)) = iter.next() {
         // IEEE1800-2017 Clause 22.5.1, page 676, Syntax 22-2.
         // Ignore whitespace immediately after text_macro_name.
         if is_leading_whitespace {
             if c != '\\' && !c.is_ascii_whitespace() {
                 // Non-whitespace character, move onto main loop.
                 is_leading_whitespace = false;
+                begin = i;
             } else if is_backslash_prev && c == '\n' {
                 // Drop the \n from leading continuation, then move onto main loop.
                 is_leading_whitespace = false;
+                begin = i + 1;
                 continue;
             } else {
                 // Still in leading whitespace or possible continuation.
@@ -873,24 +3109,24 @@ fn split_text(s: &str) -> Vec<String> {
             continue;
         } else if c == '"' && is_backquote_prev {
             x.push(c);
-            ret.push(x);
-            x = String::from("");
+            push_fragment(&mut ret, std::mem::take(&mut x), Range::new(begin, i + 1));
+            begin = i + 1;
         } else if c == '"' && !is_string {
-            ret.push(x);
-            x = String::from("");
+            push_fragment(&mut ret, std::mem::take(&mut x), Range::new(begin, i));
+            begin = i;
             x.push(c);
             is_string = true;
         } else if c == '"' && is_string {
             x.push(c);
-            ret.push(x);
-            x = String::from("");
+            push_fragment(&mut ret, std::mem::take(&mut x), Range::new(begin, i + 1));
+            begin = i + 1;
             is_string = false;
-        } else if c == '/' && iter.peek() == Some(&'/') && !is_string {
+        } else if c == '/' && matches!(iter.peek(), Some((_, '/'))) && !is_string {
             is_comment = true;
         } else if !is_string {
             if is_ident != is_ident_prev {
-                ret.push(x);
-                x = String::from("");
+                push_fragment(&mut ret, std::mem::take(&mut x), Range::new(begin, i));
+                begin = i;
             }
             x.push(c);
         } else {
@@ -899,26 +3135,92 @@ fn split_text(s: &str) -> Vec<String> {
 
         is_backquote_prev = c == '`';
     }
-    ret.push(x);
+    push_fragment(&mut ret, x, Range::new(begin.min(s.len()), s.len()));
     ret
 }
 
+fn push_fragment(ret: &mut Vec<DefineFragment>, text: String, range: Range) {
+    if text.is_empty() {
+        return;
+    }
+    let identifier = text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let text = if identifier {
+        text
+    } else {
+        text.replace("``", "") // Argument substitution.
+            .replace("`\\`\"", "\\\"") // Escaped backslash.
+            .replace("`\"", "\"") // Escaped quote.
+            .replace("\\\n", "\n") // Line continuation (Unix).
+            .replace("\\\r\n", "\r\n") // Line continuation (Windows).
+            .replace("\\\r", "\r") // Line continuation (old Mac).
+    };
+    ret.push(DefineFragment {
+        text,
+        identifier,
+        range,
+    });
+}
+
 fn resolve_text_macro_usage<T: AsRef<Path>, U: AsRef<Path>>(
     x: &TextMacroUsage,
     s: &str,
     path: T,
-    defines: &Defines,
+    defines: &mut Defines,
     include_paths: &[U],
-    strip_comments: bool,
+    config: &PreprocessorConfig,
     resolve_depth: usize,
-) -> Result<Option<(String, Option<(PathBuf, Range)>, Defines)>, Error> {
-    let (_, ref name, ref args) = x.nodes;
-    let id = identifier((&name.nodes.0).into(), &s).unwrap();
-
+) -> Result<Option<Expansion>, Error> {
     if resolve_depth > RECURSIVE_LIMIT {
         return Err(Error::ExceedRecursiveLimit);
     }
 
+    let (arguments, replaced) = replace_arguments(x, s, path.as_ref(), defines, resolve_depth)?;
+    let (replaced, pieces) = match replaced {
+        Some(x) => x,
+        None => return Ok(Some(Expansion::empty(arguments))),
+    };
+
+    let config = PreprocessorConfig {
+        ignore_include: false,
+        ..config.clone()
+    };
+    let source = Source::parse(SourceText::Owned(replaced), vec![], path.as_ref())?;
+    let frame = Frame::new(Arc::new(source), path.as_ref(), 0);
+    let mut taken = std::mem::take(defines);
+    add_sv_cov_pre_defines(&mut taken);
+    let (replaced, new_defines) = preprocess_frames(
+        frame,
+        taken,
+        &[],
+        include_paths,
+        &config,
+        resolve_depth,
+        None, // writer
+    )?;
+    *defines = new_defines;
+    let origins = expansion_origins(&replaced, &pieces, path.as_ref());
+    Ok(Some(Expansion {
+        text: String::from(replaced.text()),
+        origins,
+        arguments,
+        nested: replaced.expansions,
+    }))
+}
+
+// The actual arguments of the usage `x`, and the text of its define with
+// them replaced, if it has a text, with the origin of each piece. This is
+// apart from resolve_text_macro_usage(), so that its locals aren't on the
+// stack for each level of nested usages.
+fn replace_arguments(
+    x: &TextMacroUsage,
+    s: &str,
+    path: &Path,
+    defines: &Defines,
+    resolve_depth: usize,
+) -> Result<(Vec<Option<String>>, Option<(String, Origins)>), Error> {
+    let (_, ref name, ref args) = x.nodes;
+    let id = identifier((&name.nodes.0).into(), &s).unwrap();
+
     let mut args_str = String::from("");
     let mut actual_args = Vec::new();
     let no_args = args.is_none();
@@ -932,101 +3234,267 @@ fn resolve_text_macro_usage<T: AsRef<Path>, U: AsRef<Path>>(
         for arg in args.contents() {
             if let Some(arg) = arg {
                 let (ref arg,) = arg.nodes;
-                let arg = arg.str(&s).trim_end();
-                actual_args.push(Some(arg));
+                actual_args.push(Some((arg.str(&s).trim_end(), arg.offset)));
             } else {
                 actual_args.push(None);
             }
         }
     }
 
-    let define = defines.get(&id);
-    if let Some(Some(define)) = define {
-        let mut arg_map = HashMap::new();
-
-        if !define.arguments.is_empty() && no_args {
-            return Err(Error::DefineNoArgs(define.identifier.clone()));
+    let arguments = actual_args
+        .iter()
+        .map(|x| x.map(|(x, _)| String::from(x)))
+        .collect();
+    let define = match defines.get(&id) {
+        Some(Some(define)) => define,
+        Some(None) => return Ok((arguments, None)),
+        None => {
+            let locate: Locate = x.try_into().unwrap();
+            return Err(Error::DefineNotFound {
+                name: id,
+                path: PathBuf::from(path),
+                offset: locate.offset,
+            });
         }
+    };
 
-        for (i, (arg, default)) in define.arguments.iter().enumerate() {
-            let value = match actual_args.get(i) {
-                Some(Some(actual_arg)) => *actual_arg,
-                Some(None) => {
-                    if let Some(default) = default {
-                        default
-                    } else {
-                        ""
-                    }
+    let mut arg_map = HashMap::new();
+
+    if !define.arguments.is_empty() && no_args {
+        return Err(Error::DefineNoArgs(define.identifier.clone()));
+    }
+
+    // Each value is given with the offset of an actual argument.
+    for (i, (arg, default)) in define.arguments.iter().enumerate() {
+        let value = match actual_args.get(i) {
+            Some(Some((actual_arg, offset))) => (*actual_arg, Some(*offset)),
+            Some(None) => {
+                if let Some(default) = default {
+                    (default.as_str(), None)
+                } else {
+                    ("", None)
                 }
-                None => {
-                    if let Some(default) = default {
-                        default
-                    } else {
-                        return Err(Error::DefineArgNotFound(String::from(arg)));
-                    }
+            }
+            None => {
+                if let Some(default) = default {
+                    (default.as_str(), None)
+                } else {
+                    return Err(Error::DefineArgNotFound(String::from(arg)));
                 }
+            }
+        };
+        arg_map.insert(String::from(arg), value);
+    }
+
+    // restore () for textmacro without arguments
+    let paren = if define.arguments.is_empty() {
+        Some(args_str)
+    } else {
+        None
+    };
+
+    if let Some(ref text) = define.text {
+        // Each piece of the replaced text with its origin, which is the
+        // fragment within the text of the define, or an actual argument
+        // written in a source file rather than in the text of another macro.
+        let mut replaced = String::from("");
+        let mut pieces = Vec::new();
+        for fragment in text.fragments() {
+            let begin = replaced.len();
+            let value = arg_map.get(&fragment.text).filter(|_| fragment.identifier);
+            let origin = if let Some((value, offset)) = value {
+                replaced.push_str(value);
+                (*offset).filter(|_| resolve_depth == 1).map(|x| {
+                    let range = Range::new(x, x + value.len());
+                    (PathBuf::from(path), range)
+                })
+            } else {
+                replaced.push_str(&fragment.text);
+                text.origin.as_ref().map(|(x, y)| {
+                    let range =
+                        Range::new(y.begin + fragment.range.begin, y.begin + fragment.range.end);
+                    (x.clone(), range)
+                })
             };
-            arg_map.insert(String::from(arg), value);
+            pieces.push((Range::new(begin, replaced.len()), origin));
         }
 
-        // restore () for textmacro without arguments
-        let paren = if define.arguments.is_empty() {
-            Some(args_str)
-        } else {
-            None
-        };
+        if let Some(paren) = paren {
+            let begin = replaced.len();
+            replaced.push_str(&paren);
+            pieces.push((Range::new(begin, replaced.len()), None));
+        }
 
-        if let Some(ref text) = define.text {
-            let mut replaced = String::from("");
-            for text in split_text(&text.text) {
-                if let Some(value) = arg_map.get(&text) {
-                    replaced.push_str(*value);
-                } else {
-                    replaced.push_str(
-                        &text
-                            .replace("``", "")          // Argument substitution.
-                            .replace("`\\`\"", "\\\"")  // Escaped backslash.
-                            .replace("`\"", "\"")       // Escaped quote.
-                            .replace("\\\n", "\n")      // Line continuation (Unix).
-                            .replace("\\\r\n", "\r\n")  // Line continuation (Windows).
-                            .replace("\\\r", "\r"),     // Line continuation (old Mac).
-                    );
-                }
+        Ok((arguments, Some((replaced, pieces))))
+    } else {
+        Ok((arguments, None))
+    }
+}
+
+// Ranges of a text with their origins, in order.
+type Origins = Vec<(Range, Option<(PathBuf, Range)>)>;
+
+// The result of `resolve_text_macro_usage()`.
+struct Expansion {
+    text: String,
+    // The ranges of the text with their origins, in order.
+    origins: Origins,
+    arguments: Vec<Option<String>>,
+    // The expansions within the text.
+    nested: Vec<MacroExpansion>,
+}
+
+impl Expansion {
+    // The expansion of a macro without text.
+    fn empty(arguments: Vec<Option<String>>) -> Self {
+        Expansion {
+            text: String::new(),
+            origins: Vec::new(),
+            arguments,
+            nested: Vec::new(),
+        }
+    }
+
+    // Push the text to `ret`, each range with its origin.
+    fn push_to(&self, ret: &mut PreprocessedText) {
+        let mut end = 0;
+        for (range, origin) in &self.origins {
+            ret.push::<PathBuf>(&self.text[end..range.begin], None);
+            let origin = origin.as_ref().map(|(x, y)| (x, *y));
+            ret.push(&self.text[range.begin..range.end], origin);
+            end = range.end;
+        }
+        ret.push::<PathBuf>(&self.text[end..], None);
+    }
+}
+
+// The origins of `replaced`, preprocessed from the text substituted for a
+// define as `pieces` at `path`. Text of nested expansions and includes keeps
+// its own origin.
+fn expansion_origins(
+    replaced: &PreprocessedText,
+    pieces: &[(Range, Option<(PathBuf, Range)>)],
+    path: &Path,
+) -> Vec<(Range, Option<(PathBuf, Range)>)> {
+    let nested = |x: Range| {
+        replaced
+            .expansions
+            .iter()
+            .any(|e| e.parent.is_none() && e.range.begin <= x.begin && x.begin < e.range.end)
+    };
+
+    let mut ret = Vec::new();
+    for x in &replaced.origins {
+        let substituted = match &x.origin {
+            Some((origin_path, range)) if origin_path == path && !nested(x.range) => *range,
+            _ => {
+                ret.push((x.range, x.origin.clone()));
+                continue;
             }
+        };
 
-            if let Some(paren) = paren {
-                replaced.push_str(&paren);
+        let first = pieces.partition_point(|(piece, _)| piece.end <= substituted.begin);
+        for (piece, origin) in &pieces[first..] {
+            if piece.begin >= substituted.end {
+                break;
             }
+            let begin = piece.begin.max(substituted.begin);
+            let end = piece.end.min(substituted.end);
+            let range = Range::new(
+                (x.range.begin + begin - substituted.begin).min(x.range.end),
+                (x.range.begin + end - substituted.begin).min(x.range.end),
+            );
+            // The text of a fragment may be shorter than its source.
+            let origin = origin.as_ref().map(|(origin_path, origin)| {
+                let origin_begin = (origin.begin + begin - piece.begin).min(origin.end);
+                let origin_end = (origin_begin + end - begin).min(origin.end);
+                (origin_path.clone(), Range::new(origin_begin, origin_end))
+            });
+            ret.push((range, origin));
+        }
+    }
+    ret
+}
 
-            let (replaced, new_defines) = preprocess_str(
-                &replaced,
-                path.as_ref(),
-                &defines,
-                include_paths,
-                false,
-                strip_comments,
-                resolve_depth,
-                0, // include_depth
-            )?;
-            Ok(Some((
-                String::from(replaced.text()),
-                text.origin.clone(),
-                new_defines,
-            )))
-        } else {
-            Ok(None)
+// Only the enclosing directives of a `protect or `protected envelope are
+// kept; the encrypted text between them is recorded but not emitted.
+fn push_legacy_protected_envelope(
+    ret: &mut PreprocessedText,
+    x: &LegacyProtectedEnvelope,
+    s: &str,
+    path: &Path,
+    offsets: &[(usize, usize)],
+) {
+    let (ref begin, _, ref data, ref end, _) = x.nodes;
+    let locate: Locate = x.try_into().unwrap();
+    let range = Range::new(begin.nodes.0.offset, data.offset);
+    ret.push(&s[range.begin..range.end], Some((path, range)));
+    // The line break after the opening directive is part of the encrypted
+    // text, but is still needed to separate the directives.
+    ret.push::<PathBuf>("\n", None);
+    let range = Range::new(end.nodes.0.offset, locate.offset + locate.len);
+    ret.push(&s[range.begin..range.end], Some((path, range)));
+    ret.protected_regions.push(ProtectedRegion {
+        path: PathBuf::from(path),
+        range: Range::new(
+            original_offset(offsets, data.offset),
+            original_offset(offsets, data.offset + data.len),
+        ),
+    });
+}
+
+// Pass an undefined directive through as text, without the trailing
+// whitespace which is pushed like that of a macro usage.
+fn push_unknown_directive(
+    ret: &mut PreprocessedText,
+    x: &TextMacroUsage,
+    s: &str,
+    path: &Path,
+    offset: usize,
+) {
+    let (_, ref id, ref args) = x.nodes;
+    let locate: Locate = x.try_into().unwrap();
+    let end = match args {
+        Some(p) => p.nodes.2.nodes.0.offset + p.nodes.2.nodes.0.len,
+        None => {
+            // The first Locate is the identifier itself.
+            let mut end = locate.offset;
+            for x in id {
+                if let RefNode::Locate(x) = x {
+                    end = x.offset + x.len;
+                    break;
+                }
+            }
+            end
         }
-    } else if define.is_some() {
-        Ok(None)
-    } else {
-        Err(Error::DefineNotFound(id))
+    };
+    let range = Range::new(locate.offset, end);
+    ret.push(&s[locate.offset..end], Some((path, range)));
+    ret.warnings.push(Warning {
+        kind: WarningKind::UnknownDirective(identifier(id.into(), s).unwrap()),
+        path: PathBuf::from(path),
+        offset,
+    });
+}
+
+// Locate an undefined macro at the usage of the macro being expanded, if it
+// was found within the text of that macro.
+fn define_not_found_at(e: Error, path: &Path, offset: usize) -> Error {
+    match e {
+        Error::DefineNotFound { name, .. } => Error::DefineNotFound {
+            name,
+            path: PathBuf::from(path),
+            offset,
+        },
+        e => e,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
+    use std::fs::File;
+    use std::io::{BufReader, Read};
 
     fn testfile_path(s: &str) -> String {
         format!(
@@ -1047,20 +3515,117 @@ mod tests {
         contents
     }
 
+    // Serves files kept in memory with a modification time, and counts the
+    // reads of each file to tell whether it is taken from a cache.
+    #[derive(Debug, Default)]
+    struct CountingFileProvider {
+        files: Mutex<HashMap<PathBuf, (Vec<u8>, SystemTime)>>,
+        reads: Mutex<HashMap<PathBuf, usize>>,
+    }
+
+    impl CountingFileProvider {
+        fn insert(&self, path: &str, contents: &str, modified: u64) {
+            let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(modified);
+            let mut files = self.files.lock().unwrap();
+            files.insert(PathBuf::from(path), (contents.into(), modified));
+        }
+
+        fn reads(&self, path: &str) -> usize {
+            let reads = self.reads.lock().unwrap();
+            reads.get(Path::new(path)).copied().unwrap_or(0)
+        }
+    }
+
+    impl FileProvider for CountingFileProvider {
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            let mut reads = self.reads.lock().unwrap();
+            *reads.entry(PathBuf::from(path)).or_insert(0) += 1;
+            let files = self.files.lock().unwrap();
+            match files.get(path) {
+                Some((x, _)) => Ok(x.clone()),
+                None => Err(io::Error::new(io::ErrorKind::NotFound, "file not found")),
+            }
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.files.lock().unwrap().contains_key(path)
+        }
+
+        fn modified(&self, path: &Path) -> Option<SystemTime> {
+            self.files.lock().unwrap().get(path).map(|(_, x)| *x)
+        }
+    }
+
     // Most tests are called with the same arguments, so this is a convenience.
     fn preprocess_usualargs(s: &str) -> Result<(PreprocessedText, Defines), Error> {
         let include_paths = [testfile_path("")];
         preprocess(
-            testfile_path(s),   // path
-            &HashMap::new(),    // pre_defines
-            &include_paths,     // include_paths
-            false,              // strip_comments
-            false,              // ignore_include
+            testfile_path(s), // path
+            &HashMap::new(),  // pre_defines
+            &include_paths,   // include_paths
+            false,            // strip_comments
+            false,            // ignore_include
         )
     }
 
     #[test]
-    fn escaped_identifier() { // {{{
+    fn encoding_latin1() {
+        // {{{
+        let config = PreprocessorConfig {
+            encoding: Encoding::Latin1,
+            ..Default::default()
+        };
+        let (ret, _) = preprocess_with_config(
+            testfile_path("err_ReadUtf8.sv"),
+            &HashMap::new(),
+            &[testfile_path("")],
+            &config,
+        )
+        .unwrap();
+        assert!(ret
+            .text()
+            .contains("X\u{f1}\u{f2}\u{f3}\u{f4}\u{f5}\u{f6}X"));
+
+        // "endmodule" is at byte 81 of the file, but is shifted in the text
+        // by the 6 Latin-1 characters which take 2 bytes each in UTF-8.
+        let n = ret.text().find("endmodule").unwrap();
+        assert_eq!(n, 87);
+        assert_eq!(
+            ret.origin(n).unwrap(),
+            (&PathBuf::from(testfile_path("err_ReadUtf8.sv")), 81)
+        );
+        assert_eq!(
+            ret.origin_to_preprocessed(testfile_path("err_ReadUtf8.sv"), 81),
+            vec![n]
+        );
+    } // }}}
+
+    #[test]
+    fn encoding_utf8lossy() {
+        // {{{
+        let config = PreprocessorConfig {
+            encoding: Encoding::Utf8Lossy,
+            ..Default::default()
+        };
+        let (ret, _) = preprocess_with_config(
+            testfile_path("err_ReadUtf8.sv"),
+            &HashMap::new(),
+            &[testfile_path("")],
+            &config,
+        )
+        .unwrap();
+        assert!(ret.text().contains("X\u{fffd}"));
+
+        let n = ret.text().find("endmodule").unwrap();
+        assert_eq!(
+            ret.origin(n).unwrap(),
+            (&PathBuf::from(testfile_path("err_ReadUtf8.sv")), 81)
+        );
+    } // }}}
+
+    #[test]
+    fn escaped_identifier() {
+        // {{{
         let (ret, _) = preprocess_usualargs("escaped_identifier.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1070,13 +3635,11 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn err_DefineNoArgs() { // {{{
+    fn err_DefineNoArgs() {
+        // {{{
         match preprocess_usualargs("err_DefineNoArgs.sv").unwrap_err() {
             Error::DefineNoArgs(identifier) => {
-                assert_eq!(
-                    identifier,
-                    String::from("A")
-                );
+                assert_eq!(identifier, String::from("A"));
             }
             _ => {
                 panic!("Error::DefineNoArgs not raised.");
@@ -1086,13 +3649,36 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn err_DefineNotFound() { // {{{
+    fn err_DefineNotFound() {
+        // {{{
         match preprocess_usualargs("err_DefineNotFound.sv").unwrap_err() {
-            Error::DefineNotFound(identifier) => {
-                assert_eq!(
-                    identifier,
-                    String::from("A")
-                );
+            Error::DefineNotFound { name, path, offset } => {
+                assert_eq!(name, String::from("A"));
+                assert_eq!(path, PathBuf::from(testfile_path("err_DefineNotFound.sv")));
+                assert_eq!(offset, 1);
+            }
+            _ => {
+                panic!("Error::DefineNotFound not raised.");
+            }
+        };
+
+        // Within the text of another macro, the usage of that macro is
+        // reported.
+        let ret = preprocess_str(
+            "`define B `A\n  `B\n",
+            "test.sv",
+            &HashMap::new(),
+            &[] as &[String],
+            false, // ignore_include
+            false, // strip_comments
+            0,     // resolve_depth
+            0,     // include_depth
+        );
+        match ret.unwrap_err() {
+            Error::DefineNotFound { name, path, offset } => {
+                assert_eq!(name, String::from("A"));
+                assert_eq!(path, PathBuf::from("test.sv"));
+                assert_eq!(offset, 15);
             }
             _ => {
                 panic!("Error::DefineNotFound not raised.");
@@ -1102,13 +3688,11 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn err_DefineArgNotFound() { // {{{
+    fn err_DefineArgNotFound() {
+        // {{{
         match preprocess_usualargs("err_DefineArgNotFound.sv").unwrap_err() {
             Error::DefineArgNotFound(identifier) => {
-                assert_eq!(
-                    identifier,
-                    String::from("c")
-                );
+                assert_eq!(identifier, String::from("c"));
             }
             _ => {
                 panic!("Error::DefineArgNotFound not raised.");
@@ -1118,7 +3702,30 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn err_ReadUtf8() { // {{{
+    fn err_IncludeNotFound() {
+        // {{{
+        match preprocess_usualargs("err_IncludeNotFound.sv").unwrap_err() {
+            Error::IncludeNotFound {
+                name,
+                path,
+                offset,
+                include_paths,
+            } => {
+                assert_eq!(name, PathBuf::from("missing.svh"));
+                assert_eq!(path, PathBuf::from(testfile_path("err_IncludeNotFound.sv")));
+                assert_eq!(offset, 1);
+                assert_eq!(include_paths, vec![PathBuf::from(testfile_path(""))]);
+            }
+            _ => {
+                panic!("Error::IncludeNotFound not raised.");
+            }
+        };
+    } // }}}
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn err_ReadUtf8() {
+        // {{{
         match preprocess_usualargs("err_ReadUtf8.sv").unwrap_err() {
             Error::ReadUtf8(path) => {
                 assert_eq!(
@@ -1137,7 +3744,8 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn IEEE18002017_keywords_if2_13642005() { // {{{
+    fn IEEE18002017_keywords_if2_13642005() {
+        // {{{
         let (ret, _) = preprocess_usualargs("IEEE18002017_keywords_if2_13642005.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1147,7 +3755,8 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn IEEE18002017_keywords_m2_13642001() { // {{{
+    fn IEEE18002017_keywords_m2_13642001() {
+        // {{{
         let (ret, _) = preprocess_usualargs("IEEE18002017_keywords_m2_13642001.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1157,7 +3766,8 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn IEEE18002017_keywords_m2_18002005() { // {{{
+    fn IEEE18002017_keywords_m2_18002005() {
+        // {{{
         let (ret, _) = preprocess_usualargs("IEEE18002017_keywords_m2_18002005.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1167,7 +3777,8 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn IEEE18002017_macro_argument_expansion() { // {{{
+    fn IEEE18002017_macro_argument_expansion() {
+        // {{{
         let (ret, _) = preprocess_usualargs("IEEE18002017_macro_argument_expansion.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1177,7 +3788,8 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn IEEE18002017_macro_delimit_tokens() { // {{{
+    fn IEEE18002017_macro_delimit_tokens() {
+        // {{{
         let (ret, _) = preprocess_usualargs("IEEE18002017_macro_delimit_tokens.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1187,7 +3799,8 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn IEEE18002017_macro_mix_quotes() { // {{{
+    fn IEEE18002017_macro_mix_quotes() {
+        // {{{
         let (ret, _) = preprocess_usualargs("IEEE18002017_macro_mix_quotes.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1197,7 +3810,8 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn IEEE18002017_macro_noexpand_string() { // {{{
+    fn IEEE18002017_macro_noexpand_string() {
+        // {{{
         let (ret, _) = preprocess_usualargs("IEEE18002017_macro_noexpand_string.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1207,7 +3821,8 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn IEEE18002017_macro_with_defaults() { // {{{
+    fn IEEE18002017_macro_with_defaults() {
+        // {{{
         let (ret, _) = preprocess_usualargs("IEEE18002017_macro_with_defaults.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1217,7 +3832,8 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn IEEE18002017_macro_without_defaults() { // {{{
+    fn IEEE18002017_macro_without_defaults() {
+        // {{{
         let (ret, _) = preprocess_usualargs("IEEE18002017_macro_without_defaults.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1226,103 +3842,371 @@ mod tests {
     } // }}}
 
     #[test]
-    fn celldefine() { // {{{
-        let (ret, _) = preprocess_usualargs("celldefine.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("celldefine.sv")
-        );
-    } // }}}
+    fn celldefine() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("celldefine.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("celldefine.sv"));
+
+        let text = ret.text();
+        let begin = text.find("`celldefine\n").unwrap();
+        let end = text.find("`endcelldefine\n").unwrap() + "`endcelldefine\n".len();
+        assert_eq!(ret.celldefines().len(), 1);
+        assert_eq!(ret.celldefines()[0].begin, begin);
+        assert_eq!(ret.celldefines()[0].end, end);
+        assert!(!ret.is_celldefine(begin - 1));
+        assert!(ret.is_celldefine(begin));
+        assert!(!ret.is_celldefine(end));
+    } // }}}
+
+    #[test]
+    fn coverage_constants() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("coverage_constants.sv").unwrap();
+        assert_eq!(
+            ret.text(),
+            testfile_contents("expected/coverage_constants.sv")
+        );
+    } // }}}
+
+    #[test]
+    fn default_nettype() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("default_nettype.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("default_nettype.sv"));
+
+        let text = ret.text();
+        assert_eq!(ret.default_nettype(0), "wire");
+        let pos = text.find("`default_nettype tri0").unwrap();
+        assert_eq!(ret.default_nettype(pos - 1), "tri");
+        assert_eq!(ret.default_nettype(pos), "tri0");
+        let pos = text.find("`default_nettype none").unwrap();
+        assert_eq!(ret.default_nettype(pos), "none");
+        assert_eq!(ret.default_nettype(text.len()), "none");
+    } // }}}
+
+    #[test]
+    fn ifdef_expression() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("ifdef_expression.sv").unwrap();
+        assert_eq!(
+            ret.text(),
+            testfile_contents("expected/ifdef_expression.sv")
+        );
+        assert_eq!(ret.skipped_regions()[0].guard, "(A && !B)");
+    } // }}}
+
+    #[test]
+    fn ifdef_include_skipped() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("ifdef_include_skipped.sv").unwrap();
+        assert_eq!(
+            ret.text(),
+            testfile_contents("expected/ifdef_include_skipped.sv")
+        );
+    } // }}}
+
+    #[test]
+    fn ifdef_nested() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("ifdef_nested.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("expected/ifdef_nested.sv"));
+    } // }}}
+
+    #[test]
+    fn ifdef_nested_skipped_regions() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("ifdef_nested.sv").unwrap();
+        let contents = testfile_contents("ifdef_nested.sv");
+        let regions = ret.skipped_regions();
+        assert_eq!(regions.len(), 2);
+
+        assert_eq!(
+            regions[0].path,
+            PathBuf::from(testfile_path("ifdef_nested.sv"))
+        );
+        assert_eq!(regions[0].guard, "OPT_1");
+        assert_eq!(
+            &contents[regions[0].range.begin..regions[0].range.end],
+            "//wire a = 1'b1;\n"
+        );
+
+        // The nested `ifdef is covered by the region of its parent.
+        assert_eq!(regions[1].guard, "DEBUG");
+        let region = &contents[regions[1].range.begin..regions[1].range.end];
+        assert!(region.starts_with("`ifdef OPT_2"));
+        assert!(region.ends_with("`endif\n"));
+    } // }}}
+
+    #[test]
+    fn ifdef_predefined() {
+        // {{{
+        let mut defines = HashMap::new();
+        defines.insert(String::from("behavioral"), None);
+        let (ret, _) = preprocess(
+            testfile_path("ifdef_predefined.sv"),
+            &defines,
+            &[] as &[String],
+            false, // strip_comments
+            false, // ignore_include
+        )
+        .unwrap();
+        assert_eq!(
+            ret.text(),
+            testfile_contents("expected/ifdef_predefined.sv")
+        )
+    } // }}}
+
+    #[test]
+    fn ifdef_elsif_predefined() {
+        // {{{
+        let src = "`ifdef X\nwire a;\n`elsif __FILE__\nwire b;\n`else\nwire c;\n`endif\n\
+                   `ifndef __LINE__\nwire d;\n`elsif X\nwire e;\n`else\nwire f;\n`endif\n";
+        let (ret, _) = Preprocessor::new().preprocess_str(src, "a.sv").unwrap();
+        let text = ret.text();
+        assert!(text.contains("wire b;"));
+        assert!(text.contains("wire f;"));
+        for x in &["wire a;", "wire c;", "wire d;", "wire e;"] {
+            assert!(!text.contains(x), "{}", x);
+        }
+    } // }}}
+
+    #[test]
+    fn ifdef_undefined() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("ifdef_undefined.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("expected/ifdef_undefined.sv"));
+        assert_eq!(
+            ret.origin(10).unwrap().0,
+            &PathBuf::from(testfile_path("ifdef_undefined.sv"))
+        );
+        assert_eq!(ret.origin(10).unwrap().1, 10);
+        assert_eq!(ret.origin(50).unwrap().1, 98);
+        assert_eq!(ret.origin(70).unwrap().1, 124);
+    } // }}}
+
+    #[test]
+    fn ifndef_undefined() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("ifndef_undefined.sv").unwrap();
+        assert_eq!(
+            ret.text(),
+            testfile_contents("expected/ifndef_undefined.sv")
+        );
+    } // }}}
+
+    #[test]
+    fn include_ignore() {
+        // {{{
+        let include_paths = [testfile_path("")];
+        let (ret, _) = preprocess(
+            testfile_path("include_ignore.sv"),
+            &HashMap::new(),
+            &include_paths,
+            false, // strip_comments
+            true,  // ignore_include
+        )
+        .unwrap();
+        assert_eq!(ret.text(), testfile_contents("expected/include_ignore.sv"));
+    } // }}}
+
+    #[test]
+    fn include_cache() {
+        // {{{
+        let config = PreprocessorConfig {
+            cache: Some(PreprocessCache::new()),
+            ..Default::default()
+        };
+        for _ in 0..2 {
+            let (ret, _) = preprocess_with_config(
+                testfile_path("include_noindent.sv"),
+                &HashMap::new(),
+                &[testfile_path("")],
+                &config,
+            )
+            .unwrap();
+            assert_eq!(
+                ret.text(),
+                testfile_contents("expected/include_noindent.sv")
+            );
+            assert_eq!(
+                ret.includes(),
+                [PathBuf::from(testfile_path("included.svh"))]
+            );
+        }
 
-    #[test]
-    fn coverage_constants() { // {{{
-        let (ret, _) = preprocess_usualargs("coverage_constants.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("expected/coverage_constants.sv")
-        );
+        // Both include_noindent.sv and included.svh are cached.
+        let cache = config.cache.unwrap();
+        assert_eq!(cache.len(), 2);
+        cache.clear();
+        assert!(cache.is_empty());
     } // }}}
 
     #[test]
-    fn default_nettype() { // {{{
-        let (ret, _) = preprocess_usualargs("default_nettype.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("default_nettype.sv")
+    fn include_cache_hit() {
+        // {{{
+        let files = Arc::new(CountingFileProvider::default());
+        files.insert(
+            "top.sv",
+            "`include \"included.svh\"\nmodule A; `W endmodule\n",
+            1,
         );
+        files.insert("included.svh", "`define W wire a;\n", 1);
+        let cache = PreprocessCache::new();
+        let preprocessor = Preprocessor::new()
+            .cache(cache.clone())
+            .file_provider(files.clone());
+        for _ in 0..2 {
+            let (ret, _) = preprocessor.preprocess("top.sv").unwrap();
+            assert!(ret.text().contains("module A; wire a; endmodule"));
+        }
+
+        // The second run takes both files from the cache.
+        assert_eq!(files.reads("top.sv"), 1);
+        assert_eq!(files.reads("included.svh"), 1);
+        assert_eq!(cache.len(), 2);
     } // }}}
 
     #[test]
-    fn ifdef_nested() { // {{{
-        let (ret, _) = preprocess_usualargs("ifdef_nested.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("expected/ifdef_nested.sv")
+    fn include_cache_invalidated() {
+        // {{{
+        let files = Arc::new(CountingFileProvider::default());
+        files.insert(
+            "top.sv",
+            "`include \"included.svh\"\nmodule A; `W endmodule\n",
+            1,
         );
+        files.insert("included.svh", "`define W wire a;\n", 1);
+        let cache = PreprocessCache::new();
+        let preprocessor = Preprocessor::new()
+            .cache(cache.clone())
+            .file_provider(files.clone());
+        let (ret, _) = preprocessor.preprocess("top.sv").unwrap();
+        assert!(ret.text().contains("module A; wire a; endmodule"));
+
+        // Only the changed file is read again.
+        files.insert("included.svh", "`define W wire b;\n", 2);
+        let (ret, _) = preprocessor.preprocess("top.sv").unwrap();
+        assert!(ret.text().contains("module A; wire b; endmodule"));
+        assert_eq!(files.reads("top.sv"), 1);
+        assert_eq!(files.reads("included.svh"), 2);
+        assert_eq!(cache.len(), 2);
     } // }}}
 
     #[test]
-    fn ifdef_predefined() { // {{{
-        let mut defines = HashMap::new();
-        defines.insert(String::from("behavioral"), None);
+    fn include_env() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("include_env.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("expected/include_env.sv"));
+
+        let include_paths = ["$CARGO_MANIFEST_DIR/testcases"];
         let (ret, _) = preprocess(
-            testfile_path("ifdef_predefined.sv"),
-            &defines,
-            &[] as &[String],
-            false, // strip_comments
-            false, // ignore_include
+            testfile_path("include_noindent.sv"),
+            &HashMap::new(),
+            &include_paths,
+            false,
+            false,
         )
         .unwrap();
         assert_eq!(
             ret.text(),
-            testfile_contents("expected/ifdef_predefined.sv")
-        )
+            testfile_contents("expected/include_noindent.sv")
+        );
     } // }}}
 
     #[test]
-    fn ifdef_undefined() { // {{{
-        let (ret, _) = preprocess_usualargs("ifdef_undefined.sv").unwrap();
+    fn include_fallback() {
+        // {{{
+        let config = PreprocessorConfig {
+            include_fallback: IncludeFallback {
+                case_insensitive: true,
+                extensions: vec![(String::from("vh"), String::from("svh"))],
+            },
+            ..Default::default()
+        };
+        let (ret, _) = preprocess_with_config(
+            testfile_path("include_fallback.sv"),
+            &HashMap::new(),
+            &[testfile_path("")],
+            &config,
+        )
+        .unwrap();
         assert_eq!(
             ret.text(),
-            testfile_contents("expected/ifdef_undefined.sv")
+            testfile_contents("expected/include_fallback.sv")
         );
+
+        let kinds: Vec<_> = ret.warnings().iter().map(|x| x.kind.clone()).collect();
         assert_eq!(
-            ret.origin(10).unwrap().0,
-            &PathBuf::from(testfile_path("ifdef_undefined.sv"))
+            kinds,
+            vec![
+                WarningKind::IncludeFallback {
+                    name: PathBuf::from("INCLUDED.SVH"),
+                    resolved: PathBuf::from(testfile_path("included.svh")),
+                },
+                WarningKind::IncludeFallback {
+                    name: PathBuf::from("included.vh"),
+                    resolved: PathBuf::from(testfile_path("included.svh")),
+                },
+            ]
         );
-        assert_eq!(ret.origin(10).unwrap().1, 10);
-        assert_eq!(ret.origin(50).unwrap().1, 98);
-        assert_eq!(ret.origin(70).unwrap().1, 124);
+
+        // Without fallbacks, the file isn't found.
+        let ret = preprocess_usualargs("include_fallback.sv");
+        assert!(matches!(ret, Err(Error::IncludeNotFound { .. })));
     } // }}}
 
+    #[cfg(feature = "mmap")]
     #[test]
-    fn ifndef_undefined() { // {{{
-        let (ret, _) = preprocess_usualargs("ifndef_undefined.sv").unwrap();
+    fn mmap() {
+        // {{{
+        // The test cases aren't modified while the tests run.
+        let config = PreprocessorConfig {
+            mmap: Some(unsafe { MapFiles::new() }),
+            ..Default::default()
+        };
+        let (ret, _) = preprocess_with_config(
+            testfile_path("include_noindent.sv"),
+            &HashMap::new(),
+            &[testfile_path("")],
+            &config,
+        )
+        .unwrap();
         assert_eq!(
             ret.text(),
-            testfile_contents("expected/ifndef_undefined.sv")
+            testfile_contents("expected/include_noindent.sv")
+        );
+
+        // A file which isn't UTF-8 is read, and reported as usual.
+        let ret = preprocess_with_config(
+            testfile_path("err_ReadUtf8.sv"),
+            &HashMap::new(),
+            &[] as &[String],
+            &config,
         );
+        assert!(matches!(ret, Err(Error::ReadUtf8(_))));
     } // }}}
 
     #[test]
-    fn include_ignore() { // {{{
-        let include_paths = [testfile_path("")];
+    fn include_guard() {
+        // {{{
         let (ret, _) = preprocess(
-            testfile_path("include_ignore.sv"),
+            testfile_path("include_guard.sv"),
             &HashMap::new(),
-            &include_paths,
+            &[testfile_path("")],
             false, // strip_comments
-            true, // ignore_include
+            false, // ignore_include
         )
         .unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("expected/include_ignore.sv")
-        );
+        assert_eq!(ret.text(), testfile_contents("expected/include_guard.sv"));
+
+        // The second include of include_guard.svh is skipped entirely,
+        // rather than producing a skipped `ifndef body.
+        assert!(ret.skipped_regions().is_empty());
     } // }}}
 
     #[test]
-    fn include_noindent() { // {{{
+    fn include_noindent() {
+        // {{{
         let (ret, _) = preprocess_usualargs("include_noindent.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1359,19 +4243,85 @@ mod tests {
     } // }}}
 
     #[test]
-    fn include_quoted_a() { // {{{
-        let ret = preprocess_usualargs("include_quoted_a.sv");
-        assert_eq!(format!("{:?}", ret), "Err(Include { source: File { source: Os { code: 2, kind: NotFound, message: \"No such file or directory\" }, path: \"`PATH\" } })");
+    fn include_origin_to_preprocessed() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("include_withindent.sv").unwrap();
+        // Newlines inserted around included text have no origin to return to.
+        for (n, _) in ret.text().char_indices().filter(|(_, c)| *c != '\n') {
+            let (path, pos) = ret.origin(n).unwrap();
+            assert!(ret.origin_to_preprocessed(path, pos).contains(&n));
+        }
+
+        let positions: Vec<_> = (0..ret.text().len() + 1).collect();
+        let origins = ret.origins(&positions);
+        for n in positions {
+            assert_eq!(origins[n], ret.origin(n));
+        }
+        let origins = ret.origins(&[30, 10, 80, 0]);
+        assert_eq!(origins[1], ret.origin(10));
+        assert_eq!(origins[3], ret.origin(0));
+
+        let path = PathBuf::from(testfile_path("included.svh"));
+        let n = ret.text().find("output").unwrap();
+        assert_eq!(ret.origin_to_preprocessed(&path, 0), vec![n]);
+        assert!(ret.origin_to_preprocessed(&path, 1000).is_empty());
+    } // }}}
+
+    #[test]
+    fn include_segments() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("include_withindent.sv").unwrap();
+        let segments: Vec<_> = ret.segments().collect();
+
+        // Segments are contiguous and cover the whole text.
+        assert_eq!(segments.first().unwrap().0.begin, 0);
+        assert_eq!(segments.last().unwrap().0.end, ret.text().len());
+        for x in segments.windows(2) {
+            assert_eq!(x[0].0.end, x[1].0.begin);
+        }
+
+        // The text before the include is from one place, so one segment.
+        assert_eq!((segments[0].0.begin, segments[0].0.end), (0, 34));
+        assert_eq!((segments[0].2.begin, segments[0].2.end), (0, 34));
+
+        let path = PathBuf::from(testfile_path("included.svh"));
+        let (range, _, origin_range) = segments.iter().find(|(_, p, _)| **p == path).unwrap();
+        assert_eq!(
+            &ret.text()[range.begin..range.end],
+            "output a;\ninput b, c;\n\n"
+        );
+        assert_eq!((origin_range.begin, origin_range.end), (0, 23));
+    } // }}}
+
+    #[test]
+    fn include_quoted_a() {
+        // {{{
+        match preprocess_usualargs("include_quoted_a.sv").unwrap_err() {
+            Error::IncludeNotFound { name, .. } => {
+                assert_eq!(name, PathBuf::from("`PATH"));
+            }
+            _ => {
+                panic!("Error::IncludeNotFound not raised.");
+            }
+        };
     } // }}}
 
     #[test]
-    fn include_quoted_b() { // {{{
-        let ret = preprocess_usualargs("include_quoted_b.sv");
-        assert_eq!(format!("{:?}", ret), "Err(Include { source: File { source: Os { code: 2, kind: NotFound, message: \"No such file or directory\" }, path: \"`PATH\" } })");
+    fn include_quoted_b() {
+        // {{{
+        match preprocess_usualargs("include_quoted_b.sv").unwrap_err() {
+            Error::IncludeNotFound { name, .. } => {
+                assert_eq!(name, PathBuf::from("`PATH"));
+            }
+            _ => {
+                panic!("Error::IncludeNotFound not raised.");
+            }
+        };
     } // }}}
 
     #[test]
-    fn include_quoted_c() { // {{{
+    fn include_quoted_c() {
+        // {{{
         let (ret, _) = preprocess_usualargs("include_quoted_c.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1380,7 +4330,8 @@ mod tests {
     } // }}}
 
     #[test]
-    fn include_quoted_d() { // {{{
+    fn include_quoted_d() {
+        // {{{
         let (ret, _) = preprocess_usualargs("include_quoted_d.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1389,18 +4340,35 @@ mod tests {
     } // }}}
 
     #[test]
-    fn include_recursive() { // {{{
-        let ret = preprocess_usualargs("include_recursive.svh");
-        let expected = format!(
-            "Err({}ExceedRecursiveLimit{})",
-            "Include { source: ".repeat(RECURSIVE_LIMIT+1),
-            " }".repeat(RECURSIVE_LIMIT+1),
+    fn include_quoted_e() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("include_quoted_e.sv").unwrap();
+        assert_eq!(
+            ret.text(),
+            testfile_contents("expected/include_quoted_e.sv")
         );
-        assert_eq!(format!("{:?}", ret), expected);
     } // }}}
 
     #[test]
-    fn include_sameline_comment() { // {{{
+    fn include_recursive() {
+        // {{{
+        let ret = preprocess_usualargs("include_recursive.svh");
+        let error = ret.unwrap_err();
+        let includes = error.includes();
+        assert_eq!(includes.len(), RECURSIVE_LIMIT + 1);
+        assert!(includes
+            .iter()
+            .all(|(path, line)| { path.ends_with("include_recursive.svh") && *line == 2 }));
+        let mut error = &error;
+        while let Error::Include { source, .. } = error {
+            error = &**source;
+        }
+        assert!(matches!(error, Error::ExceedRecursiveLimit));
+    } // }}}
+
+    #[test]
+    fn include_sameline_comment() {
+        // {{{
         let (ret, _) = preprocess_usualargs("include_sameline_comment.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1424,19 +4392,22 @@ mod tests {
     } // }}}
 
     #[test]
-    fn include_sameline_include() { // {{{
+    fn include_sameline_include() {
+        // {{{
         let ret = preprocess_usualargs("include_sameline_include.sv");
         assert_eq!(format!("{:?}", ret), "Err(IncludeLine)");
     } // }}}
 
     #[test]
-    fn include_sameline_keyword() { // {{{
+    fn include_sameline_keyword() {
+        // {{{
         let ret = preprocess_usualargs("include_sameline_keyword.sv");
         assert_eq!(format!("{:?}", ret), "Err(IncludeLine)");
     } // }}}
 
     #[test]
-    fn include_withindent() { // {{{
+    fn include_withindent() {
+        // {{{
         let (ret, _) = preprocess_usualargs("include_withindent.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1473,53 +4444,71 @@ mod tests {
     } // }}}
 
     #[test]
-    fn keywords() { // {{{
+    fn keywords() {
+        // {{{
         let (ret, _) = preprocess_usualargs("keywords.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("keywords.sv")
-        );
+        assert_eq!(ret.text(), testfile_contents("keywords.sv"));
     } // }}}
 
     #[test]
-    fn line() { // {{{
+    fn line() {
+        // {{{
         let (ret, _) = preprocess_usualargs("line.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("line.sv"));
+    } // }}}
+
+    #[test]
+    fn line_endings() {
+        // {{{
+        let config = PreprocessorConfig {
+            encoding: Encoding::Latin1,
+            normalize_line_endings: true,
+            ..Default::default()
+        };
+        let (ret, _) = preprocess_with_config(
+            testfile_path("line_endings.sv"),
+            &HashMap::new(),
+            &[testfile_path("")],
+            &config,
+        )
+        .unwrap();
         assert_eq!(
             ret.text(),
-            testfile_contents("line.sv")
+            "module M;\n// Latin-1: \u{e9}\nwire a;\nendmodule\n"
         );
+
+        let path = PathBuf::from(testfile_path("line_endings.sv"));
+        let n = ret.text().find("wire").unwrap();
+        assert_eq!(ret.origin(n).unwrap(), (&path, 26));
+        let n = ret.text().find("endmodule").unwrap();
+        assert_eq!(ret.origin(n).unwrap(), (&path, 34));
     } // }}}
 
     #[test]
-    fn macro_arguments() { // {{{
+    fn macro_arguments() {
+        // {{{
         let (ret, _) = preprocess_usualargs("macro_arguments.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("expected/macro_arguments.sv")
-        );
+        assert_eq!(ret.text(), testfile_contents("expected/macro_arguments.sv"));
     } // }}}
 
     #[test]
-    fn macro_basic() { // {{{
+    fn macro_basic() {
+        // {{{
         let (ret, _) = preprocess_usualargs("macro_basic.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("expected/macro_basic.sv")
-        );
+        assert_eq!(ret.text(), testfile_contents("expected/macro_basic.sv"));
     } // }}}
 
     #[test]
-    fn macro_comment() { // {{{
+    fn macro_comment() {
+        // {{{
         let (ret, _) = preprocess_usualargs("macro_comment.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("expected/macro_comment.sv")
-        );
+        assert_eq!(ret.text(), testfile_contents("expected/macro_comment.sv"));
     } // }}}
 
     #[test]
     #[ignore = "Exposes unfixed PP parser bug."]
-    fn macro_comment_embedded() { // {{{
+    fn macro_comment_embedded() {
+        // {{{
         let (ret, _) = preprocess_usualargs("macro_comment_embedded.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1528,7 +4517,8 @@ mod tests {
     } // }}}
 
     #[test]
-    fn macro_delimiters() { // {{{
+    fn macro_delimiters() {
+        // {{{
         let (ret, _) = preprocess_usualargs("macro_delimiters.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1538,16 +4528,15 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn macro_FILE() { // {{{
+    fn macro_FILE() {
+        // {{{
         let (ret, _) = preprocess_usualargs("macro_FILE.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("expected/macro_FILE.sv")
-        );
+        assert_eq!(ret.text(), testfile_contents("expected/macro_FILE.sv"));
     } // }}}
 
     #[test]
-    fn macro_identifier() { // {{{
+    fn macro_identifier() {
+        // {{{
         let (ret, _) = preprocess_usualargs("macro_identifier.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1557,16 +4546,15 @@ mod tests {
 
     #[test]
     #[allow(non_snake_case)]
-    fn macro_LINE() { // {{{
+    fn macro_LINE() {
+        // {{{
         let (ret, _) = preprocess_usualargs("macro_LINE.sv").unwrap();
-        assert_eq!(
-            ret.text(),
-            testfile_contents("expected/macro_LINE.sv")
-        );
+        assert_eq!(ret.text(), testfile_contents("expected/macro_LINE.sv"));
     } // }}}
 
     #[test]
-    fn macro_multiline_comment() { // {{{
+    fn macro_multiline_comment() {
+        // {{{
         let (ret, _) = preprocess_usualargs("macro_multiline_comment.sv").unwrap();
         assert_eq!(
             ret.text(),
@@ -1575,68 +4563,580 @@ mod tests {
     } // }}}
 
     #[test]
-    fn macro_recursion_direct() { // {{{
+    fn macro_recursion_direct() {
+        // {{{
         let ret = preprocess_usualargs("macro_recursion_direct.sv");
         assert_eq!(format!("{:?}", ret), "Err(ExceedRecursiveLimit)");
     } // }}}
 
     #[test]
-    fn macro_recursion_indirect() { // {{{
+    fn macro_recursion_indirect() {
+        // {{{
         let ret = preprocess_usualargs("macro_recursion_indirect.sv");
         assert_eq!(format!("{:?}", ret), "Err(ExceedRecursiveLimit)");
     } // }}}
 
     #[test]
-    fn pragma() { // {{{
+    fn file_provider() {
+        // {{{
+        let mut files = MemoryFileProvider::new();
+        files.insert("top.sv", "`include \"a.svh\"\nmodule A; `W endmodule\n");
+        files.insert("inc/A.svh", "`define W wire a;\n");
+        let config = PreprocessorConfig {
+            file_provider: Some(Arc::new(files)),
+            include_fallback: IncludeFallback {
+                case_insensitive: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (ret, _) =
+            preprocess_with_config("top.sv", &HashMap::new(), &["inc"], &config).unwrap();
+        assert!(ret.text().contains("module A; wire a; endmodule"));
+        assert_eq!(ret.includes(), &[PathBuf::from("inc/A.svh")]);
+
+        match preprocess_with_config("other.sv", &HashMap::new(), &[] as &[String], &config)
+            .unwrap_err()
+        {
+            Error::File { path, .. } => assert_eq!(path, PathBuf::from("other.sv")),
+            x => panic!("unexpected error: {:?}", x),
+        };
+    } // }}}
+
+    #[test]
+    fn system_include_paths() {
+        // {{{
+        let mut files = MemoryFileProvider::new();
+        files.insert(
+            "top.sv",
+            "`include <defs.svh>\n`include \"defs.svh\"\nmodule A; `S `U endmodule\n",
+        );
+        files.insert("defs.svh", "`define U wire u;\n");
+        files.insert("inc/defs.svh", "`define S wire i;\n");
+        files.insert("sys/defs.svh", "`define S wire s;\n");
+        files.insert("local.sv", "`include <defs.svh>\n`include <local.svh>\n");
+        files.insert("local.svh", "");
+        files.insert("plain.sv", "`include <defs.svh>\n");
+        let files = Arc::new(files);
+
+        let (ret, _) = Preprocessor::new()
+            .include_path("inc")
+            .system_include_path("sys")
+            .file_provider(files.clone())
+            .preprocess("top.sv")
+            .unwrap();
+        assert!(ret.text().contains("module A; wire s; wire u; endmodule"));
+        assert_eq!(
+            ret.includes(),
+            &[PathBuf::from("sys/defs.svh"), PathBuf::from("defs.svh")]
+        );
+
+        // Without system include paths, angle brackets are like quotes.
+        let (ret, _) = Preprocessor::new()
+            .include_path("inc")
+            .file_provider(files.clone())
+            .preprocess("plain.sv")
+            .unwrap();
+        assert_eq!(ret.includes(), &[PathBuf::from("defs.svh")]);
+
+        match Preprocessor::new()
+            .system_include_path("sys")
+            .file_provider(files)
+            .preprocess("local.sv")
+            .unwrap_err()
+        {
+            Error::IncludeNotFound {
+                name,
+                include_paths,
+                ..
+            } => {
+                assert_eq!(name, PathBuf::from("local.svh"));
+                assert_eq!(include_paths, vec![PathBuf::from("sys")]);
+            }
+            x => panic!("unexpected error: {:?}", x),
+        };
+    } // }}}
+
+    #[test]
+    fn preprocess_bytes() {
+        // {{{
+        let bytes = b"`define S \"\xe9\"\nmodule A; initial $display(`S); endmodule\n";
+        let preprocessor = Preprocessor::new().encoding(Encoding::Latin1);
+        let (ret, _) = preprocessor.preprocess_bytes(bytes, "top.sv").unwrap();
+        assert!(ret.text().contains("$display(\"\u{e9}\")"));
+        // Offsets are of the bytes, before decoding.
+        let pos = ret.text().find("module").unwrap();
+        assert_eq!(ret.origin(pos), Some((&PathBuf::from("top.sv"), 14)));
+
+        let (ret2, _) = preprocessor
+            .preprocess_reader(&bytes[..], "top.sv")
+            .unwrap();
+        assert_eq!(ret2.text(), ret.text());
+
+        match Preprocessor::new()
+            .preprocess_bytes(bytes, "top.sv")
+            .unwrap_err()
+        {
+            Error::ReadUtf8(path) => assert_eq!(path, PathBuf::from("top.sv")),
+            x => panic!("unexpected error: {:?}", x),
+        };
+    } // }}}
+
+    #[test]
+    fn preprocess_all() {
+        // {{{
+        let mut files = MemoryFileProvider::new();
+        files.insert(
+            "a.sv",
+            "`define W 8\n`default_nettype none\nmodule A; endmodule\n",
+        );
+        files.insert("b.sv", "module B; wire [`W-1:0] b; endmodule\n");
+        let (texts, defines) = Preprocessor::new()
+            .file_provider(Arc::new(files))
+            .preprocess_all(&["a.sv", "b.sv"])
+            .unwrap();
+        assert_eq!(texts.len(), 2);
+        // The second file uses the macro and the net type left by the first.
+        assert!(texts[1].text().contains("wire [8-1:0] b;"));
+        assert_eq!(texts[1].default_nettype(0), "none");
+        assert!(defines.contains_key("W"));
+    } // }}}
+
+    #[test]
+    fn byte_order_mark() {
+        // {{{
+        let bytes = b"\xef\xbb\xbfmodule A;\r\nendmodule\n";
+        let preprocessor = Preprocessor::new().normalize_line_endings(true);
+        let (ret, _) = preprocessor.preprocess_bytes(bytes, "top.sv").unwrap();
+        assert_eq!(ret.text(), "module A;\nendmodule\n");
+        // Offsets are of the bytes, including the byte order mark.
+        let path = PathBuf::from("top.sv");
+        let pos = ret.text().find("endmodule").unwrap();
+        assert_eq!(ret.origin(pos), Some((&path, 14)));
+        assert_eq!(ret.origin_to_preprocessed(&path, 14), vec![pos]);
+
+        let bytes = b"\xff\xfem\x00";
+        match Preprocessor::new()
+            .preprocess_bytes(bytes, "top.sv")
+            .unwrap_err()
+        {
+            Error::LeadingBytes { path, description } => {
+                assert_eq!(path, PathBuf::from("top.sv"));
+                assert_eq!(description, "a UTF-16 byte order mark");
+            }
+            x => panic!("unexpected error: {:?}", x),
+        };
+    } // }}}
+
+    #[test]
+    fn macro_expansions() {
+        // {{{
+        let src = "`define A(x) (x + `B)\n`define B 1\nassign a = `A(b);\n";
+        let (ret, _) = Preprocessor::new().preprocess_str(src, "top.sv").unwrap();
+        let text = |x: &MacroExpansion| &ret.text()[x.range.begin..x.range.end];
+        let expansions = ret.expansions();
+        assert_eq!(expansions.len(), 2);
+
+        assert_eq!(expansions[0].name, "A");
+        assert_eq!(expansions[0].path, PathBuf::from("top.sv"));
+        assert_eq!(expansions[0].offset, src.find("`A(").unwrap());
+        assert_eq!(expansions[0].arguments, vec![Some(String::from("b"))]);
+        assert_eq!(text(&expansions[0]), "(b + 1)");
+        assert_eq!(expansions[0].parent, None);
+
+        // `B is used within the text of `A.
+        assert_eq!(expansions[1].name, "B");
+        assert_eq!(expansions[1].offset, expansions[0].offset);
+        assert!(expansions[1].arguments.is_empty());
+        assert_eq!(text(&expansions[1]), "1");
+        assert_eq!(expansions[1].parent, Some(0));
+    } // }}}
+
+    #[test]
+    fn define_fragments() {
+        // {{{
+        let src = "`define M(a) \\\n  a + \\\n  b\nassign x = `M(y);\n";
+        let (ret, defines) = Preprocessor::new().preprocess_str(src, "top.sv").unwrap();
+        let text = defines["M"].as_ref().unwrap().text.as_ref().unwrap();
+        let fragments = text.fragments();
+        let fragments: Vec<_> = fragments
+            .iter()
+            .map(|x| (x.text.as_str(), x.identifier, x.range.begin, x.range.end))
+            .collect();
+        assert_eq!(
+            fragments,
+            vec![
+                ("  ", false, 3, 5),
+                ("a", true, 5, 6),
+                (" + \n  ", false, 6, 13),
+                ("b", true, 13, 14),
+            ]
+        );
+
+        // Each piece of the expansion originates from the text of the define,
+        // or from the actual argument.
+        let path = PathBuf::from("top.sv");
+        let n = ret.text().find('+').unwrap();
+        assert_eq!(ret.origin(n), Some((&path, src.find('+').unwrap())));
+        let n = ret.text().find("b;").unwrap();
+        assert_eq!(ret.origin(n), Some((&path, src.find('b').unwrap())));
+        let n = ret.text().find('y').unwrap();
+        assert_eq!(ret.origin(n), Some((&path, src.find("y)").unwrap())));
+    } // }}}
+
+    #[test]
+    fn shared_text() {
+        // {{{
+        let preprocessor = Preprocessor::new();
+        let src = "module A;\n  wire a; // comment\nendmodule\n";
+        let (ret, _) = preprocessor.preprocess_str(src, "a.sv").unwrap();
+        assert!(matches!(ret.text, Text::Shared(..)));
+        assert_eq!(ret.text(), src);
+
+        let src = "module A;\n`ifdef W\n  wire b;\n`endif\n  wire a;\nendmodule\n";
+        let (mut ret, _) = preprocessor.preprocess_str(src, "a.sv").unwrap();
+        assert!(matches!(ret.text, Text::Owned(_)));
+        assert!(!ret.text().contains("ifdef"));
+
+        let pos = ret.append("wire b;");
+        assert_eq!(&ret.text()[pos..], "wire b;");
+    } // }}}
+
+    #[test]
+    fn observer() {
+        // {{{
+        #[derive(Debug, Default)]
+        struct Recorder {
+            events: Mutex<Vec<String>>,
+        }
+
+        impl PreprocessObserver for Recorder {
+            fn define(&self, define: &Define, _path: &Path, offset: usize) {
+                let x = format!("define {} {}", define.identifier, offset);
+                self.events.lock().unwrap().push(x);
+            }
+
+            fn undef(&self, identifier: &str, _path: &Path, offset: usize) {
+                let x = format!("undef {} {}", identifier, offset);
+                self.events.lock().unwrap().push(x);
+            }
+
+            fn include_enter(&self, path: &Path, _from: &Path, offset: usize) {
+                let path = path.file_name().unwrap().to_string_lossy();
+                let x = format!("include_enter {} {}", path, offset);
+                self.events.lock().unwrap().push(x);
+            }
+
+            fn include_leave(&self, path: &Path) {
+                let path = path.file_name().unwrap().to_string_lossy();
+                let x = format!("include_leave {}", path);
+                self.events.lock().unwrap().push(x);
+            }
+
+            fn conditional(
+                &self,
+                identifier: Option<&str>,
+                taken: bool,
+                _path: &Path,
+                offset: usize,
+            ) {
+                let x = format!("conditional {:?} {} {}", identifier, taken, offset);
+                self.events.lock().unwrap().push(x);
+            }
+        }
+
+        let recorder = Arc::new(Recorder::default());
+        let config = PreprocessorConfig {
+            observer: Some(recorder.clone()),
+            ..Default::default()
+        };
+        for file in ["undef.sv", "include_noindent.sv"] {
+            preprocess_with_config(
+                testfile_path(file),
+                &HashMap::new(),
+                &[testfile_path("")],
+                &config,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![
+                "undef FOO 311",
+                "undef FOO 322",
+                "undef FOO 343",
+                "define FOO 366",
+                "conditional Some(\"FOO\") true 382",
+                "conditional Some(\"FOO\") false 462",
+                "undef FOO 548",
+                "conditional Some(\"FOO\") false 559",
+                "conditional Some(\"FOO\") true 643",
+                "include_enter included.svh 25",
+                "conditional Some(\"behavioral\") false 23",
+                "conditional None true 61",
+                "include_leave included.svh",
+            ]
+        );
+    } // }}}
+
+    #[test]
+    fn pragma() {
+        // {{{
         let (ret, _) = preprocess_usualargs("pragma.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("pragma.sv"));
+    } // }}}
+
+    #[test]
+    fn pragma_protect_envelope() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("pragma_protect_envelope.sv").unwrap();
         assert_eq!(
             ret.text(),
-            testfile_contents("pragma.sv")
+            testfile_contents("expected/pragma_protect_envelope.sv")
         );
+
+        let contents = testfile_contents("pragma_protect_envelope.sv");
+        let regions = ret.protected_regions();
+        assert_eq!(regions.len(), 1);
+        let region = &contents[regions[0].range.begin..regions[0].range.end];
+        assert!(region.starts_with("`pragma protect version=1"));
+        assert!(region.ends_with("CiAgaW5wdXQgYTsKICBvdXRwdXQgYjs=\n"));
     } // }}}
 
     #[test]
-    fn resetall() { // {{{
-        let (ret, _) = preprocess_usualargs("resetall.sv").unwrap();
+    fn pre_define_text() {
+        // {{{
+        let mut defines = HashMap::new();
+        defines.insert(String::from("WIDTH"), Some(Define::with_text("WIDTH", "8")));
+        defines.insert(String::from("NAME"), Some(Define::with_text("NAME", "top")));
+        let (ret, _) = preprocess(
+            testfile_path("pre_define_text.sv"),
+            &defines,
+            &[testfile_path("")],
+            false,
+            false,
+        )
+        .unwrap();
+        assert_eq!(ret.text(), testfile_contents("expected/pre_define_text.sv"));
+    } // }}}
+
+    #[test]
+    fn preprocess_to() {
+        // {{{
+        let mut text = Vec::new();
+        let (ret, _) = super::preprocess_to(
+            &mut text,
+            testfile_path("include_guard.sv"),
+            &HashMap::new(),
+            &[testfile_path("")],
+            &PreprocessorConfig::default(),
+        )
+        .unwrap();
         assert_eq!(
-            ret.text(),
-            testfile_contents("resetall.sv")
+            String::from_utf8(text).unwrap(),
+            testfile_contents("expected/include_guard.sv")
         );
+        assert_eq!(ret.text(), "");
+
+        let (expected, _) = preprocess(
+            testfile_path("include_guard.sv"),
+            &HashMap::new(),
+            &[testfile_path("")],
+            false, // strip_comments
+            false, // ignore_include
+        )
+        .unwrap();
+        let segments = |x: &PreprocessedText| {
+            x.segments()
+                .map(|(x, path, y)| (x.begin, x.end, path.clone(), y.begin, y.end))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(segments(&ret), segments(&expected));
     } // }}}
 
     #[test]
-    fn timescale() { // {{{
-        let (ret, _) = preprocess_usualargs("timescale.sv").unwrap();
+    fn preprocessor() {
+        // {{{
+        let preprocessor = Preprocessor::new()
+            .include_path(testfile_path(""))
+            .define("behavioral", None)
+            .strip_comments(true);
+        let (ret, _) = preprocessor
+            .preprocess(testfile_path("ifdef_predefined.sv"))
+            .unwrap();
+        let (expected, _) = preprocess(
+            testfile_path("ifdef_predefined.sv"),
+            &preprocessor.defines,
+            &[testfile_path("")],
+            true,  // strip_comments
+            false, // ignore_include
+        )
+        .unwrap();
+        assert_eq!(ret.text(), expected.text());
+
+        let (ret, defines) = preprocessor
+            .define("WIDTH", Some(Define::with_text("WIDTH", "8")))
+            .preprocess_str(
+                "`ifdef behavioral\nwire [`WIDTH-1:0] a; // a\n`endif\n",
+                "a.sv",
+            )
+            .unwrap();
+        assert_eq!(ret.text(), "wire [8-1:0] a; ");
+        assert!(defines.contains_key("WIDTH"));
+    } // }}}
+
+    #[test]
+    fn protect() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("protect.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("expected/protect.sv"));
+
+        let contents = testfile_contents("protect.sv");
+        let regions = ret
+            .protected_regions()
+            .iter()
+            .map(|x| &contents[x.range.begin..x.range.end])
+            .collect::<Vec<_>>();
         assert_eq!(
-            ret.text(),
-            testfile_contents("timescale.sv")
+            regions,
+            [
+                "\n  assign b = `undefined_macro(a) /* unterminated\n",
+                "\n8Z4@&L@V#QH?SFE:EHXF0M)5'`ifdef\n",
+            ]
         );
     } // }}}
 
     #[test]
-    fn unconnected_drive() { // {{{
-        let (ret, _) = preprocess_usualargs("unconnected_drive.sv").unwrap();
+    fn resetall() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("resetall.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("resetall.sv"));
+        assert_eq!(ret.default_nettype(ret.text().len()), "wire");
+    } // }}}
+
+    #[test]
+    fn timescale() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("timescale.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("timescale.sv"));
+
+        let timescales = ret.timescales();
+        assert_eq!(timescales.len(), 3);
+        assert_eq!(timescales[0].unit, "1s");
+        assert_eq!(timescales[0].precision, "10ms");
+        assert_eq!(timescales[1].unit, "10us");
+        assert_eq!(timescales[1].precision, "100ns");
+        assert_eq!(timescales[2].unit, "100ps");
+        assert_eq!(timescales[2].precision, "100fs");
         assert_eq!(
-            ret.text(),
-            testfile_contents("unconnected_drive.sv")
+            timescales[2].path,
+            PathBuf::from(testfile_path("timescale.sv"))
         );
+        assert!(testfile_contents("timescale.sv")[timescales[2].offset..]
+            .starts_with("`timescale 100 ps / 100 fs"));
+    } // }}}
+
+    #[test]
+    fn unconnected_drive() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("unconnected_drive.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("unconnected_drive.sv"));
     } // }}}
 
     #[test]
-    fn undef() { // {{{
+    fn undef() {
+        // {{{
         let (ret, _) = preprocess_usualargs("undef.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("expected/undef.sv"));
+    } // }}}
+
+    #[test]
+    fn undefineall() {
+        // {{{
+        let (ret, _) = preprocess_usualargs("undefineall.sv").unwrap();
+        assert_eq!(ret.text(), testfile_contents("expected/undefineall.sv"));
+    } // }}}
+
+    #[test]
+    fn unknown_directives() {
+        // {{{
+        match preprocess_usualargs("unknown_directives.sv").unwrap_err() {
+            Error::DefineNotFound { name, .. } => assert_eq!(name, "protect128"),
+            x => panic!("unexpected error: {:?}", x),
+        }
+
+        let (ret, _) = Preprocessor::new()
+            .pass_unknown_directives(true)
+            .preprocess(testfile_path("unknown_directives.sv"))
+            .unwrap();
         assert_eq!(
             ret.text(),
-            testfile_contents("expected/undef.sv")
+            testfile_contents("expected/unknown_directives.sv")
+        );
+        let path = PathBuf::from(testfile_path("unknown_directives.sv"));
+        assert_eq!(
+            ret.warnings(),
+            &[
+                Warning {
+                    kind: WarningKind::UnknownDirective(String::from("protect128")),
+                    path: path.clone(),
+                    offset: 10,
+                },
+                Warning {
+                    kind: WarningKind::UnknownDirective(String::from("vendor_option")),
+                    path: path.clone(),
+                    offset: 22,
+                },
+                Warning {
+                    kind: WarningKind::UnknownDirective(String::from("endprotect128")),
+                    path,
+                    offset: 64,
+                },
+            ]
         );
+        assert_eq!(ret.origin(10).unwrap().1, 10);
     } // }}}
 
     #[test]
-    fn undefineall() { // {{{
-        let (ret, _) = preprocess_usualargs("undefineall.sv").unwrap();
+    fn warnings() {
+        // {{{
+        let mut defines = HashMap::new();
+        defines.insert(String::from("C"), None);
+        let (ret, _) = preprocess(
+            testfile_path("warnings.sv"),
+            &defines,
+            &[] as &[String],
+            false, // strip_comments
+            false, // ignore_include
+        )
+        .unwrap();
+        let path = PathBuf::from(testfile_path("warnings.sv"));
         assert_eq!(
-            ret.text(),
-            testfile_contents("expected/undefineall.sv")
+            ret.warnings(),
+            &[
+                Warning {
+                    kind: WarningKind::MacroRedefined(String::from("A")),
+                    path: path.clone(),
+                    offset: 24,
+                },
+                Warning {
+                    kind: WarningKind::UndefUndefined(String::from("B")),
+                    path: path.clone(),
+                    offset: 36,
+                },
+                Warning {
+                    kind: WarningKind::PreDefinesCleared(vec![String::from("C")]),
+                    path: path.clone(),
+                    offset: 45,
+                },
+            ]
         );
+        let diagnostics = ret.diagnostics();
+        let first = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostics.count(Severity::Warning), 3);
+        assert_eq!(first.code, "macro-redefined");
+        assert_eq!(first.message, "Macro redefined: A");
+        assert_eq!(first.origin, Some((path, 24)));
     } // }}}
 }
@@ -1,5 +1,6 @@
 use std::cmp::Ordering;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Copy, Clone, Debug, Eq)]
 pub struct Range {
     pub begin: usize,
@@ -0,0 +1,7 @@
+// Argument file in the style accepted by common simulators.
+-sverilog
++incdir+${CARGO_MANIFEST_DIR}/testcases/+$CARGO_MANIFEST_DIR/testcases/expected
++define+WIDTH=8+DEBUG
+$CARGO_MANIFEST_DIR/testcases/include_noindent.sv
+/* Paths within args_nested.f are relative to this directory. */
+-F ${CARGO_MANIFEST_DIR}/testcases/args_nested.f
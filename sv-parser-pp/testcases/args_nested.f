@@ -0,0 +1 @@
+macro_basic.sv
@@ -0,0 +1,3 @@
+
+`include "missing.svh" // File does not exist in any include path.
+
@@ -0,0 +1,13 @@
+module A;
+`define A
+`define C
+wire a;
+
+wire d;
+
+wire f;
+
+wire g;
+
+
+endmodule
@@ -0,0 +1,9 @@
+module M;
+wire a;
+
+wire b;
+
+`define DEFINED
+wire c;
+
+endmodule
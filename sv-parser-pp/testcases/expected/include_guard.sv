@@ -0,0 +1,12 @@
+module A;
+// Guarded by a macro.
+`define INCLUDE_GUARD_SVH
+typedef logic guard_t;
+
+
+
+`pragma once
+typedef logic once_t;
+
+
+endmodule
@@ -0,0 +1,6 @@
+module secret (a, b);
+  input a;
+  output b;
+`pragma protect begin_protected
+`pragma protect end_protected
+endmodule
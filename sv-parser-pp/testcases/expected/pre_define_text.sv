@@ -0,0 +1,4 @@
+module top #(parameter int W = 8) ();
+logic [8-1:0] a;
+
+endmodule
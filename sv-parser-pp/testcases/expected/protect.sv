@@ -0,0 +1,10 @@
+module secret (a, b);
+  input a;
+  output b;
+`protect
+`endprotect
+endmodule
+module encrypted (a, b);
+`protected
+`endprotected
+endmodule
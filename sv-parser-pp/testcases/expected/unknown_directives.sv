@@ -0,0 +1,6 @@
+module A;
+`protect128
+`vendor_option(fast, 2) // option
+wire a;
+`endprotect128
+endmodule
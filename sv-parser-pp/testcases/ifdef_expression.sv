@@ -0,0 +1,25 @@
+module A;
+`define A
+`define C
+`ifdef (A && !B)
+  wire a;
+`else
+  wire b;
+`endif
+`ifdef (B || !C)
+  wire c;
+`elsif (B -> C)
+  wire d;
+`endif
+`ifndef (A && (B || C))
+  wire e;
+`else
+  wire f;
+`endif
+`ifdef (B || A && C)
+  wire g;
+`endif
+`ifdef (A -> B <-> C)
+  wire h;
+`endif
+endmodule
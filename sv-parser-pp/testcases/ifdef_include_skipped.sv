@@ -0,0 +1,23 @@
+module M;
+`ifdef UNDEFINED
+`include "missing_a.svh"
+`elsif ALSO_UNDEFINED
+`include "missing_b.svh"
+`else
+  wire a;
+`endif
+`ifndef UNDEFINED
+  wire b;
+`else
+  `ifdef UNDEFINED_INNER
+  `else
+`include "missing_c.svh"
+  `endif
+`endif
+`define DEFINED
+`ifdef DEFINED
+  wire c;
+`elsif UNDEFINED
+`include "missing_d.svh"
+`endif
+endmodule
@@ -0,0 +1,3 @@
+module and_op (a, b, c);
+`include "${CARGO_MANIFEST_DIR}/testcases/included.svh"
+endmodule
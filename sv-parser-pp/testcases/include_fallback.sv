@@ -0,0 +1,4 @@
+module and_op (a, b, c);
+`include "INCLUDED.SVH"
+`include "included.vh"
+endmodule
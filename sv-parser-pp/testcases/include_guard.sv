@@ -0,0 +1,6 @@
+module A;
+`include "include_guard.svh"
+`include "include_guard.svh"
+`include "include_once.svh"
+`include "include_once.svh"
+endmodule
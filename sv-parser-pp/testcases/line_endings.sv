@@ -0,0 +1,3 @@
+module M;
+// Latin-1: 
+wire a;endmodule
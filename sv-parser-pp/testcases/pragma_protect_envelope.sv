@@ -0,0 +1,14 @@
+module secret (a, b);
+  input a;
+  output b;
+`pragma protect begin_protected
+`pragma protect version=1
+`pragma protect encrypt_agent="Acme"
+`pragma protect key_keyowner="Acme", key_method="rsa", key_block
+ZGVmYXVsdCBrZXkgYmxvY2sgZm9yIHRlc3Rpbmc//+dGhlIHByZXByb2Nlc3Nvcg==
+`pragma protect data_method="aes128-cbc"
+`pragma protect encoding=(enctype="base64", line_length=76, bytes=48)
+`pragma protect data_block
+`ifdef "unterminated /* bW9kdWxlIHNlY3JldCAoYSwgYik7CiAgaW5wdXQgYTsKICBvdXRwdXQgYjs=
+`pragma protect end_protected
+endmodule
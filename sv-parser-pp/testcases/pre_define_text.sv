@@ -0,0 +1,5 @@
+module `NAME #(parameter int W = `WIDTH) ();
+`ifdef WIDTH
+  logic [`WIDTH-1:0] a;
+`endif
+endmodule
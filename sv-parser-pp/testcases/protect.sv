@@ -0,0 +1,12 @@
+module secret (a, b);
+  input a;
+  output b;
+`protect
+  assign b = `undefined_macro(a) /* unterminated
+`endprotect
+endmodule
+module encrypted (a, b);
+`protected
+8Z4@&L@V#QH?SFE:EHXF0M)5'`ifdef
+`endprotected
+endmodule
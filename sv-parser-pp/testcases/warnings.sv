@@ -0,0 +1,5 @@
+`define A 1
+`define A 1
+`define A 2
+`undef B
+`undefineall
@@ -51,6 +51,91 @@ static ANY_NODE_DISPLAY_FOOTER: &str = r##"
 }
 "##;
 
+static NODE_KIND_HEADER: &str = r##"
+/// The type of a node, without the node itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NodeKind {
+    Locate,
+"##;
+
+static NODE_KIND_STR_HEADER: &str = r##"
+}
+
+impl NodeKind {
+    /// The name of the node type.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodeKind::Locate => "Locate",
+"##;
+
+static NODE_KIND_FOOTER: &str = r##"
+        }
+    }
+}
+
+impl std::fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+"##;
+
+static REF_NODE_KIND_HEADER: &str = r##"
+impl<'a> RefNode<'a> {
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            RefNode::Locate(_) => NodeKind::Locate,
+"##;
+
+static ANY_NODE_KIND_HEADER: &str = r##"
+impl AnyNode {
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            AnyNode::Locate(_) => NodeKind::Locate,
+"##;
+
+static NODE_KIND_MATCH_FOOTER: &str = r##"
+        }
+    }
+}
+"##;
+
+static VISITOR_HEADER: &str = r##"
+/// A pass over the syntax tree. Every node type has a `visit_*` method
+/// which does nothing by default, so an implementation only overrides the
+/// node types it needs. Use `walk()` to call it for each node.
+pub trait Visitor<'a> {
+    fn visit(&mut self, node: RefNode<'a>) {
+        match node {
+            RefNode::Locate(x) => self.visit_locate(x),
+"##;
+
+static VISITOR_METHODS_HEADER: &str = r##"
+        }
+    }
+
+    fn visit_locate(&mut self, _node: &'a Locate) {}
+"##;
+
+static VISITOR_FOOTER: &str = r##"
+}
+"##;
+
+fn snake_case(name: &str) -> String {
+    let mut ret = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i != 0 {
+                ret.push('_');
+            }
+            ret.push(c.to_ascii_lowercase());
+        } else {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
 fn main() {
     let out_dir = env::var("OUT_DIR").unwrap();
     let dest = Path::new(&out_dir).join("any_node.rs");
@@ -60,11 +145,23 @@ fn main() {
     let mut any_node = String::from(ANY_NODE_HEADER);
     let mut ref_node_display = String::from(REF_NODE_DISPLAY_HEADER);
     let mut any_node_display = String::from(ANY_NODE_DISPLAY_HEADER);
+    let mut node_kind = String::from(NODE_KIND_HEADER);
+    let mut node_kind_str = String::from(NODE_KIND_STR_HEADER);
+    let mut ref_node_kind = String::from(REF_NODE_KIND_HEADER);
+    let mut any_node_kind = String::from(ANY_NODE_KIND_HEADER);
+    let mut visitor = String::from(VISITOR_HEADER);
+    let mut visitor_methods = String::from(VISITOR_METHODS_HEADER);
 
     let re_node = Regex::new(r"#\[derive.*Node.*\]").unwrap();
 
+    // The Verilog-AMS nodes are only there with the ams feature.
+    let ams = env::var_os("CARGO_FEATURE_AMS").is_some();
+
     for entry in WalkDir::new("src") {
         let entry = entry.unwrap();
+        if !ams && entry.path().starts_with("src/verilog_ams") {
+            continue;
+        }
         if entry.path().is_file() {
             let f = File::open(entry.path()).unwrap();
             let f = BufReader::new(f);
@@ -83,6 +180,31 @@ fn main() {
                         "{}            AnyNode::{}(_) => write!(f, \"{}\"),\n",
                         any_node_display, name, name
                     );
+                    node_kind = format!("{}    {},\n", node_kind, name);
+                    node_kind_str = format!(
+                        "{}            NodeKind::{} => \"{}\",\n",
+                        node_kind_str, name, name
+                    );
+                    ref_node_kind = format!(
+                        "{}            RefNode::{}(_) => NodeKind::{},\n",
+                        ref_node_kind, name, name
+                    );
+                    any_node_kind = format!(
+                        "{}            AnyNode::{}(_) => NodeKind::{},\n",
+                        any_node_kind, name, name
+                    );
+                    visitor = format!(
+                        "{}            RefNode::{}(x) => self.visit_{}(x),\n",
+                        visitor,
+                        name,
+                        snake_case(&name)
+                    );
+                    visitor_methods = format!(
+                        "{}    fn visit_{}(&mut self, _node: &'a {}) {{}}\n",
+                        visitor_methods,
+                        snake_case(&name),
+                        name
+                    );
                     hit_node = false;
                 }
                 if re_node.is_match(&line) {
@@ -96,8 +218,16 @@ fn main() {
     any_node = format!("{}{}\n", any_node, ANY_NODE_FOOTER);
     ref_node_display = format!("{}{}\n", ref_node_display, REF_NODE_DISPLAY_FOOTER);
     any_node_display = format!("{}{}\n", any_node_display, ANY_NODE_DISPLAY_FOOTER);
+    node_kind = format!("{}{}{}\n", node_kind, node_kind_str, NODE_KIND_FOOTER);
+    ref_node_kind = format!("{}{}\n", ref_node_kind, NODE_KIND_MATCH_FOOTER);
+    any_node_kind = format!("{}{}\n", any_node_kind, NODE_KIND_MATCH_FOOTER);
+    visitor = format!("{}{}{}\n", visitor, visitor_methods, VISITOR_FOOTER);
     let _ = write!(out, "{}", ref_node);
     let _ = write!(out, "{}", any_node);
     let _ = write!(out, "{}", ref_node_display);
     let _ = write!(out, "{}", any_node_display);
+    let _ = write!(out, "{}", node_kind);
+    let _ = write!(out, "{}", ref_node_kind);
+    let _ = write!(out, "{}", any_node_kind);
+    let _ = write!(out, "{}", visitor);
 }
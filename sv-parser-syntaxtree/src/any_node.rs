@@ -1,5 +1,7 @@
 use crate::*;
 use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::ops::Range;
 
 // -----------------------------------------------------------------------------
 
@@ -23,6 +25,22 @@ impl<'a> Iter<'a> {
         let next: NodeEvents = self.next.into();
         EventIter { next }
     }
+
+    /// Only the nodes of type `T`.
+    pub fn of<T>(self) -> TypedIter<'a, T> {
+        TypedIter {
+            iter: self,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn with_ancestors(self) -> AncestorIter<'a> {
+        AncestorIter {
+            events: self.event(),
+            stack: Vec::new(),
+            current: None,
+        }
+    }
 }
 
 impl<'a> Iterator for Iter<'a> {
@@ -41,6 +59,57 @@ impl<'a> Iterator for Iter<'a> {
 
 // -----------------------------------------------------------------------------
 
+impl<'a> RefNode<'a> {
+    /// The nodes directly below this node, in order.
+    pub fn children(&self) -> RefNodes<'a> {
+        self.next()
+    }
+
+    /// The byte range from the first to the last token of the node, like
+    /// `Node::span()`.
+    pub fn span(&self) -> Option<Range<usize>> {
+        token_span(vec![self.clone()].into())
+    }
+}
+
+impl AnyNode {
+    /// The byte range from the first to the last token of the node, like
+    /// `Node::span()`.
+    pub fn span(&self) -> Option<Range<usize>> {
+        RefNode::from(self).span()
+    }
+}
+
+// The range from the first to the last token of `nodes`, which are outside
+// of white space.
+pub(crate) fn token_span(nodes: RefNodes) -> Option<Range<usize>> {
+    let mut ret: Option<Range<usize>> = None;
+    let mut depth = 0;
+    for n in Iter::new(nodes).event() {
+        match n {
+            NodeEvent::Enter(RefNode::WhiteSpace(_)) => depth += 1,
+            NodeEvent::Leave(RefNode::WhiteSpace(_)) => depth -= 1,
+            NodeEvent::Enter(RefNode::Locate(x)) if depth == 0 => {
+                let begin = ret.map_or(x.offset, |x| x.start);
+                ret = Some(begin..x.offset + x.len);
+            }
+            _ => (),
+        }
+    }
+    ret
+}
+
+// -----------------------------------------------------------------------------
+
+/// Call `visitor` for each node in `nodes` in the same order as `Iter`.
+pub fn walk<'a, V: Visitor<'a>, T: Into<RefNodes<'a>>>(visitor: &mut V, nodes: T) {
+    for node in Iter::new(nodes.into()) {
+        visitor.visit(node);
+    }
+}
+
+// -----------------------------------------------------------------------------
+
 #[derive(Debug, Clone)]
 pub enum NodeEvent<'a> {
     Enter(RefNode<'a>),
@@ -70,6 +139,122 @@ impl<'a> Iterator for EventIter<'a> {
     }
 }
 
+impl<'a> EventIter<'a> {
+    /// The position of the iterator, which doesn't borrow the nodes, so that
+    /// iteration can be stopped and later resumed by `EventIter::resume()`.
+    pub fn cursor(&self) -> EventCursor {
+        let mut remaining = vec![0];
+        for x in &self.next.0 {
+            match x {
+                NodeEvent::Enter(_) => *remaining.last_mut().unwrap() += 1,
+                NodeEvent::Leave(_) => remaining.push(0),
+            }
+        }
+        EventCursor { remaining }
+    }
+
+    /// Iterate over the events of `nodes` from `cursor`, which is taken from
+    /// an iterator over the same nodes. Only the nodes enclosing the position
+    /// and their children are visited to resume.
+    pub fn resume(nodes: RefNodes<'a>, cursor: &EventCursor) -> Self {
+        let mut next = Vec::new();
+        let mut nodes = nodes.0;
+        let mut levels = cursor.remaining.iter().peekable();
+        while let Some(remaining) = levels.next() {
+            let begin = nodes.len() - remaining;
+            for x in nodes[begin..].iter().rev() {
+                next.push(NodeEvent::Enter(x.clone()));
+            }
+            if levels.peek().is_some() {
+                // The node entered last at this depth is still open.
+                let parent = nodes[begin - 1].clone();
+                next.push(NodeEvent::Leave(parent.clone()));
+                nodes = parent.children().0;
+            }
+        }
+        EventIter {
+            next: NodeEvents(next),
+        }
+    }
+}
+
+/// A position within the events of nodes, given by `EventIter::cursor()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventCursor {
+    // The number of nodes left to enter at each depth, outermost first.
+    remaining: Vec<usize>,
+}
+
+// -----------------------------------------------------------------------------
+
+pub struct TypedIter<'a, T> {
+    iter: Iter<'a>,
+    phantom: PhantomData<&'a T>,
+}
+
+impl<'a, T: 'a> Iterator for TypedIter<'a, T>
+where
+    &'a T: TryFrom<RefNode<'a>>,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Ok(x) = <&'a T>::try_from(self.iter.next()?) {
+                return Some(x);
+            }
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Iterates like `Iter`, while `ancestors()` gives the nodes which enclose the
+/// node last returned.
+pub struct AncestorIter<'a> {
+    events: EventIter<'a>,
+    stack: Vec<RefNode<'a>>,
+    current: Option<RefNode<'a>>,
+}
+
+impl<'a> AncestorIter<'a> {
+    /// The nodes enclosing the node last returned, outermost first.
+    pub fn ancestors(&self) -> &[RefNode<'a>] {
+        &self.stack
+    }
+
+    /// The node directly enclosing the node last returned.
+    pub fn parent(&self) -> Option<&RefNode<'a>> {
+        self.stack.last()
+    }
+
+    /// Whether the node last returned is within a node of `kind`.
+    pub fn is_inside(&self, kind: NodeKind) -> bool {
+        self.stack.iter().any(|x| x.kind() == kind)
+    }
+}
+
+impl<'a> Iterator for AncestorIter<'a> {
+    type Item = RefNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(x) = self.current.take() {
+            self.stack.push(x);
+        }
+        loop {
+            match self.events.next()? {
+                NodeEvent::Enter(x) => {
+                    self.current = Some(x.clone());
+                    return Some(x);
+                }
+                NodeEvent::Leave(_) => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
 // -----------------------------------------------------------------------------
 
 impl<'a> From<Iter<'a>> for EventIter<'a> {
@@ -100,6 +285,12 @@ impl<'a> From<Vec<RefNode<'a>>> for RefNodes<'a> {
     }
 }
 
+impl<'a> From<RefNode<'a>> for RefNodes<'a> {
+    fn from(x: RefNode<'a>) -> Self {
+        RefNodes(vec![x])
+    }
+}
+
 impl<'a> From<&'a Locate> for RefNodes<'a> {
     fn from(x: &'a Locate) -> Self {
         vec![RefNode::Locate(x)].into()
@@ -482,3 +673,82 @@ where
         ret.into()
     }
 }
+
+// -----------------------------------------------------------------------------
+
+impl<T: LocatesMut> LocatesMut for Vec<T> {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+        for x in self {
+            x.locates_mut(f);
+        }
+    }
+}
+
+impl<T: LocatesMut> LocatesMut for Option<T> {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+        if let Some(x) = self {
+            x.locates_mut(f);
+        }
+    }
+}
+
+impl<T: LocatesMut> LocatesMut for Box<T> {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+        (**self).locates_mut(f);
+    }
+}
+
+macro_rules! impl_locates_mut_tuple {
+    ($($t:ident $x:ident),+) => {
+        impl<$($t: LocatesMut),+> LocatesMut for ($($t,)+) {
+            fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+                let ($($x,)+) = self;
+                $($x.locates_mut(f);)+
+            }
+        }
+    };
+}
+
+impl_locates_mut_tuple!(T0 t0);
+impl_locates_mut_tuple!(T0 t0, T1 t1);
+impl_locates_mut_tuple!(T0 t0, T1 t1, T2 t2);
+impl_locates_mut_tuple!(T0 t0, T1 t1, T2 t2, T3 t3);
+impl_locates_mut_tuple!(T0 t0, T1 t1, T2 t2, T3 t3, T4 t4);
+impl_locates_mut_tuple!(T0 t0, T1 t1, T2 t2, T3 t3, T4 t4, T5 t5);
+impl_locates_mut_tuple!(T0 t0, T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6);
+impl_locates_mut_tuple!(T0 t0, T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7);
+impl_locates_mut_tuple!(T0 t0, T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8);
+impl_locates_mut_tuple!(T0 t0, T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8, T9 t9);
+impl_locates_mut_tuple!(
+    T0 t0, T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8, T9 t9, T10 t10
+);
+
+impl<T: LocatesMut> LocatesMut for Paren<T> {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+        self.nodes.locates_mut(f);
+    }
+}
+
+impl<T: LocatesMut> LocatesMut for Brace<T> {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+        self.nodes.locates_mut(f);
+    }
+}
+
+impl<T: LocatesMut> LocatesMut for Bracket<T> {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+        self.nodes.locates_mut(f);
+    }
+}
+
+impl<T: LocatesMut> LocatesMut for ApostropheBrace<T> {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+        self.nodes.locates_mut(f);
+    }
+}
+
+impl<T: LocatesMut, U: LocatesMut> LocatesMut for List<T, U> {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+        self.nodes.locates_mut(f);
+    }
+}
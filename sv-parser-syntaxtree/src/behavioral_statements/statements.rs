@@ -44,6 +44,8 @@ pub enum StatementItem {
     RandsequenceStatement(Box<RandsequenceStatement>),
     RandcaseStatement(Box<RandcaseStatement>),
     ExpectPropertyStatement(Box<ExpectPropertyStatement>),
+    #[cfg(feature = "ams")]
+    ContributionStatement(Box<ContributionStatement>),
 }
 
 #[derive(Clone, Debug, PartialEq, Node)]
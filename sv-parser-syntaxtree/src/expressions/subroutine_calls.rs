@@ -21,6 +21,7 @@ pub enum SystemTfCall {
     ArgOptionl(Box<SystemTfCallArgOptional>),
     ArgDataType(Box<SystemTfCallArgDataType>),
     ArgExpression(Box<SystemTfCallArgExpression>),
+    ArgTokens(Box<SystemTfCallArgTokens>),
 }
 
 #[derive(Clone, Debug, PartialEq, Node)]
@@ -47,6 +48,28 @@ pub struct SystemTfCallArgExpression {
     ),
 }
 
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct SystemTfCallArgTokens {
+    pub nodes: (SystemTfIdentifier, Paren<Vec<SystemTfArgToken>>),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub enum SystemTfArgToken {
+    StringLiteral(Box<StringLiteral>),
+    Paren(Box<SystemTfArgTokenParen>),
+    Text(Box<SystemTfArgTokenText>),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct SystemTfArgTokenParen {
+    pub nodes: (Paren<Vec<SystemTfArgToken>>,),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct SystemTfArgTokenText {
+    pub nodes: (Locate, Vec<WhiteSpace>),
+}
+
 #[derive(Clone, Debug, PartialEq, Node)]
 pub enum SubroutineCall {
     TfCall(Box<TfCall>),
@@ -131,9 +131,9 @@ pub struct IfdefDirective {
     pub nodes: (
         Symbol,
         Keyword,
-        TextMacroIdentifier,
+        IfdefCondition,
         IfdefGroupOfLines,
-        Vec<(Symbol, Keyword, TextMacroIdentifier, ElsifGroupOfLines)>,
+        Vec<(Symbol, Keyword, IfdefCondition, ElsifGroupOfLines)>,
         Option<(Symbol, Keyword, ElseGroupOfLines)>,
         Symbol,
         Keyword,
@@ -145,15 +145,48 @@ pub struct IfndefDirective {
     pub nodes: (
         Symbol,
         Keyword,
-        TextMacroIdentifier,
+        IfdefCondition,
         IfndefGroupOfLines,
-        Vec<(Symbol, Keyword, TextMacroIdentifier, ElsifGroupOfLines)>,
+        Vec<(Symbol, Keyword, IfdefCondition, ElsifGroupOfLines)>,
         Option<(Symbol, Keyword, ElseGroupOfLines)>,
         Symbol,
         Keyword,
     ),
 }
 
+#[derive(Clone, Debug, PartialEq, Node)]
+pub enum IfdefCondition {
+    TextMacroIdentifier(Box<TextMacroIdentifier>),
+    Paren(Box<IfdefConditionParen>),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct IfdefConditionParen {
+    pub nodes: (Paren<IfdefMacroExpression>,),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct IfdefMacroExpression {
+    pub nodes: (IfdefMacroOperand, Vec<(Symbol, IfdefMacroOperand)>),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub enum IfdefMacroOperand {
+    TextMacroIdentifier(Box<TextMacroIdentifier>),
+    Not(Box<IfdefMacroOperandNot>),
+    Paren(Box<IfdefMacroOperandParen>),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct IfdefMacroOperandNot {
+    pub nodes: (Symbol, IfdefMacroOperand),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct IfdefMacroOperandParen {
+    pub nodes: (Paren<IfdefMacroExpression>,),
+}
+
 #[derive(Clone, Debug, PartialEq, Node)]
 pub struct IfdefGroupOfLines {
     pub nodes: (Vec<SourceDescription>,),
@@ -181,6 +214,8 @@ pub enum SourceDescription {
     NotDirective(Box<SourceDescriptionNotDirective>),
     CompilerDirective(Box<CompilerDirective>),
     EscapedIdentifier(Box<EscapedIdentifier>),
+    ProtectedEnvelope(Box<ProtectedEnvelope>),
+    LegacyProtectedEnvelope(Box<LegacyProtectedEnvelope>),
 }
 
 #[derive(Clone, Debug, PartialEq, Node)]
@@ -276,6 +311,16 @@ pub struct PragmaKeyword {
     pub nodes: (SimpleIdentifier,),
 }
 
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct ProtectedEnvelope {
+    pub nodes: (Pragma, Locate, Pragma),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct LegacyProtectedEnvelope {
+    pub nodes: (Symbol, Keyword, Locate, Symbol, Keyword),
+}
+
 #[derive(Clone, Debug, PartialEq, Node)]
 pub struct LineCompilerDirective {
     pub nodes: (Symbol, Keyword, Number, StringLiteral, Level),
@@ -13,10 +13,13 @@ pub mod general;
 pub mod instantiations;
 pub mod preprocessor;
 pub mod primitive_instances;
+pub mod query;
 pub mod source_text;
 pub mod special_node;
 pub mod specify_section;
 pub mod udp_declaration_and_instantiation;
+#[cfg(feature = "ams")]
+pub mod verilog_ams;
 pub use any_node::*;
 pub use behavioral_statements::*;
 pub use declarations::*;
@@ -25,19 +28,25 @@ pub use general::*;
 pub use instantiations::*;
 pub use preprocessor::*;
 pub use primitive_instances::*;
+pub use query::*;
 pub use source_text::*;
 pub use special_node::*;
 pub use specify_section::*;
 pub use udp_declaration_and_instantiation::*;
+#[cfg(feature = "ams")]
+pub use verilog_ams::*;
 
 pub(crate) use sv_parser_macros::*;
 
 // -----------------------------------------------------------------------------
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Copy, Clone, Default, Debug, PartialEq)]
 pub struct Locate {
     pub offset: usize,
     pub line: u32,
+    /// Column of `offset` in bytes, starting from 1 like `line`.
+    pub column: u32,
     pub len: usize,
 }
 
@@ -47,16 +56,71 @@ impl Locate {
     }
 }
 
+/// The beginnings of the lines of a text, to find the line and column of
+/// byte offsets without counting newlines for each, e.g. for the offsets
+/// within original files given by `PreprocessedText::origin()`.
+#[derive(Clone, Debug)]
+pub struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(s: &str) -> Self {
+        let mut starts = vec![0];
+        for (i, x) in s.bytes().enumerate() {
+            if x == b'\n' {
+                starts.push(i + 1);
+            }
+        }
+        LineIndex { starts }
+    }
+
+    /// The line and column of `offset`, both starting from 1 as in `Locate`.
+    pub fn line_column(&self, offset: usize) -> (u32, u32) {
+        let line = self.starts.partition_point(|x| *x <= offset);
+        let column = offset - self.starts[line - 1] + 1;
+        (line as u32, column as u32)
+    }
+}
+
 // -----------------------------------------------------------------------------
 
 pub trait Node<'a> {
     fn next(&'a self) -> RefNodes<'a>;
+
+    fn kind(&self) -> NodeKind;
+
+    /// The byte range of the text from the first to the last token of the
+    /// node, without the white space after it, or `None` if it has no tokens.
+    fn span(&'a self) -> Option<core::ops::Range<usize>> {
+        any_node::token_span(self.next())
+    }
 }
 
 impl<'a> Node<'a> for Locate {
     fn next(&'a self) -> RefNodes<'a> {
         vec![].into()
     }
+
+    fn span(&'a self) -> Option<core::ops::Range<usize>> {
+        Some(self.offset..self.offset + self.len)
+    }
+
+    fn kind(&self) -> NodeKind {
+        NodeKind::Locate
+    }
+}
+
+/// Mutable access to all `Locate`s of a node, in order, e.g. to move them
+/// after the text before the node has been edited.
+pub trait LocatesMut {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate));
+}
+
+impl LocatesMut for Locate {
+    fn locates_mut(&mut self, f: &mut dyn FnMut(&mut Locate)) {
+        f(self)
+    }
 }
 
 impl<'a> IntoIterator for &'a Locate {
@@ -0,0 +1,86 @@
+use crate::*;
+
+// -----------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Axis {
+    Descendant,
+    Child,
+}
+
+/// A path of node kinds to search for, e.g.
+/// `Query::find(NodeKind::ModuleDeclaration).descendant(NodeKind::AlwaysConstruct)`.
+/// Each step after the first looks among the descendants or the direct
+/// children of the node found by the previous one.
+#[derive(Clone, Debug)]
+pub struct Query {
+    steps: Vec<(Axis, NodeKind)>,
+}
+
+/// A node found by a `Query`, with the nodes found by each of its steps.
+#[derive(Clone, Debug)]
+pub struct QueryMatch<'a> {
+    pub captures: Vec<RefNode<'a>>,
+}
+
+impl<'a> QueryMatch<'a> {
+    /// The node found by the last step.
+    pub fn node(&self) -> &RefNode<'a> {
+        self.captures.last().unwrap()
+    }
+}
+
+impl Query {
+    /// Any node of `kind`, including the nodes the query is run on.
+    pub fn find(kind: NodeKind) -> Self {
+        Query {
+            steps: vec![(Axis::Descendant, kind)],
+        }
+    }
+
+    /// A node of `kind` within the node found so far.
+    pub fn descendant(mut self, kind: NodeKind) -> Self {
+        self.steps.push((Axis::Descendant, kind));
+        self
+    }
+
+    /// A node of `kind` directly below the node found so far.
+    pub fn child(mut self, kind: NodeKind) -> Self {
+        self.steps.push((Axis::Child, kind));
+        self
+    }
+
+    /// Get the matches within `nodes`, in the order of `Iter`.
+    pub fn run<'a, T: Into<RefNodes<'a>>>(&self, nodes: T) -> Vec<QueryMatch<'a>> {
+        let mut ret = Vec::new();
+        let mut captures = Vec::new();
+        let nodes = Iter::new(nodes.into()).collect();
+        self.step(0, nodes, &mut captures, &mut ret);
+        ret
+    }
+
+    fn step<'a>(
+        &self,
+        i: usize,
+        nodes: Vec<RefNode<'a>>,
+        captures: &mut Vec<RefNode<'a>>,
+        ret: &mut Vec<QueryMatch<'a>>,
+    ) {
+        let kind = self.steps[i].1;
+        for node in nodes.into_iter().filter(|x| x.kind() == kind) {
+            captures.push(node.clone());
+            if let Some((axis, _)) = self.steps.get(i + 1) {
+                let next = match axis {
+                    Axis::Descendant => Iter::new(node.children()).collect(),
+                    Axis::Child => node.children().0,
+                };
+                self.step(i + 1, next, captures, ret);
+            } else {
+                ret.push(QueryMatch {
+                    captures: captures.clone(),
+                });
+            }
+            captures.pop();
+        }
+    }
+}
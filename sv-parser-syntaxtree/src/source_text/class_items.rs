@@ -8,6 +8,7 @@ pub enum ClassItem {
     Method(Box<ClassItemMethod>),
     Constraint(Box<ClassItemConstraint>),
     Declaration(Box<ClassItemDeclaration>),
+    InterfaceClassDeclaration(Box<ClassItemInterfaceClassDeclaration>),
     Covergroup(Box<ClassItemCovergroup>),
     LocalParameterDeclaration(Box<(LocalParameterDeclaration, Symbol)>),
     ParameterDeclaration(Box<(ParameterDeclaration, Symbol)>),
@@ -34,6 +35,11 @@ pub struct ClassItemDeclaration {
     pub nodes: (Vec<AttributeInstance>, ClassDeclaration),
 }
 
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct ClassItemInterfaceClassDeclaration {
+    pub nodes: (Vec<AttributeInstance>, InterfaceClassDeclaration),
+}
+
 #[derive(Clone, Debug, PartialEq, Node)]
 pub struct ClassItemCovergroup {
     pub nodes: (Vec<AttributeInstance>, CovergroupDeclaration),
@@ -89,6 +89,7 @@ pub enum UseClause {
     Cell(Box<UseClauseCell>),
     Named(Box<UseClauseNamed>),
     CellNamed(Box<UseClauseCellNamed>),
+    Parameter(Box<UseClauseParameter>),
 }
 
 #[derive(Clone, Debug, PartialEq, Node)]
@@ -110,6 +111,16 @@ pub struct UseClauseNamed {
     ),
 }
 
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct UseClauseParameter {
+    pub nodes: (
+        Keyword,
+        Option<(Option<(LibraryIdentifier, Symbol)>, CellIdentifier)>,
+        ParameterValueAssignment,
+        Option<(Symbol, Config)>,
+    ),
+}
+
 #[derive(Clone, Debug, PartialEq, Node)]
 pub struct UseClauseCellNamed {
     pub nodes: (
@@ -56,6 +56,10 @@ pub enum ModuleCommonItem {
     LoopGenerateConstruct(Box<LoopGenerateConstruct>),
     ConditionalGenerateConstruct(Box<ConditionalGenerateConstruct>),
     ElaborationSystemTask(Box<ElaborationSystemTask>),
+    #[cfg(feature = "ams")]
+    AnalogConstruct(Box<AnalogConstruct>),
+    #[cfg(feature = "ams")]
+    BranchDeclaration(Box<BranchDeclaration>),
 }
 
 #[derive(Clone, Debug, PartialEq, Node)]
@@ -23,6 +23,10 @@ pub enum Description {
     PackageItem(Box<DescriptionPackageItem>),
     BindDirective(Box<DescriptionBindDirective>),
     ConfigDeclaration(Box<ConfigDeclaration>),
+    #[cfg(feature = "ams")]
+    NatureDeclaration(Box<NatureDeclaration>),
+    #[cfg(feature = "ams")]
+    DisciplineDeclaration(Box<DisciplineDeclaration>),
 }
 
 #[derive(Clone, Debug, PartialEq, Node)]
@@ -20,26 +20,31 @@ pub enum WhiteSpace {
     CompilerDirective(Box<CompilerDirective>),
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Paren<T> {
     pub nodes: (Symbol, T, Symbol),
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Brace<T> {
     pub nodes: (Symbol, T, Symbol),
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Bracket<T> {
     pub nodes: (Symbol, T, Symbol),
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct ApostropheBrace<T> {
     pub nodes: (Symbol, T, Symbol),
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct List<T, U> {
     pub nodes: (U, Vec<(T, U)>),
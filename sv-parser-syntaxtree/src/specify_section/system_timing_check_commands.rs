@@ -238,9 +238,7 @@ pub struct WidthTimingCheck {
             ControlledReferenceEvent,
             Symbol,
             TimingCheckLimit,
-            Symbol,
-            Threshold,
-            Option<(Symbol, Option<Notifier>)>,
+            Option<(Symbol, Threshold, Option<(Symbol, Option<Notifier>)>)>,
         )>,
         Symbol,
     ),
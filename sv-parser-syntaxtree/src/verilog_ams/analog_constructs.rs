@@ -0,0 +1,38 @@
+use crate::*;
+
+// -----------------------------------------------------------------------------
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct AnalogConstruct {
+    pub nodes: (Keyword, Option<Keyword>, Statement),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct BranchDeclaration {
+    pub nodes: (
+        Keyword,
+        Paren<List<Symbol, BranchTerminal>>,
+        List<Symbol, BranchIdentifier>,
+        Symbol,
+    ),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct BranchTerminal {
+    pub nodes: (NetIdentifier, Option<Bracket<ConstantRangeExpression>>),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct ContributionStatement {
+    pub nodes: (BranchLvalue, Symbol, Expression, Symbol),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct BranchLvalue {
+    pub nodes: (Identifier, Paren<List<Symbol, BranchTerminal>>),
+}
+
+#[derive(Clone, Debug, PartialEq, Node)]
+pub struct BranchIdentifier {
+    pub nodes: (Identifier,),
+}
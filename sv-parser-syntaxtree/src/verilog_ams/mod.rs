@@ -0,0 +1,4 @@
+pub mod analog_constructs;
+pub mod natures_and_disciplines;
+pub use analog_constructs::*;
+pub use natures_and_disciplines::*;
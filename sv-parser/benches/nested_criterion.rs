@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use sv_parser::parse_sv_str;
+
+// Nested parentheses, concatenations and conditional operators make the
+// expression rules backtrack over the whole inner expression at every level.
+fn gen_text(kind: &str, depth: usize) -> String {
+    let expr = match kind {
+        "paren" => format!("{}a{}", "(".repeat(depth), ")".repeat(depth)),
+        "concat" => format!("{}a{}", "{".repeat(depth), "}".repeat(depth)),
+        _ => {
+            let mut e = String::from("a");
+            for i in 0..depth {
+                e = format!("(b{} ? {} : c)", i, e);
+            }
+            e
+        }
+    };
+    format!("module m; assign x = {}; endmodule\n", expr)
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let defines = HashMap::new();
+    let includes: Vec<PathBuf> = Vec::new();
+    for kind in &["paren", "concat", "cond"] {
+        let mut group = c.benchmark_group(format!("nested_{}", kind));
+        for depth in &[5, 10, 20, 40] {
+            let text = gen_text(kind, *depth);
+            group.bench_function(depth.to_string(), |b| {
+                b.iter_with_large_drop(|| {
+                    parse_sv_str(&text, "", &defines, &includes, false, false)
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
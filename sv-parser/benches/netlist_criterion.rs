@@ -0,0 +1,146 @@
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use sv_parser::parse_sv_str;
+
+// Count the allocations, most of which are of the boxed node storage of the
+// syntax tree.
+struct CountingAlloc;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// Measures the number of allocations instead of the time, so that criterion
+// reports and compares the allocations of a parse like its time.
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> usize {
+        ALLOCS.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, i: usize) -> usize {
+        ALLOCS.load(Ordering::Relaxed) - i
+    }
+
+    fn add(&self, v1: &usize, v2: &usize) -> usize {
+        v1 + v2
+    }
+
+    fn zero(&self) -> usize {
+        0
+    }
+
+    fn to_f64(&self, value: &usize) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for Allocations {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        let (n, unit) = match *throughput {
+            Throughput::Bytes(x) | Throughput::BytesDecimal(x) => (x, "B/alloc"),
+            Throughput::Elements(x) => (x, "elem/alloc"),
+        };
+        for value in values {
+            *value = n as f64 / *value;
+        }
+        unit
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+// A flat gate-level netlist like a synthesis output, with `cells` instances.
+fn gen_netlist(cells: usize) -> String {
+    let mut ret = String::from("module top (input clk, input [63:0] a, output [63:0] y);\n");
+    for i in 0..cells {
+        ret.push_str(&format!("  wire n{};\n", i));
+    }
+    for i in 0..cells {
+        let prev = if i == 0 {
+            String::from("a[0]")
+        } else {
+            format!("n{}", i - 1)
+        };
+        ret.push_str(&format!(
+            "  AND2X1 u{} (.A({}), .B(a[{}]), .Y(n{}));\n",
+            i,
+            prev,
+            i % 64,
+            i
+        ));
+    }
+    ret.push_str("  assign y = {63'd0, n0};\nendmodule\n");
+    ret
+}
+
+fn netlist_benchmark<M: Measurement>(c: &mut Criterion<M>, name: &str) {
+    let defines = HashMap::new();
+    let includes: Vec<PathBuf> = Vec::new();
+    let mut group = c.benchmark_group(name);
+    for cells in &[1000, 10000] {
+        let text = gen_netlist(*cells);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(cells.to_string(), |b| {
+            b.iter_with_large_drop(|| parse_sv_str(&text, "", &defines, &includes, false, false))
+        });
+    }
+    group.finish();
+}
+
+fn time_benchmark(c: &mut Criterion) {
+    netlist_benchmark(c, "netlist");
+}
+
+fn allocations_benchmark(c: &mut Criterion<Allocations>) {
+    netlist_benchmark(c, "netlist_allocations");
+}
+
+criterion_group! {
+    name = time;
+    config = Criterion::default().sample_size(10);
+    targets = time_benchmark
+}
+
+criterion_group! {
+    name = allocations;
+    config = Criterion::default().with_measurement(Allocations).sample_size(10);
+    targets = allocations_benchmark
+}
+
+criterion_main!(time, allocations);
@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use sv_parser::{preprocess_str, PreprocessedText};
+
+// Each macro usage gives several segments in the preprocessed text.
+fn gen_text(n: usize) -> PreprocessedText {
+    let mut s = String::from("`define A a\n");
+    for _ in 0..n {
+        s.push_str("`A b\n");
+    }
+    let include_paths: Vec<PathBuf> = Vec::new();
+    let (text, _) = preprocess_str(
+        &s,
+        "bench.sv",
+        &HashMap::new(),
+        &include_paths,
+        false, // ignore_include
+        false, // strip_comments
+        0,     // resolve_depth
+        0,     // include_depth
+    )
+    .unwrap();
+    text
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let text = gen_text(20000);
+    let positions: Vec<usize> = (0..text.text().len()).collect();
+
+    let mut group = c.benchmark_group("origin");
+    group.bench_function("origin", |b| {
+        b.iter(|| {
+            for x in &positions {
+                black_box(text.origin(*x));
+            }
+        })
+    });
+    group.bench_function("origins", |b| b.iter(|| black_box(text.origins(&positions))));
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
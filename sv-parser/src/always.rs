@@ -0,0 +1,270 @@
+use crate::*;
+
+/// The keyword of an always construct.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlwaysKind {
+    Always,
+    AlwaysComb,
+    AlwaysLatch,
+    AlwaysFf,
+}
+
+/// The logic an always construct describes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlwaysClass {
+    /// `always_comb`, or `always` with an event control without edges, e.g.
+    /// `@*` or `@(a or b)`.
+    Combinational,
+    /// `always_latch`.
+    Latch,
+    /// `always_ff`, or `always` with an edge in its event control.
+    Sequential,
+    /// `always` without an event control, e.g. `always #5 clk = ~clk;`.
+    Other,
+}
+
+/// The edge of an event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Posedge,
+    Negedge,
+    Edge,
+}
+
+/// An event of the event control of an always construct, e.g. `posedge clk`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlwaysEvent {
+    pub edge: Option<Edge>,
+    pub expression: String,
+}
+
+/// The reset of a sequential always construct.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResetInfo {
+    pub name: String,
+    /// Whether the reset is asserted when low, e.g. `rst_n` in `if (!rst_n)`.
+    pub active_low: bool,
+    /// Whether it is in the event control, i.e. an asynchronous reset.
+    pub asynchronous: bool,
+}
+
+/// The classification of an always construct, with the clock and reset of a
+/// sequential one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlwaysInfo {
+    pub kind: AlwaysKind,
+    pub class: AlwaysClass,
+    /// The events of the event control. This is empty for `@*`, or for an
+    /// always construct without an event control.
+    pub events: Vec<AlwaysEvent>,
+    /// The edge and expression of the clock of a sequential construct.
+    pub clock: Option<(Edge, String)>,
+    pub reset: Option<ResetInfo>,
+}
+
+impl AlwaysInfo {
+    /// Classify `node`, which is a node of `tree`.
+    ///
+    /// The reset is found from the condition of an `if` which is the first
+    /// statement of the body, e.g. `if (!rst_n)`, `if (rst)` or
+    /// `if (rst_n == 1'b0)`. It is asynchronous if the signal is an edge of
+    /// the event control, and the clock is then the other edge. Otherwise it
+    /// is taken as a synchronous reset only if its name contains `rst` or
+    /// `reset`, as the condition could as well be an enable.
+    pub fn new(tree: &SyntaxTree, node: &AlwaysConstruct) -> Self {
+        let kind = match &node.nodes.0 {
+            AlwaysKeyword::Always(_) => AlwaysKind::Always,
+            AlwaysKeyword::AlwaysComb(_) => AlwaysKind::AlwaysComb,
+            AlwaysKeyword::AlwaysLatch(_) => AlwaysKind::AlwaysLatch,
+            AlwaysKeyword::AlwaysFf(_) => AlwaysKind::AlwaysFf,
+        };
+
+        let (control, body) = match &node.nodes.1.nodes.2 {
+            StatementItem::ProceduralTimingControlStatement(x) => {
+                let body = match &x.nodes.1 {
+                    StatementOrNull::Statement(x) => Some(&**x),
+                    _ => None,
+                };
+                match &x.nodes.0 {
+                    ProceduralTimingControl::EventControl(x) => (Some(&**x), body),
+                    _ => (None, body),
+                }
+            }
+            _ => (None, Some(&node.nodes.1)),
+        };
+        let events = control.map(|x| events(tree, x)).unwrap_or_default();
+
+        let edged = events.iter().any(|x| x.edge.is_some());
+        let class = match kind {
+            AlwaysKind::AlwaysComb => AlwaysClass::Combinational,
+            AlwaysKind::AlwaysLatch => AlwaysClass::Latch,
+            AlwaysKind::AlwaysFf => AlwaysClass::Sequential,
+            AlwaysKind::Always if edged => AlwaysClass::Sequential,
+            AlwaysKind::Always if control.is_some() => AlwaysClass::Combinational,
+            AlwaysKind::Always => AlwaysClass::Other,
+        };
+
+        let mut ret = AlwaysInfo {
+            kind,
+            class,
+            events,
+            clock: None,
+            reset: None,
+        };
+        if class != AlwaysClass::Sequential {
+            return ret;
+        }
+
+        let reset = body.and_then(first_statement).and_then(|x| match x {
+            StatementItem::ConditionalStatement(x) => {
+                match x.nodes.2.nodes.1.nodes.0.contents().as_slice() {
+                    [ExpressionOrCondPattern::Expression(x)] => reset_condition(tree, x),
+                    _ => None,
+                }
+            }
+            _ => None,
+        });
+        if let Some((name, active_low)) = reset {
+            let asynchronous = ret
+                .events
+                .iter()
+                .any(|x| x.edge.is_some() && x.expression == name);
+            let lower = name.to_ascii_lowercase();
+            if asynchronous || lower.contains("rst") || lower.contains("reset") {
+                ret.reset = Some(ResetInfo {
+                    name,
+                    active_low,
+                    asynchronous,
+                });
+            }
+        }
+
+        let reset = ret.reset.as_ref().filter(|x| x.asynchronous);
+        ret.clock = ret.events.iter().find_map(|x| match x.edge {
+            Some(_) if reset.is_some_and(|y| y.name == x.expression) => None,
+            Some(edge) => Some((edge, x.expression.clone())),
+            None => None,
+        });
+        ret
+    }
+}
+
+fn text<'a, T: Into<RefNodes<'a>>>(tree: &SyntaxTree, nodes: T) -> Option<String> {
+    tree.get_str_trim(nodes).map(String::from)
+}
+
+fn events(tree: &SyntaxTree, control: &EventControl) -> Vec<AlwaysEvent> {
+    let mut ret = Vec::new();
+    match control {
+        EventControl::EventIdentifier(x) => ret.push(AlwaysEvent {
+            edge: None,
+            expression: text(tree, &x.nodes.1).unwrap_or_default(),
+        }),
+        EventControl::EventExpression(x) => {
+            for node in &**x {
+                if let RefNode::EventExpressionExpression(x) = node {
+                    let edge = x.nodes.0.as_ref().map(|x| match x {
+                        EdgeIdentifier::Posedge(_) => Edge::Posedge,
+                        EdgeIdentifier::Negedge(_) => Edge::Negedge,
+                        EdgeIdentifier::Edge(_) => Edge::Edge,
+                    });
+                    ret.push(AlwaysEvent {
+                        edge,
+                        expression: text(tree, &x.nodes.1).unwrap_or_default(),
+                    });
+                }
+            }
+        }
+        _ => (),
+    }
+    ret
+}
+
+// The first statement within any `begin`-`end` blocks.
+fn first_statement(statement: &Statement) -> Option<&StatementItem> {
+    match &statement.nodes.2 {
+        StatementItem::SeqBlock(x) => match x.nodes.3.first()? {
+            StatementOrNull::Statement(x) => first_statement(x),
+            _ => None,
+        },
+        x => Some(x),
+    }
+}
+
+// The signal of a reset condition, and whether it is asserted when low.
+fn reset_condition(tree: &SyntaxTree, condition: &Expression) -> Option<(String, bool)> {
+    match condition {
+        Expression::Primary(x) => primary_condition(tree, x),
+        Expression::Unary(x) => match tree.get_str_trim(&x.nodes.0)? {
+            "!" | "~" => {
+                let (name, active_low) = primary_condition(tree, &x.nodes.2)?;
+                Some((name, !active_low))
+            }
+            _ => None,
+        },
+        Expression::Binary(x) => {
+            let equal = match tree.get_str_trim(&x.nodes.1)? {
+                "==" | "===" => true,
+                "!=" | "!==" => false,
+                _ => return None,
+            };
+            let (name, high) = match (signal(tree, &x.nodes.0), signal(tree, &x.nodes.3)) {
+                (Some(name), None) => (name, level(tree, &x.nodes.3)?),
+                (None, Some(name)) => (name, level(tree, &x.nodes.0)?),
+                _ => return None,
+            };
+            Some((name, equal != high))
+        }
+        _ => None,
+    }
+}
+
+fn primary_condition(tree: &SyntaxTree, primary: &Primary) -> Option<(String, bool)> {
+    match primary {
+        Primary::Hierarchical(x) => Some((text(tree, &**x)?, false)),
+        Primary::MintypmaxExpression(x) => match &x.nodes.0.nodes.1 {
+            MintypmaxExpression::Expression(x) => reset_condition(tree, x),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// The name of a signal, e.g. `rst_n` or `rst[0]`.
+fn signal(tree: &SyntaxTree, expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::Primary(x) => match &**x {
+            Primary::Hierarchical(x) => text(tree, &**x),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// The value of a literal 0 or 1, e.g. `1'b0` or `'1`.
+fn level(tree: &SyntaxTree, expression: &Expression) -> Option<bool> {
+    let literal = match expression {
+        Expression::Primary(x) => match &**x {
+            Primary::PrimaryLiteral(x) => x,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let value = match &**literal {
+        PrimaryLiteral::Number(x) => match &**x {
+            Number::IntegralNumber(x) => IntegralValue::new(tree, x)?.to_u64()?,
+            _ => return None,
+        },
+        PrimaryLiteral::UnbasedUnsizedLiteral(x) => match tree.get_str_trim(&**x)? {
+            "'0" => 0,
+            "'1" => 1,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match value {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
@@ -0,0 +1,123 @@
+use crate::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+/// A cache of the results of `parse_sv()`, so that a file is parsed again
+/// only when it, a file it includes, or the arguments changed.
+///
+/// Entries are keyed by a hash of the path and contents of the file, the
+/// pre-defines, the include paths and the options. An entry is used only if
+/// each file included when it was parsed, as given by
+/// `PreprocessedText::includes()`, still has the same contents. Clones of a
+/// cache share the same entries.
+#[derive(Clone, Debug, Default)]
+pub struct ParseCache {
+    entries: Arc<Mutex<HashMap<u64, ParseCacheEntry>>>,
+}
+
+#[derive(Debug)]
+struct ParseCacheEntry {
+    // The hash of the contents of each included file, or `None` if it
+    // couldn't be read.
+    includes: Vec<(PathBuf, Option<u64>)>,
+    result: Arc<(SyntaxTree, Defines)>,
+}
+
+impl ParseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Preprocess and parse the file at `path` like `parse_sv()`, or get the
+    /// result of an earlier call if nothing it depends on changed.
+    pub fn parse_sv<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+        &self,
+        path: T,
+        pre_defines: &Defines<V>,
+        include_paths: &[U],
+        ignore_include: bool,
+        allow_incomplete: bool,
+    ) -> Result<Arc<(SyntaxTree, Defines)>, Error> {
+        let path = path.as_ref();
+        let contents = std::fs::read(path).map_err(|x| Error::File {
+            source: x,
+            path: PathBuf::from(path),
+        })?;
+
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        contents.hash(&mut hasher);
+        let mut defines: Vec<_> = pre_defines.iter().collect();
+        defines.sort_by_key(|x| x.0);
+        for (name, define) in defines {
+            name.hash(&mut hasher);
+            let define = define
+                .as_ref()
+                .map(|x| (&x.arguments, x.text.as_ref().map(|x| &x.text)));
+            define.hash(&mut hasher);
+        }
+        for x in include_paths {
+            x.as_ref().hash(&mut hasher);
+        }
+        (ignore_include, allow_incomplete).hash(&mut hasher);
+        let key = hasher.finish();
+
+        if let Some(x) = self.get(key) {
+            return Ok(x);
+        }
+
+        let result = parse_sv(
+            path,
+            pre_defines,
+            include_paths,
+            ignore_include,
+            allow_incomplete,
+        )?;
+        let includes = result.0.text().includes();
+        let includes = includes.iter().map(|x| (x.clone(), file_hash(x))).collect();
+        let result = Arc::new(result);
+        let entry = ParseCacheEntry {
+            includes,
+            result: result.clone(),
+        };
+        self.entries.lock().unwrap().insert(key, entry);
+        Ok(result)
+    }
+
+    /// The number of cached results.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn get(&self, key: u64) -> Option<Arc<(SyntaxTree, Defines)>> {
+        let (includes, result) = {
+            let entries = self.entries.lock().unwrap();
+            let entry = entries.get(&key)?;
+            (entry.includes.clone(), entry.result.clone())
+        };
+        // The included files are read without holding the lock.
+        if includes.iter().all(|(path, hash)| file_hash(path) == *hash) {
+            Some(result)
+        } else {
+            self.entries.lock().unwrap().remove(&key);
+            None
+        }
+    }
+}
+
+fn file_hash(path: &Path) -> Option<u64> {
+    let contents = std::fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    Some(hasher.finish())
+}
@@ -0,0 +1,152 @@
+use crate::*;
+
+/// The kind of a `Difference`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Inserted,
+    Removed,
+    Modified,
+}
+
+/// A node which differs between two trees. `old` is the node of the old tree
+/// and `new` the node of the new tree, so `old` is `None` for an inserted
+/// node and `new` is `None` for a removed one. Their spans are given by
+/// `RefNode::span()`.
+#[derive(Clone, Debug)]
+pub struct Difference<'a> {
+    pub change: Change,
+    pub old: Option<RefNode<'a>>,
+    pub new: Option<RefNode<'a>>,
+}
+
+/// Compare two trees by their nodes and tokens, ignoring white space and
+/// comments. Each difference is reported at the innermost node which
+/// contains it, in the order of the trees, so an empty result means that
+/// only the formatting has changed.
+pub fn diff<'a>(old: &'a SyntaxTree, new: &'a SyntaxTree) -> Vec<Difference<'a>> {
+    let mut ret = Vec::new();
+    let trees = (old, new);
+    diff_node(trees, (&old.node).into(), (&new.node).into(), &mut ret);
+    ret
+}
+
+fn diff_node<'a>(
+    trees: (&'a SyntaxTree, &'a SyntaxTree),
+    old: RefNode<'a>,
+    new: RefNode<'a>,
+    ret: &mut Vec<Difference<'a>>,
+) {
+    let modified = Difference {
+        change: Change::Modified,
+        old: Some(old.clone()),
+        new: Some(new.clone()),
+    };
+    if old.kind() != new.kind() {
+        ret.push(modified);
+        return;
+    }
+
+    let old_children = children(&old);
+    let new_children = children(&new);
+    let old_tokens: Vec<_> = old_children.iter().map(|x| tokens(trees.0, x)).collect();
+    let new_tokens: Vec<_> = new_children.iter().map(|x| tokens(trees.1, x)).collect();
+
+    // The longest common subsequence of the children with equal tokens.
+    let (n, m) = (old_children.len(), new_children.len());
+    let mut lcs = vec![vec![0; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_tokens[i] == new_tokens[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_tokens[i] == new_tokens[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        // Collect the children up to the next common one.
+        let (i0, j0) = (i, j);
+        while i < n && j < m && old_tokens[i] != new_tokens[j] {
+            if lcs[i + 1][j] >= lcs[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        if i == n || j == m {
+            i = n;
+            j = m;
+        }
+        pairs.push((i0..i, j0..j));
+    }
+
+    let mut changes = Vec::new();
+    for (olds, news) in pairs {
+        let len = olds.len().min(news.len());
+        for k in 0..len {
+            let x = old_children[olds.start + k].clone();
+            let y = new_children[news.start + k].clone();
+            // A different token is reported as a change of this node.
+            if let RefNode::Locate(_) = x {
+                ret.push(modified);
+                return;
+            }
+            changes.push((Some(x), Some(y)));
+        }
+        for x in &old_children[olds.start + len..olds.end] {
+            changes.push((Some(x.clone()), None));
+        }
+        for y in &new_children[news.start + len..news.end] {
+            changes.push((None, Some(y.clone())));
+        }
+    }
+    for change in changes {
+        match change {
+            (Some(x), Some(y)) => diff_node(trees, x, y, ret),
+            (old, None) => ret.push(Difference {
+                change: Change::Removed,
+                old,
+                new: None,
+            }),
+            (None, new) => ret.push(Difference {
+                change: Change::Inserted,
+                old: None,
+                new,
+            }),
+        }
+    }
+}
+
+// The children of `node` without white space.
+fn children<'a>(node: &RefNode<'a>) -> Vec<RefNode<'a>> {
+    node.children()
+        .0
+        .into_iter()
+        .filter(|x| !matches!(x, RefNode::WhiteSpace(_)))
+        .collect()
+}
+
+// The text of the tokens of `node`.
+fn tokens<'a>(tree: &'a SyntaxTree, node: &RefNode<'a>) -> Vec<&'a str> {
+    let mut ret = Vec::new();
+    let mut depth = 0;
+    for n in Iter::new(vec![node.clone()].into()).event() {
+        match n {
+            NodeEvent::Enter(RefNode::WhiteSpace(_)) => depth += 1,
+            NodeEvent::Leave(RefNode::WhiteSpace(_)) => depth -= 1,
+            NodeEvent::Enter(RefNode::Locate(x)) if depth == 0 => {
+                ret.extend(tree.get_str(x));
+            }
+            _ => (),
+        }
+    }
+    ret
+}
@@ -0,0 +1,99 @@
+use crate::*;
+
+/// Options for `SyntaxTree::to_dot()`.
+#[derive(Clone, Debug)]
+pub struct DotConfig {
+    /// The kinds of the nodes to include, or all if `None`. A node is drawn
+    /// as a child of its nearest included ancestor. A token is of the kind
+    /// `NodeKind::Locate`.
+    pub kinds: Option<Vec<NodeKind>>,
+    /// The depth of the deepest nodes to include, where the root is at 0.
+    pub max_depth: Option<usize>,
+    /// The number of characters of the source excerpt of each node, or 0 for
+    /// no excerpt.
+    pub excerpt_len: usize,
+}
+
+impl std::default::Default for DotConfig {
+    fn default() -> Self {
+        DotConfig {
+            kinds: None,
+            max_depth: None,
+            excerpt_len: 40,
+        }
+    }
+}
+
+impl SyntaxTree {
+    /// Export the whole tree like `to_dot_node()`.
+    pub fn to_dot(&self, config: &DotConfig) -> String {
+        self.to_dot_node(self, config)
+    }
+
+    /// Export the specified node as a Graphviz DOT graph, with an edge from
+    /// each node to each of its children. A node is labelled with its kind
+    /// and the start of its text. White space is left out.
+    pub fn to_dot_node<'a, T: Into<RefNodes<'a>>>(&self, nodes: T, config: &DotConfig) -> String {
+        let mut ret = String::from("digraph SyntaxTree {\n  node [shape=box];\n");
+        let mut count = 0;
+        // The id of each entered node, or `None` if it isn't drawn.
+        let mut stack: Vec<Option<usize>> = Vec::new();
+        let mut skip = 0;
+        for n in Iter::new(nodes.into()).event() {
+            match n {
+                NodeEvent::Enter(RefNode::WhiteSpace(_)) => skip += 1,
+                NodeEvent::Leave(RefNode::WhiteSpace(_)) => skip -= 1,
+                NodeEvent::Enter(x) if skip == 0 => {
+                    let in_depth = config.max_depth.is_none_or(|d| stack.len() <= d);
+                    let in_kinds = config.kinds.as_ref().is_none_or(|k| k.contains(&x.kind()));
+                    if !(in_depth && in_kinds) {
+                        stack.push(None);
+                        continue;
+                    }
+
+                    let label = self.dot_label(&x, config.excerpt_len);
+                    ret.push_str(&format!("  n{} [label=\"{}\"];\n", count, label));
+                    if let Some(parent) = stack.iter().rev().find_map(|x| *x) {
+                        ret.push_str(&format!("  n{} -> n{};\n", parent, count));
+                    }
+                    stack.push(Some(count));
+                    count += 1;
+                }
+                NodeEvent::Leave(_) if skip == 0 => {
+                    stack.pop();
+                }
+                _ => (),
+            }
+        }
+        ret.push_str("}\n");
+        ret
+    }
+
+    fn dot_label(&self, node: &RefNode, excerpt_len: usize) -> String {
+        let mut ret = match node {
+            RefNode::Locate(_) => String::from("Token"),
+            _ => node.to_string(),
+        };
+        if excerpt_len == 0 {
+            return ret;
+        }
+        if let (Some(first), Some(last)) = token_range(vec![node.clone()]) {
+            let text = &self.text.text()[first.offset..last.offset + last.len];
+            let mut excerpt: String = text.chars().take(excerpt_len).collect();
+            if excerpt.len() < text.len() {
+                excerpt.push_str("...");
+            }
+            ret.push_str("\\n");
+            for c in excerpt.chars() {
+                match c {
+                    '"' => ret.push_str("\\\""),
+                    '\\' => ret.push_str("\\\\"),
+                    '\n' => ret.push_str("\\n"),
+                    '\r' => (),
+                    _ => ret.push(c),
+                }
+            }
+        }
+        ret
+    }
+}
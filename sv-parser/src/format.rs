@@ -0,0 +1,349 @@
+use crate::*;
+use std::collections::HashSet;
+
+/// Options for `SyntaxTree::format()`.
+#[derive(Clone, Debug)]
+pub struct FormatConfig {
+    /// The number of spaces of each level of indentation.
+    pub indent_width: usize,
+    /// Lines longer than this are broken after a comma where possible, and
+    /// continued two levels further in.
+    pub max_width: usize,
+    /// Align the assignment operators of consecutive lines at the same
+    /// level, e.g. of a run of continuous assignments.
+    pub align_assignments: bool,
+}
+
+impl std::default::Default for FormatConfig {
+    fn default() -> Self {
+        FormatConfig {
+            indent_width: 2,
+            max_width: 100,
+            align_assignments: true,
+        }
+    }
+}
+
+impl SyntaxTree {
+    /// Format the whole tree like `format_node()`.
+    pub fn format(&self, config: &FormatConfig) -> String {
+        self.format_node(self, config)
+    }
+
+    /// Render the specified node as text, indented by the blocks, port lists
+    /// and statement bodies around each line. The line breaks of the text
+    /// and its comments are kept, with at most one blank line in a row, and
+    /// the white space within a line becomes a single space. A tree from
+    /// `parse_sv_raw()` keeps its compiler directives.
+    pub fn format_node<'a, T: Into<RefNodes<'a>>>(
+        &self,
+        nodes: T,
+        config: &FormatConfig,
+    ) -> String {
+        let nodes: RefNodes = nodes.into();
+        let layout = Layout::new(RefNodes(nodes.0.clone()));
+        let lines = self.lines(nodes);
+
+        let line_starts: HashSet<usize> = lines
+            .iter()
+            .filter_map(|x| x.first().map(|x| x.offset))
+            .collect();
+        let level = |offset: usize| {
+            layout
+                .indents
+                .iter()
+                .filter(|x| x.begin <= offset && offset < x.end)
+                .filter(|x| !x.new_line || line_starts.contains(&x.begin))
+                .count()
+        };
+
+        // The text of each piece with the space before it, the level of the
+        // line and the piece at which it's aligned.
+        let mut rendered: Vec<(Vec<String>, usize, Option<usize>)> = Vec::new();
+        for line in &lines {
+            let mut chunks = Vec::new();
+            let mut align = None;
+            for (i, piece) in line.iter().enumerate() {
+                let space =
+                    i != 0 && piece.space && (piece.comment || !matches!(piece.text, "," | ";"));
+                chunks.push(format!("{}{}", if space { " " } else { "" }, piece.text));
+                if align.is_none() && layout.aligns.contains(&piece.offset) {
+                    align = Some(i);
+                }
+            }
+            let level = line.first().map_or(0, |x| level(x.offset));
+            rendered.push((chunks, level, align));
+        }
+
+        if config.align_assignments {
+            let mut i = 0;
+            while i < rendered.len() {
+                let mut j = i;
+                while j < rendered.len()
+                    && rendered[j].2.is_some()
+                    && rendered[j].1 == rendered[i].1
+                {
+                    j += 1;
+                }
+                if j - i > 1 {
+                    let width = |x: &(Vec<String>, usize, Option<usize>)| -> usize {
+                        x.0[..x.2.unwrap()].iter().map(|x| x.len()).sum()
+                    };
+                    let max = rendered[i..j].iter().map(width).max().unwrap_or(0);
+                    for x in &mut rendered[i..j] {
+                        let pad = max - width(x);
+                        let op = &mut x.0[x.2.unwrap()];
+                        let text = op.trim_start().to_string();
+                        *op = format!("{}{}", " ".repeat(pad + 1), text);
+                    }
+                }
+                i = j.max(i + 1);
+            }
+        }
+
+        let mut ret = String::new();
+        let mut blank = true;
+        for (chunks, level, _) in rendered {
+            if chunks.is_empty() {
+                if !blank {
+                    ret.push('\n');
+                }
+                blank = true;
+                continue;
+            }
+            blank = false;
+            let indent = " ".repeat(level * config.indent_width);
+            let continued = " ".repeat((level + 2) * config.indent_width);
+            for line in wrap(&chunks, &indent, &continued, config.max_width) {
+                ret.push_str(line.trim_end());
+                ret.push('\n');
+            }
+        }
+        while ret.ends_with("\n\n") {
+            ret.pop();
+        }
+        ret
+    }
+
+    // The tokens and comments of `nodes` by line, with an empty line for
+    // each blank line.
+    fn lines<'a>(&'a self, nodes: RefNodes<'a>) -> Vec<Vec<Piece<'a>>> {
+        let mut ret = vec![Vec::new()];
+        let mut space = false;
+        let mut depth = 0;
+        for n in Iter::new(nodes).event() {
+            match n {
+                NodeEvent::Enter(RefNode::WhiteSpace(x)) => {
+                    if depth == 0 {
+                        match x {
+                            WhiteSpace::Newline(x) => {
+                                for _ in self.get_str(&**x).unwrap_or("").matches('\n') {
+                                    ret.push(Vec::new());
+                                }
+                                space = false;
+                            }
+                            WhiteSpace::Space(_) => space = true,
+                            WhiteSpace::Comment(x) => {
+                                let text = self.get_str(&**x).unwrap_or("");
+                                push_piece(&mut ret, x, text.trim_end(), space, true);
+                                // A one line comment ends with its line break.
+                                space = !text.ends_with('\n');
+                                if !space {
+                                    ret.push(Vec::new());
+                                }
+                            }
+                            WhiteSpace::CompilerDirective(x) => {
+                                if let Some(text) = self.get_str_trim(&**x) {
+                                    push_piece(&mut ret, x, text, space, true);
+                                }
+                                space = true;
+                            }
+                        }
+                    }
+                    depth += 1;
+                }
+                NodeEvent::Leave(RefNode::WhiteSpace(_)) => depth -= 1,
+                NodeEvent::Enter(RefNode::Locate(x)) if depth == 0 => {
+                    let text = self.get_str(x).unwrap_or("");
+                    push_piece(&mut ret, x, text, space, false);
+                    space = false;
+                }
+                _ => (),
+            }
+        }
+        ret
+    }
+}
+
+fn push_piece<'a, T: Into<RefNodes<'a>>>(
+    lines: &mut Vec<Vec<Piece<'a>>>,
+    node: T,
+    text: &'a str,
+    space: bool,
+    comment: bool,
+) {
+    if let (Some(first), _) = token_range(node) {
+        lines.last_mut().unwrap().push(Piece {
+            text,
+            offset: first.offset,
+            space,
+            comment,
+        });
+    }
+}
+
+struct Piece<'a> {
+    text: &'a str,
+    offset: usize,
+    // Whether there's white space before it in the text.
+    space: bool,
+    comment: bool,
+}
+
+// A region of the text indented one level. One with `new_line` is only
+// indented if it starts a line, like the statement of `if`.
+struct Indent {
+    begin: usize,
+    end: usize,
+    new_line: bool,
+}
+
+// The indented regions of a node and the offsets of its assignment operators.
+struct Layout {
+    indents: Vec<Indent>,
+    aligns: HashSet<usize>,
+}
+
+impl Layout {
+    fn new(nodes: RefNodes) -> Self {
+        let mut ret = Layout {
+            indents: Vec::new(),
+            aligns: HashSet::new(),
+        };
+        for node in Iter::new(nodes) {
+            match node {
+                RefNode::ModuleDeclarationNonansi(x) => ret.block(&x.nodes.0, &x.nodes.3),
+                RefNode::ModuleDeclarationAnsi(x) => ret.block(&x.nodes.0, &x.nodes.3),
+                RefNode::InterfaceDeclarationNonansi(x) => ret.block(&x.nodes.0, &x.nodes.3),
+                RefNode::InterfaceDeclarationAnsi(x) => ret.block(&x.nodes.0, &x.nodes.3),
+                RefNode::ProgramDeclarationNonansi(x) => ret.block(&x.nodes.0, &x.nodes.3),
+                RefNode::ProgramDeclarationAnsi(x) => ret.block(&x.nodes.0, &x.nodes.3),
+                RefNode::PackageDeclaration(x) => ret.block(&x.nodes.4, &x.nodes.7),
+                RefNode::ClassDeclaration(x) => ret.block(&x.nodes.7, &x.nodes.9),
+                RefNode::FunctionBodyDeclarationWithoutPort(x) => ret.block(&x.nodes.3, &x.nodes.6),
+                RefNode::FunctionBodyDeclarationWithPort(x) => ret.block(&x.nodes.4, &x.nodes.7),
+                RefNode::TaskBodyDeclarationWithoutPort(x) => ret.block(&x.nodes.2, &x.nodes.5),
+                RefNode::TaskBodyDeclarationWithPort(x) => ret.block(&x.nodes.3, &x.nodes.6),
+                RefNode::SeqBlock(x) => match &x.nodes.1 {
+                    Some(label) => ret.block(label, &x.nodes.4),
+                    None => ret.block(&x.nodes.0, &x.nodes.4),
+                },
+                RefNode::ParBlock(x) => match &x.nodes.1 {
+                    Some(label) => ret.block(label, &x.nodes.4),
+                    None => ret.block(&x.nodes.0, &x.nodes.4),
+                },
+                RefNode::CaseStatementNormal(x) => ret.block(&x.nodes.2, &x.nodes.5),
+                RefNode::CaseStatementMatches(x) => ret.block(&x.nodes.3, &x.nodes.6),
+                RefNode::CaseStatementInside(x) => ret.block(&x.nodes.3, &x.nodes.6),
+                RefNode::GenerateRegion(x) => ret.block(&x.nodes.0, &x.nodes.2),
+                RefNode::GenerateBlockMultiple(x) => match &x.nodes.2 {
+                    Some(label) => ret.block(label, &x.nodes.4),
+                    None => ret.block(&x.nodes.1, &x.nodes.4),
+                },
+                RefNode::ListOfPortDeclarations(x) => ret.paren(&x.nodes.0),
+                RefNode::ListOfPorts(x) => ret.paren(&x.nodes.0),
+                RefNode::ParameterPortListDeclaration(x) => ret.paren(&x.nodes.1),
+                RefNode::ParameterPortListAssignment(x) => ret.paren(&x.nodes.1),
+                RefNode::HierarchicalInstance(x) => ret.paren(&x.nodes.1),
+                RefNode::ConditionalStatement(x) => {
+                    ret.body(&x.nodes.3);
+                    for x in &x.nodes.4 {
+                        ret.body(&x.3);
+                    }
+                    if let Some(x) = &x.nodes.5 {
+                        ret.body(&x.1);
+                    }
+                }
+                RefNode::CaseItemNondefault(x) => ret.body(&x.nodes.2),
+                RefNode::CaseItemDefault(x) => ret.body(&x.nodes.2),
+                RefNode::LoopStatementForever(x) => ret.body(&x.nodes.1),
+                RefNode::LoopStatementRepeat(x) => ret.body(&x.nodes.2),
+                RefNode::LoopStatementWhile(x) => ret.body(&x.nodes.2),
+                RefNode::LoopStatementFor(x) => ret.body(&x.nodes.2),
+                RefNode::LoopStatementForeach(x) => ret.body(&x.nodes.2),
+                RefNode::ProceduralTimingControlStatement(x) => ret.body(&x.nodes.1),
+                RefNode::NetAssignment(x) => ret.align(&x.nodes.1),
+                RefNode::VariableAssignment(x) => ret.align(&x.nodes.1),
+                RefNode::BlockingAssignmentVariable(x) => ret.align(&x.nodes.1),
+                RefNode::NonblockingAssignment(x) => ret.align(&x.nodes.1),
+                RefNode::OperatorAssignment(x) => ret.align(&x.nodes.1),
+                RefNode::ParamAssignment(x) => {
+                    if let Some(x) = &x.nodes.2 {
+                        ret.align(&x.0);
+                    }
+                }
+                _ => (),
+            }
+        }
+        ret
+    }
+
+    // Indent from after `open` up to `close`.
+    fn block<'a, T: Into<RefNodes<'a>>, U: Into<RefNodes<'a>>>(&mut self, open: T, close: U) {
+        if let ((_, Some(open)), (Some(close), _)) = (token_range(open), token_range(close)) {
+            self.indents.push(Indent {
+                begin: open.offset + open.len,
+                end: close.offset,
+                new_line: false,
+            });
+        }
+    }
+
+    // Indent within the parentheses.
+    fn paren<T>(&mut self, x: &Paren<T>) {
+        self.block(&x.nodes.0, &x.nodes.2);
+    }
+
+    // Indent `body` if it starts a line.
+    fn body<'a, T: Into<RefNodes<'a>>>(&mut self, body: T) {
+        if let (Some(first), Some(last)) = token_range(body) {
+            self.indents.push(Indent {
+                begin: first.offset,
+                end: last.offset + last.len,
+                new_line: true,
+            });
+        }
+    }
+
+    fn align<'a, T: Into<RefNodes<'a>>>(&mut self, op: T) {
+        if let (Some(first), _) = token_range(op) {
+            self.aligns.insert(first.offset);
+        }
+    }
+}
+
+// Break `chunks` after commas to keep within `max_width` where possible.
+fn wrap(chunks: &[String], indent: &str, continued: &str, max_width: usize) -> Vec<String> {
+    let mut ret = Vec::new();
+    let mut line = String::from(indent);
+    let mut start = true;
+    let mut last_comma = None;
+    for chunk in chunks {
+        if !start && line.len() + chunk.len() > max_width {
+            if let Some(pos) = last_comma.take() {
+                let rest = line.split_off(pos);
+                ret.push(line);
+                line = format!("{}{}", continued, rest.trim_start());
+                start = rest.trim().is_empty();
+            }
+        }
+        let chunk = if start { chunk.trim_start() } else { chunk };
+        line.push_str(chunk);
+        start = false;
+        if chunk == "," {
+            last_comma = Some(line.len());
+        }
+    }
+    ret.push(line);
+    ret
+}
@@ -0,0 +1,343 @@
+use crate::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// The kind of a `DesignUnit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DesignUnitKind {
+    Module,
+    Interface,
+    Program,
+}
+
+/// A parameter value of an `Instance`, e.g. `.W(8)` or `8`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParameterValue {
+    /// The parameter, which is `None` for a value by position.
+    pub name: Option<String>,
+    /// The value, which is `None` for `.W()`.
+    pub value: Option<String>,
+}
+
+/// An instance within a `DesignUnit`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instance {
+    pub name: String,
+    /// The name of the instantiated design unit.
+    pub unit_name: String,
+    /// The index of the instantiated design unit in `Hierarchy::units`, which
+    /// is `None` if no design unit of the name was parsed.
+    pub unit: Option<usize>,
+    pub parameters: Vec<ParameterValue>,
+}
+
+/// A module, interface or program with the instances within it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DesignUnit {
+    pub name: String,
+    pub kind: DesignUnitKind,
+    /// The index of the tree it is declared in.
+    pub tree: usize,
+    pub instances: Vec<Instance>,
+}
+
+/// Options for `Hierarchy::find_top_modules()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TopConfig {
+    /// Whether a module instantiated by a `bind` directive isn't a top, e.g.
+    /// a checker bound into the design.
+    pub ignore_bound: bool,
+    /// The patterns of the names of testbench wrappers, e.g. `tb_*` or
+    /// `*_tb`, where `*` matches any characters. The modules which a top
+    /// matching a pattern instantiates are the tops instead of it.
+    pub testbenches: Vec<String>,
+}
+
+impl std::default::Default for TopConfig {
+    fn default() -> Self {
+        TopConfig {
+            ignore_bound: true,
+            testbenches: Vec::new(),
+        }
+    }
+}
+
+/// The graph of which design units instantiate which, over a set of trees.
+/// The instances of all generate blocks are included, since which of them
+/// are elaborated isn't known without evaluating their conditions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Hierarchy {
+    /// The design units in the order of the trees and of their declarations.
+    pub units: Vec<DesignUnit>,
+    /// The names of the design units instantiated by `bind` directives.
+    pub bound: Vec<String>,
+}
+
+impl Hierarchy {
+    /// Build the hierarchy of the design units of `trees`. An instance refers
+    /// to the first design unit of its name.
+    pub fn new<'a, T: IntoIterator<Item = &'a SyntaxTree>>(trees: T) -> Self {
+        let mut units = Vec::new();
+        let mut bound = Vec::new();
+        for (i, tree) in trees.into_iter().enumerate() {
+            let mut unit_ids = HashMap::new();
+            let mut iter = tree.into_iter().with_ancestors();
+            while let Some(node) = iter.next() {
+                let kind = match node {
+                    RefNode::ModuleDeclaration(_) => Some(DesignUnitKind::Module),
+                    RefNode::InterfaceDeclaration(_) => Some(DesignUnitKind::Interface),
+                    RefNode::ProgramDeclaration(_) => Some(DesignUnitKind::Program),
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    let name = unwrap_node!(
+                        node.clone(),
+                        ModuleIdentifier,
+                        InterfaceIdentifier,
+                        ProgramIdentifier
+                    );
+                    unit_ids.insert(node.id(), units.len());
+                    units.push(DesignUnit {
+                        name: name.and_then(|x| text(tree, x)).unwrap_or_default(),
+                        kind,
+                        tree: i,
+                        instances: Vec::new(),
+                    });
+                    continue;
+                }
+
+                let (unit_name, parameters, instances) = match node {
+                    RefNode::ModuleInstantiation(x) => {
+                        (RefNode::from(&x.nodes.0), &x.nodes.1, &x.nodes.2)
+                    }
+                    RefNode::InterfaceInstantiation(x) => {
+                        (RefNode::from(&x.nodes.0), &x.nodes.1, &x.nodes.2)
+                    }
+                    RefNode::ProgramInstantiation(x) => {
+                        (RefNode::from(&x.nodes.0), &x.nodes.1, &x.nodes.2)
+                    }
+                    _ => continue,
+                };
+                let unit_name = text(tree, unit_name).unwrap_or_default();
+                if iter.is_inside(NodeKind::BindDirective) && !bound.contains(&unit_name) {
+                    bound.push(unit_name.clone());
+                }
+                let unit = iter
+                    .ancestors()
+                    .iter()
+                    .rev()
+                    .find_map(|x| unit_ids.get(&x.id()));
+                let unit = match unit {
+                    Some(x) => &mut units[*x],
+                    None => continue,
+                };
+                let parameters = parameter_values(tree, parameters);
+                for x in instances.contents() {
+                    unit.instances.push(Instance {
+                        name: text(tree, &x.nodes.0.nodes.0).unwrap_or_default(),
+                        unit_name: unit_name.clone(),
+                        unit: None,
+                        parameters: parameters.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut ret = Hierarchy { units, bound };
+        for unit in 0..ret.units.len() {
+            for i in 0..ret.units[unit].instances.len() {
+                let target = ret.find(&ret.units[unit].instances[i].unit_name);
+                ret.units[unit].instances[i].unit = target;
+            }
+        }
+        ret
+    }
+
+    /// The index of the first design unit named `name`.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.units.iter().position(|x| x.name == name)
+    }
+
+    /// The design units which no design unit instantiates, i.e. the tops of
+    /// the design, and interfaces which are only used as ports.
+    pub fn tops(&self) -> Vec<usize> {
+        (0..self.units.len())
+            .filter(|x| self.parents(*x).is_empty())
+            .collect()
+    }
+
+    /// The modules which no design unit instantiates, e.g. to find the top
+    /// module of a design for a flow script.
+    pub fn find_top_modules(&self, config: &TopConfig) -> Vec<usize> {
+        let mut ret = Vec::new();
+        let mut stack: Vec<_> = self.tops().into_iter().rev().collect();
+        let mut visited = vec![false; self.units.len()];
+        while let Some(unit) = stack.pop() {
+            let x = &self.units[unit];
+            if visited[unit] || x.kind != DesignUnitKind::Module {
+                continue;
+            }
+            visited[unit] = true;
+            if config.ignore_bound && self.bound.contains(&x.name) {
+                continue;
+            }
+            if config.testbenches.iter().any(|y| glob(y, &x.name)) {
+                stack.extend(x.instances.iter().rev().filter_map(|y| y.unit));
+            } else {
+                ret.push(unit);
+            }
+        }
+        ret.sort_unstable();
+        ret
+    }
+
+    /// The design units which instantiate `unit`.
+    pub fn parents(&self, unit: usize) -> Vec<usize> {
+        (0..self.units.len())
+            .filter(|x| {
+                self.units[*x]
+                    .instances
+                    .iter()
+                    .any(|y| y.unit == Some(unit))
+            })
+            .collect()
+    }
+
+    /// The instances whose design unit wasn't parsed, each with the index of
+    /// the design unit it is in.
+    pub fn unresolved(&self) -> Vec<(usize, &Instance)> {
+        let mut ret = Vec::new();
+        for (i, unit) in self.units.iter().enumerate() {
+            for x in unit.instances.iter().filter(|x| x.unit.is_none()) {
+                ret.push((i, x));
+            }
+        }
+        ret
+    }
+
+    /// The names of the design units which are instantiated but weren't
+    /// parsed, each once, e.g. to report the files missing from a build.
+    pub fn undeclared(&self) -> Vec<&str> {
+        let mut ret = Vec::new();
+        for (_, x) in self.unresolved() {
+            if !ret.contains(&x.unit_name.as_str()) {
+                ret.push(x.unit_name.as_str());
+            }
+        }
+        ret
+    }
+
+    /// The names of the design units which each design unit instantiates,
+    /// whether they were parsed or not.
+    pub fn dependencies(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+        let mut ret: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for unit in &self.units {
+            let names = ret.entry(unit.name.as_str()).or_default();
+            for x in &unit.instances {
+                names.insert(x.unit_name.as_str());
+            }
+        }
+        ret
+    }
+
+    /// The design units ordered so that each comes after the design units it
+    /// instantiates, e.g. to compile them bottom-up. The tree of each is
+    /// given by `DesignUnit::tree`. An instantiation which closes a cycle,
+    /// e.g. of a recursive generate, is ignored.
+    pub fn compile_order(&self) -> Vec<usize> {
+        let mut ret = Vec::new();
+        let mut visited = vec![false; self.units.len()];
+        for unit in 0..self.units.len() {
+            self.post_order(unit, &mut visited, &mut ret);
+        }
+        ret
+    }
+
+    fn post_order(&self, unit: usize, visited: &mut [bool], ret: &mut Vec<usize>) {
+        if visited[unit] {
+            return;
+        }
+        visited[unit] = true;
+        for x in &self.units[unit].instances {
+            if let Some(x) = x.unit {
+                self.post_order(x, visited, ret);
+            }
+        }
+        ret.push(unit);
+    }
+
+    /// The hierarchical names of the instances below `unit` depth-first,
+    /// e.g. `top.u0.u1`, each with the index of its design unit. A design
+    /// unit which instantiates itself, directly or not, is descended into
+    /// only once.
+    pub fn paths(&self, unit: usize) -> Vec<(String, Option<usize>)> {
+        let mut ret = Vec::new();
+        let mut stack = vec![unit];
+        self.walk(&self.units[unit].name, &mut stack, &mut ret);
+        ret
+    }
+
+    fn walk(&self, path: &str, stack: &mut Vec<usize>, ret: &mut Vec<(String, Option<usize>)>) {
+        let unit = *stack.last().unwrap();
+        for x in &self.units[unit].instances {
+            let path = format!("{}.{}", path, x.name);
+            ret.push((path.clone(), x.unit));
+            if let Some(unit) = x.unit {
+                if !stack.contains(&unit) {
+                    stack.push(unit);
+                    self.walk(&path, stack, ret);
+                    stack.pop();
+                }
+            }
+        }
+    }
+}
+
+fn text<'a, T: Into<RefNodes<'a>>>(tree: &SyntaxTree, nodes: T) -> Option<String> {
+    tree.get_str_trim(nodes).map(String::from)
+}
+
+// Whether `name` matches `pattern`, where `*` matches any characters.
+fn glob(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        Some(i) => {
+            let (head, tail) = (&pattern[..i], &pattern[i + 1..]);
+            name.starts_with(head)
+                && (head.len()..=name.len())
+                    .any(|j| name.is_char_boundary(j) && glob(tail, &name[j..]))
+        }
+        None => pattern == name,
+    }
+}
+
+fn parameter_values(
+    tree: &SyntaxTree,
+    parameters: &Option<ParameterValueAssignment>,
+) -> Vec<ParameterValue> {
+    let list = match parameters {
+        Some(x) => &x.nodes.1.nodes.1,
+        None => return Vec::new(),
+    };
+    match list {
+        Some(ListOfParameterAssignments::Ordered(x)) => x
+            .nodes
+            .0
+            .contents()
+            .into_iter()
+            .map(|x| ParameterValue {
+                name: None,
+                value: text(tree, x),
+            })
+            .collect(),
+        Some(ListOfParameterAssignments::Named(x)) => x
+            .nodes
+            .0
+            .contents()
+            .into_iter()
+            .map(|x| ParameterValue {
+                name: text(tree, &x.nodes.1),
+                value: text(tree, &x.nodes.2.nodes.1),
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
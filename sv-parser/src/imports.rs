@@ -0,0 +1,155 @@
+use crate::*;
+
+/// A name declared at the top of a package, e.g. a parameter, a type, an
+/// enum label or a function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageMember {
+    pub name: String,
+    /// The kind of the declaring identifier, e.g. `NodeKind::ParameterIdentifier`.
+    pub kind: NodeKind,
+    /// The location of the declaring identifier.
+    pub locate: Locate,
+}
+
+/// A package with the names declared in it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageInfo {
+    pub name: String,
+    /// The index of the tree it is declared in.
+    pub tree: usize,
+    /// The members in the order they are declared.
+    pub members: Vec<PackageMember>,
+}
+
+/// An item of an `import`, e.g. `pkg::*` or `pkg::WIDTH`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportInfo {
+    /// The index of the tree it is in.
+    pub tree: usize,
+    /// The name of the module, interface, program, package or class it is
+    /// in, which is `None` for an import into the compilation unit.
+    pub scope: Option<String>,
+    pub package_name: String,
+    /// The index of the imported package in `Imports::packages`, which is
+    /// `None` if no package of the name was parsed.
+    pub package: Option<usize>,
+    /// The imported name, which is `None` for `*`.
+    pub item: Option<String>,
+    /// The location of the package identifier of the item.
+    pub locate: Locate,
+}
+
+/// The packages and the imports of a set of trees, to find the declarations
+/// which each import brings into scope.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Imports {
+    /// The packages in the order of the trees and of their declarations.
+    pub packages: Vec<PackageInfo>,
+    /// The imports in the order of the trees and of their items.
+    pub imports: Vec<ImportInfo>,
+}
+
+impl Imports {
+    /// Collect the packages and imports of `trees`. An import refers to the
+    /// first package of its name.
+    pub fn new<'a, T: IntoIterator<Item = &'a SyntaxTree>>(trees: T) -> Self {
+        let mut ret = Imports::default();
+        for (i, tree) in trees.into_iter().enumerate() {
+            let symbols = SymbolTable::new(tree);
+            for scope in symbols.scopes() {
+                if scope.node.kind() != NodeKind::PackageDeclaration {
+                    continue;
+                }
+                let mut members: Vec<_> = scope
+                    .declarations
+                    .iter()
+                    .filter_map(|(name, declaration)| {
+                        let locate = unwrap_locate!(declaration.clone())?;
+                        Some(PackageMember {
+                            name: String::from(*name),
+                            kind: declaration.kind(),
+                            locate: *locate,
+                        })
+                    })
+                    .collect();
+                members.sort_by_key(|x| x.locate.offset);
+                ret.packages.push(PackageInfo {
+                    name: String::from(scope.name.unwrap_or("")),
+                    tree: i,
+                    members,
+                });
+            }
+
+            let mut iter = tree.into_iter().with_ancestors();
+            while let Some(node) = iter.next() {
+                let (package, item) = match node {
+                    RefNode::PackageImportItemIdentifier(x) => (&x.nodes.0, Some(&x.nodes.2)),
+                    RefNode::PackageImportItemAsterisk(x) => (&x.nodes.0, None),
+                    _ => continue,
+                };
+                let scope = iter.ancestors().iter().rev().find_map(|x| match x {
+                    RefNode::ModuleDeclaration(_)
+                    | RefNode::InterfaceDeclaration(_)
+                    | RefNode::ProgramDeclaration(_)
+                    | RefNode::PackageDeclaration(_)
+                    | RefNode::ClassDeclaration(_) => unwrap_node!(
+                        x.clone(),
+                        ModuleIdentifier,
+                        InterfaceIdentifier,
+                        ProgramIdentifier,
+                        PackageIdentifier,
+                        ClassIdentifier
+                    ),
+                    _ => None,
+                });
+                ret.imports.push(ImportInfo {
+                    tree: i,
+                    scope: scope.and_then(|x| text(tree, x)),
+                    package_name: text(tree, package).unwrap_or_default(),
+                    package: None,
+                    item: item.and_then(|x| text(tree, x)),
+                    locate: unwrap_locate!(package).copied().unwrap_or_default(),
+                });
+            }
+        }
+
+        for i in 0..ret.imports.len() {
+            ret.imports[i].package = ret.find(&ret.imports[i].package_name);
+        }
+        ret
+    }
+
+    /// The index of the first package named `name`.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.packages.iter().position(|x| x.name == name)
+    }
+
+    /// The declarations which `import` brings into scope, i.e. all members of
+    /// the package for `*`, or the member of the name. A name which a package
+    /// imports from another package is not included, as it can't be imported
+    /// again without an `export`.
+    pub fn members(&self, import: &ImportInfo) -> Vec<&PackageMember> {
+        let package = match import.package {
+            Some(x) => &self.packages[x],
+            None => return Vec::new(),
+        };
+        package
+            .members
+            .iter()
+            .filter(|x| import.item.as_ref().is_none_or(|y| x.name == *y))
+            .collect()
+    }
+
+    /// The imports whose package wasn't parsed, or whose name isn't declared
+    /// in the package.
+    pub fn unresolved(&self) -> Vec<&ImportInfo> {
+        self.imports
+            .iter()
+            .filter(|x| x.package.is_none() || (x.item.is_some() && self.members(x).is_empty()))
+            .collect()
+    }
+}
+
+fn text<'a, T: Into<RefNodes<'a>>>(tree: &SyntaxTree, nodes: T) -> Option<String> {
+    tree.get_str_trim(nodes).map(String::from)
+}
@@ -0,0 +1,176 @@
+use crate::*;
+
+// The nodes whose ports and declarations are not of the module they are in.
+const NESTED: &[NodeKind] = &[
+    NodeKind::ModuleDeclaration,
+    NodeKind::InterfaceDeclaration,
+    NodeKind::ProgramDeclaration,
+    NodeKind::CheckerDeclaration,
+    NodeKind::ClassDeclaration,
+    NodeKind::FunctionDeclaration,
+    NodeKind::TaskDeclaration,
+];
+
+/// An interface port of a module, e.g. `bus_if.master bus`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InterfacePort {
+    pub name: String,
+    /// The interface, which is `None` for a generic `interface` port.
+    pub interface: Option<String>,
+    pub modport: Option<String>,
+}
+
+/// A virtual interface declared in a module, e.g. `virtual bus_if.tb vif;`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VirtualInterface {
+    pub name: String,
+    pub interface: String,
+    pub modport: Option<String>,
+}
+
+/// The interface ports, modports and virtual interfaces of a module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InterfaceUsage {
+    pub module: String,
+    pub ports: Vec<InterfacePort>,
+    /// The modports of the ports and virtual interfaces as `interface.modport`,
+    /// without duplicates and in the order they first appear. The interface
+    /// of a generic `interface.modport` port is `interface`.
+    pub modports: Vec<String>,
+    pub virtual_interfaces: Vec<VirtualInterface>,
+}
+
+impl InterfaceUsage {
+    /// Collect the interface usage of `module`, which is a node of `tree`.
+    ///
+    /// An ANSI port whose type is a bare identifier, e.g. `bus_if bus`, is
+    /// parsed as an interface port, so a port of a user-defined type without
+    /// a direction is reported as well.
+    pub fn new(tree: &SyntaxTree, module: &ModuleDeclaration) -> Self {
+        let mut ret = InterfaceUsage {
+            module: unwrap_node!(module, ModuleIdentifier)
+                .and_then(|x| text(tree, x))
+                .unwrap_or_default(),
+            ports: Vec::new(),
+            modports: Vec::new(),
+            virtual_interfaces: Vec::new(),
+        };
+
+        // The interface and modport of the ANSI port before, which a port
+        // without a direction or type takes.
+        let mut prev: Option<(Option<String>, Option<String>)> = None;
+        let mut iter = Iter::new(module.into()).with_ancestors();
+        while let Some(node) = iter.next() {
+            let path = iter.ancestors();
+            if path.iter().skip(1).any(|x| NESTED.contains(&x.kind())) {
+                continue;
+            }
+            match node {
+                RefNode::AnsiPortDeclaration(x) => {
+                    let name = unwrap_node!(x, PortIdentifier)
+                        .and_then(|x| text(tree, x))
+                        .unwrap_or_default();
+                    let header = unwrap_node!(
+                        x,
+                        PortDirection,
+                        NetPortType,
+                        VariablePortType,
+                        InterfacePortHeader
+                    );
+                    prev = match header {
+                        Some(RefNode::InterfacePortHeader(x)) => Some(port_header(tree, x)),
+                        Some(_) => None,
+                        None => prev,
+                    };
+                    if let Some((interface, modport)) = &prev {
+                        ret.push_port(InterfacePort {
+                            name,
+                            interface: interface.clone(),
+                            modport: modport.clone(),
+                        });
+                    }
+                }
+                RefNode::InterfacePortDeclaration(x) => {
+                    let interface = text(tree, &x.nodes.0);
+                    let modport = x.nodes.1.as_ref().and_then(|(_, x)| text(tree, x));
+                    for (name, _) in x.nodes.2.nodes.0.contents() {
+                        ret.push_port(InterfacePort {
+                            name: text(tree, name).unwrap_or_default(),
+                            interface: interface.clone(),
+                            modport: modport.clone(),
+                        });
+                    }
+                }
+                RefNode::DataDeclarationVariable(x) => {
+                    let virtual_type = match &x.nodes.3 {
+                        DataTypeOrImplicit::DataType(x) => match x.as_ref() {
+                            DataType::Virtual(x) => x,
+                            _ => continue,
+                        },
+                        _ => continue,
+                    };
+                    let interface = text(tree, &virtual_type.nodes.2).unwrap_or_default();
+                    let modport = virtual_type
+                        .nodes
+                        .4
+                        .as_ref()
+                        .and_then(|(_, x)| text(tree, x));
+                    for assignment in x.nodes.4.nodes.0.contents() {
+                        let name = unwrap_node!(assignment, VariableIdentifier)
+                            .and_then(|x| text(tree, x))
+                            .unwrap_or_default();
+                        ret.push_modport(Some(&interface), modport.as_deref());
+                        ret.virtual_interfaces.push(VirtualInterface {
+                            name,
+                            interface: interface.clone(),
+                            modport: modport.clone(),
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+        ret
+    }
+
+    /// Collect the interface usage of each module of `tree`, in the order of
+    /// the source.
+    pub fn of_tree(tree: &SyntaxTree) -> Vec<Self> {
+        tree.iter_of::<ModuleDeclaration>()
+            .map(|(x, _)| InterfaceUsage::new(tree, x))
+            .collect()
+    }
+
+    fn push_port(&mut self, port: InterfacePort) {
+        self.push_modport(port.interface.as_deref(), port.modport.as_deref());
+        self.ports.push(port);
+    }
+
+    fn push_modport(&mut self, interface: Option<&str>, modport: Option<&str>) {
+        if let Some(modport) = modport {
+            let modport = format!("{}.{}", interface.unwrap_or("interface"), modport);
+            if !self.modports.contains(&modport) {
+                self.modports.push(modport);
+            }
+        }
+    }
+}
+
+fn port_header(
+    tree: &SyntaxTree,
+    header: &InterfacePortHeader,
+) -> (Option<String>, Option<String>) {
+    match header {
+        InterfacePortHeader::Identifier(x) => (
+            text(tree, &x.nodes.0),
+            x.nodes.1.as_ref().and_then(|(_, x)| text(tree, x)),
+        ),
+        InterfacePortHeader::Interface(x) => {
+            (None, x.nodes.1.as_ref().and_then(|(_, x)| text(tree, x)))
+        }
+    }
+}
+
+fn text<'a, T: Into<RefNodes<'a>>>(tree: &SyntaxTree, nodes: T) -> Option<String> {
+    tree.get_str_trim(nodes).map(String::from)
+}
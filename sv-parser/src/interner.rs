@@ -0,0 +1,88 @@
+use crate::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The id of a string of an `Interner`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(u32);
+
+impl SymbolId {
+    /// The index of the string in the order it was first interned.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Stores each distinct string once, so that names repeated across a design,
+/// e.g. `clk`, share their storage and compare by their `SymbolId`.
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    ids: HashMap<Arc<str>, SymbolId>,
+    strings: Vec<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the id of `s`, adding it if it is new.
+    pub fn intern(&mut self, s: &str) -> SymbolId {
+        if let Some(x) = self.ids.get(s) {
+            return *x;
+        }
+        let id = SymbolId(self.strings.len() as u32);
+        let s: Arc<str> = Arc::from(s);
+        self.strings.push(s.clone());
+        self.ids.insert(s, id);
+        id
+    }
+
+    /// Get the id of `s` without adding it.
+    pub fn get(&self, s: &str) -> Option<SymbolId> {
+        self.ids.get(s).copied()
+    }
+
+    /// Get the string of `id`.
+    pub fn resolve(&self, id: SymbolId) -> &str {
+        &self.strings[id.index()]
+    }
+
+    /// Get the id of the text of `nodes` of `tree` without trailing white
+    /// space, e.g. of an identifier, adding it if it is new.
+    pub fn intern_node<'a, T: Into<RefNodes<'a>>>(
+        &mut self,
+        tree: &SyntaxTree,
+        nodes: T,
+    ) -> Option<SymbolId> {
+        tree.get_str_trim(nodes).map(|x| self.intern(x))
+    }
+
+    /// Intern the name of each identifier of `tree`, i.e. each
+    /// `SimpleIdentifier`, `EscapedIdentifier` and `SystemTfIdentifier`, and
+    /// get them in order with the `Locate` of the name.
+    pub fn intern_identifiers<'a>(&mut self, tree: &'a SyntaxTree) -> Vec<(SymbolId, &'a Locate)> {
+        let mut ret = Vec::new();
+        for node in tree {
+            let locate = match node {
+                RefNode::SimpleIdentifier(x) => &x.nodes.0,
+                RefNode::EscapedIdentifier(x) => &x.nodes.0,
+                RefNode::SystemTfIdentifier(x) => &x.nodes.0,
+                _ => continue,
+            };
+            if let Some(x) = self.intern_node(tree, locate) {
+                ret.push((x, locate));
+            }
+        }
+        ret
+    }
+
+    /// The number of distinct strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
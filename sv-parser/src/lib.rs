@@ -1,42 +1,115 @@
 #![recursion_limit = "256"]
 
-use nom_greedyerror::error_position;
+use nom::error::ErrorKind;
+use nom::Slice;
+use nom_greedyerror::{error_position, GreedyError, GreedyErrorKind};
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::ThreadPoolBuilder;
+use std::convert::TryFrom;
 use std::fmt;
 use std::hash::BuildHasher;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-pub use sv_parser_error::Error;
+pub use sv_parser_error::{Diagnostic, Diagnostics, Error, Location, Severity};
+use sv_parser_parser::utils::{expected, is_systemverilog_only};
+pub use sv_parser_parser::utils::{
+    nest_limit, set_nest_limit, set_tolerant_system_tf, set_work_limit, tolerant_system_tf,
+    work_limit, DEFAULT_NEST_LIMIT,
+};
 use sv_parser_parser::{
-    lib_parser, lib_parser_incomplete, sv_parser, sv_parser_incomplete, Span, SpanInfo,
+    description_parser, descriptions_parser, expression_parser, lib_parser, lib_parser_incomplete,
+    module_item_parser, package_item_parser, source_text_header_parser, statement_parser,
+    sv_parser, sv_parser_incomplete, verilog_parser, verilog_parser_incomplete, IResult, Span,
+    SpanInfo,
 };
+pub use sv_parser_parser::{find_unterminated, tokenize, Token, TokenKind};
+#[cfg(feature = "fs")]
+pub use sv_parser_pp::preprocess::FsFileProvider;
+#[cfg(feature = "mmap")]
+pub use sv_parser_pp::preprocess::MapFiles;
 pub use sv_parser_pp::preprocess::{
-    preprocess, preprocess_str, Define, DefineText, Defines, PreprocessedText,
+    preprocess, preprocess_all, preprocess_str, preprocess_to, preprocess_with_config, Define,
+    DefineFragment, DefineText, Defines, Encoding, FileProvider, IncludeFallback, MacroExpansion,
+    MemoryFileProvider, PreprocessCache, PreprocessObserver, PreprocessedText, Preprocessor,
+    PreprocessorConfig, ProtectedRegion, SkippedRegion, Timescale, Warning, WarningKind,
 };
 pub use sv_parser_syntaxtree::*;
 
+mod always;
+#[cfg(feature = "fs")]
+mod cache;
+mod diff;
+mod dot;
+mod format;
+mod hierarchy;
+mod imports;
+mod interfaces;
+mod interner;
+mod lint;
+mod module_info;
+mod number;
+mod project;
+mod session;
+mod symbols;
+mod tags;
+#[cfg(feature = "serde")]
+mod verible;
+pub use always::{AlwaysClass, AlwaysEvent, AlwaysInfo, AlwaysKind, Edge, ResetInfo};
+#[cfg(feature = "fs")]
+pub use cache::ParseCache;
+pub use diff::{diff, Change, Difference};
+pub use dot::DotConfig;
+pub use format::FormatConfig;
+pub use hierarchy::{DesignUnit, DesignUnitKind, Hierarchy, Instance, ParameterValue, TopConfig};
+pub use imports::{ImportInfo, Imports, PackageInfo, PackageMember};
+pub use interfaces::{InterfacePort, InterfaceUsage, VirtualInterface};
+pub use interner::{Interner, SymbolId};
+pub use lint::{Linter, Rule, RuleContext};
+pub use module_info::{Direction, ModuleInfo, ParameterInfo, PortInfo};
+pub use number::{real_value, Bit, IntegralValue, NumberBase};
+pub use project::{CompilationUnit, CompilationUnitMode, DefineReport, MacroLocation, Project};
+pub use session::{Definition, DocumentSession, Position, TextEdit};
+pub use symbols::{Scope, SymbolTable};
+pub use tags::{Tag, TagKind, Tags};
+#[cfg(feature = "serde")]
+pub use verible::to_verible_json;
+
+/// A parsed tree with the preprocessed text it was parsed from. The nodes
+/// don't borrow the text, but hold the offsets of their tokens in `Locate`,
+/// so the tree owns all it needs and can be stored or sent to other threads.
 pub struct SyntaxTree {
     node: AnyNode,
     text: PreprocessedText,
+    // The offset of the text counted by `new_locate()`, with its line and the
+    // offset of that line, so that the text added before is counted once.
+    end: Option<(usize, u32, usize)>,
+}
+
+// Fails to build if a node or the text stops being `Send + Sync`, e.g. by
+// holding an `Rc`, so that trees can be shared by parallel analyses.
+fn _assert_send_sync() {
+    fn assert<T: Send + Sync>() {}
+    assert::<SyntaxTree>();
+    assert::<AnyNode>();
+    assert::<RefNode<'static>>();
+    assert::<PreprocessedText>();
+    assert::<Error>();
 }
 
 impl SyntaxTree {
+    fn new(node: AnyNode, text: PreprocessedText) -> Self {
+        SyntaxTree {
+            node,
+            text,
+            end: None,
+        }
+    }
+
     /// Get `&str` from the specified node
     pub fn get_str<'a, T: Into<RefNodes<'a>>>(&self, nodes: T) -> Option<&str> {
-        let mut beg = None;
-        let mut end = 0;
-        for n in Iter::new(nodes.into()) {
-            if let RefNode::Locate(x) = n {
-                if beg.is_none() {
-                    beg = Some(x.offset);
-                }
-                end = x.offset + x.len;
-            }
-        }
-        if let Some(beg) = beg {
-            let ret = unsafe { self.text.text().get_unchecked(beg..end) };
-            Some(ret)
-        } else {
-            None
-        }
+        self.text.get_str(nodes)
     }
 
     /// Get `&str` without trailing `WhiteSpace` from the specified node
@@ -69,10 +142,499 @@ impl SyntaxTree {
         }
     }
 
+    /// The preprocessed text the tree was parsed from, to which the offsets
+    /// of its `Locate`s refer.
+    pub fn text(&self) -> &PreprocessedText {
+        &self.text
+    }
+
     /// Get source code location of the specified `Locate`
     pub fn get_origin(&self, locate: &Locate) -> Option<(&PathBuf, usize)> {
         self.text.origin(locate.offset)
     }
+
+    /// Get the nodes of type `T` in order, each with the `Locate` of its
+    /// first token, e.g. `tree.iter_of::<ModuleDeclaration>()`. The `Locate`
+    /// is `None` for a node without tokens.
+    pub fn iter_of<'a, T: 'a>(&'a self) -> impl Iterator<Item = (&'a T, Option<&'a Locate>)>
+    where
+        &'a T: TryFrom<RefNode<'a>> + Into<RefNodes<'a>>,
+    {
+        self.into_iter().of::<T>().map(|x| (x, token_range(x).0))
+    }
+
+    /// Mutable access to the root node, to rename, replace, insert or remove
+    /// nodes. The tokens of new nodes are made with `new_locate()` or taken
+    /// from another tree with `import()`, and `to_source()` gives the text of
+    /// the modified tree.
+    pub fn node_mut(&mut self) -> &mut AnyNode {
+        &mut self.node
+    }
+
+    /// Add `s` to the text of the tree and get a `Locate` of it for a new
+    /// token. The new text has no origin.
+    pub fn new_locate(&mut self, s: &str) -> Locate {
+        let offset = self.text.append(s);
+        let text = self.text.text();
+        let (from, line, line_begin) = self.end.unwrap_or((0, 1, 0));
+        let (line, line_begin) = count_lines(&text[..offset], from, line, line_begin);
+        let end = count_lines(text, offset, line, line_begin);
+        self.end = Some((text.len(), end.0, end.1));
+        Locate {
+            offset,
+            line,
+            column: (offset - line_begin) as u32 + 1,
+            len: s.len(),
+        }
+    }
+
+    /// Get a copy of `node` of `other`, with new tokens in this tree for
+    /// each of its tokens, so that it can be inserted into this tree.
+    pub fn import<T: Clone + LocatesMut>(&mut self, other: &SyntaxTree, node: &T) -> T {
+        let mut ret = node.clone();
+        ret.locates_mut(&mut |x| {
+            *x = self.new_locate(&other.text.text()[x.offset..x.offset + x.len]);
+        });
+        ret
+    }
+
+    /// Regenerate the source from the tokens of the tree in order, with its
+    /// white space and comments. For a tree which hasn't been modified, this
+    /// is exactly the preprocessed text it was parsed from.
+    pub fn to_source(&self) -> String {
+        let mut ret = String::new();
+        for n in self {
+            if let RefNode::Locate(x) = n {
+                ret.push_str(&self.text.text()[x.offset..x.offset + x.len]);
+            }
+        }
+        ret
+    }
+
+    /// Serialize the tree to JSON as `{"text": ..., "node": ...}`, with the
+    /// preprocessed text to which the offsets of each `Locate` refer. A node
+    /// struct is an object with its `type` and its `nodes`, and an enum is an
+    /// object with the name of its variant as the only key.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "text": self.text.text(),
+            "node": &self.node,
+        })
+        .to_string()
+    }
+
+    /// Serialize the tree and its preprocessed text to a compact binary form,
+    /// e.g. to cache the parse result on disk. It can be read back by
+    /// `from_bytes()` of the same version of sv-parser only.
+    #[cfg(feature = "serde")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let version = env!("CARGO_PKG_VERSION");
+        bincode::serialize(&(version, &self.text, &self.node)).unwrap()
+    }
+
+    /// Read a tree serialized by `to_bytes()`.
+    #[cfg(feature = "serde")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<SyntaxTree, Error> {
+        let mut reader = bytes;
+        let version: String =
+            bincode::deserialize_from(&mut reader).map_err(|x| Error::Cache(x.to_string()))?;
+        if version != env!("CARGO_PKG_VERSION") {
+            return Err(Error::Cache(format!("written by version {}", version)));
+        }
+        let (text, node) =
+            bincode::deserialize_from(&mut reader).map_err(|x| Error::Cache(x.to_string()))?;
+        Ok(SyntaxTree::new(node, text))
+    }
+
+    /// Get the tokens of the tree in order, each with the white space and
+    /// comments around it. The white space after a token is its trailing
+    /// trivia up to and including the first line break, and the leading
+    /// trivia of the next token after that.
+    pub fn trivia(&self) -> Vec<TokenTrivia<'_>> {
+        let mut ret: Vec<TokenTrivia> = Vec::new();
+        let mut pending = Vec::new();
+        let mut depth = 0;
+        for n in self.into_iter().event() {
+            match n {
+                NodeEvent::Enter(RefNode::WhiteSpace(x)) => {
+                    if depth == 0 {
+                        pending.push(x);
+                    }
+                    depth += 1;
+                }
+                NodeEvent::Leave(RefNode::WhiteSpace(_)) => {
+                    depth -= 1;
+                }
+                NodeEvent::Enter(RefNode::Locate(x)) if depth == 0 => {
+                    let mut leading = std::mem::take(&mut pending);
+                    if let Some(last) = ret.last_mut() {
+                        let rest = leading.split_off(self.trailing_len(&leading));
+                        last.trailing = leading;
+                        leading = rest;
+                    }
+                    ret.push(TokenTrivia {
+                        locate: x,
+                        leading,
+                        trailing: vec![],
+                    });
+                }
+                _ => (),
+            }
+        }
+        if let Some(last) = ret.last_mut() {
+            last.trailing = pending;
+        }
+        ret
+    }
+
+    /// Get the leading trivia of the first token of the specified node
+    pub fn leading_trivia<'a, T: Into<RefNodes<'a>>>(&'a self, nodes: T) -> Vec<&'a WhiteSpace> {
+        let (first, _) = token_range(nodes);
+        first
+            .and_then(|x| self.token_trivia(x))
+            .map_or(vec![], |x| x.leading)
+    }
+
+    /// Get the trailing trivia of the last token of the specified node
+    pub fn trailing_trivia<'a, T: Into<RefNodes<'a>>>(&'a self, nodes: T) -> Vec<&'a WhiteSpace> {
+        let (_, last) = token_range(nodes);
+        last.and_then(|x| self.token_trivia(x))
+            .map_or(vec![], |x| x.trailing)
+    }
+
+    /// Get the comments which are synthesis pragmas, like `// synopsys
+    /// translate_off` or `/* synthesis syn_keep = 1 */`, in order. Each is
+    /// attached to the innermost node with tokens both before and after it.
+    pub fn pragma_comments(&self) -> Vec<PragmaComment<'_>> {
+        let root: RefNode = (&self.node).into();
+        let mut ret: Vec<PragmaComment> = Vec::new();
+        // The open nodes, of which the first `seen` have a token so far.
+        let mut stack: Vec<RefNode> = Vec::new();
+        let mut seen = 0;
+        // The indices in `ret` of the comments before the next token, each
+        // with the number of nodes which may still enclose it.
+        let mut pending: Vec<(usize, usize)> = Vec::new();
+        let mut depth = 0;
+        for n in self.into_iter().event() {
+            match n {
+                NodeEvent::Enter(RefNode::WhiteSpace(x)) => {
+                    if let (0, WhiteSpace::Comment(x)) = (depth, x) {
+                        let pragma = self.get_str(&**x).and_then(pragma_directives);
+                        if let Some((prefix, directives)) = pragma {
+                            pending.push((ret.len(), seen));
+                            ret.push(PragmaComment {
+                                comment: x,
+                                prefix,
+                                directives,
+                                node: root.clone(),
+                            });
+                        }
+                    }
+                    depth += 1;
+                }
+                NodeEvent::Leave(RefNode::WhiteSpace(_)) => {
+                    depth -= 1;
+                }
+                NodeEvent::Enter(RefNode::Locate(_)) if depth == 0 => {
+                    for (i, open) in pending.drain(..) {
+                        if open > 0 {
+                            ret[i].node = stack[open - 1].clone();
+                        }
+                    }
+                    seen = stack.len();
+                }
+                NodeEvent::Leave(RefNode::Locate(_)) => (),
+                NodeEvent::Enter(x) if depth == 0 => {
+                    stack.push(x);
+                }
+                NodeEvent::Leave(_) if depth == 0 => {
+                    stack.pop();
+                    seen = seen.min(stack.len());
+                    for (_, open) in &mut pending {
+                        *open = (*open).min(stack.len());
+                    }
+                }
+                _ => (),
+            }
+        }
+        ret
+    }
+
+    /// Dump the whole tree like `dump_node()`.
+    pub fn dump(&self) -> String {
+        self.dump_node(self)
+    }
+
+    /// Dump the specified node for reading, one line per node nested by
+    /// depth, with its kind, the line and column of its first token, its
+    /// byte range and the start of its text. White space is left out.
+    pub fn dump_node<'a, T: Into<RefNodes<'a>>>(&self, nodes: T) -> String {
+        let mut ret = String::new();
+        let mut depth = 0;
+        let mut skip = 0;
+        for n in Iter::new(nodes.into()).event() {
+            match n {
+                NodeEvent::Enter(RefNode::WhiteSpace(_)) => skip += 1,
+                NodeEvent::Leave(RefNode::WhiteSpace(_)) => skip -= 1,
+                NodeEvent::Enter(x) if skip == 0 => {
+                    let kind = match x {
+                        RefNode::Locate(_) => String::from("Token"),
+                        _ => x.to_string(),
+                    };
+                    let line = match token_range(vec![x]) {
+                        (Some(first), Some(last)) => {
+                            let (beg, end) = (first.offset, last.offset + last.len);
+                            let mut excerpt: String =
+                                self.text.text()[beg..end].chars().take(40).collect();
+                            if excerpt.len() < end - beg {
+                                excerpt.push_str("...");
+                            }
+                            format!(
+                                "{} @{}:{} [{}..{}] {:?}",
+                                kind, first.line, first.column, beg, end, excerpt
+                            )
+                        }
+                        _ => kind,
+                    };
+                    ret.push_str(&format!("{}{}\n", "  ".repeat(depth), line));
+                    depth += 1;
+                }
+                NodeEvent::Leave(_) if skip == 0 => depth -= 1,
+                _ => (),
+            }
+        }
+        ret
+    }
+
+    // The trivia of the token at the offset of `locate`, found from the white
+    // space between it and the tokens before and after it, like `trivia()`.
+    fn token_trivia(&self, locate: &Locate) -> Option<TokenTrivia<'_>> {
+        let (path, locate) = token_path((&self.node).into(), locate.offset)?;
+        let (mut trailing, next) = token_gap(&path, true);
+        let (mut leading, prev) = token_gap(&path, false);
+        if next {
+            trailing.truncate(self.trailing_len(&trailing));
+        }
+        if prev {
+            leading.drain(..self.trailing_len(&leading));
+        }
+        Some(TokenTrivia {
+            locate,
+            leading,
+            trailing,
+        })
+    }
+
+    // The length of the trailing trivia of a token among the white space up
+    // to the next token, which ends at the first line break.
+    fn trailing_len(&self, white_space: &[&WhiteSpace]) -> usize {
+        // A one-line comment ends with its newline.
+        let newline = white_space.iter().position(|x| match x {
+            WhiteSpace::Newline(_) => true,
+            WhiteSpace::Comment(x) => self.get_str(&**x).is_some_and(|x| x.ends_with('\n')),
+            _ => false,
+        });
+        newline.map_or(white_space.len(), |x| x + 1)
+    }
+}
+
+// The children of each node on the way down to a token, each with the index
+// of the child on the way.
+type TokenPath<'a> = Vec<(Vec<RefNode<'a>>, usize)>;
+
+// The way from `node` down to the token at `offset`. Only the children
+// before the one on the way are looked into, by their first token, so this
+// doesn't walk the whole tree.
+fn token_path(mut node: RefNode<'_>, offset: usize) -> Option<(TokenPath<'_>, &Locate)> {
+    let mut ret = Vec::new();
+    loop {
+        let children = node.children().0;
+        let mut found = None;
+        for (i, x) in children.iter().enumerate() {
+            if let RefNode::WhiteSpace(_) = x {
+                continue;
+            }
+            match until_token(x.clone(), true, &mut Vec::new()) {
+                Some(x) if x.offset <= offset => found = Some(i),
+                Some(_) => break,
+                None => (),
+            }
+        }
+        let i = found?;
+        node = children[i].clone();
+        ret.push((children, i));
+        if let RefNode::Locate(x) = node {
+            return if x.offset == offset {
+                Some((ret, x))
+            } else {
+                None
+            };
+        }
+    }
+}
+
+// The white space after the token at the end of `path` up to the next token,
+// or before it from the previous token, and whether there is such a token.
+fn token_gap<'a>(path: &TokenPath<'a>, forward: bool) -> (Vec<&'a WhiteSpace>, bool) {
+    let mut ret = Vec::new();
+    let mut found = false;
+    'outer: for (children, i) in path.iter().rev() {
+        let siblings: Vec<_> = if forward {
+            children[i + 1..].to_vec()
+        } else {
+            children[..*i].iter().rev().cloned().collect()
+        };
+        for x in siblings {
+            if until_token(x, forward, &mut ret).is_some() {
+                found = true;
+                break 'outer;
+            }
+        }
+    }
+    if !forward {
+        ret.reverse();
+    }
+    (ret, found)
+}
+
+// Push the white space of `node` onto `ret` in order, or in reverse, up to
+// its first token in that order, which is returned.
+fn until_token<'a>(
+    node: RefNode<'a>,
+    forward: bool,
+    ret: &mut Vec<&'a WhiteSpace>,
+) -> Option<&'a Locate> {
+    let mut stack = vec![node];
+    while let Some(x) = stack.pop() {
+        match x {
+            RefNode::WhiteSpace(x) => ret.push(x),
+            RefNode::Locate(x) => return Some(x),
+            _ => {
+                let mut children = x.children().0;
+                if forward {
+                    children.reverse();
+                }
+                stack.append(&mut children);
+            }
+        }
+    }
+    None
+}
+
+// The line after the newlines of `text` from `from`, with the offset of its
+// start, given the line of `from` and the offset of its start.
+fn count_lines(text: &str, from: usize, mut line: u32, mut line_begin: usize) -> (u32, usize) {
+    for (i, _) in text[from..].match_indices('\n') {
+        line += 1;
+        line_begin = from + i + 1;
+    }
+    (line, line_begin)
+}
+
+/// A token of a `SyntaxTree` with the white space and comments around it.
+#[derive(Clone, Debug)]
+pub struct TokenTrivia<'a> {
+    pub locate: &'a Locate,
+    pub leading: Vec<&'a WhiteSpace>,
+    pub trailing: Vec<&'a WhiteSpace>,
+}
+
+/// A comment of a `SyntaxTree` which is a synthesis pragma.
+#[derive(Clone, Debug)]
+pub struct PragmaComment<'a> {
+    pub comment: &'a Comment,
+    /// The word the comment starts with, like `synopsys` or `synthesis`.
+    pub prefix: &'a str,
+    pub directives: Vec<PragmaDirective<'a>>,
+    /// The innermost node with tokens both before and after the comment.
+    pub node: RefNode<'a>,
+}
+
+/// A directive of a `PragmaComment`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PragmaDirective<'a> {
+    TranslateOff,
+    TranslateOn,
+    FullCase,
+    ParallelCase,
+    /// Any other directive, with the value after `=` if there is one.
+    Attribute(&'a str, Option<&'a str>),
+}
+
+// The words which start the comments taken as synthesis pragmas.
+const PRAGMA_PREFIXES: &[&str] = &["synopsys", "synthesis", "pragma", "cadence"];
+
+// The prefix and directives of the comment `s`, if it is a synthesis pragma.
+fn pragma_directives(s: &str) -> Option<(&str, Vec<PragmaDirective<'_>>)> {
+    let body = match s.strip_prefix("//") {
+        Some(x) => x,
+        None => s.strip_prefix("/*")?.strip_suffix("*/")?,
+    };
+    let mut words = body.split_whitespace();
+    let prefix = words.next()?;
+    if !PRAGMA_PREFIXES.contains(&prefix) {
+        return None;
+    }
+
+    // Split `=` from the words around it, as in `syn_keep=1`.
+    let mut tokens = Vec::new();
+    for word in words {
+        let mut rest = word;
+        while let Some(i) = rest.find('=') {
+            if i > 0 {
+                tokens.push(&rest[..i]);
+            }
+            tokens.push("=");
+            rest = &rest[i + 1..];
+        }
+        if !rest.is_empty() {
+            tokens.push(rest);
+        }
+    }
+
+    let mut directives = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let directive = match tokens[i] {
+            "translate_off" => PragmaDirective::TranslateOff,
+            "translate_on" => PragmaDirective::TranslateOn,
+            "full_case" => PragmaDirective::FullCase,
+            "parallel_case" => PragmaDirective::ParallelCase,
+            "=" => {
+                i += 1;
+                continue;
+            }
+            x if tokens.get(i + 1) == Some(&"=") => {
+                let value = tokens.get(i + 2).copied();
+                i += 2;
+                PragmaDirective::Attribute(x, value)
+            }
+            x => PragmaDirective::Attribute(x, None),
+        };
+        directives.push(directive);
+        i += 1;
+    }
+    Some((prefix, directives))
+}
+
+// The first and last tokens of `nodes`, not counting those within white space.
+fn token_range<'a, T: Into<RefNodes<'a>>>(nodes: T) -> (Option<&'a Locate>, Option<&'a Locate>) {
+    let mut first = None;
+    let mut last = None;
+    let mut depth = 0;
+    for n in Iter::new(nodes.into()).event() {
+        match n {
+            NodeEvent::Enter(RefNode::WhiteSpace(_)) => depth += 1,
+            NodeEvent::Leave(RefNode::WhiteSpace(_)) => depth -= 1,
+            NodeEvent::Enter(RefNode::Locate(x)) if depth == 0 => {
+                first = first.or(Some(x));
+                last = Some(x);
+            }
+            _ => (),
+        }
+    }
+    (first, last)
 }
 
 impl fmt::Display for SyntaxTree {
@@ -171,6 +733,13 @@ impl fmt::Debug for SyntaxTree {
     }
 }
 
+impl<'a> From<&'a SyntaxTree> for RefNodes<'a> {
+    fn from(x: &'a SyntaxTree) -> Self {
+        let ref_node: RefNode = (&x.node).into();
+        RefNodes(vec![ref_node])
+    }
+}
+
 impl<'a> IntoIterator for &'a SyntaxTree {
     type Item = RefNode<'a>;
     type IntoIter = Iter<'a>;
@@ -181,6 +750,11 @@ impl<'a> IntoIterator for &'a SyntaxTree {
     }
 }
 
+/// Preprocess and parse the file at `path`. All of the text must be parsed,
+/// or the `Error::Parse` of the furthest position reached is returned, with
+/// its origin and line. If `allow_incomplete`, parsing instead stops quietly
+/// at the first description which can't be parsed, and the tree holds those
+/// before it.
 pub fn parse_sv<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
     path: T,
     pre_defines: &Defines<V>,
@@ -198,6 +772,10 @@ pub fn parse_sv<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
     parse_sv_pp(text, defines, allow_incomplete)
 }
 
+/// Parse `text`, as returned by `preprocess()` or `Preprocessor`, like
+/// `parse_sv()`. The tree keeps `text`, so that `SyntaxTree::get_origin()`
+/// maps the `Locate` of a token to its file and offset there, also for text
+/// from included files.
 pub fn parse_sv_pp(
     text: PreprocessedText,
     defines: Defines,
@@ -210,41 +788,93 @@ pub fn parse_sv_pp(
         sv_parser(span)
     };
     match result {
-        Ok((_, x)) => Ok((
-            SyntaxTree {
-                node: x.into(),
-                text,
-            },
-            defines,
-        )),
-        Err(x) => {
-            let pos = match x {
-                nom::Err::Incomplete(_) => None,
-                nom::Err::Error(e) => error_position(&e),
-                nom::Err::Failure(e) => error_position(&e),
-            };
-            let origin = if let Some(pos) = pos {
-                if let Some(origin) = text.origin(pos) {
-                    Some((origin.0.clone(), origin.1))
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
-            Err(Error::Parse(origin))
+        Ok((_, x)) => Ok((SyntaxTree::new(x.into(), text), defines)),
+        Err(x) => Err(parse_error(&text, x).1),
+    }
+}
+
+/// Parse like `parse_sv()`, but instead of returning the tree, call `f` with
+/// the events of the header of the source text and then of each description,
+/// as soon as it is parsed. Only one description is kept in memory at a time,
+/// and there are no events for the `SourceText` node itself. Parsing stops at
+/// the first error, after the events of the descriptions before it.
+pub fn parse_sv_events<T, U, V, F>(
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+    f: F,
+) -> Result<Defines, Error>
+where
+    T: AsRef<Path>,
+    U: AsRef<Path>,
+    V: BuildHasher,
+    F: FnMut(&PreprocessedText, NodeEvent),
+{
+    let (text, defines) = preprocess(
+        path,
+        pre_defines,
+        include_paths,
+        false, // strip_comments
+        ignore_include,
+    )?;
+    parse_sv_pp_events(text, defines, f)
+}
+
+/// Parse like `parse_sv_pp()`, but call `f` with events like
+/// `parse_sv_events()`.
+pub fn parse_sv_pp_events<F>(
+    text: PreprocessedText,
+    defines: Defines,
+    mut f: F,
+) -> Result<Defines, Error>
+where
+    F: FnMut(&PreprocessedText, NodeEvent),
+{
+    let span = Span::new_extra(text.text(), SpanInfo::default());
+    let (mut span, header) =
+        source_text_header_parser(span).map_err(|x| parse_error(&text, x).1)?;
+    for x in Iter::new((&header).into()).event() {
+        f(&text, x);
+    }
+    while !span.fragment().is_empty() {
+        let (s, description) = description_parser(span).map_err(|x| parse_error(&text, x).1)?;
+        for x in Iter::new((&description).into()).event() {
+            f(&text, x);
         }
+        span = s;
     }
+    Ok(defines)
 }
 
-pub fn parse_sv_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+/// Parse like `parse_sv()`, but skip statements and items which can't be
+/// parsed instead of failing. Each error is returned as a diagnostic along
+/// with the tree of the rest of the text.
+pub fn parse_sv_recover<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+) -> Result<(SyntaxTree, Defines, Vec<Error>), Error> {
+    let (text, defines) = preprocess(
+        path,
+        pre_defines,
+        include_paths,
+        false, // strip_comments
+        ignore_include,
+    )?;
+    parse_sv_pp_recover(text, defines)
+}
+
+/// Parse like `parse_sv_str()`, but recover from errors like
+/// `parse_sv_recover()`.
+pub fn parse_sv_str_recover<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
     s: &str,
     path: T,
     pre_defines: &Defines<V>,
     include_paths: &[U],
     ignore_include: bool,
-    allow_incomplete: bool,
-) -> Result<(SyntaxTree, Defines), Error> {
+) -> Result<(SyntaxTree, Defines, Vec<Error>), Error> {
     let (text, defines) = preprocess_str(
         s,
         path,
@@ -255,35 +885,55 @@ pub fn parse_sv_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
         0, // resolve_depth
         0, // include_depth
     )?;
-    parse_sv_pp(text, defines, allow_incomplete)
+    parse_sv_pp_recover(text, defines)
 }
 
-pub fn parse_lib<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+/// Parse like `parse_sv_pp()`, but recover from errors like
+/// `parse_sv_recover()`.
+/// The text of each skipped statement or item is blanked in the text of the
+/// returned tree. When an error can't be skipped, such as a missing
+/// `endmodule`, the tree holds the descriptions before it.
+pub fn parse_sv_pp_recover(
+    text: PreprocessedText,
+    defines: Defines,
+) -> Result<(SyntaxTree, Defines, Vec<Error>), Error> {
+    let mut diagnostics = Vec::new();
+    let tree = recover(text, &mut diagnostics)?;
+    Ok((tree, defines, diagnostics))
+}
+
+/// Preprocess and parse like `parse_sv_recover()`, but give all problems as
+/// diagnostics: the warnings of preprocessing, an error for each skipped
+/// statement or item, and the error which stopped preprocessing or parsing,
+/// in which case there is no tree.
+pub fn parse_sv_diagnostics<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
     path: T,
     pre_defines: &Defines<V>,
     include_paths: &[U],
     ignore_include: bool,
-    allow_incomplete: bool,
-) -> Result<(SyntaxTree, Defines), Error> {
-    let (text, defines) = preprocess(
+) -> (Option<(SyntaxTree, Defines)>, Diagnostics) {
+    match preprocess(
         path,
         pre_defines,
         include_paths,
         false, // strip_comments
         ignore_include,
-    )?;
-    parse_lib_pp(text, defines, allow_incomplete)
+    ) {
+        Ok((text, defines)) => parse_sv_pp_diagnostics(text, defines),
+        Err(x) => (None, error_diagnostics(&x)),
+    }
 }
 
-pub fn parse_lib_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+/// Parse like `parse_sv_str()`, but give all problems as diagnostics like
+/// `parse_sv_diagnostics()`.
+pub fn parse_sv_str_diagnostics<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
     s: &str,
     path: T,
     pre_defines: &Defines<V>,
     include_paths: &[U],
     ignore_include: bool,
-    allow_incomplete: bool,
-) -> Result<(SyntaxTree, Defines), Error> {
-    let (text, defines) = preprocess_str(
+) -> (Option<(SyntaxTree, Defines)>, Diagnostics) {
+    match preprocess_str(
         s,
         path,
         pre_defines,
@@ -292,65 +942,761 @@ pub fn parse_lib_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
         false, // strip_comments
         0, // resolve_depth
         0, // include_depth
-    )?;
-    parse_lib_pp(text, defines, allow_incomplete)
+    ) {
+        Ok((text, defines)) => parse_sv_pp_diagnostics(text, defines),
+        Err(x) => (None, error_diagnostics(&x)),
+    }
 }
 
-pub fn parse_lib_pp(
+/// Parse like `parse_sv_pp()`, but give all problems as diagnostics like
+/// `parse_sv_diagnostics()`.
+pub fn parse_sv_pp_diagnostics(
     text: PreprocessedText,
     defines: Defines,
-    allow_incomplete: bool,
-) -> Result<(SyntaxTree, Defines), Error> {
-    let span = Span::new_extra(text.text(), SpanInfo::default());
-    let result = if allow_incomplete {
-        lib_parser_incomplete(span)
-    } else {
-        lib_parser(span)
-    };
-    match result {
-        Ok((_, x)) => Ok((
-            SyntaxTree {
-                node: x.into(),
-                text,
-            },
-            defines,
-        )),
+) -> (Option<(SyntaxTree, Defines)>, Diagnostics) {
+    let mut diagnostics = text.diagnostics();
+    let mut errors = Vec::new();
+    let tree = recover(text, &mut errors);
+    diagnostics.extend(errors.iter().map(Diagnostic::from));
+    match tree {
+        Ok(x) => (Some((x, defines)), diagnostics),
         Err(x) => {
-            let pos = match x {
-                nom::Err::Incomplete(_) => None,
-                nom::Err::Error(e) => error_position(&e),
-                nom::Err::Failure(e) => error_position(&e),
-            };
-            let origin = if let Some(pos) = pos {
-                if let Some(origin) = text.origin(pos) {
-                    Some((origin.0.clone(), origin.1))
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
-            Err(Error::Parse(origin))
+            diagnostics.push(Diagnostic::from(&x));
+            (None, diagnostics)
         }
     }
 }
 
-#[macro_export]
-macro_rules! unwrap_node {
-    ($n:expr, $( $ty:tt ),+) => {{
-        let unwrap = || {
-            for x in $n {
-                match x {
-                    $($crate::RefNode::$ty(x) => return Some($crate::RefNode::$ty(x)),)*
-                    _ => (),
-                }
+fn error_diagnostics(error: &Error) -> Diagnostics {
+    let mut ret = Diagnostics::new();
+    ret.push(Diagnostic::from(error));
+    ret
+}
+
+// Parse `text`, skipping what can't be parsed, and push the error of each
+// skip to `diagnostics`.
+fn recover(mut text: PreprocessedText, diagnostics: &mut Vec<Error>) -> Result<SyntaxTree, Error> {
+    loop {
+        let span = Span::new_extra(text.text(), SpanInfo::default());
+        let (pos, error) = match sv_parser(span) {
+            Ok((_, x)) => {
+                return Ok(SyntaxTree::new(x.into(), text));
             }
-            None
+            Err(x) => parse_error(&text, x),
         };
-        unwrap()
-    }};
+        diagnostics.push(error);
+        match pos.and_then(|x| recover_range(text.text(), x)) {
+            Some((begin, end)) if diagnostics.len() < RECOVER_LIMIT => text.blank(begin, end),
+            _ => break,
+        }
+    }
+
+    let span = Span::new_extra(text.text(), SpanInfo::default());
+    match sv_parser_incomplete(span) {
+        Ok((_, x)) => Ok(SyntaxTree::new(x.into(), text)),
+        Err(x) => Err(parse_error(&text, x).1),
+    }
 }
 
+pub fn parse_sv_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    s: &str,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let (text, defines) = preprocess_str(
+        s,
+        path,
+        pre_defines,
+        include_paths,
+        ignore_include,
+        false, // strip_comments
+        0, // resolve_depth
+        0, // include_depth
+    )?;
+    parse_sv_pp(text, defines, allow_incomplete)
+}
+
+/// Parse `bytes` as the contents of the file `path`, preprocessed by
+/// `preprocessor` as by `Preprocessor::preprocess_bytes()`, so they are
+/// decoded by its `Encoding`. Include files are still read from the files of
+/// the preprocessor.
+pub fn parse_sv_bytes<T: AsRef<Path>>(
+    bytes: &[u8],
+    path: T,
+    preprocessor: &Preprocessor,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let (text, defines) = preprocessor.preprocess_bytes(bytes, path)?;
+    parse_sv_pp(text, defines, allow_incomplete)
+}
+
+/// Parse what `reader` reads to its end like `parse_sv_bytes()`, e.g. from an
+/// archive, a socket or a pipe.
+pub fn parse_sv_reader<R: Read, T: AsRef<Path>>(
+    reader: R,
+    path: T,
+    preprocessor: &Preprocessor,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let (text, defines) = preprocessor.preprocess_reader(reader, path)?;
+    parse_sv_pp(text, defines, allow_incomplete)
+}
+
+/// Parse the file at `path` without preprocessing it, for tools which must
+/// keep the directives as written rather than one expansion of them. Each
+/// directive is kept where white space may appear, as a
+/// `WhiteSpace::CompilerDirective` node, and the branches of `ifdef and
+/// `ifndef are kept as `SourceDescription`s. Macros aren't expanded, so
+/// their usages are only accepted where white space is.
+#[cfg(feature = "fs")]
+pub fn parse_sv_raw<T: AsRef<Path>>(path: T, allow_incomplete: bool) -> Result<SyntaxTree, Error> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).map_err(|x| Error::File {
+        source: x,
+        path: PathBuf::from(path),
+    })?;
+    let s = String::from_utf8(bytes).map_err(|_| Error::ReadUtf8(PathBuf::from(path)))?;
+    parse_sv_raw_str(&s, path, allow_incomplete)
+}
+
+/// Parse `s` like `parse_sv_raw()`, as the text of `path`.
+pub fn parse_sv_raw_str<T: AsRef<Path>>(
+    s: &str,
+    path: T,
+    allow_incomplete: bool,
+) -> Result<SyntaxTree, Error> {
+    let text = PreprocessedText::from_source(s, path);
+    let (tree, _) = parse_sv_pp(text, Defines::default(), allow_incomplete)?;
+    Ok(tree)
+}
+
+// The stack size of the threads of `parse_sv_files()`, as the parser recurses
+// deeply. This is the usual size of the main thread.
+#[cfg(not(target_arch = "wasm32"))]
+const PARSE_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Options for `parse_sv_files()`.
+#[derive(Clone, Debug, Default)]
+pub struct ParseConfig {
+    /// Preprocesses each file, with its pre-defines, include paths and options.
+    pub preprocessor: Preprocessor,
+    pub allow_incomplete: bool,
+    /// The number of threads, or 0 for one per CPU.
+    pub threads: usize,
+}
+
+/// Preprocess and parse each of `paths`, like `parse_sv()`, on a pool of
+/// threads. The results are in the order of `paths`. Each file starts from
+/// the pre-defines of `config`, so macros defined by one file aren't seen by
+/// the others. The threads take the `nest_limit()`, `tolerant_system_tf()`
+/// and `work_limit()` of the calling thread. Where there are no threads, e.g.
+/// on `wasm32`, the files are parsed in turn on the calling thread.
+pub fn parse_sv_files<T: AsRef<Path> + Sync>(
+    paths: &[T],
+    config: &ParseConfig,
+) -> Vec<Result<(SyntaxTree, Defines), Error>> {
+    let limit = nest_limit();
+    let tolerant = tolerant_system_tf();
+    let work = work_limit();
+    let parse = |path: &T| {
+        set_nest_limit(limit);
+        set_tolerant_system_tf(tolerant);
+        set_work_limit(work);
+        let (text, defines) = config.preprocessor.preprocess(path)?;
+        parse_sv_pp(text, defines, config.allow_incomplete)
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(config.threads)
+            .stack_size(PARSE_STACK_SIZE)
+            .build();
+        if let Ok(pool) = pool {
+            return pool.install(|| paths.par_iter().map(parse).collect());
+        }
+    }
+    paths.iter().map(parse).collect()
+}
+
+/// Parse `s`, the text of `tree` after an edit, like `parse_sv_str()`. Only
+/// the descriptions which the edit touches are parsed again, and the others
+/// are taken from `tree` and moved after the edit. The edited range is found
+/// by comparing the preprocessed texts, so edits of macros are followed into
+/// their expansions. Where this isn't possible, the whole text is parsed.
+pub fn reparse_sv_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    tree: SyntaxTree,
+    s: &str,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let (text, defines) = preprocess_str(
+        s,
+        path,
+        pre_defines,
+        include_paths,
+        ignore_include,
+        false, // strip_comments
+        0, // resolve_depth
+        0, // include_depth
+    )?;
+    match reparse(tree, &text) {
+        Some(node) => Ok((SyntaxTree::new(node, text), defines)),
+        None => parse_sv_pp(text, defines, allow_incomplete),
+    }
+}
+
+// The source text of `tree` for `text`, parsing again only the descriptions
+// which differ from those of the text of `tree`.
+fn reparse(tree: SyntaxTree, text: &PreprocessedText) -> Option<AnyNode> {
+    let (old, new) = (tree.text.text(), text.text());
+    let mut source_text = match tree.node {
+        AnyNode::SourceText(x) => x,
+        _ => return None,
+    };
+    // `begin_keywords changes how the text after it is parsed.
+    if old.contains("`begin_keywords") || new.contains("`begin_keywords") {
+        return None;
+    }
+
+    // The edited range is `prefix..old_end` of `old` and `prefix..new_end` of
+    // `new`.
+    let prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(old.len().min(new.len()) - prefix)
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    if old_end == prefix && new_end == prefix {
+        return Some(AnyNode::SourceText(source_text));
+    }
+
+    let descriptions = &mut source_text.nodes.2;
+    let ranges: Vec<_> = descriptions
+        .iter()
+        .map(|x| {
+            let mut locates = x.into_iter().filter_map(|x| match x {
+                RefNode::Locate(x) => Some((x.offset, x.offset + x.len)),
+                _ => None,
+            });
+            let (begin, end) = locates.next().unwrap_or_default();
+            (begin, locates.last().map_or(end, |x| x.1))
+        })
+        .collect();
+    let i = ranges.iter().position(|&(_, end)| end >= prefix)?;
+    let j = ranges.iter().rposition(|&(begin, _)| begin <= old_end)?;
+    if j < i || prefix < ranges[i].0 || old_end > ranges[j].1 {
+        return None;
+    }
+
+    let (begin, end) = (ranges[i].0, ranges[j].1);
+    let moved_end = end - old_end + new_end;
+    let span = Span::new_extra(new, SpanInfo::default()).slice(begin..moved_end);
+    let (_, reparsed) = descriptions_parser(span).ok()?;
+
+    // Move the descriptions after the edit by the change of its length and of
+    // its number of lines, and the tokens on the line where it ends by the
+    // change of the length of its last line.
+    let line_begin = |s: &str, pos: usize| s[..pos].rfind('\n').map_or(0, |x| x + 1);
+    let lines = |s: &str, begin: usize, end: usize| s[begin..end].matches('\n').count();
+    let end_line = descriptions
+        .get(j + 1)
+        .and_then(|x| unwrap_locate!(x))
+        .map(|x| x.line);
+    let line_delta = lines(new, begin, moved_end) as i64 - lines(old, begin, end) as i64;
+    let column_delta =
+        (moved_end - line_begin(new, moved_end)) as i64 - (end - line_begin(old, end)) as i64;
+    for x in &mut descriptions[j + 1..] {
+        x.locates_mut(&mut |x| {
+            if Some(x.line) == end_line {
+                x.column = (x.column as i64 + column_delta) as u32;
+            }
+            x.offset = x.offset - end + moved_end;
+            x.line = (x.line as i64 + line_delta) as u32;
+        });
+    }
+    descriptions.splice(i..=j, reparsed);
+    Some(AnyNode::SourceText(source_text))
+}
+
+/// Parse like `parse_sv()`, but only accept IEEE1364-2005 Verilog.
+/// SystemVerilog keywords, operators and user-defined types are rejected with
+/// `Error::SystemVerilog`, except within `begin_keywords of an IEEE1800
+/// version.
+pub fn parse_verilog<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let (text, defines) = preprocess(
+        path,
+        pre_defines,
+        include_paths,
+        false, // strip_comments
+        ignore_include,
+    )?;
+    parse_verilog_pp(text, defines, allow_incomplete)
+}
+
+pub fn parse_verilog_pp(
+    text: PreprocessedText,
+    defines: Defines,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let span = Span::new_extra(text.text(), SpanInfo::default());
+    let result = if allow_incomplete {
+        verilog_parser_incomplete(span)
+    } else {
+        verilog_parser(span)
+    };
+    match result {
+        Ok((_, x)) => {
+            let tree = SyntaxTree::new(x.into(), text);
+            if let Some((locate, construct)) = find_systemverilog(&tree) {
+                let origin = tree.get_origin(&locate).map(|(x, y)| (x.clone(), y));
+                return Err(Error::SystemVerilog { origin, construct });
+            }
+            Ok((tree, defines))
+        }
+        Err(x) => Err(parse_error(&text, x).1),
+    }
+}
+
+// The first construct of `tree` which IEEE1364-2005 lacks, outside of
+// `begin_keywords of an IEEE1800 version.
+fn find_systemverilog(tree: &SyntaxTree) -> Option<(Locate, String)> {
+    let mut versions = vec![];
+    let mut port_names = vec![];
+    for node in tree {
+        let construct = match node {
+            // The name of a port without a type is taken for a type.
+            RefNode::TfPortItem(x) if x.nodes.4.is_none() => {
+                port_names.extend(unwrap_locate!(&x.nodes.3).map(|x| x.offset));
+                None
+            }
+            RefNode::KeywordsDirective(x) => {
+                let version = tree.get_str(&x.nodes.3).unwrap_or("");
+                versions.push(version.starts_with("1800"));
+                None
+            }
+            RefNode::EndkeywordsDirective(_) => {
+                versions.pop();
+                None
+            }
+            _ if versions.last() == Some(&true) => None,
+            RefNode::Keyword(x) => tree.get_str(&x.nodes.0),
+            RefNode::Symbol(x) => tree.get_str(&x.nodes.0),
+            RefNode::DataTypeType(x) => tree.get_str_trim(x),
+            RefNode::ClassType(x) => tree.get_str_trim(x),
+            RefNode::NetDeclarationNetTypeIdentifier(x) => tree.get_str_trim(&x.nodes.0),
+            _ => None,
+        };
+        let construct = match node {
+            RefNode::Keyword(_) | RefNode::Symbol(_) => {
+                construct.filter(|x| is_systemverilog_only(x))
+            }
+            _ => construct,
+        };
+        if let Some(construct) = construct {
+            let locate = unwrap_locate!(node.clone())?;
+            let is_type = matches!(node, RefNode::DataTypeType(_) | RefNode::ClassType(_));
+            if !(is_type && port_names.contains(&locate.offset)) {
+                return Some((*locate, String::from(construct)));
+            }
+        }
+    }
+    None
+}
+
+pub fn parse_verilog_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    s: &str,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let (text, defines) = preprocess_str(
+        s,
+        path,
+        pre_defines,
+        include_paths,
+        ignore_include,
+        false, // strip_comments
+        0, // resolve_depth
+        0, // include_depth
+    )?;
+    parse_verilog_pp(text, defines, allow_incomplete)
+}
+
+pub fn parse_lib<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let (text, defines) = preprocess(
+        path,
+        pre_defines,
+        include_paths,
+        false, // strip_comments
+        ignore_include,
+    )?;
+    parse_lib_pp(text, defines, allow_incomplete)
+}
+
+pub fn parse_lib_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    s: &str,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let (text, defines) = preprocess_str(
+        s,
+        path,
+        pre_defines,
+        include_paths,
+        ignore_include,
+        false, // strip_comments
+        0, // resolve_depth
+        0, // include_depth
+    )?;
+    parse_lib_pp(text, defines, allow_incomplete)
+}
+
+pub fn parse_lib_pp(
+    text: PreprocessedText,
+    defines: Defines,
+    allow_incomplete: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let span = Span::new_extra(text.text(), SpanInfo::default());
+    let result = if allow_incomplete {
+        lib_parser_incomplete(span)
+    } else {
+        lib_parser(span)
+    };
+    match result {
+        Ok((_, x)) => Ok((SyntaxTree::new(x.into(), text), defines)),
+        Err(x) => Err(parse_error(&text, x).1),
+    }
+}
+
+/// Parse `s` as a single expression, like `parse_sv_str()` parses a whole
+/// source text.
+pub fn parse_expression_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    s: &str,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    parse_snippet_str(
+        s,
+        path,
+        pre_defines,
+        include_paths,
+        ignore_include,
+        expression_parser,
+    )
+}
+
+/// Parse `s` as a single statement, like `parse_sv_str()` parses a whole
+/// source text.
+pub fn parse_statement_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    s: &str,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    parse_snippet_str(
+        s,
+        path,
+        pre_defines,
+        include_paths,
+        ignore_include,
+        statement_parser,
+    )
+}
+
+/// Parse `s` as a single module item, like `parse_sv_str()` parses a whole
+/// source text.
+pub fn parse_module_item_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    s: &str,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    parse_snippet_str(
+        s,
+        path,
+        pre_defines,
+        include_paths,
+        ignore_include,
+        module_item_parser,
+    )
+}
+
+/// Parse `s` as a single package item, like `parse_sv_str()` parses a whole
+/// source text.
+pub fn parse_package_item_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher>(
+    s: &str,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+) -> Result<(SyntaxTree, Defines), Error> {
+    parse_snippet_str(
+        s,
+        path,
+        pre_defines,
+        include_paths,
+        ignore_include,
+        package_item_parser,
+    )
+}
+
+fn parse_snippet_str<T: AsRef<Path>, U: AsRef<Path>, V: BuildHasher, W: Into<AnyNode>>(
+    s: &str,
+    path: T,
+    pre_defines: &Defines<V>,
+    include_paths: &[U],
+    ignore_include: bool,
+    parser: fn(Span) -> IResult<Span, W>,
+) -> Result<(SyntaxTree, Defines), Error> {
+    let (text, defines) = preprocess_str(
+        s,
+        path,
+        pre_defines,
+        include_paths,
+        ignore_include,
+        false, // strip_comments
+        0, // resolve_depth
+        0, // include_depth
+    )?;
+    let span = Span::new_extra(text.text(), SpanInfo::default());
+    match parser(span) {
+        Ok((_, x)) => Ok((SyntaxTree::new(x.into(), text), defines)),
+        Err(x) => Err(parse_error(&text, x).1),
+    }
+}
+
+// The number of errors after which `parse_sv_pp_recover()` gives up.
+const RECOVER_LIMIT: usize = 100;
+
+// Keywords which end a statement or item, and where recovery resumes.
+const RECOVER_END_KEYWORDS: &[&str] = &[
+    "end",
+    "endcase",
+    "endchecker",
+    "endclass",
+    "endclocking",
+    "endconfig",
+    "endfunction",
+    "endgenerate",
+    "endgroup",
+    "endinterface",
+    "endmodule",
+    "endpackage",
+    "endprimitive",
+    "endprogram",
+    "endproperty",
+    "endsequence",
+    "endspecify",
+    "endtable",
+    "endtask",
+    "join",
+    "join_any",
+    "join_none",
+];
+
+// The range of `s` to blank to recover from an error at `pos`: the statement
+// or item around `pos`, from the previous `;`, `begin` or end keyword to the
+// next `;` or end keyword. If that is empty, the stray token before `pos`,
+// which is where the parser stops after it, is blanked.
+fn recover_range(s: &str, pos: usize) -> Option<(usize, usize)> {
+    let tokens = recover_tokens(s);
+    let i = tokens.iter().position(|&(_, end)| end > pos)?;
+    let token = |j: usize| &s[tokens[j].0..tokens[j].1];
+    let is_end = |j: usize| RECOVER_END_KEYWORDS.contains(&token(j));
+
+    let begin = (0..i)
+        .rev()
+        .find(|&j| token(j) == ";" || token(j) == "begin" || is_end(j))
+        .map_or(0, |j| tokens[j].1);
+    let end = (i..tokens.len())
+        .find(|&j| token(j) == ";" || is_end(j))
+        .map_or(
+            s.len(),
+            |j| if is_end(j) { tokens[j].0 } else { tokens[j].1 },
+        );
+
+    if tokens.iter().any(|&(x, y)| begin <= x && y <= end) {
+        Some((begin, end))
+    } else {
+        let j = tokens.iter().rposition(|&(x, _)| x < pos).unwrap_or(i);
+        Some(tokens[j])
+    }
+}
+
+// The ranges of the tokens of `s`, skipping comments. Only words, string
+// literals and single characters are distinguished.
+fn recover_tokens(s: &str) -> Vec<(usize, usize)> {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+    let mut ret = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let rest = &s[i..];
+        let comment = rest.starts_with("//") || rest.starts_with("/*");
+        let len = if c.is_whitespace() {
+            continue;
+        } else if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(x) = rest.strip_prefix("/*") {
+            x.find("*/").map_or(rest.len(), |x| x + 4)
+        } else if c == '"' {
+            let mut escaped = false;
+            rest[1..]
+                .find(|c| {
+                    let found = !escaped && c == '"';
+                    escaped = !escaped && c == '\\';
+                    found
+                })
+                .map_or(rest.len(), |x| x + 2)
+        } else if c == '\\' {
+            rest.find(char::is_whitespace).unwrap_or(rest.len())
+        } else if is_word(c) {
+            rest.find(|c| !is_word(c)).unwrap_or(rest.len())
+        } else {
+            c.len_utf8()
+        };
+        if !comment {
+            ret.push((i, i + len));
+        }
+        while chars.peek().is_some_and(|&(j, _)| j < i + len) {
+            chars.next();
+        }
+    }
+    ret
+}
+
+// The furthest position reached by a failed parse of `text`, and the error
+// there.
+fn parse_error(
+    text: &PreprocessedText,
+    e: nom::Err<GreedyError<Span, ErrorKind>>,
+) -> (Option<usize>, Error) {
+    if let nom::Err::Failure(e) = &e {
+        let nest = e
+            .errors
+            .iter()
+            .find(|(_, x)| matches!(x, GreedyErrorKind::Nom(ErrorKind::TooLarge)));
+        if let Some((s, _)) = nest {
+            let pos = s.location_offset();
+            let origin = text.origin(pos).map(|(x, y)| (x.clone(), y));
+            return (Some(pos), Error::NestTooDeep { origin });
+        }
+        let work = e
+            .errors
+            .iter()
+            .find(|(_, x)| matches!(x, GreedyErrorKind::Nom(ErrorKind::Count)));
+        if let Some((s, _)) = work {
+            let pos = s.location_offset();
+            let origin = text.origin(pos).map(|(x, y)| (x.clone(), y));
+            return (Some(pos), Error::TooComplex { origin });
+        }
+    }
+
+    let (pos, rules) = match e {
+        nom::Err::Incomplete(_) => (None, vec![]),
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            let mut rules: Vec<String> = vec![];
+            for (_, kind) in &e.errors {
+                if let GreedyErrorKind::Context(x) = kind {
+                    if rules.last().map(|y| y.as_str()) != Some(*x) {
+                        rules.push(String::from(*x));
+                    }
+                }
+            }
+            (error_position(&e), rules)
+        }
+    };
+
+    // The furthest symbol or keyword which failed may be beyond the position
+    // of the error, which is only that of the deepest alternative.
+    let (pos, expected) = match (pos, expected()) {
+        (Some(pos), Some((x, expected))) if x >= pos => (Some(x), expected),
+        (pos, _) => (pos, vec![]),
+    };
+
+    // The preprocessor rejects most unclosed string literals and block
+    // comments, but not strings which a later quote closes on another line.
+    let unterminated =
+        find_unterminated(text.text()).filter(|x| pos.is_none_or(|pos| x.locate.offset <= pos));
+    if let Some(x) = unterminated {
+        let origin = text.origin(x.locate.offset).map(|(x, y)| (x.clone(), y));
+        let error = match x.kind {
+            TokenKind::String => Error::UnterminatedString { origin },
+            _ => Error::UnterminatedComment { origin },
+        };
+        return (Some(x.locate.offset), error);
+    }
+
+    let origin = pos.and_then(|pos| text.origin(pos).map(|(x, y)| (x.clone(), y)));
+    let line = pos.map(|pos| {
+        let s = text.text();
+        let begin = s[..pos].rfind('\n').map_or(0, |x| x + 1);
+        let end = s[pos..].find('\n').map_or(s.len(), |x| pos + x);
+        Box::from(s[begin..end].trim_end_matches('\r'))
+    });
+
+    let error = Error::Parse {
+        origin,
+        line,
+        rules: rules.into_boxed_slice(),
+        expected: expected.into_boxed_slice(),
+    };
+    (pos, error)
+}
+
+/// Get the first node of any of the given types in `$n`, itself included, in
+/// the order of its iterator, e.g. `unwrap_node!(x, ModuleIdentifier,
+/// InterfaceIdentifier)`. The result is an `Option<RefNode>` of that variant.
+#[macro_export]
+macro_rules! unwrap_node {
+    ($n:expr, $( $ty:tt ),+) => {{
+        let unwrap = || {
+            for x in $n {
+                match x {
+                    $($crate::RefNode::$ty(x) => return Some($crate::RefNode::$ty(x)),)*
+                    _ => (),
+                }
+            }
+            None
+        };
+        unwrap()
+    }};
+}
+
+/// Get the first `Locate` in `$n`, which is of its first token or white space.
 #[macro_export]
 macro_rules! unwrap_locate {
     ($n:expr) => {{
@@ -361,65 +1707,2222 @@ macro_rules! unwrap_locate {
                     _ => (),
                 }
             }
-            None
+            None
+        };
+        unwrap()
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test() {
+        let src = "/* comment */";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let comment = unwrap_node!(&syntax_tree, Comment);
+        assert!(comment.is_some());
+    }
+
+    #[test]
+    fn test_continuous() {
+        let src = r##"`ifdef A
+`endif
+
+module FetchStage();
+    always_comb begin
+        for (int j = i + 1; j < FETCH_WIDTH; j++) begin
+        end
+        break;
+    end
+
+    AddrPath fetchAddrOut;
+endmodule"##;
+
+        let src_broken = r##"`ifdef A
+endif
+
+module FetchStage();
+    always_comb begin
+        for (int j = i + 1; j < FETCH_WIDTH; j++) begin
+        end
+        break;
+    end
+
+    AddrPath fetchAddrOut;
+endmodule"##;
+
+        let path = PathBuf::from("");
+        let defines = HashMap::new();
+        let ret = parse_sv_str(src, &path, &defines, &[""], false, false);
+        assert!(ret.is_ok());
+        let ret = parse_sv_str(src_broken, &path, &defines, &[""], false, false);
+        assert!(ret.is_err());
+        let ret = parse_sv_str(src, &path, &defines, &[""], false, false);
+        assert!(ret.is_ok());
+        let ret = parse_sv_str(src_broken, &path, &defines, &[""], false, false);
+        assert!(ret.is_err());
+        let ret = parse_sv_str(src, &path, &defines, &[""], false, false);
+        assert!(ret.is_ok());
+    }
+
+    #[test]
+    fn test_line_column() {
+        let src = "module A;\n  wire a;\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let net = unwrap_node!(&syntax_tree, NetDeclaration).unwrap();
+        let locate = unwrap_locate!(net).unwrap();
+        assert_eq!((locate.line, locate.column), (2, 3));
+
+        let index = LineIndex::new(src);
+        assert_eq!(index.line_column(locate.offset), (2, 3));
+        assert_eq!(index.line_column(0), (1, 1));
+        assert_eq!(index.line_column(src.len()), (4, 1));
+    }
+
+    #[test]
+    fn test_parse_sv_pp_origin() {
+        let src = "module A(a, b, c);\n`include \"included.svh\"\nendmodule\n";
+        let include_path = PathBuf::from("../sv-parser-pp/testcases");
+        let (text, defines) = preprocess_str(
+            src,
+            "a.sv",
+            &HashMap::new(),
+            &[&include_path],
+            false,
+            false,
+            0,
+            0,
+        )
+        .unwrap();
+        let (syntax_tree, _) = parse_sv_pp(text, defines, false).unwrap();
+
+        let mut origins = vec![];
+        for node in &syntax_tree {
+            if let RefNode::Keyword(x) = node {
+                let locate = &x.nodes.0;
+                let origin = syntax_tree.get_origin(locate).unwrap();
+                origins.push((syntax_tree.get_str(locate).unwrap(), origin));
+            }
+        }
+        let included = include_path.join("included.svh");
+        assert_eq!(
+            origins,
+            [
+                ("module", (&PathBuf::from("a.sv"), 0)),
+                ("output", (&included, 0)),
+                ("input", (&included, 10)),
+                ("and", (&included, 71)),
+                ("endmodule", (&PathBuf::from("a.sv"), 43)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let src = "module A;\n  wire a\nendmodule\n";
+        let ret = parse_sv_str(
+            src,
+            PathBuf::from("a.sv"),
+            &HashMap::new(),
+            &[""],
+            false,
+            false,
+        );
+        match ret {
+            Err(Error::Parse {
+                origin,
+                line,
+                rules,
+                expected,
+            }) => {
+                assert_eq!(origin, Some((PathBuf::from("a.sv"), 19)));
+                assert_eq!(line.as_deref(), Some("endmodule"));
+                assert!(rules.contains(&String::from("module_declaration")));
+                assert!(expected.contains(&String::from(";")));
+            }
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[test]
+    fn test_error_location() {
+        let src = "module A;\n  wire a\nendmodule\n";
+        let dir = std::env::temp_dir().join("sv-parser-test_error_location");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.sv");
+        std::fs::write(&path, src).unwrap();
+
+        let error = parse_sv(&path, &HashMap::new(), &[""], false, false).unwrap_err();
+        assert_eq!(error.origin(), Some((path.as_path(), 19)));
+        let location = error.location().unwrap();
+        assert_eq!((location.line, location.column), (3, 1));
+        assert_eq!(location.source_line, "endmodule");
+        assert_eq!(location, Location::new(&path, 19, src));
+        assert_eq!(
+            Location::new("b.sv", 13, src).to_string(),
+            "b.sv:2:4\n  |\n2 |   wire a\n  |    ^"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "codespan")]
+    #[test]
+    fn test_render() {
+        let dir = std::env::temp_dir().join("sv-parser-test_render");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.sv");
+        std::fs::write(&path, "module A;\n  wire a\nendmodule\n").unwrap();
+
+        let error = parse_sv(&path, &HashMap::new(), &[""], false, false).unwrap_err();
+        let text = error.render();
+        assert!(text.starts_with("error[parse]: Parse error, expected"));
+        assert!(text.contains("a.sv:3:1"));
+        assert!(text.contains("endmodule"));
+        assert!(text.contains(&"^".repeat(9)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_error_includes() {
+        let dir = std::env::temp_dir().join("sv-parser-test_error_includes");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("top.sv");
+        std::fs::write(&path, "module A;\n`include \"a.svh\"\nendmodule\n").unwrap();
+        std::fs::write(dir.join("a.svh"), "wire a;\n\n`include \"b.svh\"\n").unwrap();
+        std::fs::write(dir.join("b.svh"), "`UNDEFINED\n").unwrap();
+
+        let error = parse_sv(&path, &HashMap::new(), &[&dir], false, false).unwrap_err();
+        let includes: Vec<_> = error
+            .includes()
+            .into_iter()
+            .map(|(path, line)| (path.file_name().unwrap().to_str().unwrap(), line))
+            .collect();
+        assert_eq!(includes, vec![("a.svh", 3), ("top.sv", 2)]);
+        assert!(error.path().unwrap().ends_with("b.svh"));
+
+        let diagnostic = Diagnostic::from(&error);
+        assert_eq!(diagnostic.code, "define-not-found");
+        assert_eq!(diagnostic.notes.len(), 2);
+        assert!(diagnostic.notes[0].starts_with("included from "));
+        assert!(diagnostic.notes[0].ends_with("a.svh:3"));
+        assert!(diagnostic.notes[1].ends_with("top.sv:2"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_trailing_input() {
+        let src = "module A; endmodule\nendmodule\n";
+        let parse = |allow_incomplete| {
+            parse_sv_str(
+                src,
+                PathBuf::from("a.sv"),
+                &HashMap::new(),
+                &[""],
+                false,
+                allow_incomplete,
+            )
+        };
+        match parse(false) {
+            Err(Error::Parse { origin, line, .. }) => {
+                // The furthest position reached is past the trailing keyword.
+                assert_eq!(origin, Some((PathBuf::from("a.sv"), 29)));
+                assert_eq!(line.as_deref(), Some("endmodule"));
+            }
+            _ => panic!("unexpected result"),
+        }
+        assert!(parse(true).is_ok());
+    }
+
+    #[test]
+    fn test_parse_sv_raw() {
+        let src = r##"`include "b.svh"
+`define W 8
+module A;
+`ifdef X
+  wire [`W-1:0] a;
+`else
+  wire b;
+`endif
+endmodule
+"##;
+        let syntax_tree = parse_sv_raw_str(src, PathBuf::from("a.sv"), false).unwrap();
+
+        let mut directives = vec![];
+        for node in &syntax_tree {
+            let directive = match node {
+                RefNode::IncludeCompilerDirective(x) => syntax_tree.get_str_trim(x),
+                RefNode::TextMacroDefinition(x) => syntax_tree.get_str_trim(x),
+                RefNode::IfdefDirective(x) => syntax_tree.get_str_trim(x),
+                RefNode::NetDeclaration(_) => panic!("unexpected net declaration"),
+                _ => None,
+            };
+            directives.extend(directive);
+        }
+        assert_eq!(directives.len(), 3);
+        assert_eq!(directives[0], "`include \"b.svh\"");
+        assert_eq!(directives[1], "`define W 8");
+        assert!(directives[2].starts_with("`ifdef X"));
+        assert!(directives[2].contains("wire [`W-1:0] a;"));
+        assert!(directives[2].ends_with("`endif"));
+
+        let src = "module A;\n  assign a = `W;\nendmodule\n";
+        assert!(parse_sv_raw_str(src, PathBuf::from("a.sv"), false).is_err());
+    }
+
+    #[test]
+    fn test_unterminated() {
+        let parse = |src| {
+            parse_sv_str(
+                src,
+                PathBuf::from("a.sv"),
+                &HashMap::new(),
+                &[""],
+                false,
+                false,
+            )
+        };
+
+        // The preprocessor pairs the first quote with the second one.
+        let src = "module A;\n  initial $display(\"a);\n  initial $display(\"b);\nendmodule\n";
+        match parse(src) {
+            Err(Error::UnterminatedString { origin }) => {
+                assert_eq!(origin, Some((PathBuf::from("a.sv"), 29)));
+            }
+            _ => panic!("unexpected result"),
+        }
+
+        let src = "module A;\n/* c\nendmodule\n";
+        match parse(src) {
+            Err(Error::UnterminatedComment { origin }) => {
+                assert_eq!(origin, Some((PathBuf::from("a.sv"), 10)));
+            }
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[test]
+    fn test_nest_too_deep() {
+        let src = |n| {
+            format!(
+                "module A;\n  assign a = {}b{};\nendmodule\n",
+                "(".repeat(n),
+                ")".repeat(n)
+            )
+        };
+
+        set_nest_limit(8);
+        let ret = parse_sv_str(
+            &src(9),
+            PathBuf::from("a.sv"),
+            &HashMap::new(),
+            &[""],
+            false,
+            false,
+        );
+        match ret {
+            Err(Error::NestTooDeep { origin }) => {
+                assert_eq!(origin, Some((PathBuf::from("a.sv"), 32)));
+            }
+            _ => panic!("unexpected result"),
+        }
+
+        let ret = parse_sv_str(
+            &src(8),
+            PathBuf::from("a.sv"),
+            &HashMap::new(),
+            &[""],
+            false,
+            false,
+        );
+        assert!(ret.is_ok());
+        set_nest_limit(DEFAULT_NEST_LIMIT);
+    }
+
+    #[test]
+    fn test_work_limit() {
+        let src = "module A;\n  assign a = b + c;\nendmodule\n";
+        let parse = || {
+            parse_sv_str(
+                src,
+                PathBuf::from("a.sv"),
+                &HashMap::new(),
+                &[""],
+                false,
+                false,
+            )
+        };
+
+        set_work_limit(Some(10));
+        let ret = parse();
+        set_work_limit(None);
+        match ret {
+            Err(Error::TooComplex { origin }) => assert!(origin.is_some()),
+            _ => panic!("unexpected result"),
+        }
+
+        set_work_limit(Some(100_000));
+        let ret = parse();
+        set_work_limit(None);
+        assert!(ret.is_ok());
+    }
+
+    #[test]
+    fn test_tolerant_system_tf() {
+        let src = "module A;\n  initial $vendor(int a, (b c), \")\");\nendmodule\n";
+        let parse = || {
+            parse_sv_str(
+                src,
+                PathBuf::from("a.sv"),
+                &HashMap::new(),
+                &[""],
+                false,
+                false,
+            )
+        };
+
+        assert!(parse().is_err());
+
+        set_tolerant_system_tf(true);
+        let ret = parse();
+        set_tolerant_system_tf(false);
+        let (syntax_tree, _) = ret.unwrap();
+        let mut tokens = vec![];
+        for node in &syntax_tree {
+            if let RefNode::SystemTfArgTokenText(x) = node {
+                tokens.push(syntax_tree.get_str(&x.nodes.0).unwrap());
+            }
+        }
+        assert_eq!(tokens, ["int", "a", ",", "b", "c", ","]);
+        let calls = (&syntax_tree)
+            .into_iter()
+            .filter(|x| matches!(x, RefNode::SystemTfCallArgTokens(_)))
+            .count();
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_trivia() {
+        let src = "// a\nmodule A; // b\n  // c\n  wire x; /* d */\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let comments = |x: Vec<&WhiteSpace>| -> Vec<String> {
+            x.into_iter()
+                .filter_map(|x| match x {
+                    // A one-line comment ends with its newline.
+                    WhiteSpace::Comment(x) => syntax_tree
+                        .get_str(&**x)
+                        .map(|x| String::from(x.trim_end())),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let trivia = syntax_tree.trivia();
+        assert_eq!(trivia.len(), 7);
+        assert_eq!(comments(trivia[0].leading.clone()), vec!["// a"]);
+        assert_eq!(comments(trivia[2].trailing.clone()), vec!["// b"]);
+        assert_eq!(comments(trivia[3].leading.clone()), vec!["// c"]);
+        assert_eq!(comments(trivia[5].trailing.clone()), vec!["/* d */"]);
+
+        // A token alone has the same trivia as in the whole tree.
+        for x in &trivia {
+            assert_eq!(syntax_tree.leading_trivia(x.locate), x.leading);
+            assert_eq!(syntax_tree.trailing_trivia(x.locate), x.trailing);
+        }
+
+        if let Some(RefNode::NetDeclaration(x)) = unwrap_node!(&syntax_tree, NetDeclaration) {
+            assert_eq!(comments(syntax_tree.leading_trivia(x)), vec!["// c"]);
+            assert_eq!(comments(syntax_tree.trailing_trivia(x)), vec!["/* d */"]);
+        } else {
+            panic!("net declaration not found");
+        }
+    }
+
+    #[test]
+    fn test_pragma_comments() {
+        let src = r##"module A;
+  // synopsys translate_off
+  initial $display("sim");
+  // synopsys translate_on
+  always_comb begin
+    case (sel) // synopsys full_case parallel_case
+      1'b0: a = b;
+      default: a = c;
+    endcase
+  end
+  wire w /* synthesis syn_keep=1 */;
+  // not a pragma
+endmodule
+"##;
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let pragmas = syntax_tree.pragma_comments();
+        let node = |i: usize| {
+            syntax_tree
+                .get_str_trim(vec![pragmas[i].node.clone()])
+                .unwrap()
+        };
+        assert_eq!(pragmas.len(), 4);
+
+        assert_eq!(pragmas[0].prefix, "synopsys");
+        assert_eq!(pragmas[0].directives, [PragmaDirective::TranslateOff]);
+        assert!(matches!(pragmas[0].node, RefNode::ModuleDeclarationAnsi(_)));
+        assert_eq!(pragmas[1].directives, [PragmaDirective::TranslateOn]);
+
+        assert_eq!(
+            pragmas[2].directives,
+            [PragmaDirective::FullCase, PragmaDirective::ParallelCase]
+        );
+        assert!(matches!(pragmas[2].node, RefNode::CaseStatementNormal(_)));
+        assert!(node(2).starts_with("case (sel)"));
+
+        assert_eq!(pragmas[3].prefix, "synthesis");
+        assert_eq!(
+            pragmas[3].directives,
+            [PragmaDirective::Attribute("syn_keep", Some("1"))]
+        );
+        assert_eq!(node(3), "wire w /* synthesis syn_keep=1 */;");
+    }
+
+    #[test]
+    fn test_parse_snippets() {
+        let (syntax_tree, _) = parse_expression_str(
+            " a + b * 2",
+            PathBuf::from(""),
+            &HashMap::new(),
+            &[""],
+            false,
+        )
+        .unwrap();
+        match unwrap_node!(&syntax_tree, Expression) {
+            Some(RefNode::Expression(x)) => {
+                assert_eq!(syntax_tree.get_str(x), Some("a + b * 2"));
+            }
+            _ => panic!("expression not found"),
+        }
+
+        let (syntax_tree, _) =
+            parse_statement_str("x = y;\n", PathBuf::from(""), &HashMap::new(), &[""], false)
+                .unwrap();
+        assert!(unwrap_node!(&syntax_tree, Statement).is_some());
+
+        let (syntax_tree, _) =
+            parse_module_item_str("wire w;", PathBuf::from(""), &HashMap::new(), &[""], false)
+                .unwrap();
+        assert!(unwrap_node!(&syntax_tree, NetDeclaration).is_some());
+
+        let src = "function int f();\n  return 0;\nendfunction\n";
+        let (syntax_tree, _) =
+            parse_package_item_str(src, PathBuf::from(""), &HashMap::new(), &[""], false).unwrap();
+        assert!(unwrap_node!(&syntax_tree, FunctionDeclaration).is_some());
+
+        // The whole text must be consumed.
+        let ret = parse_module_item_str(
+            "wire w; wire",
+            PathBuf::from(""),
+            &HashMap::new(),
+            &[""],
+            false,
+        );
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn test_parse_sv_events() {
+        let src = r##"// header
+timeunit 1ns;
+module A;
+  wire a;
+endmodule
+`begin_keywords "1364-2005"
+module B;
+  wire logic;
+endmodule
+`end_keywords
+"##;
+        let (text, defines) = preprocess_str(
+            src,
+            PathBuf::from(""),
+            &HashMap::new(),
+            &[""],
+            false,
+            false,
+            0,
+            0,
+        )
+        .unwrap();
+        let mut events = vec![];
+        parse_sv_pp_events(text, defines, |_, x| events.push(format!("{:?}", x))).unwrap();
+
+        // The events are those of the whole tree, without its root.
+        let (tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let expected: Vec<_> = (&tree)
+            .into_iter()
+            .event()
+            .filter(|x| {
+                !matches!(
+                    x,
+                    NodeEvent::Enter(RefNode::SourceText(_))
+                        | NodeEvent::Leave(RefNode::SourceText(_))
+                )
+            })
+            .map(|x| format!("{:?}", x))
+            .collect();
+        assert_eq!(events, expected);
+
+        // The descriptions before an error are still seen.
+        let src = "module A;\nendmodule\nmodule B;\n";
+        let (text, defines) = preprocess_str(
+            src,
+            PathBuf::from(""),
+            &HashMap::new(),
+            &[""],
+            false,
+            false,
+            0,
+            0,
+        )
+        .unwrap();
+        let mut modules = vec![];
+        let ret = parse_sv_pp_events(text, defines, |text, x| {
+            if let NodeEvent::Enter(RefNode::ModuleIdentifier(x)) = x {
+                modules.push(String::from(unwrap_locate!(x).unwrap().str(text.text())));
+            }
+        });
+        assert!(matches!(ret, Err(Error::Parse { .. })));
+        assert_eq!(modules, vec!["A"]);
+    }
+
+    #[test]
+    fn test_parse_sv_files() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testcases");
+        let paths = [
+            dir.join("test1.sv"),
+            dir.join("missing.sv"),
+            dir.join("test2.sv"),
+        ];
+        let config = ParseConfig {
+            threads: 2,
+            ..ParseConfig::default()
+        };
+        let results = parse_sv_files(&paths, &config);
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        for i in [0, 2] {
+            let (tree, _) = results[i].as_ref().unwrap();
+            let (expected, _) =
+                parse_sv(&paths[i], &HashMap::new(), &[""], false, false).unwrap();
+            assert_eq!(tree.node, expected.node);
+        }
+    }
+
+    #[test]
+    fn test_syntax_tree_owned() {
+        let src = String::from("module A;\n  wire a;\nendmodule\n");
+        let (syntax_tree, _) = parse_sv_str(
+            &src,
+            PathBuf::from(""),
+            &HashMap::new(),
+            &[""],
+            false,
+            false,
+        )
+        .unwrap();
+        drop(src);
+
+        let handle = std::thread::spawn(move || match unwrap_node!(&syntax_tree, NetDeclaration) {
+            Some(RefNode::NetDeclaration(x)) => syntax_tree.get_str_trim(x).map(String::from),
+            _ => None,
+        });
+        assert_eq!(handle.join().unwrap().as_deref(), Some("wire a;"));
+    }
+
+    #[test]
+    fn test_imports() {
+        let srcs = [
+            "package p;\n  parameter W = 8;\n  typedef enum {A, B} e_t;\n\
+             \x20 function int f(); return 0; endfunction\nendpackage\n",
+            "import p::*;\nmodule m;\n  import p::W;\n  import q::*;\n  import p::X;\nendmodule\n",
+        ];
+        let trees: Vec<_> = srcs
+            .iter()
+            .map(|x| {
+                parse_sv_str(x, PathBuf::from(""), &HashMap::new(), &[""], false, false)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let imports = Imports::new(&trees);
+
+        assert_eq!(imports.packages.len(), 1);
+        let members: Vec<_> = imports.packages[0]
+            .members
+            .iter()
+            .map(|x| x.name.as_str())
+            .collect();
+        assert_eq!(members, vec!["W", "A", "B", "e_t", "f"]);
+        assert_eq!(
+            imports.packages[0].members[0].kind,
+            NodeKind::ParameterIdentifier
+        );
+
+        let items: Vec<_> = imports
+            .imports
+            .iter()
+            .map(|x| {
+                (
+                    x.scope.as_deref(),
+                    x.package_name.as_str(),
+                    x.package,
+                    x.item.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                (None, "p", Some(0), None),
+                (Some("m"), "p", Some(0), Some("W")),
+                (Some("m"), "q", None, None),
+                (Some("m"), "p", Some(0), Some("X")),
+            ]
+        );
+        assert_eq!(imports.imports[0].locate.offset, 7);
+        assert_eq!(imports.members(&imports.imports[0]).len(), 5);
+        let w = imports.members(&imports.imports[1]);
+        assert_eq!((w.len(), w[0].name.as_str()), (1, "W"));
+
+        let unresolved: Vec<_> = imports
+            .unresolved()
+            .iter()
+            .map(|x| (x.package_name.as_str(), x.item.as_deref()))
+            .collect();
+        assert_eq!(unresolved, vec![("q", None), ("p", Some("X"))]);
+    }
+
+    #[test]
+    fn test_tags() {
+        let src = r##"package pkg;
+  typedef logic [7:0] byte_t;
+  parameter int W = 8;
+endpackage
+module top #(parameter N = 1) (input a);
+  localparam M = 2;
+  function int f(int x); return x; endfunction
+  task t; endtask
+endmodule
+class C;
+endclass
+"##;
+        let dir = std::env::temp_dir().join("sv-parser-test_tags");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.sv");
+        std::fs::write(&path, src).unwrap();
+
+        let (syntax_tree, _) = parse_sv(&path, &HashMap::new(), &[""], false, false).unwrap();
+        let tags = Tags::new(&[syntax_tree]);
+        let found: Vec<_> = tags
+            .tags
+            .iter()
+            .map(|x| {
+                let scope = x.scope.as_ref().map(|(_, y)| y.as_str());
+                (x.name.as_str(), x.kind, scope, x.location.line)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("pkg", TagKind::Package, None, 1),
+                ("byte_t", TagKind::Typedef, Some("pkg"), 2),
+                ("W", TagKind::Parameter, Some("pkg"), 3),
+                ("top", TagKind::Module, None, 5),
+                ("N", TagKind::Parameter, Some("top"), 5),
+                ("M", TagKind::Parameter, Some("top"), 6),
+                ("f", TagKind::Function, Some("top"), 7),
+                ("t", TagKind::Task, Some("top"), 8),
+                ("C", TagKind::Class, None, 10),
+            ]
+        );
+
+        let ctags = tags.to_ctags();
+        let file = path.display();
+        assert!(ctags.starts_with("!_TAG_FILE_FORMAT"));
+        assert!(ctags.contains(&format!("C\t{}\t10;\"\tC\n", file)));
+        assert!(ctags.contains(&format!("N\t{}\t5;\"\tc\tmodule:top\n", file)));
+        assert!(ctags.find("C\t").unwrap() < ctags.find("N\t").unwrap());
+
+        let etags = tags.to_etags();
+        assert!(etags.starts_with(&format!("\u{c}\n{},", file)));
+        assert!(etags.contains("module top\u{7f}top\u{1}5,77\n"));
+        assert!(etags.contains("  localparam M\u{7f}M\u{1}6,"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_document_session() {
+        let src = "module A;\n  wire a;\n  assign a = 1;\nendmodule\n";
+        let mut session = DocumentSession::new("test.sv", src, Preprocessor::new(), false);
+        assert!(session.error().is_none());
+        let definition = session.definition(Position {
+            line: 2,
+            character: 9,
+        });
+        assert_eq!(
+            definition,
+            Some(Definition {
+                path: PathBuf::from("test.sv"),
+                begin: 17,
+                end: 18,
+            })
+        );
+        let nodes = session.nodes_at(Position {
+            line: 2,
+            character: 9,
+        });
+        assert!(nodes
+            .iter()
+            .any(|x| matches!(x, RefNode::ContinuousAssign(_))));
+
+        session.apply_edits(&[TextEdit {
+            start: Position {
+                line: 1,
+                character: 9,
+            },
+            end: Position {
+                line: 1,
+                character: 9,
+            },
+            text: String::from("\n  wire b;"),
+        }]);
+        assert_eq!(
+            session.text(),
+            "module A;\n  wire a;\n  wire b;\n  assign a = 1;\nendmodule\n"
+        );
+        let definition = session.definition(Position {
+            line: 3,
+            character: 9,
+        });
+        assert_eq!(definition.map(|x| x.begin), Some(17));
+
+        // Characters are counted in UTF-16 code units.
+        session.set_text("// \u{1f600}x\nmodule A;\nendmodule\n");
+        let position = Position {
+            line: 0,
+            character: 5,
+        };
+        assert_eq!(session.offset(position), 7);
+        assert_eq!(session.position(7), position);
+
+        session.set_text("module");
+        assert!(session.tree().is_none());
+        assert!(session.error().is_some());
+    }
+
+    #[test]
+    fn test_linter() {
+        // Reports `always` in favor of `always_comb` or `always_ff`, and the
+        // number of always constructs of each tree.
+        struct PlainAlways(usize);
+
+        impl Rule for PlainAlways {
+            fn name(&self) -> &str {
+                "plain_always"
+            }
+
+            fn event<'a>(&mut self, event: &NodeEvent<'a>, context: &mut RuleContext<'a, '_>) {
+                if let NodeEvent::Enter(x) = event {
+                    if let RefNode::AlwaysKeyword(AlwaysKeyword::Always(_)) = x {
+                        assert_eq!(context.ancestors[0].kind(), NodeKind::SourceText);
+                        context.report(x, "use always_comb or always_ff");
+                    }
+                    if let RefNode::AlwaysConstruct(_) = x {
+                        self.0 += 1;
+                    }
+                }
+            }
+
+            fn finish<'a>(&mut self, context: &mut RuleContext<'a, '_>) {
+                let tree = context.tree;
+                let root: RefNode = (&tree.node).into();
+                context.report_with(Severity::Note, &root, self.0.to_string());
+                self.0 = 0;
+            }
+        }
+
+        let srcs = [
+            "module m;\n  always @* a = b;\n  always_ff @(posedge clk) q <= d;\nendmodule\n",
+            "module n;\n  always #5 clk = ~clk;\nendmodule\n",
+        ];
+        let trees: Vec<_> = srcs
+            .iter()
+            .map(|x| {
+                parse_sv_str(x, PathBuf::from(""), &HashMap::new(), &[""], false, false)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let mut linter = Linter::new();
+        linter.add_rule(PlainAlways(0));
+        assert_eq!(linter.rules(), vec!["plain_always"]);
+
+        let diagnostics: Vec<_> = linter
+            .run(&trees)
+            .into_iter()
+            .map(|x| {
+                assert_eq!(x.code, "plain_always");
+                (x.severity, x.message, x.origin.map(|x| x.1), x.len)
+            })
+            .collect();
+        let always = String::from("use always_comb or always_ff");
+        assert_eq!(
+            diagnostics,
+            vec![
+                (Severity::Warning, always.clone(), Some(12), 6),
+                (Severity::Note, String::from("2"), Some(0), 73),
+                (Severity::Warning, always, Some(12), 6),
+                (Severity::Note, String::from("1"), Some(0), 43),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_always_info() {
+        let src = r#"
+module A;
+  always_ff @(posedge clk or negedge rst_n) begin
+    if (!rst_n) q <= 0;
+    else q <= d;
+  end
+  always @(posedge clk)
+    if (reset == 1'b1) q <= 0;
+    else if (en) q <= d;
+  always @(posedge clk) if (en) q <= d;
+  always @* y = a & b;
+  always_latch if (g) l <= d;
+  always #5 clk = ~clk;
+endmodule
+"#;
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let info: Vec<_> = syntax_tree
+            .iter_of::<AlwaysConstruct>()
+            .map(|(x, _)| AlwaysInfo::new(&syntax_tree, x))
+            .collect();
+        let classes: Vec<_> = info.iter().map(|x| x.class).collect();
+        assert_eq!(
+            classes,
+            [
+                AlwaysClass::Sequential,
+                AlwaysClass::Sequential,
+                AlwaysClass::Sequential,
+                AlwaysClass::Combinational,
+                AlwaysClass::Latch,
+                AlwaysClass::Other,
+            ]
+        );
+
+        assert_eq!(info[0].kind, AlwaysKind::AlwaysFf);
+        assert_eq!(info[0].events.len(), 2);
+        assert_eq!(info[0].clock, Some((Edge::Posedge, String::from("clk"))));
+        let reset = ResetInfo {
+            name: String::from("rst_n"),
+            active_low: true,
+            asynchronous: true,
+        };
+        assert_eq!(info[0].reset, Some(reset));
+
+        assert_eq!(info[1].clock, Some((Edge::Posedge, String::from("clk"))));
+        let reset = ResetInfo {
+            name: String::from("reset"),
+            active_low: false,
+            asynchronous: false,
+        };
+        assert_eq!(info[1].reset, Some(reset));
+
+        assert_eq!(info[2].reset, None);
+        assert!(info[3].events.is_empty());
+        assert_eq!(info[3].clock, None);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_project() {
+        let dir = std::env::temp_dir().join("sv-parser-test_project");
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.sv");
+        let b = dir.join("b.sv");
+        std::fs::write(
+            &a,
+            "`define W 8\nmodule A;\n  wire [`W-1:0] a;\nendmodule\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &b,
+            "module B;\n  A a();\n  C c();\n  wire [`W-1:0] b;\nendmodule\nmodule A;\nendmodule\n",
+        )
+        .unwrap();
+        let filelist = dir.join("files.f");
+        std::fs::write(
+            &filelist,
+            format!("+define+X\n{}\n{}\n", a.display(), b.display()),
+        )
+        .unwrap();
+
+        let project = Project::from_filelist(&filelist).unwrap();
+        assert_eq!(project.files, vec![a.clone(), b.clone()]);
+        let unit = project.parse();
+        assert_eq!(unit.paths, vec![a, b.clone()]);
+        assert_eq!(unit.trees.len(), 2);
+        assert!(unit.defines.contains_key("W"));
+        assert!(unit.defines.contains_key("X"));
+        assert_eq!(unit.hierarchy().units.len(), 3);
+
+        let diagnostics = unit.diagnostics.into_vec();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].code, "duplicate-definition");
+        assert_eq!(diagnostics[0].origin, Some((b.clone(), 64)));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].code, "undefined-unit");
+        assert_eq!(diagnostics[1].origin, Some((b, 21)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_sv_bytes() {
+        let bytes = b"// \xe9\nmodule A;\nendmodule\n";
+        let preprocessor = Preprocessor::new().encoding(Encoding::Latin1);
+        let (syntax_tree, _) = parse_sv_bytes(bytes, "test.sv", &preprocessor, false).unwrap();
+        let (x, _) = syntax_tree.iter_of::<ModuleIdentifier>().next().unwrap();
+        let locate = unwrap_locate!(x).unwrap();
+        assert_eq!(
+            syntax_tree.get_origin(locate),
+            Some((&PathBuf::from("test.sv"), 12))
+        );
+
+        let (syntax_tree, _) =
+            parse_sv_reader(&bytes[..], "test.sv", &preprocessor, false).unwrap();
+        assert_eq!(syntax_tree.to_source(), "// \u{e9}\nmodule A;\nendmodule\n");
+
+        assert!(parse_sv_bytes(bytes, "test.sv", &Preprocessor::new(), false).is_err());
+    }
+
+    #[test]
+    fn test_project_mode() {
+        let mut files = MemoryFileProvider::new();
+        files.insert(
+            "a.sv",
+            "`define W 8\n`default_nettype none\nmodule A;\nendmodule\n",
+        );
+        files.insert("b.sv", "module B;\n  wire [`W-1:0] b;\nendmodule\n");
+        let mut project = Project {
+            files: vec![PathBuf::from("a.sv"), PathBuf::from("b.sv")],
+            config: PreprocessorConfig {
+                file_provider: Some(Arc::new(files)),
+                ..PreprocessorConfig::default()
+            },
+            ..Project::default()
+        };
+
+        let unit = project.parse();
+        assert!(unit.diagnostics.is_empty());
+        assert_eq!(unit.trees.len(), 2);
+        assert_eq!(unit.trees[1].text().default_nettype(0), "none");
+
+        project.mode = CompilationUnitMode::PerFile;
+        let unit = project.parse();
+        assert_eq!(unit.paths, vec![PathBuf::from("a.sv")]);
+        let diagnostics = unit.diagnostics.into_vec();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "define-not-found");
+    }
+
+    #[test]
+    fn test_define_report() {
+        let mut files = MemoryFileProvider::new();
+        files.insert(
+            "a.sv",
+            "`define USED 1\n`define UNUSED 2\n`define DEAD 3\n`define GUARD\n",
+        );
+        files.insert(
+            "b.sv",
+            "module B;\n`ifdef GUARD\n  wire [`USED:0] b;\n`else\n  wire [`DEAD:0] b;\n`endif\n`ifdef SYNTHESIS\n`endif\n`ifndef PRE\n`endif\nendmodule\n",
+        );
+        let mut defines = HashMap::new();
+        defines.insert(String::from("PRE"), None);
+        let project = Project {
+            files: vec![PathBuf::from("a.sv"), PathBuf::from("b.sv")],
+            defines,
+            config: PreprocessorConfig {
+                file_provider: Some(Arc::new(files)),
+                ..PreprocessorConfig::default()
+            },
+            ..Project::default()
+        };
+
+        let report = project.define_report();
+        let names =
+            |x: &[MacroLocation]| -> Vec<String> { x.iter().map(|x| x.name.clone()).collect() };
+        assert_eq!(names(&report.unused), vec!["UNUSED"]);
+        assert_eq!(names(&report.skipped_only), vec!["DEAD"]);
+        assert_eq!(names(&report.undefined_guards), vec!["SYNTHESIS"]);
+        assert_eq!(report.unused[0].path, PathBuf::from("a.sv"));
+        assert_eq!(report.undefined_guards[0].path, PathBuf::from("b.sv"));
+
+        let diagnostics = report.diagnostics().into_vec();
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[1].code, "skipped-only-define");
+        assert_eq!(
+            diagnostics[1].message,
+            "Macro used only in skipped branches: DEAD"
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_parse_cache() {
+        let dir = std::env::temp_dir().join("sv-parser-test_parse_cache");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("top.sv");
+        std::fs::write(&path, "module A;\n`include \"a.svh\"\nendmodule\n").unwrap();
+        std::fs::write(dir.join("a.svh"), "wire a;\n").unwrap();
+
+        let cache = ParseCache::new();
+        let defines = HashMap::new();
+        let x = cache
+            .parse_sv(&path, &defines, &[&dir], false, false)
+            .unwrap();
+        let y = cache
+            .parse_sv(&path, &defines, &[&dir], false, false)
+            .unwrap();
+        assert!(Arc::ptr_eq(&x, &y));
+        assert_eq!(x.0.text().includes(), [dir.join("a.svh")]);
+
+        // A change of an included file or of the defines is a miss.
+        std::fs::write(dir.join("a.svh"), "wire b;\n").unwrap();
+        let y = cache
+            .parse_sv(&path, &defines, &[&dir], false, false)
+            .unwrap();
+        assert!(!Arc::ptr_eq(&x, &y));
+        assert!(unwrap_node!(&y.0, NetDeclaration).is_some());
+        assert!(y.0.text().text().contains("wire b;"));
+        let mut defines = HashMap::new();
+        defines.insert(String::from("A"), None);
+        let z = cache
+            .parse_sv(&path, &defines, &[&dir], false, false)
+            .unwrap();
+        assert!(!Arc::ptr_eq(&y, &z));
+        assert_eq!(cache.len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_syntax_tree_shared() {
+        let src = "module A;\n  wire a;\nendmodule\nmodule B;\n  wire b;\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+
+        let nets: Vec<_> = (0..2)
+            .into_par_iter()
+            .map(|i| {
+                let (x, _) = syntax_tree.iter_of::<NetDeclaration>().nth(i)?;
+                syntax_tree.get_str_trim(x).map(String::from)
+            })
+            .collect();
+        let expected = [Some(String::from("wire a;")), Some(String::from("wire b;"))];
+        assert_eq!(nets, expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_bytes() {
+        let src = "`define W 8\nmodule A;\n  wire [`W-1:0] a; // comment\nendmodule\n";
+        let path = PathBuf::from("a.sv");
+        let (syntax_tree, _) =
+            parse_sv_str(src, path, &HashMap::new(), &[""], false, false).unwrap();
+        let bytes = syntax_tree.to_bytes();
+        assert!(bytes.len() < syntax_tree.to_json().len());
+
+        let cached = SyntaxTree::from_bytes(&bytes).unwrap();
+        assert_eq!(cached.node, syntax_tree.node);
+        assert_eq!(cached.to_source(), syntax_tree.to_source());
+        let (x, _) = cached.iter_of::<NetDeclaration>().next().unwrap();
+        let locate = unwrap_locate!(x).unwrap();
+        assert_eq!(cached.get_origin(locate), syntax_tree.get_origin(locate));
+
+        assert!(SyntaxTree::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_json() {
+        let src = "module A;\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&syntax_tree.to_json()).unwrap();
+        assert_eq!(json["text"], src);
+        assert_eq!(json["node"]["type"], "SourceText");
+
+        let description = &json["node"]["nodes"][2][0];
+        let module = &description["ModuleDeclaration"]["Ansi"];
+        assert_eq!(module["type"], "ModuleDeclarationAnsi");
+        let header = &module["nodes"][0];
+        let identifier = &header["nodes"][3]["nodes"][0]["SimpleIdentifier"];
+        let locate = &identifier["nodes"][0];
+        assert_eq!(locate["offset"], 7);
+        assert_eq!(locate["len"], 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_verible_json() {
+        let src = "module A;\n  wire [7:0] a;\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&syntax_tree.to_verible_json("test.sv")).unwrap();
+        let tree = &json["test.sv"]["tree"];
+        assert_eq!(tree["tag"], "kSourceText");
+
+        let module = &tree["children"][0];
+        assert_eq!(module["tag"], "kModuleDeclarationAnsi");
+        let header = &module["children"][0];
+        assert_eq!(header["tag"], "kModuleAnsiHeader");
+        assert_eq!(
+            header["children"][0],
+            serde_json::json!({"start": 0, "end": 6, "tag": "module", "text": "module"})
+        );
+        assert_eq!(
+            header["children"][1],
+            serde_json::json!({"start": 7, "end": 8, "tag": "SymbolIdentifier", "text": "A"})
+        );
+        assert!(syntax_tree
+            .to_verible_json("test.sv")
+            .contains(r#""tag":"TK_DecNumber""#));
+    }
+
+    #[test]
+    fn test_dump() {
+        let src = "module A;\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let module = unwrap_node!(&syntax_tree, ModuleIdentifier).unwrap();
+        assert_eq!(
+            syntax_tree.dump_node(module),
+            "ModuleIdentifier @1:8 [7..8] \"A\"\n  Identifier @1:8 [7..8] \"A\"\n    SimpleIdentifier @1:8 [7..8] \"A\"\n      Token @1:8 [7..8] \"A\"\n"
+        );
+
+        let dump = syntax_tree.dump();
+        assert!(dump.starts_with("SourceText @1:1 [0..19] \"module A;\\nendmodule\"\n"));
+        assert!(dump.contains("\n        Keyword @2:1 [10..19] \"endmodule\"\n"));
+        assert!(!dump.contains("WhiteSpace"));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let src = "module A;\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let config = DotConfig {
+            kinds: Some(vec![
+                NodeKind::ModuleDeclarationAnsi,
+                NodeKind::ModuleIdentifier,
+                NodeKind::Keyword,
+            ]),
+            ..DotConfig::default()
+        };
+        assert_eq!(
+            syntax_tree.to_dot(&config),
+            "digraph SyntaxTree {\n  node [shape=box];\n  n0 [label=\"ModuleDeclarationAnsi\\nmodule A;\\nendmodule\"];\n  n1 [label=\"Keyword\\nmodule\"];\n  n0 -> n1;\n  n2 [label=\"ModuleIdentifier\\nA\"];\n  n0 -> n2;\n  n3 [label=\"Keyword\\nendmodule\"];\n  n0 -> n3;\n}\n"
+        );
+
+        let config = DotConfig {
+            max_depth: Some(1),
+            excerpt_len: 0,
+            ..DotConfig::default()
+        };
+        let dot = syntax_tree.to_dot(&config);
+        assert!(dot.contains("  n0 [label=\"SourceText\"];\n"));
+        let edges = dot.matches(" -> n").count();
+        assert_eq!(edges, dot.matches("[label=").count() - 1);
+        assert!(!dot.contains("ModuleIdentifier"));
+    }
+
+    #[test]
+    fn test_visitor() {
+        struct Modules<'a> {
+            syntax_tree: &'a SyntaxTree,
+            names: Vec<&'a str>,
+            keywords: usize,
+        }
+
+        impl<'a> Visitor<'a> for Modules<'a> {
+            fn visit_module_identifier(&mut self, node: &'a ModuleIdentifier) {
+                let name = self.syntax_tree.get_str_trim(node).unwrap();
+                self.names.push(name);
+            }
+
+            fn visit_keyword(&mut self, _node: &'a Keyword) {
+                self.keywords += 1;
+            }
+        }
+
+        let src = "module A; endmodule\nmodule B; wire b; endmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let mut modules = Modules {
+            syntax_tree: &syntax_tree,
+            names: Vec::new(),
+            keywords: 0,
+        };
+        walk(&mut modules, &syntax_tree);
+        assert_eq!(modules.names, vec!["A", "B"]);
+        assert_eq!(modules.keywords, 5);
+    }
+
+    #[test]
+    fn test_transform() {
+        let src = "module A(input a);\n  assign b = a;\nendmodule\n";
+        let (mut syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        assert_eq!(syntax_tree.to_source(), src);
+
+        // Rename `a` to `c`.
+        let mut offsets = Vec::new();
+        for node in &syntax_tree {
+            if let RefNode::SimpleIdentifier(x) = node {
+                if syntax_tree.get_str(&x.nodes.0) == Some("a") {
+                    offsets.push(x.nodes.0.offset);
+                }
+            }
+        }
+        let c = syntax_tree.new_locate("c");
+        assert_eq!((c.line, c.column), (4, 1));
+        let d = syntax_tree.new_locate("\nd");
+        assert_eq!((d.line, d.column), (4, 2));
+        assert_eq!(syntax_tree.new_locate("e").line, 5);
+        syntax_tree.node_mut().locates_mut(&mut |x| {
+            if offsets.contains(&x.offset) {
+                *x = c;
+            }
+        });
+
+        // Add a port taken from another module.
+        let (other, _) = parse_sv_str(
+            "module B(input x, input d); endmodule",
+            PathBuf::from(""),
+            &HashMap::new(),
+            &[""],
+            false,
+            false,
+        )
+        .unwrap();
+        let port = unwrap_node!(&other, ListOfPortDeclarations).unwrap();
+        let port = match port {
+            RefNode::ListOfPortDeclarations(x) => &(x.nodes.0).nodes.1.as_ref().unwrap().nodes.1[0],
+            _ => unreachable!(),
         };
-        unwrap()
-    }};
-}
+        let port = syntax_tree.import(&other, port);
+        if let AnyNode::SourceText(x) = syntax_tree.node_mut() {
+            if let Description::ModuleDeclaration(x) = &mut x.nodes.2[0] {
+                if let ModuleDeclaration::Ansi(x) = &mut **x {
+                    let ports = x.nodes.0.nodes.6.as_mut().unwrap();
+                    (ports.nodes.0).nodes.1.as_mut().unwrap().nodes.1.push(port);
+                }
+            }
+        }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::collections::HashMap;
+        assert_eq!(
+            syntax_tree.to_source(),
+            "module A(input c, input d);\n  assign b = c;\nendmodule\n"
+        );
+        let c = unwrap_node!(&syntax_tree, PortIdentifier).unwrap();
+        let c = unwrap_locate!(c).unwrap();
+        assert_eq!(syntax_tree.get_str(c), Some("c"));
+        assert_eq!(syntax_tree.get_origin(c), None);
+    }
 
     #[test]
-    fn test() {
-        let src = "/* comment */";
+    fn test_format() {
+        let src = r##"module A (
+input a,
+      input bb
+);
+
+
+  wire x , yy;
+assign x = a;
+        assign yy = bb; // sum
+always @(posedge a)
+if (x) begin
+yy <= 1;
+end
+else yy <= 0;
+endmodule
+"##;
         let (syntax_tree, _) =
             parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
-        let comment = unwrap_node!(&syntax_tree, Comment);
-        assert!(comment.is_some());
+        let expected = r##"module A (
+  input a,
+  input bb
+);
+
+  wire x, yy;
+  assign x  = a;
+  assign yy = bb; // sum
+  always @(posedge a)
+    if (x) begin
+      yy <= 1;
+    end
+    else yy <= 0;
+endmodule
+"##;
+        assert_eq!(syntax_tree.format(&FormatConfig::default()), expected);
+
+        let src = "module B; assign y = {a, b, c, d, e, f};\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let config = FormatConfig {
+            max_width: 20,
+            ..FormatConfig::default()
+        };
+        let module = unwrap_node!(&syntax_tree, ModuleDeclarationAnsi).unwrap();
+        assert_eq!(
+            syntax_tree.format_node(module, &config),
+            "module B; assign y = {a,\n    b, c, d, e, f};\nendmodule\n"
+        );
     }
 
     #[test]
-    fn test_continuous() {
-        let src = r##"`ifdef A
-`endif
+    fn test_unwrap_node() {
+        let src = "interface I; endinterface\nmodule M; endmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let mut names = Vec::new();
+        for node in &syntax_tree {
+            if let RefNode::Description(x) = node {
+                match unwrap_node!(x, ModuleIdentifier, InterfaceIdentifier) {
+                    Some(RefNode::ModuleIdentifier(x)) => {
+                        names.push(("module", syntax_tree.get_str_trim(x).unwrap()))
+                    }
+                    Some(RefNode::InterfaceIdentifier(x)) => {
+                        names.push(("interface", syntax_tree.get_str_trim(x).unwrap()))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+        assert_eq!(names, vec![("interface", "I"), ("module", "M")]);
+        assert!(unwrap_node!(&syntax_tree, ClassIdentifier).is_none());
+    }
 
-module FetchStage();
-    always_comb begin
-        for (int j = i + 1; j < FETCH_WIDTH; j++) begin
-        end
-        break;
+    #[test]
+    fn test_node_kind() {
+        let src = "module A; wire a, b; endmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let mut counts = HashMap::new();
+        for node in &syntax_tree {
+            *counts.entry(node.kind()).or_insert(0) += 1;
+        }
+        assert_eq!(counts[&NodeKind::ModuleIdentifier], 1);
+        assert_eq!(counts[&NodeKind::NetIdentifier], 2);
+        assert_eq!(counts.get(&NodeKind::ClassIdentifier), None);
+
+        let module = unwrap_node!(&syntax_tree, ModuleDeclarationAnsi).unwrap();
+        assert_eq!(module.kind(), NodeKind::ModuleDeclarationAnsi);
+        assert_eq!(module.kind().as_str(), "ModuleDeclarationAnsi");
+        assert_eq!(module.kind().to_string(), module.to_string());
+        if let RefNode::ModuleDeclarationAnsi(x) = module {
+            assert_eq!(x.kind(), NodeKind::ModuleDeclarationAnsi);
+            let any: AnyNode = x.clone().into();
+            assert_eq!(any.kind(), NodeKind::ModuleDeclarationAnsi);
+        }
+    }
+
+    #[test]
+    fn test_ancestors() {
+        let src = r##"module A;
+  wire a;
+  generate
+    if (1) begin
+      wire b;
     end
+  endgenerate
+  function f;
+    reg c;
+  endfunction
+endmodule
+"##;
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let mut iter = syntax_tree.into_iter().with_ancestors();
+        let mut found = Vec::new();
+        while let Some(node) = iter.next() {
+            let name = match node {
+                RefNode::NetIdentifier(x) => syntax_tree.get_str_trim(x),
+                RefNode::VariableIdentifier(x) => syntax_tree.get_str_trim(x),
+                _ => continue,
+            };
+            found.push((
+                name.unwrap(),
+                iter.parent().map(|x| x.kind()),
+                iter.is_inside(NodeKind::GenerateRegion),
+                iter.is_inside(NodeKind::FunctionDeclaration),
+            ));
+            assert_eq!(iter.ancestors()[0].kind(), NodeKind::SourceText);
+        }
+        assert_eq!(
+            found[0],
+            ("a", Some(NodeKind::NetDeclAssignment), false, false)
+        );
+        assert_eq!(
+            found[1],
+            ("b", Some(NodeKind::NetDeclAssignment), true, false)
+        );
+        assert_eq!((found[2].0, found[2].2, found[2].3), ("c", false, true));
+        assert_eq!(found.len(), 3);
+    }
 
-    AddrPath fetchAddrOut;
-endmodule"##;
+    #[test]
+    fn test_event_cursor() {
+        let src = "module A; wire a; initial begin end endmodule\nmodule B; endmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let ids = |x: EventIter| {
+            x.map(|x| match x {
+                NodeEvent::Enter(x) => (true, x.id()),
+                NodeEvent::Leave(x) => (false, x.id()),
+            })
+            .collect::<Vec<_>>()
+        };
+        let all = ids(syntax_tree.into_iter().event());
+        let mut events = syntax_tree.into_iter().event();
+        for i in 0..=all.len() {
+            let resumed = EventIter::resume((&syntax_tree).into(), &events.cursor());
+            assert_eq!(ids(resumed), &all[i..]);
+            events.next();
+        }
+    }
 
-        let src_broken = r##"`ifdef A
-endif
+    #[test]
+    fn test_iter_of() {
+        let src = "module A; endmodule\n/* B */ module B #(parameter P = 1); endmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let modules: Vec<_> = syntax_tree
+            .iter_of::<ModuleDeclaration>()
+            .map(|(x, locate)| {
+                let name = unwrap_node!(x, ModuleIdentifier).unwrap();
+                let locate = locate.unwrap();
+                (
+                    syntax_tree.get_str_trim(name).unwrap(),
+                    locate.line,
+                    locate.column,
+                )
+            })
+            .collect();
+        assert_eq!(modules, vec![("A", 1, 1), ("B", 2, 9)]);
 
-module FetchStage();
-    always_comb begin
-        for (int j = i + 1; j < FETCH_WIDTH; j++) begin
-        end
-        break;
-    end
+        let names: Vec<_> = syntax_tree
+            .into_iter()
+            .of::<ParameterIdentifier>()
+            .map(|x| syntax_tree.get_str_trim(x).unwrap())
+            .collect();
+        assert_eq!(names, vec!["P"]);
+    }
 
-    AddrPath fetchAddrOut;
-endmodule"##;
+    #[test]
+    fn test_query() {
+        let src = r##"module A;
+  always @(posedge clk) q <= d;
+  always_comb x = y;
+endmodule
+module B;
+  initial @(e) x = 0;
+endmodule
+"##;
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let query = Query::find(NodeKind::ModuleDeclaration)
+            .descendant(NodeKind::AlwaysConstruct)
+            .descendant(NodeKind::EventControl);
+        let matches = query.run(&syntax_tree);
+        assert_eq!(matches.len(), 1);
+        let captures: Vec<_> = matches[0]
+            .captures
+            .iter()
+            .map(|x| syntax_tree.get_str_trim(x.clone()).unwrap())
+            .collect();
+        assert_eq!(
+            captures,
+            vec![
+                "module A;\n  always @(posedge clk) q <= d;\n  always_comb x = y;\nendmodule",
+                "always @(posedge clk) q <= d;",
+                "@(posedge clk)",
+            ]
+        );
+        assert_eq!(matches[0].node().kind(), NodeKind::EventControl);
 
-        let path = PathBuf::from("");
-        let defines = HashMap::new();
-        let ret = parse_sv_str(src, &path, &defines, &[""], false, false);
-        assert!(ret.is_ok());
-        let ret = parse_sv_str(src_broken, &path, &defines, &[""], false, false);
-        assert!(ret.is_err());
-        let ret = parse_sv_str(src, &path, &defines, &[""], false, false);
-        assert!(ret.is_ok());
-        let ret = parse_sv_str(src_broken, &path, &defines, &[""], false, false);
+        let query = Query::find(NodeKind::ModuleDeclaration).child(NodeKind::ModuleDeclarationAnsi);
+        assert_eq!(query.run(&syntax_tree).len(), 2);
+        let query = Query::find(NodeKind::SourceText).child(NodeKind::ModuleDeclaration);
+        assert!(query.run(&syntax_tree).is_empty());
+    }
+
+    #[test]
+    fn test_get_str() {
+        let src = "module A; wire a; endmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let wire = unwrap_node!(&syntax_tree, NetDeclaration).unwrap();
+        assert_eq!(syntax_tree.get_str(wire.clone()), Some("wire a; "));
+        assert_eq!(syntax_tree.get_str_trim(wire.clone()), Some("wire a;"));
+
+        let text = syntax_tree.text();
+        let locate = unwrap_locate!(wire.clone()).unwrap();
+        assert_eq!(text.get_str(locate), Some("wire"));
+        assert_eq!(text.get_str(wire), Some("wire a; "));
+        assert_eq!(text.get_str(&Vec::<Locate>::new()), None);
+    }
+
+    #[test]
+    fn test_span() {
+        let src = "module A; /* c */ wire a; // d\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let wire = unwrap_node!(&syntax_tree, NetDeclaration).unwrap();
+        assert_eq!(wire.span(), Some(18..25));
+        assert_eq!(&src[wire.span().unwrap()], "wire a;");
+
+        let module = unwrap_node!(&syntax_tree, ModuleDeclarationAnsi).unwrap();
+        if let RefNode::ModuleDeclarationAnsi(x) = module {
+            assert_eq!(x.span(), Some(0..40));
+            assert_eq!(x.nodes.3.span(), Some(31..40));
+            assert_eq!(x.nodes.3.nodes.0.span(), Some(31..40));
+            let any: AnyNode = x.clone().into();
+            assert_eq!(any.span(), Some(0..40));
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dirs = [
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testcases"),
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../sv-parser-pp/testcases"),
+        ];
+        let mut checked = 0;
+        for dir in &dirs {
+            let mut paths: Vec<_> = std::fs::read_dir(dir)
+                .unwrap()
+                .map(|x| x.unwrap().path())
+                .filter(|x| x.extension().is_some_and(|x| x == "sv"))
+                .collect();
+            paths.sort();
+            for path in paths {
+                // Some of the preprocessor testcases aren't meant to be parsed.
+                if let Ok((syntax_tree, _)) = parse_sv(&path, &HashMap::new(), &[dir], false, false)
+                {
+                    assert_eq!(
+                        syntax_tree.to_source(),
+                        syntax_tree.text().text(),
+                        "{}",
+                        path.display()
+                    );
+                    checked += 1;
+                }
+            }
+        }
+        assert!(checked >= 2);
+
+        let src = "/* a */\nmodule A;  // b\n\twire  a ;\n`define X 1\nendmodule  \n\n";
+        let syntax_tree = parse_sv_raw_str(src, "", false).unwrap();
+        assert_eq!(syntax_tree.to_source(), src);
+    }
+
+    #[test]
+    fn test_diff() {
+        let parse = |src| {
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false)
+                .unwrap()
+                .0
+        };
+        let old = parse("module A; wire a; assign a = 1; endmodule");
+        let new = parse("module A;\n  wire a; // c\n  assign a=1;\nendmodule\n");
+        assert!(diff(&old, &new).is_empty());
+
+        let new = parse("module A; wire a, b; assign a = 2; endmodule");
+        let differences: Vec<_> = diff(&old, &new)
+            .into_iter()
+            .map(|x| {
+                let old = x.old.map(|x| (x.kind(), old.get_str_trim(x).unwrap()));
+                let new = x.new.map(|x| (x.kind(), new.get_str_trim(x).unwrap()));
+                (x.change, old, new)
+            })
+            .collect();
+        assert_eq!(
+            differences,
+            vec![
+                (Change::Inserted, None, Some((NodeKind::Symbol, ","))),
+                (
+                    Change::Inserted,
+                    None,
+                    Some((NodeKind::NetDeclAssignment, "b"))
+                ),
+                (
+                    Change::Modified,
+                    Some((NodeKind::UnsignedNumber, "1")),
+                    Some((NodeKind::UnsignedNumber, "2"))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_symbols() {
+        let src = "package P;\n  parameter W = 8;\n  typedef logic [W-1:0] word_t;\nendpackage\n\
+                   module Sub(input a);\n  wire x;\nendmodule\n\
+                   module Top;\n  import P::*;\n  word_t w;\n  wire a;\n  Sub u (.a(a));\n\
+                   \x20 initial begin : blk\n    reg a;\n    a = 1;\n  end\n\
+                   \x20 assign w = u.x + P::W + blk.a;\nendmodule\n";
+        let (tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let symbols = SymbolTable::new(&tree);
+
+        // Each identifier with the line of its declaration.
+        let mut resolved = Vec::new();
+        for node in &tree {
+            if let RefNode::SimpleIdentifier(x) = node {
+                let line = x.nodes.0.line;
+                let declaration = symbols.resolve(node.clone());
+                let declared = declaration.and_then(|x| unwrap_locate!(x)).map(|x| x.line);
+                resolved.push((line, tree.get_str_trim(x).unwrap(), declared));
+            }
+        }
+        assert_eq!(
+            resolved,
+            vec![
+                (1, "P", Some(1)),
+                (2, "W", Some(2)),
+                (3, "W", Some(2)),
+                (3, "word_t", Some(3)),
+                (5, "Sub", Some(5)),
+                (5, "a", Some(5)),
+                (6, "x", Some(6)),
+                (8, "Top", Some(8)),
+                (9, "P", Some(1)),
+                (10, "word_t", Some(3)),
+                (10, "w", Some(10)),
+                (11, "a", Some(11)),
+                (12, "Sub", Some(5)),
+                (12, "u", Some(12)),
+                (12, "a", Some(5)),
+                (12, "a", Some(11)),
+                (13, "blk", Some(13)),
+                (14, "a", Some(14)),
+                (15, "a", Some(14)),
+                (17, "w", Some(10)),
+                (17, "u", Some(12)),
+                (17, "x", Some(6)),
+                (17, "P", Some(1)),
+                (17, "W", Some(2)),
+                (17, "blk", Some(13)),
+                (17, "a", Some(14)),
+            ]
+        );
+        assert_eq!(symbols.scopes().len(), 5);
+    }
+
+    #[test]
+    fn test_interface_usage() {
+        let src = "module A (bus_if.master m, s, interface.slave g, input clk);\n\
+                   \x20 virtual bus_if.tb vif, vif2;\n\
+                   \x20 class C;\n    virtual bus_if c;\n  endclass\n\
+                   endmodule\n\
+                   module B (p, q);\n  bus_if.master p;\n  input q;\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let usage = InterfaceUsage::of_tree(&syntax_tree);
+        fn ports(x: &InterfaceUsage) -> Vec<(&str, Option<&str>, Option<&str>)> {
+            x.ports
+                .iter()
+                .map(|x| {
+                    let interface = x.interface.as_deref();
+                    (x.name.as_str(), interface, x.modport.as_deref())
+                })
+                .collect()
+        }
+
+        assert_eq!(usage[0].module, "A");
+        assert_eq!(
+            ports(&usage[0]),
+            vec![
+                ("m", Some("bus_if"), Some("master")),
+                ("s", Some("bus_if"), Some("master")),
+                ("g", None, Some("slave")),
+            ]
+        );
+        assert_eq!(
+            usage[0].modports,
+            vec!["bus_if.master", "interface.slave", "bus_if.tb"]
+        );
+        let virtual_interfaces: Vec<_> = usage[0]
+            .virtual_interfaces
+            .iter()
+            .map(|x| (x.name.as_str(), x.interface.as_str(), x.modport.as_deref()))
+            .collect();
+        assert_eq!(
+            virtual_interfaces,
+            vec![
+                ("vif", "bus_if", Some("tb")),
+                ("vif2", "bus_if", Some("tb"))
+            ]
+        );
+
+        assert_eq!(usage[1].module, "B");
+        assert_eq!(
+            ports(&usage[1]),
+            vec![("p", Some("bus_if"), Some("master"))]
+        );
+        assert_eq!(usage[1].modports, vec!["bus_if.master"]);
+        assert!(usage[1].virtual_interfaces.is_empty());
+    }
+
+    #[test]
+    fn test_module_info() {
+        let src = "module A #(parameter int W = 8, localparam D = W * 2) (\n\
+                   \x20 input logic [W-1:0] a, b,\n  output reg [D-1:0] y,\n  intf.mp bus\n);\n\
+                   endmodule\n\
+                   module B #(parameter type T = logic) (a, y);\n  parameter N = 4;\n\
+                   \x20 input [N-1:0] a;\n  output y;\n  wire y;\nendmodule\n";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let modules: Vec<_> = syntax_tree
+            .iter_of::<ModuleDeclaration>()
+            .map(|(x, _)| ModuleInfo::new(&syntax_tree, x))
+            .collect();
+        type Port<'a> = (&'a str, Option<Direction>, Option<&'a str>, Option<&'a str>);
+        fn ports(x: &ModuleInfo) -> Vec<Port<'_>> {
+            x.ports
+                .iter()
+                .map(|x| {
+                    let data_type = x.data_type.as_deref();
+                    let width_expr = x.width_expr.as_deref();
+                    (x.name.as_str(), x.direction, data_type, width_expr)
+                })
+                .collect()
+        }
+        fn parameters(x: &ModuleInfo) -> Vec<(&str, Option<&str>, Option<&str>, bool)> {
+            x.parameters
+                .iter()
+                .map(|x| {
+                    let data_type = x.data_type.as_deref();
+                    (x.name.as_str(), data_type, x.default.as_deref(), x.local)
+                })
+                .collect()
+        }
+
+        assert_eq!(modules[0].name, "A");
+        assert_eq!(
+            ports(&modules[0]),
+            vec![
+                ("a", Some(Direction::Input), Some("logic"), Some("[W-1:0]")),
+                ("b", Some(Direction::Input), Some("logic"), Some("[W-1:0]")),
+                ("y", Some(Direction::Output), Some("reg"), Some("[D-1:0]")),
+                ("bus", None, Some("intf.mp"), None),
+            ]
+        );
+        assert_eq!(
+            parameters(&modules[0]),
+            vec![
+                ("W", Some("int"), Some("8"), false),
+                ("D", None, Some("W * 2"), true),
+            ]
+        );
+
+        assert_eq!(modules[1].name, "B");
+        assert_eq!(
+            ports(&modules[1]),
+            vec![
+                ("a", Some(Direction::Input), None, Some("[N-1:0]")),
+                ("y", Some(Direction::Output), None, None),
+            ]
+        );
+        assert_eq!(
+            parameters(&modules[1]),
+            vec![
+                ("T", Some("type"), Some("logic"), false),
+                ("N", None, Some("4"), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_top_modules() {
+        let src = "module tb_top;\n  dut u ();\nendmodule\nmodule dut;\nendmodule\n\
+                   module chk;\nendmodule\nbind dut chk c ();\nmodule other;\nendmodule\n";
+        let (tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let hierarchy = Hierarchy::new(Some(&tree));
+        assert_eq!(hierarchy.bound, vec![String::from("chk")]);
+
+        let mut config = TopConfig::default();
+        assert_eq!(hierarchy.find_top_modules(&config), vec![0, 3]);
+        config.testbenches = vec![String::from("tb_*")];
+        assert_eq!(hierarchy.find_top_modules(&config), vec![1, 3]);
+        config.ignore_bound = false;
+        config.testbenches.clear();
+        assert_eq!(hierarchy.find_top_modules(&config), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let srcs = [
+            "module top;\n  sub #(.W(8)) u0 ();\n  sub u1 (), u2 ();\n  missing m0 ();\n\
+             \x20 bus_if b ();\nendmodule\nmodule sub;\n  leaf #(4) l ();\nendmodule\n",
+            "module leaf; endmodule\ninterface bus_if; endinterface\n",
+        ];
+        let trees: Vec<_> = srcs
+            .iter()
+            .map(|x| {
+                parse_sv_str(x, PathBuf::from(""), &HashMap::new(), &[""], false, false)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let hierarchy = Hierarchy::new(&trees);
+
+        let units: Vec<_> = hierarchy
+            .units
+            .iter()
+            .map(|x| (x.name.as_str(), x.kind, x.tree))
+            .collect();
+        assert_eq!(
+            units,
+            vec![
+                ("top", DesignUnitKind::Module, 0),
+                ("sub", DesignUnitKind::Module, 0),
+                ("leaf", DesignUnitKind::Module, 1),
+                ("bus_if", DesignUnitKind::Interface, 1),
+            ]
+        );
+        let u0 = &hierarchy.units[0].instances[0];
+        assert_eq!((u0.name.as_str(), u0.unit), ("u0", Some(1)));
+        assert_eq!(
+            u0.parameters,
+            vec![ParameterValue {
+                name: Some(String::from("W")),
+                value: Some(String::from("8")),
+            }]
+        );
+        let l = &hierarchy.units[1].instances[0];
+        assert_eq!(l.parameters[0].name, None);
+        assert_eq!(l.parameters[0].value.as_deref(), Some("4"));
+
+        assert_eq!(hierarchy.tops(), vec![0]);
+        assert_eq!(hierarchy.parents(2), vec![1]);
+        let unresolved: Vec<_> = hierarchy
+            .unresolved()
+            .into_iter()
+            .map(|(unit, x)| (unit, x.name.as_str(), x.unit_name.as_str()))
+            .collect();
+        assert_eq!(unresolved, vec![(0, "m0", "missing")]);
+        assert_eq!(hierarchy.undeclared(), vec!["missing"]);
+        let dependencies = hierarchy.dependencies();
+        let top: Vec<_> = dependencies["top"].iter().copied().collect();
+        assert_eq!(top, vec!["bus_if", "missing", "sub"]);
+        assert!(dependencies["leaf"].is_empty());
+        assert_eq!(hierarchy.compile_order(), vec![2, 1, 3, 0]);
+        let paths: Vec<_> = hierarchy
+            .paths(0)
+            .into_iter()
+            .map(|(path, unit)| (path, unit.map(|x| hierarchy.units[x].name.as_str())))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                (String::from("top.u0"), Some("sub")),
+                (String::from("top.u0.l"), Some("leaf")),
+                (String::from("top.u1"), Some("sub")),
+                (String::from("top.u1.l"), Some("leaf")),
+                (String::from("top.u2"), Some("sub")),
+                (String::from("top.u2.l"), Some("leaf")),
+                (String::from("top.m0"), None),
+                (String::from("top.b"), Some("bus_if")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_number_value() {
+        let src = "module A; initial x = {8'shf0, 42, 4'b1x0z, 'dx, 12'o7, \
+                   100'd633825300114114700748351602688, 1.5e3, 2_5.0_5}; endmodule";
+        let (syntax_tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let values: Vec<_> = syntax_tree
+            .iter_of::<IntegralNumber>()
+            .map(|(x, _)| IntegralValue::new(&syntax_tree, x).unwrap())
+            .collect();
+
+        assert_eq!(values[0].size, Some(8));
+        assert!(values[0].signed);
+        assert_eq!(values[0].base, NumberBase::Hex);
+        assert_eq!(values[0].to_u64(), Some(0xf0));
+
+        assert_eq!(values[1].size, None);
+        assert!(values[1].signed);
+        assert_eq!(values[1].bits.len(), 32);
+        assert_eq!(values[1].to_u64(), Some(42));
+
+        assert!(!values[2].signed);
+        assert_eq!(values[2].bits, vec![Bit::Z, Bit::Zero, Bit::X, Bit::One]);
+        assert!(!values[2].is_known());
+        assert_eq!(values[2].to_u64(), None);
+
+        assert_eq!(values[3].bits, vec![Bit::X; 32]);
+
+        assert_eq!(values[4].base, NumberBase::Octal);
+        assert_eq!(values[4].bits.len(), 12);
+        assert_eq!(values[4].to_u64(), Some(7));
+
+        // 2 ** 99
+        assert_eq!(values[5].to_u64(), None);
+        let mut bytes = vec![0; 13];
+        bytes[12] = 0x08;
+        assert_eq!(values[5].to_bytes_le(), Some(bytes));
+
+        let reals: Vec<_> = syntax_tree
+            .iter_of::<RealNumber>()
+            .map(|(x, _)| real_value(&syntax_tree, x).unwrap())
+            .collect();
+        assert_eq!(reals, vec![1500.0, 25.05]);
+    }
+
+    #[test]
+    fn test_interner() {
+        let parse = |src| {
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false)
+                .unwrap()
+                .0
+        };
+        let a = parse("module a(input clk, rst_n); wire clk_d; endmodule");
+        let b = parse("module b(input clk); always @(posedge clk) $display(clk); endmodule");
+
+        let mut interner = Interner::new();
+        let names_a = interner.intern_identifiers(&a);
+        let names_b = interner.intern_identifiers(&b);
+        let strings: Vec<_> = names_a
+            .iter()
+            .chain(&names_b)
+            .map(|x| (x.0.index(), interner.resolve(x.0)))
+            .collect();
+        assert_eq!(
+            strings,
+            vec![
+                (0, "a"),
+                (1, "clk"),
+                (2, "rst_n"),
+                (3, "clk_d"),
+                (4, "b"),
+                (1, "clk"),
+                (1, "clk"),
+                (5, "$display"),
+                (1, "clk"),
+            ]
+        );
+        assert_eq!(interner.len(), 6);
+
+        // `clk` of both trees has the same id.
+        let clk = interner.get("clk").unwrap();
+        assert_eq!(names_a[1].0, clk);
+        assert_eq!(names_b.iter().filter(|x| x.0 == clk).count(), 3);
+        assert_eq!(names_b[1].1.line, 1);
+        assert_eq!(interner.intern("clk"), clk);
+        assert_eq!(interner.get("data"), None);
+    }
+
+    #[test]
+    fn test_reparse() {
+        let src = "module A;\n  wire a;\nendmodule\nmodule B; wire b; endmodule module C;\n  wire c;\nendmodule\n";
+        let edits = [
+            ("wire b;", "wire bb, b2;"),
+            ("wire b;", "wire b;\n  wire d;\n"),
+            ("endmodule module C", "endmodule\n\nmodule C"),
+            ("module A;\n  wire a;\nendmodule\n", ""),
+            ("  wire c;\n", ""),
+        ];
+        for (from, to) in edits {
+            let (tree, _) =
+                parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+            let edited = src.replacen(from, to, 1);
+            let (tree, _) = reparse_sv_str(
+                tree,
+                &edited,
+                PathBuf::from(""),
+                &HashMap::new(),
+                &[""],
+                false,
+                false,
+            )
+            .unwrap();
+            let (expected, _) =
+                parse_sv_str(&edited, PathBuf::from(""), &HashMap::new(), &[""], false, false)
+                    .unwrap();
+            assert_eq!(tree.node, expected.node, "{:?}", edited);
+        }
+
+        let (tree, _) =
+            parse_sv_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false).unwrap();
+        let edited = src.replacen("wire b;", "wire b", 1);
+        let ret = reparse_sv_str(
+            tree,
+            &edited,
+            PathBuf::from(""),
+            &HashMap::new(),
+            &[""],
+            false,
+            false,
+        );
         assert!(ret.is_err());
-        let ret = parse_sv_str(src, &path, &defines, &[""], false, false);
+    }
+
+    #[test]
+    fn test_parse_verilog() {
+        let src = r##"module A #(parameter W = 4) (input a, input [W-1:0] c, output reg b);
+  wire logic;
+  reg [W-1:0] r;
+  genvar i;
+  generate
+    for (i = 0; i < W; i = i + 1) begin : g
+      always @* r[i] = c[i] ^ logic;
+    end
+  endgenerate
+  function automatic f(input x);
+    f = x ? {1'b0} : 1'bx;
+  endfunction
+  always @(posedge a) b <= #1 f(logic) & (r === 4'hf);
+endmodule
+"##;
+        let ret = parse_verilog_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false);
+        assert!(ret.is_ok(), "{:?}", ret.err());
+
+        let cases = [
+            ("module A;\n  logic a;\nendmodule\n", 12, "logic"),
+            ("module A;\n  initial a++;\nendmodule\n", 21, "++"),
+            ("interface A;\nendinterface\n", 0, "interface"),
+        ];
+        for (src, offset, keyword) in cases {
+            let ret =
+                parse_verilog_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false);
+            match ret {
+                Err(Error::SystemVerilog { origin, construct }) => {
+                    assert_eq!(origin.map(|x| x.1), Some(offset));
+                    assert_eq!(construct, keyword);
+                }
+                x => panic!("unexpected result {:?}", x.err()),
+            }
+        }
+
+        let src = r##"`begin_keywords "1800-2017"
+module A;
+  logic a;
+endmodule
+`end_keywords
+"##;
+        let ret = parse_verilog_str(src, PathBuf::from(""), &HashMap::new(), &[""], false, false);
         assert!(ret.is_ok());
     }
+
+    #[test]
+    fn test_parse_diagnostics() {
+        let src = "`define X 1\n`define X 2\nmodule A;\n  wire a\n  wire b;\nendmodule\n";
+        let (ret, diagnostics) =
+            parse_sv_str_diagnostics(src, PathBuf::from("a.sv"), &HashMap::new(), &[""], false);
+        assert!(ret.is_some());
+        let codes: Vec<_> = diagnostics
+            .iter()
+            .map(|x| (x.severity, x.code.as_str()))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                (Severity::Warning, "macro-redefined"),
+                (Severity::Error, "parse")
+            ]
+        );
+        assert_eq!(
+            diagnostics.iter().next().unwrap().origin,
+            Some((PathBuf::from("a.sv"), 12))
+        );
+        assert!(diagnostics.has_errors());
+
+        let (ret, diagnostics) =
+            parse_sv_str_diagnostics("`X\n", PathBuf::from("a.sv"), &HashMap::new(), &[""], false);
+        assert!(ret.is_none());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics.count(Severity::Error), 1);
+    }
+
+    #[test]
+    fn test_recover() {
+        let src = r##"module A;
+  wire a
+  wire b;
+  wire c;
+  initial begin
+    a = ;
+    b = 1;
+  end
+  end
+endmodule
+module B;
+endmodule
+"##;
+        let (syntax_tree, _, diagnostics) =
+            parse_sv_str_recover(src, PathBuf::from(""), &HashMap::new(), &[""], false).unwrap();
+        let origins: Vec<_> = diagnostics
+            .iter()
+            .map(|x| match x {
+                Error::Parse { origin, .. } => origin.as_ref().map(|x| x.1),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(origins, vec![Some(21), Some(63), Some(87)]);
+
+        let mut modules = vec![];
+        let mut nets = vec![];
+        let mut variables = vec![];
+        for node in &syntax_tree {
+            match node {
+                RefNode::ModuleIdentifier(x) => {
+                    modules.push(syntax_tree.get_str_trim(x).unwrap());
+                }
+                RefNode::NetIdentifier(x) => nets.push(syntax_tree.get_str_trim(x).unwrap()),
+                RefNode::VariableIdentifier(x) => {
+                    variables.push(syntax_tree.get_str_trim(x).unwrap());
+                }
+                _ => (),
+            }
+        }
+        assert_eq!(modules, vec!["A", "B"]);
+        assert_eq!(nets, vec!["c"]);
+        assert_eq!(variables, vec!["b"]);
+
+        let src = "module A;\nendmodule\nmodule B;\n  wire b;\n";
+        let (syntax_tree, _, diagnostics) =
+            parse_sv_str_recover(src, PathBuf::from(""), &HashMap::new(), &[""], false).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        let module = unwrap_node!(&syntax_tree, ModuleIdentifier).unwrap();
+        let locate = unwrap_locate!(module).unwrap();
+        assert_eq!(syntax_tree.get_str(locate), Some("A"));
+    }
 }
@@ -0,0 +1,154 @@
+use crate::*;
+
+/// A check applied by a `Linter` to the events of each tree.
+pub trait Rule {
+    /// The name given as the code of the diagnostics of the rule, e.g.
+    /// `"implicit_net"`.
+    fn name(&self) -> &str;
+
+    /// The severity of the diagnostics reported by `RuleContext::report()`.
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Whether the rule uses `RuleContext::symbols`, which is built for each
+    /// tree only if a rule needs it.
+    fn needs_symbols(&self) -> bool {
+        false
+    }
+
+    /// Check the node entered or left by `event`. The events of white space
+    /// are left out.
+    fn event<'a>(&mut self, event: &NodeEvent<'a>, context: &mut RuleContext<'a, '_>);
+
+    /// Called after the last event of each tree, e.g. to report what a rule
+    /// collected over the tree.
+    fn finish<'a>(&mut self, _context: &mut RuleContext<'a, '_>) {}
+}
+
+/// The context of an event given to a `Rule`.
+pub struct RuleContext<'a, 'b> {
+    pub tree: &'a SyntaxTree,
+    /// The index of the tree in the trees given to `Linter::run()`.
+    pub index: usize,
+    /// The nodes enclosing the node of the event, outermost first.
+    pub ancestors: &'b [RefNode<'a>],
+    /// The symbol table of the tree, if a rule of the linter needs it.
+    pub symbols: Option<&'b SymbolTable<'a>>,
+    severity: Severity,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a, 'b> RuleContext<'a, 'b> {
+    /// Report a diagnostic at `node` with the severity of the rule.
+    pub fn report<T: Into<String>>(&mut self, node: &RefNode<'a>, message: T) {
+        let severity = self.severity;
+        self.report_with(severity, node, message);
+    }
+
+    /// Report a diagnostic at `node` with `severity`. Its origin is that of
+    /// the first token of the node.
+    pub fn report_with<T: Into<String>>(
+        &mut self,
+        severity: Severity,
+        node: &RefNode<'a>,
+        message: T,
+    ) {
+        let mut diagnostic = Diagnostic::new(severity, "", message);
+        if let (Some(first), Some(last)) = token_range(vec![node.clone()]) {
+            if let Some((path, offset)) = self.tree.get_origin(first) {
+                let len = last.offset + last.len - first.offset;
+                diagnostic = diagnostic.with_origin(path, offset, len);
+            }
+        }
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+/// Applies a set of `Rule`s over trees.
+#[derive(Default)]
+pub struct Linter {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_rule<T: Rule + 'static>(&mut self, rule: T) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// The names of the rules in the order they were added.
+    pub fn rules(&self) -> Vec<&str> {
+        self.rules.iter().map(|x| x.name()).collect()
+    }
+
+    /// Apply each rule to each event of each of `trees`, and get the
+    /// diagnostics in the order they were reported. The rules are called in
+    /// the order they were added for each event.
+    pub fn run<'a, T: IntoIterator<Item = &'a SyntaxTree>>(&mut self, trees: T) -> Vec<Diagnostic> {
+        let mut ret = Vec::new();
+        let needs_symbols = self.rules.iter().any(|x| x.needs_symbols());
+        for (index, tree) in trees.into_iter().enumerate() {
+            let symbols = if needs_symbols {
+                Some(SymbolTable::new(tree))
+            } else {
+                None
+            };
+            let mut stack = Vec::new();
+            let mut skip = 0;
+            for event in tree.into_iter().event() {
+                match &event {
+                    NodeEvent::Enter(RefNode::WhiteSpace(_)) => skip += 1,
+                    NodeEvent::Leave(RefNode::WhiteSpace(_)) => skip -= 1,
+                    NodeEvent::Leave(_) if skip == 0 => {
+                        stack.pop();
+                    }
+                    _ => (),
+                }
+                if skip > 0 || matches!(event, NodeEvent::Leave(RefNode::WhiteSpace(_))) {
+                    continue;
+                }
+
+                for rule in &mut self.rules {
+                    let mut context = RuleContext {
+                        tree,
+                        index,
+                        ancestors: &stack,
+                        symbols: symbols.as_ref(),
+                        severity: rule.severity(),
+                        diagnostics: Vec::new(),
+                    };
+                    rule.event(&event, &mut context);
+                    collect(&**rule, context.diagnostics, &mut ret);
+                }
+                if let NodeEvent::Enter(x) = event {
+                    stack.push(x);
+                }
+            }
+
+            for rule in &mut self.rules {
+                let mut context = RuleContext {
+                    tree,
+                    index,
+                    ancestors: &[],
+                    symbols: symbols.as_ref(),
+                    severity: rule.severity(),
+                    diagnostics: Vec::new(),
+                };
+                rule.finish(&mut context);
+                collect(&**rule, context.diagnostics, &mut ret);
+            }
+        }
+        ret
+    }
+}
+
+fn collect(rule: &dyn Rule, diagnostics: Vec<Diagnostic>, ret: &mut Vec<Diagnostic>) {
+    for mut x in diagnostics {
+        x.code = String::from(rule.name());
+        ret.push(x);
+    }
+}
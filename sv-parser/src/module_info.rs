@@ -0,0 +1,282 @@
+use crate::*;
+
+// The nodes whose ports and parameters are not of the module they are in.
+const NESTED: &[NodeKind] = &[
+    NodeKind::ModuleDeclaration,
+    NodeKind::InterfaceDeclaration,
+    NodeKind::ProgramDeclaration,
+    NodeKind::CheckerDeclaration,
+    NodeKind::ClassDeclaration,
+    NodeKind::FunctionDeclaration,
+    NodeKind::TaskDeclaration,
+    NodeKind::GenerateRegion,
+    NodeKind::LoopGenerateConstruct,
+    NodeKind::ConditionalGenerateConstruct,
+];
+
+/// The direction of a port.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Input,
+    Output,
+    Inout,
+    Ref,
+}
+
+/// A port of a `ModuleInfo`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortInfo {
+    /// The name, which is empty for a port without one, e.g. `{a, b}`.
+    pub name: String,
+    /// The direction, which is `None` for an interface port or a non-ANSI
+    /// port without a port declaration.
+    pub direction: Option<Direction>,
+    /// The type without its packed dimensions, e.g. `wire logic signed`, or
+    /// the interface and modport of an interface port. `None` if implicit.
+    pub data_type: Option<String>,
+    /// The packed dimensions, e.g. `[WIDTH-1:0]`.
+    pub width_expr: Option<String>,
+}
+
+/// A parameter of a `ModuleInfo`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParameterInfo {
+    pub name: String,
+    /// The type, which is `type` for a type parameter. `None` if implicit.
+    pub data_type: Option<String>,
+    /// The default value, or the default type of a type parameter.
+    pub default: Option<String>,
+    /// Whether it is a `localparam`, or a `parameter` in the body of a module
+    /// with a parameter port list, which can't be overridden.
+    pub local: bool,
+}
+
+/// The name, ports and parameters of a module, as text of the source with
+/// white space and comments between tokens replaced by a space.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleInfo {
+    pub name: String,
+    pub ports: Vec<PortInfo>,
+    pub parameters: Vec<ParameterInfo>,
+}
+
+impl ModuleInfo {
+    /// Get the interface of `module`, which is a node of `tree`. The ports of
+    /// a non-ANSI module are in the order of its list of ports, with the
+    /// direction and type of their port declarations. An ANSI port without
+    /// a direction or type takes them from the port before it.
+    pub fn new(tree: &SyntaxTree, module: &ModuleDeclaration) -> Self {
+        let mut ret = ModuleInfo {
+            name: unwrap_node!(module, ModuleIdentifier)
+                .and_then(|x| text(tree, x))
+                .unwrap_or_default(),
+            ports: Vec::new(),
+            parameters: Vec::new(),
+        };
+
+        let mut port_names = Vec::new();
+        let mut declarations = Vec::new();
+        let mut parameter_port_list = false;
+        let mut iter = Iter::new(module.into()).with_ancestors();
+        while let Some(node) = iter.next() {
+            let path = iter.ancestors();
+            if path.iter().skip(1).any(|x| NESTED.contains(&x.kind())) {
+                continue;
+            }
+            match node {
+                RefNode::ParameterPortList(_) => parameter_port_list = true,
+                RefNode::AnsiPortDeclaration(x) => {
+                    let port = ansi_port(tree, x, ret.ports.last());
+                    ret.ports.push(port);
+                }
+                RefNode::Port(x) => port_names.push(port_name(tree, x)),
+                RefNode::PortDeclaration(x) => declarations.extend(port_declaration(tree, x)),
+                RefNode::ParamAssignment(_) | RefNode::TypeAssignment(_) => {
+                    let local = path
+                        .iter()
+                        .any(|x| x.kind() == NodeKind::LocalParameterDeclaration);
+                    let in_header = path.iter().any(|x| x.kind() == NodeKind::ParameterPortList);
+                    let local = local || (parameter_port_list && !in_header);
+                    ret.parameters.push(parameter(tree, &node, path, local));
+                }
+                _ => (),
+            }
+        }
+
+        for name in port_names {
+            let port = declarations.iter().find(|x| x.name == name).cloned();
+            ret.ports.push(port.unwrap_or(PortInfo {
+                name,
+                direction: None,
+                data_type: None,
+                width_expr: None,
+            }));
+        }
+        ret
+    }
+}
+
+fn ansi_port(tree: &SyntaxTree, port: &AnsiPortDeclaration, prev: Option<&PortInfo>) -> PortInfo {
+    let name = unwrap_node!(port, PortIdentifier)
+        .and_then(|x| text(tree, x))
+        .unwrap_or_default();
+    let direction = unwrap_node!(port, PortDirection).map(|x| match x {
+        RefNode::PortDirection(PortDirection::Input(_)) => Direction::Input,
+        RefNode::PortDirection(PortDirection::Output(_)) => Direction::Output,
+        RefNode::PortDirection(PortDirection::Ref(_)) => Direction::Ref,
+        _ => Direction::Inout,
+    });
+    let data_type = unwrap_node!(port, NetPortType, VariablePortType, InterfacePortHeader);
+
+    if let (None, None, Some(prev)) = (direction, &data_type, prev) {
+        return PortInfo {
+            name,
+            ..prev.clone()
+        };
+    }
+    let interface = matches!(data_type, Some(RefNode::InterfacePortHeader(_)));
+    let direction = if interface {
+        None
+    } else {
+        direction
+            .or_else(|| prev.and_then(|x| x.direction))
+            .or(Some(Direction::Inout))
+    };
+    let (data_type, width_expr) = data_type.map_or((None, None), |x| split(tree, x));
+    PortInfo {
+        name,
+        direction,
+        data_type,
+        width_expr,
+    }
+}
+
+fn port_name(tree: &SyntaxTree, port: &Port) -> String {
+    let name = match port {
+        Port::Named(x) => text(tree, &x.nodes.1),
+        Port::NonNamed(x) => match &x.nodes.0 {
+            Some(PortExpression::PortReference(x)) => text(tree, &x.nodes.0),
+            _ => None,
+        },
+    };
+    name.unwrap_or_default()
+}
+
+fn port_declaration(tree: &SyntaxTree, declaration: &PortDeclaration) -> Vec<PortInfo> {
+    let (direction, data_type, width_expr) = match declaration {
+        PortDeclaration::Interface(x) => {
+            let (interface, modport) = (&x.nodes.1.nodes.0, &x.nodes.1.nodes.1);
+            let mut data_type = text(tree, interface).unwrap_or_default();
+            if let Some((_, x)) = modport {
+                data_type = format!("{}.{}", data_type, text(tree, x).unwrap_or_default());
+            }
+            (None, Some(data_type), None)
+        }
+        _ => {
+            let direction = match declaration {
+                PortDeclaration::Input(_) => Direction::Input,
+                PortDeclaration::Output(_) => Direction::Output,
+                PortDeclaration::Ref(_) => Direction::Ref,
+                _ => Direction::Inout,
+            };
+            let data_type = unwrap_node!(declaration, NetPortType, VariablePortType);
+            let (data_type, width_expr) = data_type.map_or((None, None), |x| split(tree, x));
+            (Some(direction), data_type, width_expr)
+        }
+    };
+
+    let list = unwrap_node!(
+        declaration,
+        ListOfPortIdentifiers,
+        ListOfVariableIdentifiers,
+        ListOfVariablePortIdentifiers,
+        ListOfInterfaceIdentifiers
+    );
+    let names = list.into_iter().flatten().filter(|x| {
+        matches!(
+            x,
+            RefNode::PortIdentifier(_)
+                | RefNode::VariableIdentifier(_)
+                | RefNode::InterfaceIdentifier(_)
+        )
+    });
+    names
+        .map(|x| PortInfo {
+            name: text(tree, x).unwrap_or_default(),
+            direction,
+            data_type: data_type.clone(),
+            width_expr: width_expr.clone(),
+        })
+        .collect()
+}
+
+fn parameter(tree: &SyntaxTree, node: &RefNode, path: &[RefNode], local: bool) -> ParameterInfo {
+    match node {
+        RefNode::TypeAssignment(x) => ParameterInfo {
+            name: text(tree, &x.nodes.0).unwrap_or_default(),
+            data_type: Some(String::from("type")),
+            default: x.nodes.1.as_ref().and_then(|(_, x)| text(tree, x)),
+            local,
+        },
+        RefNode::ParamAssignment(x) => {
+            let data_type = path.iter().rev().find_map(|x| match x {
+                RefNode::ParameterDeclarationParam(x) => Some(text(tree, &x.nodes.1)),
+                RefNode::LocalParameterDeclarationParam(x) => Some(text(tree, &x.nodes.1)),
+                RefNode::ParameterPortDeclarationParamList(x) => Some(text(tree, &x.nodes.0)),
+                _ => None,
+            });
+            ParameterInfo {
+                name: text(tree, &x.nodes.0).unwrap_or_default(),
+                data_type: data_type.flatten(),
+                default: x.nodes.2.as_ref().and_then(|(_, x)| text(tree, x)),
+                local,
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+// The tokens of `nodes`, each with whether it is in a packed dimension.
+fn tokens<'a, T: Into<RefNodes<'a>>>(nodes: T) -> Vec<(&'a Locate, bool)> {
+    let mut ret = Vec::new();
+    let mut iter = Iter::new(nodes.into()).with_ancestors();
+    while let Some(node) = iter.next() {
+        if let RefNode::Locate(x) = node {
+            if !iter.is_inside(NodeKind::WhiteSpace) {
+                ret.push((x, iter.is_inside(NodeKind::PackedDimension)));
+            }
+        }
+    }
+    ret
+}
+
+// The text of `tokens`, separated by a space where the source has white space.
+fn join<'a, T: IntoIterator<Item = &'a Locate>>(tree: &SyntaxTree, tokens: T) -> Option<String> {
+    let mut ret = String::new();
+    let mut end = None;
+    for x in tokens {
+        if end.is_some_and(|end| end < x.offset) {
+            ret.push(' ');
+        }
+        ret.push_str(tree.get_str(x)?);
+        end = Some(x.offset + x.len);
+    }
+    if ret.is_empty() {
+        None
+    } else {
+        Some(ret)
+    }
+}
+
+fn text<'a, T: Into<RefNodes<'a>>>(tree: &SyntaxTree, nodes: T) -> Option<String> {
+    join(tree, tokens(nodes).into_iter().map(|x| x.0))
+}
+
+// The text of a type without its packed dimensions, and of the dimensions.
+fn split(tree: &SyntaxTree, node: RefNode) -> (Option<String>, Option<String>) {
+    let (width, data_type): (Vec<_>, Vec<_>) = tokens(vec![node]).into_iter().partition(|x| x.1);
+    (
+        join(tree, data_type.into_iter().map(|x| x.0)),
+        join(tree, width.into_iter().map(|x| x.0)),
+    )
+}
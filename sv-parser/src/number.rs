@@ -0,0 +1,216 @@
+use crate::*;
+
+/// The base of an `IntegralValue`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberBase {
+    Binary,
+    Octal,
+    Decimal,
+    Hex,
+}
+
+/// A bit of a four-state value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bit {
+    Zero,
+    One,
+    X,
+    Z,
+}
+
+/// The value of an `IntegralNumber`, e.g. `8'shf0` or `42`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntegralValue {
+    /// The size before the base, e.g. 8 for `8'hff`.
+    pub size: Option<u32>,
+    /// Whether the base has `s`, or it is an unbased decimal number.
+    pub signed: bool,
+    pub base: NumberBase,
+    /// The bits from the least significant, as many as the size or at least
+    /// 32 for an unsized number. As for an assignment, the digits are
+    /// truncated to the size, or padded with zero, or with x or z if that is
+    /// the leftmost digit.
+    pub bits: Vec<Bit>,
+}
+
+impl IntegralValue {
+    /// Get the value of `node`, which is a node of `tree`. `None` is returned
+    /// if the size doesn't fit in a `u32`.
+    pub fn new(tree: &SyntaxTree, node: &IntegralNumber) -> Option<Self> {
+        let (size, base, value) = match node {
+            IntegralNumber::DecimalNumber(x) => match &**x {
+                DecimalNumber::UnsignedNumber(x) => {
+                    let bits = decimal_bits(tree.get_str(&x.nodes.0)?);
+                    return Some(Self::with_size(None, true, NumberBase::Decimal, bits));
+                }
+                DecimalNumber::BaseUnsigned(x) => {
+                    (&x.nodes.0, &x.nodes.1.nodes.0, &x.nodes.2.nodes.0)
+                }
+                DecimalNumber::BaseXNumber(x) => {
+                    (&x.nodes.0, &x.nodes.1.nodes.0, &x.nodes.2.nodes.0)
+                }
+                DecimalNumber::BaseZNumber(x) => {
+                    (&x.nodes.0, &x.nodes.1.nodes.0, &x.nodes.2.nodes.0)
+                }
+            },
+            IntegralNumber::OctalNumber(x) => (&x.nodes.0, &x.nodes.1.nodes.0, &x.nodes.2.nodes.0),
+            IntegralNumber::BinaryNumber(x) => (&x.nodes.0, &x.nodes.1.nodes.0, &x.nodes.2.nodes.0),
+            IntegralNumber::HexNumber(x) => (&x.nodes.0, &x.nodes.1.nodes.0, &x.nodes.2.nodes.0),
+        };
+
+        let size = match size {
+            Some(x) => {
+                let size = digits(tree.get_str(&x.nodes.0.nodes.0)?);
+                Some(size.parse::<u32>().ok()?)
+            }
+            None => None,
+        };
+        let base = tree.get_str(base)?;
+        let signed = base.to_ascii_lowercase().contains('s');
+        let base = match base.chars().last()?.to_ascii_lowercase() {
+            'b' => NumberBase::Binary,
+            'o' => NumberBase::Octal,
+            'h' => NumberBase::Hex,
+            _ => NumberBase::Decimal,
+        };
+        let value = digits(tree.get_str(value)?);
+        let bits = match base {
+            NumberBase::Binary => digit_bits(&value, 1, 2)?,
+            NumberBase::Octal => digit_bits(&value, 3, 8)?,
+            NumberBase::Hex => digit_bits(&value, 4, 16)?,
+            NumberBase::Decimal => match value.chars().next().and_then(four_state) {
+                Some(x) => vec![x],
+                None => decimal_bits(&value),
+            },
+        };
+        Some(Self::with_size(size, signed, base, bits))
+    }
+
+    fn with_size(size: Option<u32>, signed: bool, base: NumberBase, mut bits: Vec<Bit>) -> Self {
+        let width = match size {
+            Some(x) => x as usize,
+            None => bits.len().max(32),
+        };
+        let pad = match bits.last() {
+            Some(Bit::X) => Bit::X,
+            Some(Bit::Z) => Bit::Z,
+            _ => Bit::Zero,
+        };
+        bits.resize(width, pad);
+        IntegralValue {
+            size,
+            signed,
+            base,
+            bits,
+        }
+    }
+
+    /// Whether all bits are 0 or 1.
+    pub fn is_known(&self) -> bool {
+        self.bits.iter().all(|x| *x == Bit::Zero || *x == Bit::One)
+    }
+
+    /// The value as an unsigned integer, or `None` if it has an x or z bit or
+    /// doesn't fit in a `u64`.
+    pub fn to_u64(&self) -> Option<u64> {
+        let mut ret = 0;
+        for (i, bit) in self.bits.iter().enumerate() {
+            match bit {
+                Bit::Zero => (),
+                Bit::One if i < 64 => ret |= 1 << i,
+                _ => return None,
+            }
+        }
+        Some(ret)
+    }
+
+    /// The bytes of the value from the least significant, e.g. for
+    /// `BigUint::from_bytes_le`, or `None` if it has an x or z bit.
+    pub fn to_bytes_le(&self) -> Option<Vec<u8>> {
+        self.bits
+            .chunks(8)
+            .map(|x| {
+                let mut byte = 0;
+                for (i, bit) in x.iter().enumerate() {
+                    match bit {
+                        Bit::Zero => (),
+                        Bit::One => byte |= 1 << i,
+                        _ => return None,
+                    }
+                }
+                Some(byte)
+            })
+            .collect()
+    }
+}
+
+/// Get the value of `node`, which is a node of `tree`, e.g. 1500.0 for
+/// `1.5e3`.
+pub fn real_value(tree: &SyntaxTree, node: &RealNumber) -> Option<f64> {
+    digits(tree.get_str_trim(node)?).parse().ok()
+}
+
+// The text of a number without `_`.
+fn digits(s: &str) -> String {
+    s.chars().filter(|x| *x != '_').collect()
+}
+
+fn four_state(c: char) -> Option<Bit> {
+    match c {
+        'x' | 'X' => Some(Bit::X),
+        'z' | 'Z' | '?' => Some(Bit::Z),
+        _ => None,
+    }
+}
+
+fn bit(x: u32, i: usize) -> Bit {
+    if (x >> i) & 1 == 1 {
+        Bit::One
+    } else {
+        Bit::Zero
+    }
+}
+
+// The bits of the digits of `value`, each of `width` bits.
+fn digit_bits(value: &str, width: usize, radix: u32) -> Option<Vec<Bit>> {
+    let mut ret = Vec::new();
+    for c in value.chars().rev() {
+        if let Some(x) = four_state(c) {
+            ret.resize(ret.len() + width, x);
+            continue;
+        }
+        let x = c.to_digit(radix)?;
+        for i in 0..width {
+            ret.push(bit(x, i));
+        }
+    }
+    Some(ret)
+}
+
+// The bits of a decimal number of any length, without leading zeros.
+fn decimal_bits(value: &str) -> Vec<Bit> {
+    // The value in 32-bit words from the least significant.
+    let mut words: Vec<u32> = Vec::new();
+    for d in value.chars().filter_map(|x| x.to_digit(10)) {
+        let mut carry = u64::from(d);
+        for x in &mut words {
+            let y = u64::from(*x) * 10 + carry;
+            *x = y as u32;
+            carry = y >> 32;
+        }
+        if carry > 0 {
+            words.push(carry as u32);
+        }
+    }
+
+    let mut ret = Vec::new();
+    for x in words {
+        for i in 0..32 {
+            ret.push(bit(x, i));
+        }
+    }
+    while ret.last() == Some(&Bit::Zero) {
+        ret.pop();
+    }
+    ret
+}
@@ -0,0 +1,392 @@
+use crate::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// How the files of a `Project` make up compilation units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompilationUnitMode {
+    /// All the files are one compilation unit, so the macros defined and the
+    /// `` `default_nettype `` set by a file are in effect in the files after
+    /// it, like `-mfcu` of Questa.
+    #[default]
+    Single,
+    /// Each file is a compilation unit of its own, which starts from the
+    /// pre-defines and `` `default_nettype wire ``, like `-sfcu` of Questa.
+    PerFile,
+}
+
+/// The files of a design, preprocessed and parsed in order, by default as
+/// one compilation unit.
+#[derive(Clone, Debug, Default)]
+pub struct Project {
+    /// The source files in the order they are compiled.
+    pub files: Vec<PathBuf>,
+    pub include_paths: Vec<PathBuf>,
+    /// The macros defined before the first file, e.g. by `+define+`.
+    pub defines: Defines,
+    pub config: PreprocessorConfig,
+    pub allow_incomplete: bool,
+    pub mode: CompilationUnitMode,
+}
+
+impl Project {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The files, include paths and pre-defines given by the argument file
+    /// `path`, e.g. the `.f` file of a simulator. See
+    /// `sv_parser_pp::args::parse_file()` for the arguments recognized.
+    #[cfg(feature = "fs")]
+    pub fn from_filelist<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let (files, include_paths, defines) = sv_parser_pp::args::parse_file(path)?;
+        Ok(Project {
+            files,
+            include_paths,
+            defines,
+            ..Project::default()
+        })
+    }
+
+    /// Preprocess and parse each file in order. With
+    /// `CompilationUnitMode::Single`, the macros defined by a file are
+    /// visible in the files after it even if it fails to parse, but not if it
+    /// fails to preprocess. The problems of each file are followed by those
+    /// across the files: a module, interface, program, package or primitive
+    /// defined more than once, and the instantiation of one which isn't
+    /// defined by any file.
+    pub fn parse(&self) -> CompilationUnit {
+        let mut ret = CompilationUnit {
+            defines: self.defines.clone(),
+            ..CompilationUnit::default()
+        };
+        let mut default_nettype = String::from("wire");
+        for path in &self.files {
+            let pre_defines = match self.mode {
+                CompilationUnitMode::Single => &ret.defines,
+                CompilationUnitMode::PerFile => &self.defines,
+            };
+            let (mut text, defines) = match preprocess_with_config(
+                path,
+                pre_defines,
+                &self.include_paths,
+                &self.config,
+            ) {
+                Ok(x) => x,
+                Err(x) => {
+                    ret.diagnostics.push(Diagnostic::from(&x));
+                    continue;
+                }
+            };
+            if self.mode == CompilationUnitMode::Single {
+                text.set_initial_default_nettype(default_nettype);
+                default_nettype = String::from(text.default_nettype(text.text().len()));
+            }
+            ret.diagnostics.extend(text.diagnostics());
+            ret.defines = defines;
+            match parse_sv_pp(text, Defines::default(), self.allow_incomplete) {
+                Ok((tree, _)) => {
+                    ret.trees.push(tree);
+                    ret.paths.push(path.clone());
+                }
+                Err(x) => ret.diagnostics.push(Diagnostic::from(&x)),
+            }
+        }
+        let diagnostics = ret.check_definitions();
+        ret.diagnostics.extend(diagnostics);
+        ret
+    }
+
+    /// Preprocess each file like `parse()`, and report the macros which are
+    /// likely dead. The files which fail to preprocess are left out, and
+    /// their errors are reported. The observer of `config` isn't notified.
+    pub fn define_report(&self) -> DefineReport {
+        let recorder = Arc::new(DefineRecorder::default());
+        let config = PreprocessorConfig {
+            observer: Some(recorder.clone()),
+            ..self.config.clone()
+        };
+        let mut defines = self.defines.clone();
+        let mut errors = Diagnostics::new();
+        for path in &self.files {
+            let pre_defines = match self.mode {
+                CompilationUnitMode::Single => &defines,
+                CompilationUnitMode::PerFile => &self.defines,
+            };
+            match preprocess_with_config(path, pre_defines, &self.include_paths, &config) {
+                Ok((_, x)) => defines = x,
+                Err(x) => errors.push(Diagnostic::from(&x)),
+            }
+        }
+        let record = recorder.record.lock().unwrap();
+        DefineReport {
+            errors,
+            ..record.report(&self.defines)
+        }
+    }
+}
+
+/// The result of `Project::parse()`.
+#[derive(Debug, Default)]
+pub struct CompilationUnit {
+    /// The trees of the files which parsed, in the order of the files.
+    pub trees: Vec<SyntaxTree>,
+    /// The file of each of `trees`.
+    pub paths: Vec<PathBuf>,
+    /// The macros defined after the last file, which with
+    /// `CompilationUnitMode::PerFile` are those of the last file alone.
+    pub defines: Defines,
+    pub diagnostics: Diagnostics,
+}
+
+impl CompilationUnit {
+    /// The hierarchy of the design units of all the trees.
+    pub fn hierarchy(&self) -> Hierarchy {
+        Hierarchy::new(&self.trees)
+    }
+
+    // The diagnostics of the definitions of design units across the trees.
+    fn check_definitions(&self) -> Diagnostics {
+        let mut ret = Diagnostics::new();
+        // The file of the first definition of each name.
+        let mut defined: HashMap<String, Option<PathBuf>> = HashMap::new();
+        let mut instances = Vec::new();
+        for tree in &self.trees {
+            let mut iter = tree.into_iter().with_ancestors();
+            while let Some(node) = iter.next() {
+                if let RefNode::ModuleInstantiation(x) = &node {
+                    instances.push((tree, RefNode::from(&x.nodes.0)));
+                    continue;
+                }
+                if !matches!(iter.parent(), Some(RefNode::Description(_))) || is_extern(&node) {
+                    continue;
+                }
+                let name = match unwrap_node!(
+                    node,
+                    ModuleIdentifier,
+                    InterfaceIdentifier,
+                    ProgramIdentifier,
+                    PackageIdentifier,
+                    UdpIdentifier
+                ) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let s = text_of(tree, name.clone());
+                match defined.get(&s) {
+                    Some(first) => {
+                        let message = format!("Design unit redefined: {}", s);
+                        let mut diagnostic = diagnostic_at(
+                            tree,
+                            name,
+                            Severity::Error,
+                            "duplicate-definition",
+                            message,
+                        );
+                        if let Some(x) = first {
+                            diagnostic
+                                .notes
+                                .push(format!("first defined in {}", x.display()));
+                        }
+                        ret.push(diagnostic);
+                    }
+                    None => {
+                        let path = unwrap_locate!(name)
+                            .and_then(|x| tree.get_origin(x))
+                            .map(|x| x.0.clone());
+                        defined.insert(s, path);
+                    }
+                }
+            }
+        }
+
+        for (tree, name) in instances {
+            let s = text_of(tree, name.clone());
+            if !defined.contains_key(&s) {
+                let message = format!("Design unit not defined: {}", s);
+                ret.push(diagnostic_at(
+                    tree,
+                    name,
+                    Severity::Warning,
+                    "undefined-unit",
+                    message,
+                ));
+            }
+        }
+        ret
+    }
+}
+
+/// The macros of a project which are likely dead, found by
+/// `Project::define_report()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DefineReport {
+    /// The macros defined by `define which are never used or tested.
+    pub unused: Vec<MacroLocation>,
+    /// The macros used only within branches of `ifdef or `ifndef which
+    /// aren't taken.
+    pub skipped_only: Vec<MacroLocation>,
+    /// The macros tested by `ifdef, `ifndef or `elsif which no file defines
+    /// and which aren't pre-defined, at each test.
+    pub undefined_guards: Vec<MacroLocation>,
+    /// The errors of the files which failed to preprocess.
+    pub errors: Diagnostics,
+}
+
+impl DefineReport {
+    /// The errors, followed by the report as warnings with the codes
+    /// `unused-define`, `skipped-only-define` and `undefined-guard`.
+    pub fn diagnostics(&self) -> Diagnostics {
+        let mut ret = self.errors.clone();
+        let kinds = [
+            (&self.unused, "unused-define", "Macro never used"),
+            (
+                &self.skipped_only,
+                "skipped-only-define",
+                "Macro used only in skipped branches",
+            ),
+            (
+                &self.undefined_guards,
+                "undefined-guard",
+                "Macro never defined",
+            ),
+        ];
+        for (locations, code, message) in kinds {
+            for x in locations {
+                let message = format!("{}: {}", message, x.name);
+                ret.push(
+                    Diagnostic::new(Severity::Warning, code, message)
+                        .with_origin(&x.path, x.offset, 0),
+                );
+            }
+        }
+        ret
+    }
+}
+
+/// A macro, and the directive which defines or tests it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MacroLocation {
+    pub name: String,
+    pub path: PathBuf,
+    /// The byte offset of the directive within `path`.
+    pub offset: usize,
+}
+
+// Records the macros defined, used and tested while preprocessing.
+#[derive(Debug, Default)]
+struct DefineRecorder {
+    record: Mutex<DefineRecord>,
+}
+
+#[derive(Debug, Default)]
+struct DefineRecord {
+    // The first definition of each macro, in order.
+    defined: Vec<MacroLocation>,
+    names: HashSet<String>,
+    used: HashSet<String>,
+    used_skipped: HashSet<String>,
+    guards: Vec<MacroLocation>,
+}
+
+impl DefineRecord {
+    fn report(&self, pre_defines: &Defines) -> DefineReport {
+        let mut ret = DefineReport::default();
+        for x in &self.defined {
+            if self.used.contains(&x.name) {
+                continue;
+            }
+            if self.used_skipped.contains(&x.name) {
+                ret.skipped_only.push(x.clone());
+            } else {
+                ret.unused.push(x.clone());
+            }
+        }
+        ret.undefined_guards = self
+            .guards
+            .iter()
+            .filter(|x| !self.names.contains(&x.name) && !pre_defines.contains_key(&x.name))
+            .cloned()
+            .collect();
+        ret
+    }
+}
+
+impl PreprocessObserver for DefineRecorder {
+    fn define(&self, define: &Define, path: &Path, offset: usize) {
+        let mut record = self.record.lock().unwrap();
+        if record.names.insert(define.identifier.clone()) {
+            record.defined.push(MacroLocation {
+                name: define.identifier.clone(),
+                path: PathBuf::from(path),
+                offset,
+            });
+        }
+    }
+
+    fn conditional(&self, identifier: Option<&str>, _taken: bool, path: &Path, offset: usize) {
+        // The condition is a macro, or an expression of macros.
+        let condition = match identifier {
+            Some(x) => x,
+            None => return,
+        };
+        let mut record = self.record.lock().unwrap();
+        let names = condition
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+            .filter(|x| !x.is_empty());
+        for name in names {
+            record.used.insert(String::from(name));
+            record.guards.push(MacroLocation {
+                name: String::from(name),
+                path: PathBuf::from(path),
+                offset,
+            });
+        }
+    }
+
+    fn macro_usage(&self, identifier: &str, skipped: bool, _path: &Path, _offset: usize) {
+        let mut record = self.record.lock().unwrap();
+        if skipped {
+            record.used_skipped.insert(String::from(identifier));
+        } else {
+            record.used.insert(String::from(identifier));
+        }
+    }
+}
+
+fn is_extern(node: &RefNode) -> bool {
+    matches!(
+        node,
+        RefNode::ModuleDeclaration(ModuleDeclaration::ExternNonansi(_))
+            | RefNode::ModuleDeclaration(ModuleDeclaration::ExternAnsi(_))
+            | RefNode::InterfaceDeclaration(InterfaceDeclaration::ExternNonansi(_))
+            | RefNode::InterfaceDeclaration(InterfaceDeclaration::ExternAnsi(_))
+            | RefNode::ProgramDeclaration(ProgramDeclaration::ExternNonansi(_))
+            | RefNode::ProgramDeclaration(ProgramDeclaration::ExternAnsi(_))
+            | RefNode::UdpDeclaration(UdpDeclaration::ExternNonansi(_))
+            | RefNode::UdpDeclaration(UdpDeclaration::ExternAnsi(_))
+    )
+}
+
+fn text_of(tree: &SyntaxTree, node: RefNode) -> String {
+    tree.get_str_trim(node)
+        .map(String::from)
+        .unwrap_or_default()
+}
+
+// A diagnostic at the identifier `name`.
+fn diagnostic_at(
+    tree: &SyntaxTree,
+    name: RefNode,
+    severity: Severity,
+    code: &str,
+    message: String,
+) -> Diagnostic {
+    let mut ret = Diagnostic::new(severity, code, message);
+    if let Some(locate) = unwrap_locate!(name) {
+        if let Some((path, offset)) = tree.get_origin(locate) {
+            ret = ret.with_origin(path, offset, locate.len);
+        }
+    }
+    ret
+}
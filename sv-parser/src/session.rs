@@ -0,0 +1,230 @@
+use crate::*;
+
+/// A position in a document as a line and a character within the line, both
+/// from 0, where characters are counted in UTF-16 code units as by the
+/// Language Server Protocol.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+/// A replacement of the text between two positions, like a change of
+/// `textDocument/didChange` of the Language Server Protocol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: Position,
+    pub end: Position,
+    pub text: String,
+}
+
+/// The declaring identifier found by `DocumentSession::definition()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Definition {
+    /// The file the identifier originates from, which is a file included by
+    /// the document, or the document itself.
+    pub path: PathBuf,
+    /// The byte range of the identifier within the file. It is converted to
+    /// positions by `DocumentSession::position()` if the file is the
+    /// document.
+    pub begin: usize,
+    pub end: usize,
+}
+
+/// An open document, which is parsed again after each change, e.g. as the
+/// core of a language server.
+///
+/// A change is parsed by `reparse_sv_str()`, so only the descriptions it
+/// touches are parsed again. The files the document includes are read only
+/// once if the preprocessor is given a `PreprocessCache`, which may be shared
+/// by the sessions of all documents.
+#[derive(Debug)]
+pub struct DocumentSession {
+    path: PathBuf,
+    text: String,
+    preprocessor: Preprocessor,
+    allow_incomplete: bool,
+    tree: Option<SyntaxTree>,
+    defines: Option<Defines>,
+    error: Option<Error>,
+}
+
+impl DocumentSession {
+    /// Open the document `path` with `text`, and parse it with the
+    /// pre-defines, include paths and options of `preprocessor`.
+    pub fn new<T: AsRef<Path>, U: Into<String>>(
+        path: T,
+        text: U,
+        preprocessor: Preprocessor,
+        allow_incomplete: bool,
+    ) -> Self {
+        let mut ret = DocumentSession {
+            path: PathBuf::from(path.as_ref()),
+            text: text.into(),
+            preprocessor,
+            allow_incomplete,
+            tree: None,
+            defines: None,
+            error: None,
+        };
+        ret.parse();
+        ret
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The tree of the current text, or `None` if it failed to parse.
+    pub fn tree(&self) -> Option<&SyntaxTree> {
+        self.tree.as_ref()
+    }
+
+    /// The macros defined after the current text, or `None` if it failed to
+    /// parse.
+    pub fn defines(&self) -> Option<&Defines> {
+        self.defines.as_ref()
+    }
+
+    /// The error of the current text, if it failed to parse.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Replace the whole text, and parse it.
+    pub fn set_text<T: Into<String>>(&mut self, text: T) {
+        self.text = text.into();
+        self.parse();
+    }
+
+    /// Apply `edits` in order, each to the text left by the ones before it,
+    /// and then parse the text. A position beyond the end of a line or of the
+    /// text is taken as that end.
+    pub fn apply_edits(&mut self, edits: &[TextEdit]) {
+        for edit in edits {
+            let start = self.offset(edit.start);
+            let end = self.offset(edit.end).max(start);
+            self.text.replace_range(start..end, &edit.text);
+        }
+        self.parse();
+    }
+
+    /// The byte offset of `position` within the text.
+    pub fn offset(&self, position: Position) -> usize {
+        let mut begin = 0;
+        for _ in 0..position.line {
+            match self.text[begin..].find('\n') {
+                Some(x) => begin += x + 1,
+                None => return self.text.len(),
+            }
+        }
+        let line = &self.text[begin..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+        let mut character = 0;
+        for (i, c) in line.char_indices() {
+            if character >= position.character {
+                return begin + i;
+            }
+            character += c.len_utf16() as u32;
+        }
+        begin + line.len()
+    }
+
+    /// The position of the byte `offset` within the text.
+    pub fn position(&self, offset: usize) -> Position {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let begin = self.text[..offset].rfind('\n').map_or(0, |x| x + 1);
+        Position {
+            line: self.text[..begin].matches('\n').count() as u32,
+            character: self.text[begin..offset].encode_utf16().count() as u32,
+        }
+    }
+
+    /// The nodes enclosing the token at `position`, outermost first and
+    /// ending with the token itself. This is empty if there is no token at
+    /// the position, or if the text failed to parse. A token is found in the
+    /// first expansion of the text at the position, e.g. of a macro used more
+    /// than once.
+    pub fn nodes_at(&self, position: Position) -> Vec<RefNode<'_>> {
+        let tree = match &self.tree {
+            Some(x) => x,
+            None => return Vec::new(),
+        };
+        let offset = self.offset(position);
+        let pos = match tree
+            .text()
+            .origin_to_preprocessed(&self.path, offset)
+            .first()
+        {
+            Some(x) => *x,
+            None => return Vec::new(),
+        };
+
+        let mut iter = tree.into_iter().with_ancestors();
+        while let Some(node) = iter.next() {
+            if let RefNode::Locate(x) = &node {
+                if x.offset <= pos && pos < x.offset + x.len {
+                    let mut ret = iter.ancestors().to_vec();
+                    ret.push(node);
+                    return ret;
+                }
+            }
+        }
+        Vec::new()
+    }
+
+    /// Find the declaration of the identifier at `position` by
+    /// `SymbolTable::resolve()`.
+    pub fn definition(&self, position: Position) -> Option<Definition> {
+        let tree = self.tree.as_ref()?;
+        let nodes = self.nodes_at(position);
+        let identifier = nodes.into_iter().rev().find(|x| {
+            matches!(
+                x,
+                RefNode::SimpleIdentifier(_) | RefNode::EscapedIdentifier(_)
+            )
+        })?;
+        let declaration = SymbolTable::new(tree).resolve(identifier)?;
+
+        let locate = unwrap_locate!(declaration)?;
+        let (path, begin) = tree.get_origin(locate)?;
+        Some(Definition {
+            path: path.clone(),
+            begin,
+            end: begin + locate.len,
+        })
+    }
+
+    // Parse the text, again only where it changed if the last text parsed.
+    fn parse(&mut self) {
+        let ret = self
+            .preprocessor
+            .preprocess_str(&self.text, &self.path)
+            .and_then(|(text, defines)| match self.tree.take() {
+                Some(tree) => match reparse(tree, &text) {
+                    Some(node) => Ok((SyntaxTree::new(node, text), defines)),
+                    None => parse_sv_pp(text, defines, self.allow_incomplete),
+                },
+                None => parse_sv_pp(text, defines, self.allow_incomplete),
+            });
+        match ret {
+            Ok((tree, defines)) => {
+                self.tree = Some(tree);
+                self.defines = Some(defines);
+                self.error = None;
+            }
+            Err(x) => {
+                self.tree = None;
+                self.defines = None;
+                self.error = Some(x);
+            }
+        }
+    }
+}
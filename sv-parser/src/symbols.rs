@@ -0,0 +1,384 @@
+use crate::*;
+use std::collections::HashMap;
+
+// The nodes which make a scope for the names declared within them.
+const SCOPES: &[NodeKind] = &[
+    NodeKind::SourceText,
+    NodeKind::ModuleDeclarationNonansi,
+    NodeKind::ModuleDeclarationAnsi,
+    NodeKind::InterfaceDeclarationNonansi,
+    NodeKind::InterfaceDeclarationAnsi,
+    NodeKind::ProgramDeclarationNonansi,
+    NodeKind::ProgramDeclarationAnsi,
+    NodeKind::PackageDeclaration,
+    NodeKind::ClassDeclaration,
+    NodeKind::FunctionDeclaration,
+    NodeKind::TaskDeclaration,
+    NodeKind::SeqBlock,
+    NodeKind::ParBlock,
+    NodeKind::GenerateBlockMultiple,
+    NodeKind::LoopStatementFor,
+];
+
+// The scopes which can be found by a hierarchical name through an instance.
+const DESIGN_UNITS: &[NodeKind] = &[
+    NodeKind::ModuleDeclarationNonansi,
+    NodeKind::ModuleDeclarationAnsi,
+    NodeKind::InterfaceDeclarationNonansi,
+    NodeKind::InterfaceDeclarationAnsi,
+    NodeKind::ProgramDeclarationNonansi,
+    NodeKind::ProgramDeclarationAnsi,
+];
+
+// The scopes which can be found by a `::` prefix.
+const PACKAGES: &[NodeKind] = &[NodeKind::PackageDeclaration, NodeKind::ClassDeclaration];
+
+// The nodes whose identifier is the name of the scope they are in. The name
+// is declared in the enclosing scope.
+const NAMES: &[NodeKind] = &[
+    NodeKind::ModuleNonansiHeader,
+    NodeKind::ModuleAnsiHeader,
+    NodeKind::InterfaceNonansiHeader,
+    NodeKind::InterfaceAnsiHeader,
+    NodeKind::ProgramNonansiHeader,
+    NodeKind::ProgramAnsiHeader,
+    NodeKind::PackageDeclaration,
+    NodeKind::ClassDeclaration,
+    NodeKind::FunctionBodyDeclarationWithoutPort,
+    NodeKind::FunctionBodyDeclarationWithPort,
+    NodeKind::TaskBodyDeclarationWithoutPort,
+    NodeKind::TaskBodyDeclarationWithPort,
+    NodeKind::SeqBlock,
+    NodeKind::ParBlock,
+    NodeKind::GenerateBlockMultiple,
+];
+
+// The nodes whose identifiers are declared in the scope they are in.
+const DECLARATIONS: &[NodeKind] = &[
+    NodeKind::NetDeclAssignment,
+    NodeKind::VariableDeclAssignmentVariable,
+    NodeKind::VariableDeclAssignmentDynamicArray,
+    NodeKind::VariableDeclAssignmentClass,
+    NodeKind::AnsiPortDeclarationNet,
+    NodeKind::AnsiPortDeclarationVariable,
+    NodeKind::AnsiPortDeclarationParen,
+    NodeKind::ListOfPortIdentifiers,
+    NodeKind::ListOfVariableIdentifiers,
+    NodeKind::ListOfVariablePortIdentifiers,
+    NodeKind::ListOfInterfaceIdentifiers,
+    NodeKind::ListOfTfVariableIdentifiers,
+    NodeKind::ListOfGenvarIdentifiers,
+    NodeKind::ParamAssignment,
+    NodeKind::TypeAssignment,
+    NodeKind::TypeDeclarationDataType,
+    NodeKind::TfPortItem,
+    NodeKind::NameOfInstance,
+    NodeKind::EnumNameDeclaration,
+    NodeKind::ForVariableDeclaration,
+    NodeKind::SpecparamAssignment,
+];
+
+// The instantiations whose `NameOfInstance` names an instance of a design unit.
+const INSTANTIATIONS: &[NodeKind] = &[
+    NodeKind::ModuleInstantiation,
+    NodeKind::InterfaceInstantiation,
+    NodeKind::ProgramInstantiation,
+];
+
+// The nodes whose identifier names a port or parameter of an instance.
+const CONNECTIONS: &[NodeKind] = &[
+    NodeKind::NamedPortConnectionIdentifier,
+    NodeKind::NamedParameterAssignment,
+];
+
+/// A scope of a `SymbolTable`.
+#[derive(Clone, Debug)]
+pub struct Scope<'a> {
+    /// The node making the scope, e.g. a `ModuleDeclarationAnsi` or a
+    /// `SeqBlock`.
+    pub node: RefNode<'a>,
+    /// The index of the enclosing scope, which is `None` for the root.
+    pub parent: Option<usize>,
+    pub name: Option<&'a str>,
+    /// The identifier declaring each name, e.g. a `NetIdentifier`.
+    pub declarations: HashMap<&'a str, RefNode<'a>>,
+    /// The package and the name of each `import`, which is `None` for `*`.
+    pub imports: Vec<(&'a str, Option<&'a str>)>,
+}
+
+// How the name of a reference is qualified.
+#[derive(Clone, Debug)]
+enum Qualifier<'a> {
+    None,
+    Scope(&'a str),
+    Unit(&'a str),
+    Hierarchy(Vec<&'a str>),
+}
+
+#[derive(Clone, Debug)]
+struct Reference<'a> {
+    scope: usize,
+    name: &'a str,
+    qualifier: Qualifier<'a>,
+}
+
+/// The names declared in each scope of a tree, to find the declaration of an
+/// identifier with `resolve()`.
+#[derive(Clone, Debug)]
+pub struct SymbolTable<'a> {
+    scopes: Vec<Scope<'a>>,
+    // The references by the `id()` of their `SimpleIdentifier` or
+    // `EscapedIdentifier`.
+    references: HashMap<(usize, usize), Reference<'a>>,
+    // The scope named by a declaration.
+    named: HashMap<(usize, usize), usize>,
+    // The design unit of an instance.
+    instances: HashMap<(usize, usize), &'a str>,
+}
+
+impl<'a> SymbolTable<'a> {
+    pub fn new(tree: &'a SyntaxTree) -> Self {
+        let mut ret = SymbolTable {
+            scopes: Vec::new(),
+            references: HashMap::new(),
+            named: HashMap::new(),
+            instances: HashMap::new(),
+        };
+        let mut scope_ids = HashMap::new();
+        let mut iter = tree.into_iter().with_ancestors();
+        while let Some(node) = iter.next() {
+            let path = iter.ancestors();
+            let scope = path
+                .iter()
+                .rev()
+                .find_map(|x| scope_ids.get(&x.id()).copied());
+            if scope.is_none() || SCOPES.contains(&node.kind()) {
+                scope_ids.insert(node.id(), ret.scopes.len());
+                ret.scopes.push(Scope {
+                    node,
+                    parent: scope,
+                    name: None,
+                    declarations: HashMap::new(),
+                    imports: Vec::new(),
+                });
+                continue;
+            }
+            let scope = scope.unwrap();
+
+            match node {
+                RefNode::PackageImportItemIdentifier(x) => {
+                    let package = name(tree, (&x.nodes.0).into());
+                    let item = name(tree, (&x.nodes.2).into());
+                    ret.scopes[scope].imports.push((package, Some(item)));
+                    continue;
+                }
+                RefNode::PackageImportItemAsterisk(x) => {
+                    let package = name(tree, (&x.nodes.0).into());
+                    ret.scopes[scope].imports.push((package, None));
+                    continue;
+                }
+                RefNode::SimpleIdentifier(_) | RefNode::EscapedIdentifier(_) => (),
+                _ => continue,
+            }
+            if iter.is_inside(NodeKind::WhiteSpace) {
+                continue;
+            }
+
+            // The outermost of the identifiers enclosing the node, e.g. the
+            // `NetIdentifier` of its `Identifier`, is the declaring node.
+            let mut top = path.len();
+            while top > 0 && is_identifier(path[top - 1].kind()) {
+                top -= 1;
+            }
+            let declaration = path.get(top).cloned().unwrap_or_else(|| node.clone());
+            let parent = top.checked_sub(1).map(|x| path[x].kind());
+            let mut reference = Reference {
+                scope,
+                name: name(tree, node.clone()),
+                qualifier: Qualifier::None,
+            };
+
+            if parent.is_some_and(|x| NAMES.contains(&x)) {
+                let outer = ret.scopes[scope].parent.unwrap_or(scope);
+                let named = &mut ret.scopes[scope].name;
+                if named.is_none() {
+                    *named = Some(reference.name);
+                    ret.named.insert(declaration.id(), scope);
+                }
+                ret.scopes[outer]
+                    .declarations
+                    .entry(reference.name)
+                    .or_insert(declaration);
+                reference.scope = outer;
+            } else if parent.is_some_and(|x| DECLARATIONS.contains(&x))
+                && !iter.is_inside(NodeKind::StructUnionMember)
+            {
+                if parent == Some(NodeKind::NameOfInstance) {
+                    if let Some(unit) = design_unit(tree, path) {
+                        ret.instances.insert(declaration.id(), unit);
+                    }
+                }
+                ret.scopes[scope]
+                    .declarations
+                    .entry(reference.name)
+                    .or_insert(declaration);
+            } else if parent.is_some_and(|x| CONNECTIONS.contains(&x)) {
+                // A port or parameter of the instantiated design unit.
+                if let Some(unit) = design_unit(tree, path) {
+                    reference.qualifier = Qualifier::Unit(unit);
+                }
+            } else {
+                reference.qualifier = qualifier(tree, path, top, &node);
+            }
+            ret.references.insert(node.id(), reference);
+        }
+        ret
+    }
+
+    /// The scopes in the order they begin in the tree, so the root is first.
+    pub fn scopes(&self) -> &[Scope<'a>] {
+        &self.scopes
+    }
+
+    /// Find the identifier declaring `node`, which is an identifier such as
+    /// a `NetIdentifier` or a `HierarchicalIdentifier`. The name is looked
+    /// up through the enclosing scopes and their `import`s, in the package or
+    /// class of a `::` prefix, through the named blocks and instances of a
+    /// hierarchical name, or in the design unit of a named port connection.
+    /// `None` is returned if the declaration is not in the tree or can't be
+    /// determined statically, e.g. for the member of a struct. A declaring
+    /// identifier resolves to itself.
+    pub fn resolve(&self, node: RefNode<'a>) -> Option<RefNode<'a>> {
+        // The last name of a qualified identifier is the one referenced.
+        let leaf = node
+            .into_iter()
+            .filter(|x| {
+                matches!(
+                    x,
+                    RefNode::SimpleIdentifier(_) | RefNode::EscapedIdentifier(_)
+                )
+            })
+            .last()?;
+        let reference = self.references.get(&leaf.id())?;
+        match &reference.qualifier {
+            Qualifier::None => self.lookup(reference.scope, reference.name),
+            Qualifier::Scope(x) => {
+                let scope = self.find_scope(x, PACKAGES)?;
+                self.scopes[scope].declarations.get(reference.name).cloned()
+            }
+            Qualifier::Unit(x) => {
+                let scope = self.find_scope(x, DESIGN_UNITS)?;
+                self.scopes[scope].declarations.get(reference.name).cloned()
+            }
+            Qualifier::Hierarchy(x) => {
+                let mut declaration = self.lookup(reference.scope, x[0])?;
+                for name in x[1..].iter().chain(Some(&reference.name)) {
+                    let scope = self.enter(&declaration)?;
+                    declaration = self.scopes[scope].declarations.get(name).cloned()?;
+                }
+                Some(declaration)
+            }
+        }
+    }
+
+    // Look up `name` through `scope` and the scopes enclosing it.
+    fn lookup(&self, scope: usize, name: &str) -> Option<RefNode<'a>> {
+        let mut scope = Some(scope);
+        while let Some(x) = scope {
+            let s = &self.scopes[x];
+            if let Some(x) = s.declarations.get(name) {
+                return Some(x.clone());
+            }
+            for (package, item) in &s.imports {
+                if item.is_none_or(|x| x == name) {
+                    let package = self.find_scope(package, &[NodeKind::PackageDeclaration]);
+                    let found = package.and_then(|x| self.scopes[x].declarations.get(name));
+                    if let Some(x) = found {
+                        return Some(x.clone());
+                    }
+                }
+            }
+            scope = s.parent;
+        }
+        None
+    }
+
+    // The scope named by a declaration, or the design unit of an instance.
+    fn enter(&self, declaration: &RefNode<'a>) -> Option<usize> {
+        if let Some(x) = self.named.get(&declaration.id()) {
+            return Some(*x);
+        }
+        let unit = self.instances.get(&declaration.id())?;
+        self.find_scope(unit, DESIGN_UNITS)
+    }
+
+    fn find_scope(&self, name: &str, kinds: &[NodeKind]) -> Option<usize> {
+        self.scopes
+            .iter()
+            .position(|x| x.name == Some(name) && kinds.contains(&x.node.kind()))
+    }
+}
+
+// A `NamedPortConnectionIdentifier` is a connection, not an identifier.
+fn is_identifier(kind: NodeKind) -> bool {
+    kind.as_str().ends_with("Identifier") && !CONNECTIONS.contains(&kind)
+}
+
+fn name<'a>(tree: &'a SyntaxTree, node: RefNode<'a>) -> &'a str {
+    tree.get_str_trim(vec![node]).unwrap_or("")
+}
+
+fn children<'a>(node: &RefNode<'a>) -> Vec<RefNode<'a>> {
+    node.children()
+        .0
+        .into_iter()
+        .filter(|x| !matches!(x, RefNode::WhiteSpace(_)))
+        .collect()
+}
+
+// The name of the design unit instantiated by the instantiation in `path`.
+fn design_unit<'a>(tree: &'a SyntaxTree, path: &[RefNode<'a>]) -> Option<&'a str> {
+    let instantiation = path
+        .iter()
+        .rev()
+        .find(|x| INSTANTIATIONS.contains(&x.kind()))?;
+    let unit = children(instantiation).into_iter().next()?;
+    Some(name(tree, unit))
+}
+
+// The qualifier of the identifier `node` within `path`, from the `::` prefix
+// or the hierarchical name around it. `path[top]` is the outermost of its
+// identifiers, and its parent may hold the prefix, as in `PrimaryHierarchical`.
+fn qualifier<'a>(
+    tree: &'a SyntaxTree,
+    path: &[RefNode<'a>],
+    top: usize,
+    node: &RefNode<'a>,
+) -> Qualifier<'a> {
+    for k in (top.saturating_sub(1)..path.len()).rev() {
+        let inner = path.get(k + 1).unwrap_or(node);
+        let siblings = children(&path[k]);
+        let before = match siblings.iter().position(|x| x.id() == inner.id()) {
+            Some(x) => &siblings[..x],
+            None => continue,
+        };
+        if path[k].kind() == NodeKind::HierarchicalIdentifier {
+            let names: Vec<_> = before
+                .iter()
+                .filter(|x| x.kind() == NodeKind::Identifier)
+                .map(|x| name(tree, x.clone()))
+                .collect();
+            if !names.is_empty() {
+                return Qualifier::Hierarchy(names);
+            }
+        }
+        let scope = before
+            .iter()
+            .filter(|x| x.kind().as_str().ends_with("Scope"))
+            .find_map(|x| unwrap_node!(x.clone(), PackageIdentifier, ClassIdentifier));
+        if let Some(x) = scope {
+            return Qualifier::Scope(name(tree, x));
+        }
+    }
+    Qualifier::None
+}
@@ -0,0 +1,246 @@
+use crate::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The kind of a definition in a tags file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagKind {
+    Module,
+    Interface,
+    Program,
+    Package,
+    Class,
+    Function,
+    Task,
+    Typedef,
+    /// A parameter or localparam, including a type parameter.
+    Parameter,
+}
+
+impl TagKind {
+    /// The kind letter of Universal Ctags for SystemVerilog.
+    pub fn letter(&self) -> char {
+        match self {
+            TagKind::Module => 'm',
+            TagKind::Interface => 'I',
+            TagKind::Program => 'P',
+            TagKind::Package => 'K',
+            TagKind::Class => 'C',
+            TagKind::Function => 'f',
+            TagKind::Task => 't',
+            TagKind::Typedef => 'T',
+            TagKind::Parameter => 'c',
+        }
+    }
+}
+
+impl fmt::Display for TagKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TagKind::Module => "module",
+            TagKind::Interface => "interface",
+            TagKind::Program => "program",
+            TagKind::Package => "package",
+            TagKind::Class => "class",
+            TagKind::Function => "function",
+            TagKind::Task => "task",
+            TagKind::Typedef => "typedef",
+            TagKind::Parameter => "parameter",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A definition with the location of its name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub kind: TagKind,
+    /// The kind and name of the enclosing definition, e.g. the module of a
+    /// parameter.
+    pub scope: Option<(TagKind, String)>,
+    /// The location of the name in the file it originates from.
+    pub location: Location,
+}
+
+/// The definitions of a set of trees, to be written as a tags file for
+/// editors.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tags {
+    /// The definitions in the order of the trees and of the text.
+    pub tags: Vec<Tag>,
+}
+
+impl Tags {
+    /// Collect the modules, interfaces, programs, packages, classes,
+    /// functions, tasks, typedefs and parameters of `trees`. The origin file
+    /// of each definition is read to find its line, and a definition whose
+    /// file can't be read is left out.
+    pub fn new<'a, T: IntoIterator<Item = &'a SyntaxTree>>(trees: T) -> Self {
+        let mut ret = Tags::default();
+        let mut files: HashMap<PathBuf, Option<String>> = HashMap::new();
+        for tree in trees {
+            // The index within `ret.tags` of the definition of each node
+            // which encloses other definitions.
+            let mut scopes = HashMap::new();
+            let mut iter = tree.into_iter().with_ancestors();
+            while let Some(node) = iter.next() {
+                // The kind and name of a definition, and the node enclosing
+                // the definitions within it.
+                let parent = iter.parent().cloned();
+                let (kind, name, encloses): (_, RefNode, _) = match &node {
+                    RefNode::ModuleNonansiHeader(x) => {
+                        (TagKind::Module, (&x.nodes.3).into(), parent)
+                    }
+                    RefNode::ModuleAnsiHeader(x) => (TagKind::Module, (&x.nodes.3).into(), parent),
+                    RefNode::InterfaceNonansiHeader(x) => {
+                        (TagKind::Interface, (&x.nodes.3).into(), parent)
+                    }
+                    RefNode::InterfaceAnsiHeader(x) => {
+                        (TagKind::Interface, (&x.nodes.3).into(), parent)
+                    }
+                    RefNode::ProgramNonansiHeader(x) => {
+                        (TagKind::Program, (&x.nodes.3).into(), parent)
+                    }
+                    RefNode::ProgramAnsiHeader(x) => {
+                        (TagKind::Program, (&x.nodes.3).into(), parent)
+                    }
+                    RefNode::PackageDeclaration(x) => {
+                        (TagKind::Package, (&x.nodes.3).into(), Some(node.clone()))
+                    }
+                    RefNode::ClassDeclaration(x) => {
+                        (TagKind::Class, (&x.nodes.3).into(), Some(node.clone()))
+                    }
+                    RefNode::FunctionBodyDeclarationWithoutPort(x) => {
+                        (TagKind::Function, (&x.nodes.2).into(), Some(node.clone()))
+                    }
+                    RefNode::FunctionBodyDeclarationWithPort(x) => {
+                        (TagKind::Function, (&x.nodes.2).into(), Some(node.clone()))
+                    }
+                    RefNode::TaskBodyDeclarationWithoutPort(x) => {
+                        (TagKind::Task, (&x.nodes.1).into(), Some(node.clone()))
+                    }
+                    RefNode::TaskBodyDeclarationWithPort(x) => {
+                        (TagKind::Task, (&x.nodes.1).into(), Some(node.clone()))
+                    }
+                    RefNode::TypeDeclarationDataType(x) => {
+                        (TagKind::Typedef, (&x.nodes.2).into(), None)
+                    }
+                    RefNode::TypeDeclarationInterface(x) => {
+                        (TagKind::Typedef, (&x.nodes.5).into(), None)
+                    }
+                    RefNode::ParamAssignment(x) => (TagKind::Parameter, (&x.nodes.0).into(), None),
+                    RefNode::TypeAssignment(x) => (TagKind::Parameter, (&x.nodes.0).into(), None),
+                    _ => continue,
+                };
+                let locate = match unwrap_locate!(name.clone()) {
+                    Some(x) => *x,
+                    None => continue,
+                };
+                let (path, offset) = match tree.get_origin(&locate) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let text = files
+                    .entry(path.clone())
+                    .or_insert_with(|| std::fs::read_to_string(path).ok());
+                let text = match text.as_deref() {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                let scope = iter
+                    .ancestors()
+                    .iter()
+                    .rev()
+                    .find_map(|x| scopes.get(&x.id()))
+                    .map(|&i: &usize| (ret.tags[i].kind, ret.tags[i].name.clone()));
+                if let Some(x) = encloses {
+                    scopes.insert(x.id(), ret.tags.len());
+                }
+                ret.tags.push(Tag {
+                    name: text_of(tree, name),
+                    kind,
+                    scope,
+                    location: Location::new(path, offset, text),
+                });
+            }
+        }
+        ret
+    }
+
+    /// Format as a tags file of Exuberant Ctags, sorted by name, with the
+    /// line number as the address of each tag.
+    pub fn to_ctags(&self) -> String {
+        let mut tags: Vec<_> = self.tags.iter().collect();
+        tags.sort_by(|x, y| x.name.cmp(&y.name));
+
+        let mut ret = String::from("!_TAG_FILE_FORMAT\t2\t/extended format/\n");
+        ret.push_str("!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n");
+        for tag in tags {
+            ret.push_str(&format!(
+                "{}\t{}\t{};\"\t{}",
+                tag.name,
+                tag.location.path.display(),
+                tag.location.line,
+                tag.kind.letter()
+            ));
+            if let Some((kind, name)) = &tag.scope {
+                ret.push_str(&format!("\t{}:{}", kind, name));
+            }
+            ret.push('\n');
+        }
+        ret
+    }
+
+    /// Format as a TAGS file of Emacs, with a section for each file in the
+    /// order the files first appear.
+    pub fn to_etags(&self) -> String {
+        let mut paths: Vec<&PathBuf> = Vec::new();
+        for tag in &self.tags {
+            if !paths.contains(&&tag.location.path) {
+                paths.push(&tag.location.path);
+            }
+        }
+
+        let mut ret = String::new();
+        for path in paths {
+            let mut section = String::new();
+            for tag in self.tags.iter().filter(|x| x.location.path == *path) {
+                // The text of the line up to and including the name, and the
+                // byte offset of the line.
+                let location = &tag.location;
+                let prefix: usize = location
+                    .source_line
+                    .chars()
+                    .take(location.column - 1)
+                    .map(char::len_utf8)
+                    .sum();
+                let pattern = location
+                    .source_line
+                    .get(..prefix + tag.name.len())
+                    .unwrap_or(&location.source_line);
+                section.push_str(&format!(
+                    "{}\u{7f}{}\u{1}{},{}\n",
+                    pattern,
+                    tag.name,
+                    location.line,
+                    location.offset - prefix
+                ));
+            }
+            ret.push_str(&format!(
+                "\u{c}\n{},{}\n{}",
+                path.display(),
+                section.len(),
+                section
+            ));
+        }
+        ret
+    }
+}
+
+fn text_of(tree: &SyntaxTree, node: RefNode) -> String {
+    tree.get_str_trim(node)
+        .map(String::from)
+        .unwrap_or_default()
+}
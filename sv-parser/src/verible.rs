@@ -0,0 +1,114 @@
+use crate::*;
+use serde_json::{json, Map, Value};
+
+impl SyntaxTree {
+    /// Serialize the tree to JSON in the form of `verible-verilog-syntax
+    /// --export_json`, as `{path: {"tree": ...}}`. See `to_verible_json()`.
+    pub fn to_verible_json<T: AsRef<Path>>(&self, path: T) -> String {
+        to_verible_json(std::iter::once((path, self)))
+    }
+
+    fn verible_tree(&self) -> Value {
+        let text = self.text.text();
+        // The entered nodes, each with its children so far.
+        let mut stack: Vec<(RefNode, Vec<Value>)> = Vec::new();
+        let mut root = Value::Null;
+        let mut skip = 0;
+        for n in self.into_iter().event() {
+            match n {
+                NodeEvent::Enter(RefNode::WhiteSpace(_)) => skip += 1,
+                NodeEvent::Leave(RefNode::WhiteSpace(_)) => skip -= 1,
+                _ if skip > 0 => (),
+                NodeEvent::Enter(RefNode::Locate(x)) => {
+                    if let Some((parent, children)) = stack.last_mut() {
+                        let s = &text[x.offset..x.offset + x.len];
+                        children.push(json!({
+                            "start": x.offset,
+                            "end": x.offset + x.len,
+                            "tag": token_tag(parent, s),
+                            "text": s,
+                        }));
+                    }
+                }
+                NodeEvent::Leave(RefNode::Locate(_)) => (),
+                NodeEvent::Enter(x) => stack.push((x, Vec::new())),
+                NodeEvent::Leave(_) => {
+                    let (node, mut children) = stack.pop().unwrap();
+                    // A node with a single child is replaced by the child,
+                    // as the tree of Verible has no counterpart of the many
+                    // nodes which only wrap another.
+                    let value = if !stack.is_empty() && children.len() <= 1 {
+                        match children.pop() {
+                            Some(x) => x,
+                            None => continue,
+                        }
+                    } else {
+                        json!({
+                            "tag": format!("k{}", node),
+                            "children": children,
+                        })
+                    };
+                    match stack.last_mut() {
+                        Some((_, children)) => children.push(value),
+                        None => root = value,
+                    }
+                }
+            }
+        }
+        root
+    }
+}
+
+/// Serialize the trees of files to JSON in the form of
+/// `verible-verilog-syntax --export_json`, so that scripts written for its
+/// output can read them. The result is an object with the path of each file
+/// as a key, and `{"tree": node}` as its value. A node is an object with its
+/// `tag` and its `children`, and a token is an object with its `tag`, `text`,
+/// and its byte range from `start` to `end`.
+///
+/// The tag of a node is its kind prefixed with `k`, e.g.
+/// `kModuleDeclarationAnsi`, which differs from Verible for most kinds. The
+/// tag of a keyword or an operator is its text, and that of an identifier or
+/// a number the name of the token of Verible, e.g. `SymbolIdentifier` or
+/// `TK_DecNumber`. White space and comments are left out, and so is a node
+/// without tokens. A node with a single child is replaced by the child. The
+/// byte ranges are of the preprocessed text, which are those of the file if
+/// it has no includes or macros.
+pub fn to_verible_json<'a, T, U>(files: T) -> String
+where
+    T: IntoIterator<Item = (U, &'a SyntaxTree)>,
+    U: AsRef<Path>,
+{
+    let mut ret = Map::new();
+    for (path, tree) in files {
+        ret.insert(
+            path.as_ref().display().to_string(),
+            json!({ "tree": tree.verible_tree() }),
+        );
+    }
+    Value::Object(ret).to_string()
+}
+
+// The tag of a token, named as by Verible, where `parent` is the node of the
+// token and `s` its text.
+fn token_tag(parent: &RefNode, s: &str) -> String {
+    let tag = match parent {
+        RefNode::Keyword(_) | RefNode::Symbol(_) => s,
+        RefNode::SimpleIdentifier(_) => "SymbolIdentifier",
+        RefNode::EscapedIdentifier(_) => "EscapedIdentifier",
+        RefNode::SystemTfIdentifier(_) => "SystemTFIdentifier",
+        RefNode::UnsignedNumber(_) => "TK_DecNumber",
+        RefNode::BinaryBase(_) => "TK_BinBase",
+        RefNode::BinaryValue(_) => "TK_BinDigits",
+        RefNode::OctalBase(_) => "TK_OctBase",
+        RefNode::OctalValue(_) => "TK_OctDigits",
+        RefNode::DecimalBase(_) => "TK_DecBase",
+        RefNode::HexBase(_) => "TK_HexBase",
+        RefNode::HexValue(_) => "TK_HexDigits",
+        RefNode::XNumber(_) | RefNode::ZNumber(_) => "TK_XZDigits",
+        RefNode::UnbasedUnsizedLiteral(_) => "TK_UnBasedNumber",
+        RefNode::StringLiteral(_) => "TK_StringLiteral",
+        _ => return parent.to_string(),
+    };
+    String::from(tag)
+}